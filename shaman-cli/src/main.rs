@@ -0,0 +1,806 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{
+    ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, parser::ValueSource,
+};
+use miette::{Diagnostic, Result, Severity};
+use shaman_core::{
+    Algorithm, Calibration, Config, Format, Heuristic, Json, Messages, MovementModel, Objective,
+    Order, Output, Placement, Plan, Planner, PlannerConfig, Rng, Script, Shaman, ShamanBuilder,
+    ShamanError, Solution, ToJson, Trace,
+};
+use shaman_render::Options;
+
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    play: Play,
+}
+
+#[derive(Debug, clap::Args)]
+struct Play {
+    /// How fast to simulate
+    #[arg(short, long, default_value_t = 0.)]
+    fps: f32,
+
+    /// Don't solve right away, but print the conflicted solution
+    #[arg(short('x'))]
+    stop: bool,
+
+    /// Keep robots this many cells away from obstacles & the map's edge
+    #[arg(long, default_value_t = 0)]
+    inflate: usize,
+
+    /// How to resolve conflicts between robots
+    #[arg(long, value_enum, default_value_t)]
+    solver: Algorithm,
+
+    /// What to minimize: sum-of-costs, makespan or mix=<factor> (sum of costs plus the makespan
+    /// times the factor)
+    #[arg(long, default_value = "sum-of-costs")]
+    objective: Objective,
+
+    /// Order the prioritized solver plans robots in: distance (longest path first), input
+    /// (as they appear on the map) or random=<seed>
+    #[arg(long, default_value = "distance")]
+    order: Order,
+
+    /// Break ties between equally costly ideas of PBS & CBS randomly with this seed, to
+    /// explore other solutions reproducibly
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Solve groups of robots which don't interact separately, in parallel with the `parallel`
+    /// feature
+    #[arg(long)]
+    decompose: bool,
+
+    /// Print how much work solving took to stderr, e.g. states expanded & conflicts found
+    #[arg(long, conflicts_with_all = ["decompose", "stop"])]
+    stats: bool,
+
+    /// Keep improving the solution by replanning a few robots at a time for this long, e.g.
+    /// `5s` or `500ms`
+    #[arg(long, value_parser = duration, conflicts_with_all = ["decompose", "stats", "stop"])]
+    anytime: Option<Duration>,
+
+    /// How robots estimate the remaining cost to their goals when planning
+    #[arg(long, value_enum, default_value_t)]
+    heuristic: Heuristic,
+
+    /// How robots plan their individual routes
+    #[arg(long, value_enum, default_value_t)]
+    planner: Planner,
+
+    /// Which cells robots can move to in a single timestep
+    #[arg(long, value_enum, default_value_t)]
+    movement: MovementModel,
+
+    /// Let robots only move into the direction they face, rotating in place by 90° (45° with
+    /// eight-connected movement) per timestep at this cost, like differential drives
+    #[arg(long)]
+    rotate_cost: Option<f32>,
+
+    /// Give up planning a single route after expanding this many states
+    #[arg(long)]
+    max_expansions: Option<usize>,
+
+    /// Give up resolving conflicts after this many seconds
+    #[arg(long)]
+    max_time: Option<f32>,
+
+    /// Like --max-time, but with a unit, e.g. `5s`, `500ms` or `2m`. PBS then settles for the
+    /// cheapest conflict free solution found so far, if any
+    #[arg(long, value_parser = duration, conflicts_with = "max_time")]
+    timeout: Option<Duration>,
+
+    /// Never plan routes taking more timesteps than this
+    #[arg(long)]
+    max_route_length: Option<usize>,
+
+    /// Give up solving with CBS after expanding this many nodes of its constraint tree
+    #[arg(long)]
+    max_nodes: Option<usize>,
+
+    /// Settle for solutions costing up to this factor more than optimal ones, e.g. `1.5`, for
+    /// much faster searches on big maps (weighted A* & focal search)
+    #[arg(long, visible_alias = "wastar", value_parser = factor)]
+    suboptimality: Option<f32>,
+
+    /// Save the simulation state to this file when reaching --checkpoint-at and stop there
+    #[arg(long, requires = "checkpoint_at")]
+    checkpoint: Option<PathBuf>,
+
+    /// Timestep at which to save the --checkpoint
+    #[arg(long, requires = "checkpoint")]
+    checkpoint_at: Option<usize>,
+
+    /// Script of events changing the scenario while it is played back
+    #[arg(long, conflicts_with = "montage")]
+    events: Option<PathBuf>,
+
+    /// Let robots which finished early drift to this many cells with the least traffic
+    #[arg(long, default_value_t = 0, conflicts_with = "montage")]
+    staging: usize,
+
+    /// Play all maps at once, tiled next to each other
+    #[arg(long)]
+    montage: bool,
+
+    /// Draw the layout & the solved routes into this SVG file
+    #[arg(long, conflicts_with = "montage")]
+    svg: Option<PathBuf>,
+
+    /// Animate the solved routes into this GIF file, at --fps or 4 frames per second
+    #[arg(long, conflicts_with = "montage")]
+    gif: Option<PathBuf>,
+
+    /// Control playback with the keyboard: space to pause, ←/→ to step, +/- for speed, a robot's
+    /// letter & w to hold it up, q to quit
+    #[arg(long, conflicts_with_all = ["montage", "events", "staging", "checkpoint"])]
+    interactive: bool,
+
+    /// Warn about collisions within this many timesteps while --interactive, e.g. after holding
+    /// up a robot with its letter & w, or while driving one with --drive
+    #[arg(long, default_value_t = 5)]
+    lookahead: usize,
+
+    /// Drive this robot with the arrow keys, one move per timestep at --fps (or 2), while all
+    /// others follow their routes & evade it. Press q to quit
+    #[arg(long, value_name = "ROBOT", conflicts_with_all = ["interactive", "montage", "events", "staging", "checkpoint", "output"])]
+    drive: Option<char>,
+
+    /// What to print, e.g. the routes as JSON for a fleet controller
+    #[arg(long, value_enum, default_value_t, conflicts_with = "montage")]
+    output: Output,
+
+    /// Format of the map files, e.g. to read MovingAI benchmarks without their usual extensions
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+
+    /// Defaults for all other flags, e.g. the solver, limits, cost model & palette, instead of
+    /// the shaman.toml in the current directory
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Path to map files to use
+    maps: Vec<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Solve and play one or more maps (the default without any subcommand)
+    Play(Box<Play>),
+
+    /// Continue playing back a simulation saved with --checkpoint
+    Resume {
+        /// Path to the checkpoint file
+        checkpoint: PathBuf,
+
+        /// How fast to simulate
+        #[arg(short, long, default_value_t = 4.)]
+        fps: f32,
+    },
+
+    /// Solve a map & scrub through its reservation table timestep by timestep
+    Inspect {
+        /// Path to the map file to solve
+        map: PathBuf,
+
+        /// Keep robots this many cells away from obstacles & the map's edge
+        #[arg(long, default_value_t = 0)]
+        inflate: usize,
+
+        /// How to resolve conflicts between robots
+        #[arg(long, value_enum, default_value_t)]
+        solver: Algorithm,
+    },
+
+    /// Place random robots & goals on the layout of a map (or a random layout) and print the
+    /// resulting map
+    Generate {
+        /// Path to a map file whose obstacles to use, instead of generating random ones
+        map: Option<PathBuf>,
+
+        /// Width of the random layout, including its border
+        #[arg(long, default_value_t = 16, conflicts_with = "map")]
+        width: usize,
+
+        /// Height of the random layout, including its border
+        #[arg(long, default_value_t = 8, conflicts_with = "map")]
+        height: usize,
+
+        /// Share of cells inside the border of the random layout to block with obstacles
+        #[arg(long, default_value_t = 0.2, conflicts_with = "map")]
+        obstacle_density: f32,
+
+        /// How many robots to place
+        #[arg(short, long, default_value_t = 4)]
+        robots: usize,
+
+        /// Where to place robots and their goals
+        #[arg(short, long, value_enum, default_value_t)]
+        placement: Placement,
+
+        /// Seed for the random placement
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Solve randomly perturbed variants of a map to tell how robust its layout is
+    Perturb {
+        /// Path to the map file to perturb
+        map: PathBuf,
+
+        /// How many obstacles or goals to relocate per variant
+        #[arg(short, long, default_value_t = 5)]
+        moves: usize,
+
+        /// How many variants to solve
+        #[arg(short, long, default_value_t = 10)]
+        runs: usize,
+
+        /// Seed for the random relocations
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+
+        /// How to resolve conflicts between robots
+        #[arg(long, value_enum, default_value_t)]
+        solver: Algorithm,
+
+        /// Defaults for the solver, limits, cost model & format of the map, instead of the
+        /// shaman.toml in the current directory
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Solve a map & bundle it with its solution and statistics into a single archive, which
+    /// can be played back like a map
+    Pack {
+        /// Path to the map file to solve
+        map: PathBuf,
+
+        /// Where to write the archive to, usually ending in `.shmn`
+        output: PathBuf,
+
+        /// Keep robots this many cells away from obstacles & the map's edge
+        #[arg(long, default_value_t = 0)]
+        inflate: usize,
+
+        /// How to resolve conflicts between robots
+        #[arg(long, value_enum, default_value_t)]
+        solver: Algorithm,
+
+        /// Defaults for the solver, limits, cost model & format of the map, instead of the
+        /// shaman.toml in the current directory
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Shrink a failing map by removing rows, columns & robots, while it keeps failing, and
+    /// print the minimal map
+    Minimize {
+        /// Path to the failing map file
+        map: PathBuf,
+
+        /// Part of the error message the minimal map must still fail with
+        #[arg(long)]
+        preserve: String,
+
+        /// How to resolve conflicts between robots
+        #[arg(long, value_enum, default_value_t)]
+        solver: Algorithm,
+
+        /// Defaults for the solver, limits, cost model & format of the map, instead of the
+        /// shaman.toml in the current directory
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Estimate cost model parameters from recorded durations of actions
+    Calibrate {
+        /// Text file with one trace per line, made of `action:duration` pairs like `N:0.8`
+        traces: PathBuf,
+    },
+
+    /// Parse a map without solving it & list every problem found, not just the first one
+    Check {
+        /// Path to the map file to check
+        map: PathBuf,
+
+        /// Print the problems as message IDs & parameters (JSON), to be translated by whoever
+        /// shows them
+        #[arg(long)]
+        messages: bool,
+
+        /// Defaults for the solver, limits, cost model & format of the map, instead of the
+        /// shaman.toml in the current directory
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Compare the costs of a plan (as JSON) against those of a reference plan, check that it's
+    /// a valid solution of a map, or check a map itself like the check command
+    Validate {
+        /// Path to the JSON plan to compare, or to a map to check like the check command does,
+        /// if neither --against nor --map are given
+        plan: PathBuf,
+
+        /// Path to the JSON plan to compare against, e.g. an optimal solution
+        #[arg(long)]
+        against: Option<PathBuf>,
+
+        /// Path to the map the plan solves, to list everything its routes violate, like
+        /// collisions or moves through obstacles
+        #[arg(long)]
+        map: Option<PathBuf>,
+
+        /// Print the optimality gaps as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+
+        /// Print the table, or the violations of --map, as message IDs & parameters (JSON), to
+        /// be translated by whoever shows them
+        #[arg(long, conflicts_with = "json")]
+        messages: bool,
+
+        /// Defaults for the solver, limits, cost model & format of the map, instead of the
+        /// shaman.toml in the current directory
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Solve a map & print step-by-step instructions for each robot, like "t=0–3 go east 3
+    /// cells"
+    Itinerary {
+        /// Path to the map file to solve
+        map: PathBuf,
+
+        /// Print the instructions as JSON instead of text
+        #[arg(long)]
+        json: bool,
+
+        /// Defaults for the solver, limits, cost model & format of the map, instead of the
+        /// shaman.toml in the current directory
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Print every decision the solver made to resolve conflicts, as recorded in an archive
+    Audit {
+        /// Path to an archive written by the pack command
+        archive: PathBuf,
+
+        /// Print the decisions as JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print statistics about a map without solving it
+    #[command(group(ArgGroup::new("report").multiple(false)))]
+    Stats {
+        /// Path to a map file to analyze
+        map: PathBuf,
+
+        /// List cells many robots depend on and show them on the map
+        #[arg(long, group = "report")]
+        bottlenecks: bool,
+
+        /// Solve the map and show in which direction robots traverse each cell
+        #[arg(long, groups = ["report", "export"])]
+        flows: bool,
+
+        /// Print which robots' shortest paths collide as a DOT graph
+        #[arg(long, groups = ["report", "export"])]
+        conflicts: bool,
+
+        /// Label the --conflicts graph's edges with how often both robots collide
+        #[arg(long, requires = "conflicts")]
+        counts: bool,
+
+        /// Solve the map and explain which robots delay each other
+        #[arg(long, group = "report")]
+        delays: bool,
+
+        /// Suggest robots trading goals, which makes the map solvable or much cheaper
+        #[arg(long, group = "report")]
+        swaps: bool,
+
+        /// Solve the map and break down moves, waits, turns & energy per robot and fleet
+        #[arg(long, group = "report")]
+        breakdown: bool,
+
+        /// Print the --flows histogram or the --conflicts graph as JSON instead
+        #[arg(long, requires = "export")]
+        json: bool,
+
+        /// Defaults for the solver, limits, cost model & format of the map, instead of the
+        /// shaman.toml in the current directory
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+}
+
+impl Play {
+    /// Play back the maps, with all flags not given in `matches` taken from the config file
+    fn run(self, matches: &ArgMatches) -> Result<()> {
+        if self.maps.is_empty() {
+            return Err(miette::miette!("No map file given, see --help"));
+        }
+        let config = load_config(self.config.as_ref())?;
+        let planner = config.planner;
+        let options = Options {
+            fps: self.fps,
+            stop: self.stop,
+            inflate: given(matches, "inflate", self.inflate, config.inflate),
+            algorithm: given(matches, "solver", self.solver, config.algorithm),
+            objective: given(matches, "objective", self.objective, config.objective),
+            order: given(matches, "order", self.order, config.order),
+            seed: self.seed.or(config.seed),
+            decompose: self.decompose,
+            stats: self.stats,
+            anytime: self.anytime,
+            planner: PlannerConfig {
+                planner: given(matches, "planner", self.planner, planner.planner),
+                heuristic: given(matches, "heuristic", self.heuristic, planner.heuristic),
+                movement: given(matches, "movement", self.movement, planner.movement),
+                max_expansions: self.max_expansions.or(planner.max_expansions),
+                max_time: self
+                    .timeout
+                    .or(self.max_time.map(Duration::from_secs_f32))
+                    .or(planner.max_time),
+                max_route_length: self.max_route_length.or(planner.max_route_length),
+                max_nodes: self.max_nodes.or(planner.max_nodes),
+                rotate_cost: self.rotate_cost.or(planner.rotate_cost),
+                costs: planner.costs,
+                suboptimality: self.suboptimality.or(planner.suboptimality),
+            },
+            palette: config.palette,
+            checkpoint: self.checkpoint.zip(self.checkpoint_at),
+            script: self.events.map(Script::load).transpose()?,
+            staging: self.staging,
+            output: given(matches, "output", self.output, config.output),
+            interactive: self.interactive,
+            lookahead: self.lookahead,
+            drive: self.drive,
+            svg: self.svg,
+            gif: self.gif,
+            format: given(matches, "format", self.format, config.format),
+        };
+        if self.montage {
+            return shaman_render::montage(&self.maps, &options);
+        }
+        for map in &self.maps {
+            shaman_render::level(map, &options)?;
+        }
+        Ok(())
+    }
+}
+
+/// List every problem of `map`, failing if any of them is an error, see [Shaman::check_as]
+fn check(map: &Path, config: Option<PathBuf>, messages: bool) -> Result<()> {
+    let problems = Shaman::check_as(map, load_config(config.as_ref())?.format)?;
+    let failed = problems
+        .iter()
+        .any(|p| p.severity().is_none_or(|s| s == Severity::Error));
+    if messages {
+        let messages = problems.iter().flat_map(|p| p.messages());
+        println!("{}", Json::array(messages));
+        if failed {
+            return Err(miette::miette!("Found {} problem(s)", problems.len()));
+        }
+    } else if problems.is_empty() {
+        println!("No problems found");
+    } else {
+        let count = problems.len();
+        let report = miette::Report::new(ShamanError::Problems { count, problems });
+        match failed {
+            true => return Err(report),
+            false => eprintln!("{report:?}"),
+        }
+    }
+    Ok(())
+}
+
+/// The configuration in `file`, or else the shaman.toml in the current directory, if any
+fn load_config(file: Option<&PathBuf>) -> Result<Config> {
+    match file {
+        Some(file) => Config::load(file),
+        None => Ok(Config::discover()?.unwrap_or_default()),
+    }
+}
+
+/// The value of flag `id` if it was given on the command line, otherwise the `configured` one
+fn given<T>(matches: &ArgMatches, id: &str, value: T, configured: T) -> T {
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => value,
+        _ => configured,
+    }
+}
+
+fn main() -> Result<()> {
+    shaman_render::enable_escape_sequences();
+    miette::set_hook(Box::new(|_| {
+        Box::new(miette::MietteHandlerOpts::new().context_lines(10).build())
+    }))?;
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    match args.command {
+        Some(Command::Generate {
+            map,
+            width,
+            height,
+            obstacle_density,
+            robots,
+            placement,
+            seed,
+        }) => {
+            let mut rng = Rng::new(seed);
+            let builder = match map {
+                Some(map) => ShamanBuilder::from(&Shaman::parse(map)?),
+                None => {
+                    ShamanBuilder::random_layout_with(width, height, obstacle_density, &mut rng)?
+                }
+            };
+            let builder = builder
+                .placement(placement)
+                .random_robots_with(robots, &mut rng)?;
+            eprint!("{}", builder.build()?.difficulty());
+            print!("{builder}");
+        }
+        Some(Command::Stats {
+            map,
+            config,
+            flows: true,
+            json,
+            ..
+        }) => {
+            let config = load_config(config.as_ref())?;
+            let solution = Shaman::parse_with(map, &config)?.solve_with(config.algorithm)?;
+            let solution = Solution::try_from(solution)?;
+            let flows = solution.flows();
+            if json {
+                println!("{}", Json::array(&flows));
+            } else {
+                let mut sim = solution.into_inner();
+                sim.annotate(flows.iter().filter_map(|f| Some((f.cell, f.arrow()?))));
+                print!("{sim}");
+            }
+        }
+        Some(Command::Stats {
+            map,
+            config,
+            conflicts: true,
+            counts,
+            json,
+            ..
+        }) => {
+            let config = load_config(config.as_ref())?;
+            let graph = Shaman::parse_with(map, &config)?.conflict_graph();
+            match json {
+                true => println!("{}", graph.to_json()),
+                false => print!("{}", graph.dot(counts)),
+            }
+        }
+        Some(Command::Stats {
+            map,
+            config,
+            swaps: true,
+            ..
+        }) => {
+            let config = load_config(config.as_ref())?;
+            let suggestions = Shaman::parse_with(map, &config)?.suggest_swaps(config.algorithm);
+            if suggestions.is_empty() {
+                println!("No goal swaps make this map solvable or much cheaper");
+            }
+            for suggestion in suggestions {
+                println!("{suggestion}");
+            }
+        }
+        Some(Command::Stats {
+            map,
+            config,
+            breakdown: true,
+            ..
+        }) => {
+            let config = load_config(config.as_ref())?;
+            let solution = Shaman::parse_with(map, &config)?.solve_with(config.algorithm)?;
+            print!("{}", Solution::try_from(solution)?.stats());
+        }
+        Some(Command::Stats {
+            map,
+            config,
+            delays: true,
+            ..
+        }) => {
+            let config = load_config(config.as_ref())?;
+            let solution = Shaman::parse_with(map, &config)?.solve_with(config.algorithm)?;
+            for attribution in Solution::try_from(solution)?.delays() {
+                println!("{attribution}");
+            }
+        }
+        Some(Command::Stats {
+            map,
+            config,
+            bottlenecks,
+            ..
+        }) => {
+            let mut sim = Shaman::parse_with(map, &load_config(config.as_ref())?)?;
+            print!("{}", sim.difficulty());
+            if bottlenecks {
+                let bottlenecks = sim.bottlenecks();
+                println!("bottlenecks:");
+                for b in &bottlenecks {
+                    println!("  {b}");
+                }
+                sim.highlight(bottlenecks.iter().map(|b| b.cell));
+                print!("{sim}");
+            }
+        }
+        Some(Command::Perturb {
+            map,
+            moves,
+            runs,
+            seed,
+            solver,
+            config,
+        }) => {
+            let perturb = matches.subcommand_matches("perturb").unwrap();
+            let config = load_config(config.as_ref())?;
+            let algorithm = given(perturb, "solver", solver, config.algorithm);
+            let sim = Shaman::parse_with(map, &config)?;
+            print!("{}", sim.sensitivity(algorithm, moves, runs, seed)?);
+        }
+        Some(Command::Pack {
+            map,
+            output,
+            inflate,
+            solver,
+            config,
+        }) => {
+            let pack = matches.subcommand_matches("pack").unwrap();
+            let mut config = load_config(config.as_ref())?;
+            config.inflate = given(pack, "inflate", inflate, config.inflate);
+            config.algorithm = given(pack, "solver", solver, config.algorithm);
+            Shaman::parse_with(map, &config)?
+                .solve_with(config.algorithm)?
+                .save_archive(output)?
+        }
+        Some(Command::Minimize {
+            map,
+            preserve,
+            solver,
+            config,
+        }) => {
+            let minimize = matches.subcommand_matches("minimize").unwrap();
+            let config = load_config(config.as_ref())?;
+            let algorithm = given(minimize, "solver", solver, config.algorithm);
+            print!(
+                "{}",
+                Shaman::parse_with(map, &config)?.minimize(algorithm, &preserve)?
+            );
+        }
+        Some(Command::Calibrate { traces }) => {
+            print!("{}", Calibration::fit(&Trace::load(traces)?)?);
+        }
+        Some(Command::Check {
+            map,
+            config,
+            messages,
+        }) => check(&map, config, messages)?,
+        Some(Command::Validate {
+            plan,
+            against,
+            map,
+            json,
+            messages,
+            config,
+        }) => {
+            if against.is_none() && map.is_none() {
+                return check(&plan, config, messages);
+            }
+            if let Some(map) = map {
+                let sim = Shaman::parse_with(map, &load_config(config.as_ref())?)?;
+                let violations = sim.with_plan_file(&plan)?.violations();
+                if messages {
+                    let messages = violations.iter().flat_map(|v| v.messages());
+                    println!("{}", Json::array(messages));
+                }
+                for violation in violations.iter().filter(|_| !messages) {
+                    eprintln!("{violation}");
+                }
+                if !violations.is_empty() {
+                    return Err(miette::miette!(
+                        "Plan violates {} constraint(s)",
+                        violations.len()
+                    ));
+                }
+            }
+            let Some(against) = against else {
+                return Ok(());
+            };
+            let comparison = Plan::load(plan)?.compare(&Plan::load(against)?)?;
+            if json {
+                println!("{}", comparison.to_json());
+            } else if messages {
+                println!("{}", Json::array(comparison.messages()));
+            } else {
+                print!("{comparison}");
+            }
+        }
+        Some(Command::Itinerary { map, json, config }) => {
+            let config = load_config(config.as_ref())?;
+            let solved = Shaman::parse_with(map, &config)?.solve_with(config.algorithm)?;
+            let itineraries = solved.itineraries();
+            if json {
+                println!("{}", Json::array(&itineraries));
+            } else {
+                for itinerary in itineraries {
+                    print!("{itinerary}");
+                }
+            }
+        }
+        Some(Command::Audit { archive, json }) => {
+            let sim = Shaman::load_archive(archive)?;
+            if json {
+                println!("{}", sim.audit().to_json());
+            } else {
+                print!("{}", sim.audit());
+            }
+        }
+        Some(Command::Play(play)) => play.run(matches.subcommand_matches("play").unwrap())?,
+        Some(Command::Inspect {
+            map,
+            inflate,
+            solver,
+        }) => shaman_render::inspect(
+            &map,
+            &Options {
+                inflate,
+                algorithm: solver,
+                ..Default::default()
+            },
+        )?,
+        Some(Command::Resume { checkpoint, fps }) => shaman_render::resume(
+            &checkpoint,
+            &Options {
+                fps,
+                ..Default::default()
+            },
+        )?,
+        None => args.play.run(&matches)?,
+    }
+    Ok(())
+}
+
+/// Factor of at least `1`
+fn factor(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(f) if f >= 1. => Ok(f),
+        _ => Err(format!("expected a factor of at least 1, got '{s}'")),
+    }
+}
+
+/// Duration like `5s`, `500ms` or `2m`, bare numbers are seconds
+fn duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| c.is_alphabetic()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = value
+        .trim()
+        .parse::<f32>()
+        .map_err(|e| format!("invalid duration '{s}': {e}"))?;
+    let seconds = match unit {
+        "ms" => value / 1000.,
+        "" | "s" => value,
+        "m" => value * 60.,
+        _ => return Err(format!("unknown unit '{unit}', expected ms, s or m")),
+    };
+    Duration::try_from_secs_f32(seconds).map_err(|e| format!("invalid duration '{s}': {e}"))
+}
@@ -0,0 +1,193 @@
+//! Static analysis of scenarios, without solving them
+use std::fmt::Display;
+
+use itertools::Itertools;
+use petgraph::{algo::articulation_points::articulation_points, graph::UnGraph};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{Shaman, layout::Vertex};
+
+/// A cell which many robots depend on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bottleneck {
+    pub cell: Vertex,
+    /// Amount of robots whose shortest path crosses this cell
+    pub traffic: usize,
+    /// Whether blocking this cell splits the free space into disconnected areas
+    pub cut: bool,
+    /// Longest detour any robot would need to take, if this cell were blocked. `None` if some
+    /// robot could not reach its goal anymore
+    pub detour: Option<usize>,
+}
+
+impl Display for Bottleneck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let detour = match self.detour {
+            Some(d) => format!("detour {d}"),
+            None => "disconnects".to_string(),
+        };
+        let cut = if self.cut { ", cut" } else { "" };
+        write!(f, "{}: traffic {}, {detour}{cut}", self.cell, self.traffic)
+    }
+}
+
+/// Heuristic estimate of how hard a scenario is to solve, based on the robots' independent
+/// shortest paths
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    /// Fraction of cells on any path, which are shared by more than one robot
+    pub overlap: f32,
+    /// Fraction of cells on any path, which are shared corridor cells (at most two neighbors)
+    pub contention: f32,
+    /// Amount of goals per free cell
+    pub goal_density: f32,
+}
+
+/// Coarse classification of a [Difficulty]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rating {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Single number in `[0, 1]` combining all measures, higher is harder
+    pub fn score(&self) -> f32 {
+        (0.4 * self.overlap + 0.4 * self.contention + 0.2 * (5. * self.goal_density).min(1.))
+            .clamp(0., 1.)
+    }
+
+    pub fn rating(&self) -> Rating {
+        match self.score() {
+            s if s < 0.15 => Rating::Easy,
+            s if s < 0.4 => Rating::Medium,
+            _ => Rating::Hard,
+        }
+    }
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "difficulty:   {:.2} ({:?})", self.score(), self.rating())?;
+        writeln!(f, "overlap:      {:.2}", self.overlap)?;
+        writeln!(f, "contention:   {:.2}", self.contention)?;
+        writeln!(f, "goal density: {:.3}", self.goal_density)
+    }
+}
+
+impl Shaman {
+    /// Which cells the robots' independent shortest paths use, by robot name
+    fn traffic(&self) -> FxHashMap<Vertex, Vec<char>> {
+        let mut usage = FxHashMap::<Vertex, Vec<char>>::default();
+        for robot in self.robots.values() {
+            let mut robot = robot.clone();
+            if robot
+                .plan(&self.layout, &Default::default(), self.planner)
+                .is_err()
+            {
+                continue;
+            }
+            // Count each robot only once per cell, even if it waits there
+            for v in robot.route().iter().map(|l| l.position).unique() {
+                usage.entry(v).or_default().push(robot.name());
+            }
+        }
+        usage
+    }
+
+    /// Up to `count` free cells with the least traffic, i.e. the ones where idle robots get in
+    /// the way the least. Dead ends are preferred and no robot starts or ends on any of them
+    pub fn staging_cells(&self, count: usize) -> Vec<Vertex> {
+        let usage = self.traffic();
+        let occupied = self
+            .robots
+            .values()
+            .flat_map(|r| [Some(r.position().0), r.goal().map(|g| g.0)])
+            .flatten()
+            .collect::<FxHashSet<_>>();
+        self.layout
+            .free_cells()
+            .filter(|v| !occupied.contains(v))
+            .sorted_by_key(|v| {
+                let traffic = usage.get(v).map_or(0, |names| names.len());
+                (traffic, self.layout.neighbors(*v).count(), v.y, v.x)
+            })
+            .take(count)
+            .collect()
+    }
+
+    /// Cells used by several robots, whose blocking would disconnect the free space or would
+    /// make robots take long detours, sorted by descending traffic
+    pub fn bottlenecks(&self) -> Vec<Bottleneck> {
+        let mut graph = UnGraph::<Vertex, ()>::default();
+        let nodes = self
+            .layout
+            .free_cells()
+            .map(|v| (v, graph.add_node(v)))
+            .collect::<FxHashMap<_, _>>();
+        for (v, n) in &nodes {
+            for other in [Vertex::new(v.x + 1, v.y), Vertex::new(v.x, v.y + 1)] {
+                if let Some(o) = nodes.get(&other) {
+                    graph.add_edge(*n, *o, ());
+                }
+            }
+        }
+        let cuts = articulation_points(&graph)
+            .into_iter()
+            .map(|n| graph[n])
+            .collect::<FxHashSet<_>>();
+
+        self.traffic()
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .filter_map(|(cell, names)| {
+                let mut layout = self.layout.clone();
+                layout.block(cell);
+                let mut detour = Some(0);
+                let mut drastic = false;
+                for robot in names.iter().map(|n| &self.robots[n]) {
+                    let (start, goal) = (robot.position().0, robot.goal()?.0);
+                    let before = self.layout.distance(start, goal)?;
+                    match layout.distance(start, goal) {
+                        Some(after) => {
+                            // Detours of at least half the original path are drastic
+                            drastic |= 2 * (after - before) >= before.max(1);
+                            detour = detour.map(|d: usize| d.max(after - before));
+                        }
+                        None => detour = None,
+                    }
+                }
+                let cut = cuts.contains(&cell);
+                (cut || drastic || detour.is_none()).then_some(Bottleneck {
+                    cell,
+                    traffic: names.len(),
+                    cut,
+                    detour,
+                })
+            })
+            .sorted_by_key(|b| (std::cmp::Reverse(b.traffic), b.cell.y, b.cell.x))
+            .collect()
+    }
+
+    /// Estimate how hard this scenario is, without solving it
+    pub fn difficulty(&self) -> Difficulty {
+        let usage = self.traffic();
+        let used = usage.len().max(1) as f32;
+        let shared = usage
+            .iter()
+            .filter(|(_, n)| n.len() > 1)
+            .collect::<Vec<_>>();
+        let corridors = shared
+            .iter()
+            .filter(|(v, _)| self.layout.neighbors(**v).count() <= 2)
+            .count();
+
+        Difficulty {
+            overlap: shared.len() as f32 / used,
+            contention: corridors as f32 / used,
+            goal_density: self.robots.values().filter(|r| r.goal().is_some()).count() as f32
+                / self.layout.free_cell_count().max(1) as f32,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Bundling a solved scenario into a single file, so it can be shared & replayed anywhere
+//!
+//! An archive starts with a magic line, followed by deflate compressed sections:
+//!
+//! ```text
+//! shaman archive 1
+//! <compressed>
+//!   section meta 42
+//!   <42 bytes of content>
+//!   section config 10
+//!   ...
+//! ```
+//!
+//! - `meta`: name of the map, amount of robots & version of shaman which packed it
+//! - `config`: settings the map was solved with
+//! - `solution`: a [checkpoint](crate::checkpoint) of the solved map
+//! - `summary` & `stats`: key figures of the solution, as text & JSON respectively. The latter
+//!   are `null` for routes which violate anything, see [Shaman::violations]
+//! - `audit`: the [decisions](crate::Audit) made while solving, as JSON
+use std::path::Path;
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    Shaman,
+    audit::Audit,
+    json::{Json, ToJson},
+    solution::Solution,
+};
+
+const MAGIC: &[u8] = b"shaman archive 1\n";
+
+/// Preferred extension of archive files
+pub const ARCHIVE_EXTENSION: &str = "shmn";
+
+impl Shaman {
+    /// Serialize this (solved) scenario including its key figures, see [Self::unpack]
+    pub fn pack(&self) -> Vec<u8> {
+        let meta = format!(
+            "name {}\nrobots {}\nversion {}\n",
+            self.layout.code().name(),
+            self.robots.len(),
+            env!("CARGO_PKG_VERSION")
+        );
+        let config = format!("inflate {}\n", self.layout.inflation());
+        let stats = Solution::try_from(self.clone()).map_or(Json::Null, |s| s.stats().to_json());
+        let stats = stats.to_string();
+        let sections = [
+            ("meta", meta),
+            ("config", config),
+            ("solution", self.checkpoint()),
+            ("summary", self.summary().to_string()),
+            ("stats", stats),
+            ("audit", self.audit.to_json().to_string()),
+        ];
+
+        let mut content = String::new();
+        for (name, section) in sections {
+            content.push_str(&format!("section {name} {}\n{section}\n", section.len()));
+        }
+        [
+            MAGIC,
+            &miniz_oxide::deflate::compress_to_vec(content.as_bytes(), 9),
+        ]
+        .concat()
+    }
+
+    /// Write [Self::pack] to `file`
+    pub fn save_archive<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let file = file.as_ref();
+        std::fs::write(file, self.pack()).map_err(|e| miette!("{}: {e}", file.display()))
+    }
+
+    /// Restore a scenario from the content of an archive written by [Self::pack], ready to be
+    /// played back
+    pub fn unpack(name: &str, archive: &[u8]) -> Result<Self> {
+        let invalid = || miette!("{name}: not a valid shaman archive");
+        let compressed = archive.strip_prefix(MAGIC).ok_or_else(invalid)?;
+        let content = miniz_oxide::inflate::decompress_to_vec(compressed)
+            .ok()
+            .and_then(|c| String::from_utf8(c).ok())
+            .ok_or_else(invalid)?;
+
+        let mut rest = content.as_str();
+        let mut sections = vec![];
+        while !rest.is_empty() {
+            let (line, tail) = rest.split_once('\n').ok_or_else(invalid)?;
+            let (section, length) = match line.split_whitespace().collect_vec().as_slice() {
+                ["section", section, length] => (*section, length.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            };
+            let body = tail.get(..length).ok_or_else(invalid)?;
+            rest = tail[length..].strip_prefix('\n').ok_or_else(invalid)?;
+            sections.push((section, body));
+        }
+        let section = |wanted| {
+            sections
+                .iter()
+                .find(|(s, _)| *s == wanted)
+                .map(|(_, body)| *body)
+                .ok_or_else(|| miette!("{name}: archive has no {wanted} section"))
+        };
+
+        let mut sim = Self::from_checkpoint(name, section("solution")?)?;
+        for line in section("config")?.lines() {
+            match line.split_whitespace().collect_vec().as_slice() {
+                ["inflate", radius] => {
+                    // Checkpoints block the inflated cells already, except of older versions
+                    let radius: usize = radius.parse().map_err(|_| invalid())?;
+                    sim.layout
+                        .inflate(radius.saturating_sub(sim.layout.inflation()));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        // Archives of older versions come without an audit
+        if let Ok(audit) = section("audit") {
+            sim.audit = Json::parse(audit).and_then(|json| Audit::from_json(&json))?;
+        }
+        Ok(sim)
+    }
+
+    /// Load an archive written by [Self::save_archive]
+    pub fn load_archive<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let file = file.as_ref().display().to_string();
+        let archive = std::fs::read(&file).map_err(|e| miette!("{file}: {e}"))?;
+        Self::unpack(&file, &archive)
+    }
+}
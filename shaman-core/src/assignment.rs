@@ -0,0 +1,149 @@
+//! Anonymous goals, where robots drive to whichever goal suits the whole fleet best
+//!
+//! Goals are assigned minimizing the makespan first, i.e. the longest shortest path of any
+//! robot, and the sum of all shortest paths second. Both come from the Hungarian algorithm:
+//! paths longer than a candidate makespan are made prohibitively expensive, and the smallest
+//! candidate under which every robot still reaches a goal wins.
+use itertools::Itertools;
+use miette::Result;
+
+use crate::{Shaman, layout::Vertex};
+
+impl Shaman {
+    /// Whether robots may swap goals, see [Self::assign_goals]
+    pub fn has_anonymous_goals(&self) -> bool {
+        self.anonymous
+    }
+
+    /// Treat all goals as interchangeable from now on, so [Self::solve] first assigns them
+    pub fn with_anonymous_goals(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+
+    /// Redistribute the goals of all (unfrozen) robots among them, so the robot taking longest
+    /// to reach its goal arrives as early as possible, and replan them accordingly. Routes of
+    /// other robots are ignored, PBS resolves conflicts afterwards
+    pub fn assign_goals(&mut self) -> Result<()> {
+        let Some(goals) = self.optimal_goals() else {
+            return Ok(()); // Some robot can't reach any goal, keep the drawn ones
+        };
+        for (name, goal) in goals {
+            self.reassign(name, goal);
+        }
+        self.plan()
+    }
+
+    /// Goal of each (unfrozen) robot having one, sorted by name, if they were assigned as in
+    /// [Self::assign_goals]. `None` if some robot can't reach any goal
+    pub(crate) fn optimal_goals(&self) -> Option<Vec<(char, Vertex)>> {
+        let robots = self
+            .robots
+            .values()
+            .filter(|r| r.goal().is_some() && !r.is_frozen())
+            .sorted_by_key(|r| r.name())
+            .map(|r| (r.name(), r.position().0))
+            .collect_vec();
+        let goals = robots
+            .iter()
+            .map(|(n, _)| self.robots[n].goal().unwrap().0)
+            .collect_vec();
+        let distances = goals
+            .iter()
+            .map(|g| self.layout.distances_to(*g))
+            .collect_vec();
+        let lengths = robots
+            .iter()
+            .map(|(_, start)| {
+                let lengths = distances.iter().map(|d| d.get(start).map(|l| *l as i64));
+                lengths.collect_vec()
+            })
+            .collect_vec();
+
+        // Paths longer than the makespan cost more than all others within it together
+        let expensive = lengths.iter().flatten().flatten().sum::<i64>() + 1;
+        let assign = |makespan: i64| {
+            let cost = lengths
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|l| l.filter(|l| *l <= makespan).unwrap_or(expensive))
+                        .collect_vec()
+                })
+                .collect_vec();
+            let assignment = hungarian(&cost);
+            let total = assignment.iter().enumerate().map(|(i, j)| cost[i][*j]);
+            (total.sum::<i64>() < expensive).then_some(assignment)
+        };
+        let makespans = lengths.iter().flatten().flatten().copied().sorted().dedup();
+        let makespans = makespans.collect_vec();
+        let shortest = makespans.partition_point(|m| assign(*m).is_none());
+        let assignment = makespans.get(shortest).and_then(|m| assign(*m))?;
+        let goals = assignment.into_iter().map(|j| goals[j]);
+        Some(robots.iter().map(|(n, _)| *n).zip(goals).collect())
+    }
+
+    /// Let robot `name` drive to `goal` instead, leaving the map there if it's a drop-off.
+    /// Doesn't replan it
+    pub(crate) fn reassign(&mut self, name: char, goal: Vertex) {
+        let delivers = self.capacities.contains_key(&goal);
+        let robot = self.robots.get_mut(&name).unwrap();
+        robot.assign(goal);
+        robot.set_delivers(delivers);
+    }
+}
+
+/// Column assigned to each row of the square `cost` matrix, such that their sum is minimal
+fn hungarian(cost: &[Vec<i64>]) -> Vec<usize> {
+    let n = cost.len();
+    // Potentials of rows & columns, 1-based with the extra column 0 as start of each search
+    let (mut u, mut v) = (vec![0; n + 1], vec![0; n + 1]);
+    // Row matched to each column, 0 for none
+    let mut matched = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+    for i in 1..=n {
+        matched[0] = i;
+        let mut column = 0;
+        let mut slack = vec![i64::MAX; n + 1];
+        let mut used = vec![false; n + 1];
+        while matched[column] != 0 {
+            used[column] = true;
+            let row = matched[column];
+            let (mut delta, mut next) = (i64::MAX, 0);
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost[row - 1][j - 1] - u[row] - v[j];
+                if reduced < slack[j] {
+                    slack[j] = reduced;
+                    way[j] = column;
+                }
+                if slack[j] < delta {
+                    delta = slack[j];
+                    next = j;
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[matched[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    slack[j] -= delta;
+                }
+            }
+            column = next;
+        }
+        // Flip the augmenting path back to the start
+        while column != 0 {
+            let previous = way[column];
+            matched[column] = matched[previous];
+            column = previous;
+        }
+    }
+    let mut assignment = vec![0; n];
+    for (j, i) in matched.into_iter().enumerate().skip(1) {
+        assignment[i - 1] = j - 1;
+    }
+    assignment
+}
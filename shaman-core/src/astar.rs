@@ -0,0 +1,886 @@
+//! Low level path planning for a single robot
+use std::{
+    collections::{BinaryHeap, VecDeque},
+    iter::Sum,
+    ops::{AddAssign, Range, RangeFrom},
+    time::Duration,
+};
+
+use itertools::Itertools;
+use miette::{LabeledSpan, SourceSpan};
+use ordered_float::OrderedFloat;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    Time,
+    error::ShamanError,
+    layout::{Layout, Vertex},
+    robot::Location,
+    route::Route,
+};
+
+/// A priority constraint, which this [crate::astar::solve()] needs to respect
+#[derive(Debug, Clone, Default)]
+pub struct RightOfWay {
+    temporary: FxHashSet<(Time, Vertex)>,
+    moves: FxHashSet<(Time, Vertex, Vertex)>,
+    permanent: Vec<(RangeFrom<Time>, Vertex)>,
+    /// Moves the robot itself must not take, unlike the [Self::moves] of everybody else
+    forbidden: FxHashSet<(Time, Vertex, Vertex)>,
+}
+
+impl RightOfWay {
+    /// Reserve all `cells` for the whole time `window`
+    pub fn reserve(&mut self, cells: &[Vertex], window: Range<Time>) {
+        self.temporary
+            .extend(window.cartesian_product(cells.iter().copied()));
+    }
+
+    /// All cells reserved with [Self::reserve] (or forbidden) by the timestep they are
+    pub(crate) fn reserved(&self) -> impl Iterator<Item = (Time, Vertex)> + '_ {
+        self.temporary.iter().copied()
+    }
+
+    /// Forbid entering or staying on `v` at `time`
+    pub(crate) fn forbid(&mut self, time: Time, v: Vertex) {
+        self.temporary.insert((time, v));
+    }
+
+    /// Forbid moving from `from` to `to` between `time` and `time + 1`
+    pub(crate) fn forbid_move(&mut self, time: Time, from: Vertex, to: Vertex) {
+        self.forbidden.insert((time, from, to));
+    }
+
+    /// Whether `v` is occupied by someone else at `time`
+    pub(crate) fn blocks(&self, time: Time, v: Vertex) -> bool {
+        self.temporary.contains(&(time, v))
+            || self
+                .permanent
+                .iter()
+                .any(|(range, p)| *p == v && range.contains(&time))
+    }
+
+    /// Timestep from which on nothing is reserved at single times anymore, like
+    /// [Layout::settled] for closures
+    pub(crate) fn settled(&self) -> Time {
+        self.temporary
+            .iter()
+            .map(|(t, _)| t + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Whether `v` is occupied by someone else at any time after `time`, so a robot can't
+    /// stay there forever
+    pub(crate) fn blocks_after(&self, time: Time, v: Vertex) -> bool {
+        self.temporary.iter().any(|(t, p)| *t > time && *p == v)
+            || self.permanent.iter().any(|(_, p)| *p == v)
+    }
+
+    /// Whether moving from `from` to `to` between `now` and `now + 1` is forbidden or runs into
+    /// someone else, who either moves the opposite way or crosses the same diagonal
+    pub(crate) fn swaps(&self, now: Time, from: Vertex, to: Vertex) -> bool {
+        let (a, b) = (Vertex::new(from.x, to.y), Vertex::new(to.x, from.y));
+        let diagonal = from.x != to.x && from.y != to.y;
+        self.forbidden.contains(&(now, from, to))
+            || self.moves.contains(&(now, to, from))
+            || diagonal && (self.moves.contains(&(now, a, b)) || self.moves.contains(&(now, b, a)))
+    }
+
+    /// When each cell is occupied by someone else: at single times & forever from some time on
+    pub(crate) fn occupancy(&self) -> FxHashMap<Vertex, (Vec<Time>, Option<Time>)> {
+        let mut occupancy = FxHashMap::<Vertex, (Vec<Time>, Option<Time>)>::default();
+        for (t, v) in &self.temporary {
+            occupancy.entry(*v).or_default().0.push(*t);
+        }
+        for (range, v) in &self.permanent {
+            let from = &mut occupancy.entry(*v).or_default().1;
+            *from = Some(from.map_or(range.start, |f| f.min(range.start)));
+        }
+        occupancy
+    }
+
+    /// Like following `route`, but vanishing from the map after its last location instead of
+    /// occupying it forever, e.g. when dropping into a chute
+    pub fn passing(route: &Route) -> Self {
+        Self {
+            temporary: route.iter().map(|l| (l.time, l.position)).collect(),
+            moves: route
+                .iter()
+                .tuple_windows()
+                .map(|(a, b)| (a.time, a.position, b.position))
+                .collect(),
+            permanent: vec![],
+            forbidden: Default::default(),
+        }
+    }
+}
+
+impl AddAssign for RightOfWay {
+    fn add_assign(&mut self, other: Self) {
+        self.temporary.extend(other.temporary);
+        self.moves.extend(other.moves);
+        self.permanent.extend(other.permanent);
+        self.forbidden.extend(other.forbidden);
+    }
+}
+
+impl Sum for RightOfWay {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(RightOfWay::default(), |mut acc, other| {
+            acc += other;
+            acc
+        })
+    }
+}
+
+impl From<&Route> for RightOfWay {
+    fn from(route: &Route) -> Self {
+        Self {
+            temporary: route
+                .iter()
+                .rev()
+                .skip(1)
+                .map(|l| (l.time, l.position))
+                .collect(),
+            moves: route
+                .iter()
+                .tuple_windows()
+                .map(|(a, b)| (a.time, a.position, b.position))
+                .collect(),
+            permanent: route
+                .iter()
+                .rev()
+                .take(1)
+                .map(|l| (l.time.., l.position))
+                .collect(),
+            forbidden: Default::default(),
+        }
+    }
+}
+
+/// Possible action the robot can take on a single location
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    #[default]
+    Wait,
+    N,
+    W,
+    E,
+    S,
+    NE,
+    NW,
+    SE,
+    SW,
+    /// Rotating in place by 90° (45° when moving diagonally), see [PlannerConfig::rotate_cost]
+    Rotate(Rotation),
+}
+
+/// Way a robot rotates in place, see [Action::Rotate]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    Clockwise,
+    Counterclockwise,
+}
+
+impl Action {
+    /// Waiting & moving to any of the four adjacent cells
+    pub const ALL: [Self; 5] = [Self::N, Self::W, Self::S, Self::E, Self::Wait];
+    /// Moving to any of the four diagonally adjacent cells, see [MovementModel::EightConnected]
+    pub const DIAGONAL: [Self; 4] = [Self::NE, Self::NW, Self::SE, Self::SW];
+    /// All headings clockwise, 45° apart
+    pub(crate) const COMPASS: [Self; 8] = [
+        Self::N,
+        Self::NE,
+        Self::E,
+        Self::SE,
+        Self::S,
+        Self::SW,
+        Self::W,
+        Self::NW,
+    ];
+
+    /// Where a robot located at `v` ends up after taking this action
+    pub fn apply(&self, v: Vertex) -> Vertex {
+        v + self.direction()
+    }
+
+    pub fn direction(&self) -> Vertex {
+        match self {
+            Self::Wait | Self::Rotate(_) => Vertex::new(0, 0),
+            Self::N => Vertex::new(0, -1),
+            Self::S => Vertex::new(0, 1),
+            Self::W => Vertex::new(-1, 0),
+            Self::E => Vertex::new(1, 0),
+            Self::NE => Vertex::new(1, -1),
+            Self::NW => Vertex::new(-1, -1),
+            Self::SE => Vertex::new(1, 1),
+            Self::SW => Vertex::new(-1, 1),
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        Self::DIAGONAL.contains(self)
+    }
+
+    /// Whether this action moves to another cell, i.e. neither waits nor rotates in place
+    pub fn is_move(&self) -> bool {
+        self.direction() != Vertex::new(0, 0)
+    }
+
+    /// Arrow pointing into the direction of this action, `None` when waiting
+    pub fn arrow(&self) -> Option<char> {
+        let i = Self::COMPASS.iter().position(|a| a == self)?;
+        Some(['↑', '↗', '→', '↘', '↓', '↙', '←', '↖'][i])
+    }
+
+    /// Headings a robot facing this way reaches by a single rotation in place, i.e. by 90° or
+    /// by 45° if it moves diagonally. Robots of unknown heading ([Self::Wait]) need none
+    pub(crate) fn rotations(&self, movement: MovementModel) -> Vec<(Rotation, Self)> {
+        [Rotation::Clockwise, Rotation::Counterclockwise]
+            .into_iter()
+            .filter_map(|r| Some((r, self.rotated(r, movement)?)))
+            .collect()
+    }
+
+    /// Heading a robot facing this way has after rotating in place once, `None` if unknown
+    pub(crate) fn rotated(&self, rotation: Rotation, movement: MovementModel) -> Option<Self> {
+        let i = Self::COMPASS.iter().position(|a| a == self)?;
+        let step = match rotation {
+            Rotation::Clockwise => movement.rotation_step(),
+            Rotation::Counterclockwise => 8 - movement.rotation_step(),
+        };
+        Some(Self::COMPASS[(i + step) % 8])
+    }
+
+    /// Shorter way to rotate from this heading towards `other`, clockwise when turning around,
+    /// `None` if there's nothing to rotate or either heading is unknown ([Self::Wait])
+    pub(crate) fn rotation_to(&self, other: Self) -> Option<Rotation> {
+        let index = |a: &Self| Self::COMPASS.iter().position(|c| c == a);
+        match (index(&other)? + 8 - index(self)?) % 8 {
+            0 => None,
+            1..=4 => Some(Rotation::Clockwise),
+            _ => Some(Rotation::Counterclockwise),
+        }
+    }
+
+    /// How many rotations in place it takes to turn from this heading to `other`, `0` if either
+    /// is unknown ([Self::Wait])
+    pub(crate) fn rotations_to(&self, other: Self, movement: MovementModel) -> usize {
+        let index = |a: &Self| Self::COMPASS.iter().position(|c| c == a);
+        let (Some(i), Some(j)) = (index(self), index(&other)) else {
+            return 0;
+        };
+        let diff = i.abs_diff(j);
+        diff.min(8 - diff).div_ceil(movement.rotation_step())
+    }
+
+    /// Whether a diagonal move from `v` would cut the corner of an obstacle on `layout`, i.e.
+    /// either of the two cells it passes between is blocked
+    pub(crate) fn cuts_corner(&self, v: Vertex, layout: &Layout) -> bool {
+        let d = self.direction();
+        self.is_diagonal()
+            && (layout.is_blocked(v + Vertex::new(d.x, 0))
+                || layout.is_blocked(v + Vertex::new(0, d.y)))
+    }
+
+    /// Cost of taking this action at `here` right after `previous` according to `model`,
+    /// scaled by the [Layout::edge_cost] of the traversed edge
+    pub(crate) fn cost(
+        &self,
+        previous: Self,
+        here: Vertex,
+        layout: &Layout,
+        model: &CostModel,
+    ) -> f32 {
+        let factor = match self {
+            Self::Wait | Self::Rotate(_) => 1.,
+            _ if self.is_diagonal() => {
+                std::f32::consts::SQRT_2 * layout.edge_cost(here, self.apply(here))
+            }
+            _ => layout.edge_cost(here, self.apply(here)),
+        };
+        model.cost(previous, *self) * factor
+    }
+
+    /// Whether taking this action after `previous` changes the direction of movement
+    fn turns_from(&self, previous: Self) -> bool {
+        previous.is_move() && self.is_move() && previous != *self
+    }
+}
+
+/// How costly each [Action] is, depending on the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostModel {
+    /// Standing still for a timestep
+    pub wait: f32,
+    /// Moving in the same direction as before
+    pub straight: f32,
+    /// Starting to move after standing still
+    pub start: f32,
+    /// Changing direction
+    pub turn: f32,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            wait: 1.2,    // slightly penalizing to encourage movement
+            straight: 1., // moving in the same direction
+            start: 1.5,   // Was stopped, now starting to move
+            turn: 3.,     // Changing direction, this is costly!
+        }
+    }
+}
+
+/// Kinds of steps a [CostModel] distinguishes, in the order of its fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    Wait,
+    Straight,
+    Start,
+    Turn,
+}
+
+impl Step {
+    /// Which kind of step taking `action` right after `previous` is
+    pub(crate) fn of(previous: Action, action: Action) -> Self {
+        match (previous, action) {
+            (_, action) if !action.is_move() => Self::Wait,
+            (a, b) if a == b => Self::Straight,
+            (a, _) if !a.is_move() => Self::Start,
+            _ => Self::Turn,
+        }
+    }
+}
+
+impl CostModel {
+    /// Cost of taking `action` right after `previous`
+    pub fn cost(&self, previous: Action, action: Action) -> f32 {
+        match Step::of(previous, action) {
+            Step::Wait => self.wait,
+            Step::Straight => self.straight,
+            Step::Start => self.start,
+            Step::Turn => self.turn,
+        }
+    }
+
+    /// Cost of taking all `actions` in a row, starting from standstill
+    pub fn total(&self, actions: &[Action]) -> f32 {
+        std::iter::once(Action::Wait)
+            .chain(actions.iter().copied())
+            .tuple_windows()
+            .map(|(previous, action)| self.cost(previous, action))
+            .sum()
+    }
+}
+
+impl TryFrom<(Vertex, Vertex)> for Action {
+    type Error = ShamanError;
+
+    /// Find the action that leads from the first to the second vertex
+    fn try_from((from, to): (Vertex, Vertex)) -> Result<Self, Self::Error> {
+        Self::ALL
+            .iter()
+            .chain(&Self::DIAGONAL)
+            .copied()
+            .find(|a| a.apply(from) == to)
+            .ok_or(ShamanError::NotAdjacent { from, to })
+    }
+}
+
+/// How [solve()] estimates the remaining cost to the goal. All of them never overestimate,
+/// as long as no edge is cheaper than moving straight, so the planned routes are optimal
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Heuristic {
+    /// Horizontal plus vertical distance, ignoring obstacles
+    #[default]
+    Manhattan,
+    /// Straight line distance, ignoring obstacles. Less informed than [Self::Manhattan]
+    Euclidean,
+    /// Length of the shortest path around obstacles, computed once per goal & layout
+    TrueDistance,
+}
+
+impl Heuristic {
+    /// Lower bound of the cost to move from `from` to `goal` on `layout`
+    pub fn estimate(&self, layout: &Layout, from: Vertex, goal: Vertex) -> f32 {
+        let straight = CostModel::default().straight;
+        let distance = match self {
+            Self::Manhattan => from.manhattan_distance(goal) as f32,
+            Self::Euclidean => from.distance_squared(goal).sqrt(),
+            Self::TrueDistance => layout
+                .distances_to(goal)
+                .get(&from)
+                .map_or(f32::INFINITY, |d| *d as f32),
+        };
+        distance * straight
+    }
+}
+
+/// Which algorithm plans the route of a single robot
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Planner {
+    /// Time expanded A*, which expands every single timestep of waiting
+    #[default]
+    AStar,
+    /// Safe interval path planning, which waits on a cell as long as needed in a single step.
+    /// Much faster on congested maps, but arrives on each cell as early as possible, so it may
+    /// wait where [Self::AStar] would rather take a detour
+    Sipp,
+}
+
+/// Which cells robots can move to in a single timestep
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MovementModel {
+    /// Only horizontally & vertically adjacent cells
+    #[default]
+    FourConnected,
+    /// Also diagonally adjacent cells, as long as neither cell in between is blocked. Diagonal
+    /// moves are `√2` times as costly
+    EightConnected,
+}
+
+impl MovementModel {
+    /// Steps of [Action::COMPASS] between two headings a single rotation turns by
+    fn rotation_step(&self) -> usize {
+        match self {
+            Self::FourConnected => 2,
+            Self::EightConnected => 1,
+        }
+    }
+
+    /// All actions robots may take, including waiting
+    pub fn actions(&self) -> impl Iterator<Item = Action> {
+        let diagonal: &[Action] = match self {
+            Self::FourConnected => &[],
+            Self::EightConnected => &Action::DIAGONAL,
+        };
+        Action::ALL.into_iter().chain(diagonal.iter().copied())
+    }
+}
+
+/// Settings of planning robots, exceeding any limit fails with [ShamanError::BudgetExceeded]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PlannerConfig {
+    pub planner: Planner,
+    pub heuristic: Heuristic,
+    pub movement: MovementModel,
+    /// How many states planning a single route may expand at most
+    pub max_expansions: Option<usize>,
+    /// How long solving all conflicts may take at most
+    pub max_time: Option<Duration>,
+    /// How many timesteps a single route may take at most. [Planner::AStar] defaults to the
+    /// amount of free cells (times the robot's pace), since its routes taking longer wait
+    /// forever or run in circles
+    pub max_route_length: Option<Time>,
+    /// How many nodes of its constraint tree [crate::Algorithm::Cbs] may expand at most,
+    /// `10000` if `None`
+    pub max_nodes: Option<usize>,
+    /// Cost of rotating in place by 90° (45° when moving diagonally), which takes a timestep.
+    /// Robots then only move into the direction they face, like differential drives. `None`
+    /// lets them move into any direction right away
+    pub rotate_cost: Option<f32>,
+    /// How costly each action is
+    pub costs: CostModel,
+    /// Settle for solutions costing more than the cheapest ones in exchange for much faster
+    /// searches, e.g. `1.5`. Both levels get its square root, so their product stays within
+    /// it: low level searches inflate their heuristic by it (weighted A*) and the high level
+    /// expands the ideas with the fewest conflicts among all costing at most that much more
+    /// than the cheapest one (focal search). `None` searches optimally
+    pub suboptimality: Option<f32>,
+}
+
+/// How a single robot moves, beyond the [PlannerConfig] shared by all of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Kinematics {
+    /// Timesteps to move to an adjacent cell, see [crate::Robot::pace]
+    pub pace: Time,
+    /// Direction the robot faces, [Action::Wait] if unknown, see [PlannerConfig::rotate_cost]
+    pub heading: Action,
+}
+
+impl Default for Kinematics {
+    fn default() -> Self {
+        Self {
+            pace: 1,
+            heading: Action::Wait,
+        }
+    }
+}
+
+impl PlannerConfig {
+    /// Lower bound of the cost to move from `from` to `goal`, like [Heuristic::estimate] but
+    /// never overestimating diagonal shortcuts
+    pub(crate) fn estimate(&self, layout: &Layout, from: Vertex, goal: Vertex) -> f32 {
+        // Heuristics assume the default cost model, so scale them to the cheapest move
+        let default = CostModel::default().straight;
+        let unit = self
+            .costs
+            .straight
+            .min(self.costs.start)
+            .min(self.costs.turn)
+            / default;
+        let estimate = self.heuristic.estimate(layout, from, goal) * unit;
+        match self.movement {
+            MovementModel::FourConnected => estimate,
+            MovementModel::EightConnected => {
+                let (dx, dy) = ((from.x - goal.x).abs(), (from.y - goal.y).abs());
+                let octile =
+                    dx.max(dy) as f32 + (std::f32::consts::SQRT_2 - 1.) * dx.min(dy) as f32;
+                estimate.min(octile * default * unit)
+            }
+        }
+    }
+
+    /// Share of [Self::suboptimality] of either level of the search, `1` when searching optimally
+    pub(crate) fn bound(&self) -> f32 {
+        self.suboptimality.unwrap_or(1.).max(1.).sqrt()
+    }
+
+    /// Fail if solving conflicts took longer than [Self::max_time] since `started`
+    pub(crate) fn check_time(
+        &self,
+        layout: &Layout,
+        started: std::time::Instant,
+        progress: impl FnOnce() -> String,
+    ) -> Result<(), ShamanError> {
+        match self.max_time {
+            Some(max) if started.elapsed() > max => Err(ShamanError::BudgetExceeded {
+                src: layout.code(),
+                limit: format!("time limit of {:.3}s", max.as_secs_f32()).into(),
+                progress: progress().into(),
+                route: Box::new([]),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps track of a single route search, to give up once it exceeds the limits of its config
+pub(crate) struct Budget<'a> {
+    config: &'a PlannerConfig,
+    layout: &'a Layout,
+    start: (Vertex, SourceSpan),
+    goal: (Vertex, SourceSpan),
+    /// Timesteps the robot takes per cell, see [crate::Robot::pace]
+    pace: Time,
+    /// Timestep from which on neither the layout nor the constraints change anymore
+    settled: Time,
+    expanded: usize,
+    /// Whether any branch was abandoned for taking too long
+    pruned: bool,
+    /// Distance of the expanded cell closest to the goal
+    closest: usize,
+}
+
+impl<'a> Budget<'a> {
+    pub(crate) fn new(
+        config: &'a PlannerConfig,
+        layout: &'a Layout,
+        start: (Vertex, SourceSpan),
+        goal: (Vertex, SourceSpan),
+        pace: Time,
+        constraint: &RightOfWay,
+    ) -> Self {
+        Self {
+            config,
+            layout,
+            start,
+            goal,
+            pace,
+            settled: layout.settled().max(constraint.settled()),
+            expanded: 0,
+            pruned: false,
+            closest: start.0.manhattan_distance(goal.0),
+        }
+    }
+
+    /// Whether a route arriving at `time` after `departure` is too long to pursue any further
+    pub(crate) fn too_long(&mut self, departure: Time, time: Time) -> bool {
+        let max = self.config.max_route_length.or_else(|| {
+            (self.config.planner == Planner::AStar)
+                .then(|| self.layout.free_cell_count() * self.pace + self.settled)
+        });
+        let too_long = max.is_some_and(|max| time - departure > max);
+        self.pruned |= too_long;
+        too_long
+    }
+
+    /// Count expanding `v`, failing if that exceeds the expansion limit
+    pub(crate) fn expand(&mut self, v: Vertex) -> Result<(), ShamanError> {
+        if let Some(max) = self
+            .config
+            .max_expansions
+            .filter(|max| self.expanded >= *max)
+        {
+            return Err(self.exceeded(format!("limit of {max} expanded states")));
+        }
+        self.expanded += 1;
+        self.closest = self.closest.min(v.manhattan_distance(self.goal.0));
+        Ok(())
+    }
+
+    /// Error of having searched all states, which is a limit only if routes were pruned
+    pub(crate) fn exhausted(&self) -> ShamanError {
+        match self.config.max_route_length.filter(|_| self.pruned) {
+            Some(max) => self.exceeded(format!("route length limit of {max} timesteps")),
+            None => ShamanError::RouteNotFound {
+                src: self.layout.code(),
+                start: self.start.1,
+                goal: self.goal.1,
+            },
+        }
+    }
+
+    fn exceeded(&self, limit: String) -> ShamanError {
+        ShamanError::BudgetExceeded {
+            src: self.layout.code(),
+            limit: limit.into(),
+            progress: format!(
+                "expanding {} states, getting as close as {} cells to the goal",
+                self.expanded, self.closest
+            )
+            .into(),
+            route: Box::new([
+                LabeledSpan::new_with_span(Some("planning from here".into()), self.start.1),
+                LabeledSpan::new_with_span(Some("to here".into()), self.goal.1),
+            ]),
+        }
+    }
+}
+
+impl Drop for Budget<'_> {
+    fn drop(&mut self) {
+        crate::instrumentation::record(|stats| stats.expansions.push(self.expanded));
+    }
+}
+
+/// Plan a route with the planner of `config`, see [solve()] for all other arguments
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn plan(
+    layout: &Layout,
+    start: (Vertex, SourceSpan),
+    goal: (Vertex, SourceSpan),
+    departure: Time,
+    constraint: &RightOfWay,
+    excluded: &FxHashSet<Vertex>,
+    park: bool,
+    kinematics: Kinematics,
+    config: PlannerConfig,
+) -> Result<Route, ShamanError> {
+    let solve = match config.planner {
+        Planner::AStar => solve,
+        Planner::Sipp => crate::sipp::solve,
+    };
+    solve(
+        layout, start, goal, departure, constraint, excluded, park, kinematics, &config,
+    )
+}
+
+/// Priority-aware A*
+///
+/// Plan the shortest path from `start` -> `goal`, departing at time `departure`, avoiding static
+/// obstacles on `layout`, cells it closes for a while and the robot's `excluded` cells.
+/// Also avoid the dynamic obstacle (other robot's path) defined by `constraint`, i.e. by waiting
+/// or rerouting. Robots which `park` on their goal only arrive once nobody passes it anymore.
+/// Moving to an adjacent cell takes the `kinematics`' pace in timesteps, staying on the current
+/// one until then. Robots which need to rotate in place start facing the `kinematics`' heading.
+/// The heuristic of `config` guides the search towards the goal, within the limits of `config`
+#[allow(clippy::too_many_arguments)]
+pub fn solve(
+    layout: &Layout,
+    start: (Vertex, SourceSpan),
+    goal: (Vertex, SourceSpan),
+    departure: Time,
+    constraint: &RightOfWay,
+    excluded: &FxHashSet<Vertex>,
+    park: bool,
+    kinematics: Kinematics,
+    config: &PlannerConfig,
+) -> Result<Route, ShamanError> {
+    let mut budget = Budget::new(config, layout, start, goal, kinematics.pace, constraint);
+    let mut open = BinaryHeap::new();
+    let mut scores = FxHashMap::default();
+    let mut came_from: FxHashMap<State, State> = FxHashMap::default();
+    let s = Location {
+        time: departure,
+        position: start.0,
+    };
+    let oriented = config.rotate_cost.is_some();
+    let heading = match oriented {
+        true => kinematics.heading,
+        false => Action::Wait,
+    };
+    let s = (s, Action::Wait, heading);
+    scores.insert(s, (0.0, 0));
+    open.push(Item {
+        cost: 0.0.into(),
+        turns: 0,
+        state: s,
+    });
+
+    while let Some(item) = open.pop() {
+        let (location, previous_action, heading) = item.state;
+        if budget.too_long(departure, location.time) {
+            // Idea here is, that when we still haven't reached the goal by the time, we could have
+            // potentially reached every free cell in the layout, this branch is either waiting
+            // forever of stuck in a deadlocking loop. Don't pursue it anymore
+            continue;
+        }
+        budget.expand(location.position)?;
+
+        // Parking robots stay on their goal forever, so nobody else may pass it afterwards
+        let stays = || {
+            !constraint.blocks_after(location.time, goal.0)
+                && layout.closes_after(goal.0, location.time).is_none()
+        };
+        if location.position == goal.0 && (!park || stays()) {
+            // Reached goal
+            let mut current = item.state;
+            let mut route = VecDeque::new();
+            let mut rotations = vec![];
+            route.push_back(current.0);
+            while let Some(previous) = came_from.get(&current).copied() {
+                // Slow robots stay on the previous cell until they arrive on the next
+                for time in (previous.0.time + 1..current.0.time).rev() {
+                    route.push_front(Location {
+                        position: previous.0.position,
+                        time,
+                    });
+                }
+                if current.1 == Action::Wait
+                    && let Some(rotation) = previous.2.rotation_to(current.2)
+                {
+                    rotations.push((current.0.time, rotation));
+                }
+                route.push_front(previous.0);
+                current = previous;
+            }
+            let mut route = route.into_iter().collect::<Route>();
+            for (time, rotation) in rotations {
+                route.rotate(time, rotation);
+            }
+            return Ok(route);
+        }
+
+        // Node expansion, rotating in place stays here like waiting, but turns the robot
+        let moves = config.movement.actions().map(|action| (action, None));
+        let rotations = heading
+            .rotations(config.movement)
+            .into_iter()
+            .filter(|_| oriented)
+            .map(|(_, h)| (Action::Wait, Some(h)));
+        for (action, rotation) in moves.chain(rotations) {
+            // Oriented robots only move into the direction they face
+            if oriented && heading != Action::Wait && ![Action::Wait, heading].contains(&action) {
+                continue;
+            }
+            let now = location.time;
+            let here = location.position;
+            let pace = match action {
+                Action::Wait => 1,
+                _ => kinematics.pace,
+            };
+            let then = now + pace;
+            let there = action.apply(here);
+            let candidate = Location {
+                position: there,
+                time: then,
+            };
+            if layout.is_blocked_at(there, then) || excluded.contains(&there) {
+                // candidate not reachable
+                continue;
+            }
+            if action.cuts_corner(here, layout) {
+                continue;
+            }
+
+            // Slow robots need to stay here until they leave
+            if (now + 1..then).any(|t| constraint.blocks(t, here) || layout.is_blocked_at(here, t))
+            {
+                continue;
+            }
+
+            // Same location constraint check
+            if constraint.blocks(then, there) {
+                // candidate would collide with a priority constraint in the future
+                continue;
+            }
+
+            // Swapping location constraint check
+            if constraint.swaps(then - 1, here, there) {
+                // candidate would switch location with the priority constraint
+                continue;
+            }
+            let heading = match (rotation, action) {
+                (Some(rotated), _) => rotated,
+                (None, Action::Wait) => heading,
+                (None, action) if oriented => action,
+                (None, _) => Action::Wait,
+            };
+            let candidate = (candidate, action, heading);
+            let (g, turns) = scores[&item.state];
+            // Staying here before moving costs like waiting
+            let previous_action = match pace {
+                1 => previous_action,
+                _ => Action::Wait,
+            };
+            let dwell =
+                Action::Wait.cost(Action::Wait, here, layout, &config.costs) * (pace - 1) as f32;
+            let cost = match (rotation, config.rotate_cost) {
+                (Some(_), Some(rotate)) => rotate,
+                _ => dwell + action.cost(previous_action, here, layout, &config.costs),
+            };
+            let tentative_g = g + cost;
+            let tentative_turns = turns + action.turns_from(previous_action) as usize;
+            // Among equally costly predecessors, prefer the one with fewer direction changes
+            if scores.get(&candidate).is_none_or(|(g, turns)| {
+                tentative_g < *g || (tentative_g == *g && tentative_turns < *turns)
+            }) {
+                scores.insert(candidate, (tentative_g, tentative_turns));
+                // valid candidate
+                let h = config.estimate(layout, there, goal.0) * config.bound();
+                came_from.insert(candidate, item.state);
+                let item = Item {
+                    cost: OrderedFloat(tentative_g + h),
+                    turns: tentative_turns,
+                    state: candidate,
+                };
+                open.push(item);
+            }
+        }
+    }
+
+    Err(budget.exhausted())
+}
+
+/// Location of a search state, with the action leading there, since it determines the cost of
+/// the next, and the heading, see [PlannerConfig::rotate_cost]
+type State = (Location, Action, Action);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Item {
+    state: State,
+    cost: OrderedFloat<f32>,
+    /// Direction changes so far, to break ties between equally costly items
+    turns: usize,
+}
+
+impl Ord for Item {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // reverse for min heap
+        other
+            .cost
+            .cmp(&self.cost)
+            .then_with(|| other.turns.cmp(&self.turns))
+    }
+}
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
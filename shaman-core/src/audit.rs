@@ -0,0 +1,172 @@
+//! Record of every decision [Pbs](crate::Pbs) made while solving, e.g. to certify its behavior
+//!
+//! Each conflict PBS finds branches into both priority orderings of the two robots involved,
+//! of which only the feasible ones are pursued further, cheapest first.
+use std::fmt::Display;
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    Shaman,
+    json::{Json, ToJson},
+    layout::Vertex,
+};
+
+/// How PBS resolved a single conflict
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    /// Cost of the idea in which the conflict was found
+    pub cost: f32,
+    /// The conflicting robots
+    pub robots: (char, char),
+    /// Where the two robots collide
+    pub cells: Vec<Vertex>,
+    /// Each tried `(boss, subordinate)` ordering with the cost of the resulting idea, `None`
+    /// if the subordinate could not evade the boss
+    pub branches: Vec<(char, char, Option<f32>)>,
+}
+
+/// All decisions of solving a scenario, see [Shaman::audit]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Audit {
+    /// In the order they were made
+    pub decisions: Vec<Decision>,
+    /// Cost of the accepted conflict free idea, `None` if the scenario wasn't solved by PBS
+    pub solution: Option<f32>,
+    /// Whether PBS ran out of time & settled for the cheapest conflict free idea found so far,
+    /// instead of resolving all cheaper ones first
+    pub suboptimal: bool,
+}
+
+impl Shaman {
+    /// Decisions made while solving this scenario
+    pub fn audit(&self) -> &Audit {
+        &self.audit
+    }
+}
+
+impl Audit {
+    /// Restore an audit from its [ToJson] export
+    pub fn from_json(json: &Json) -> Result<Self> {
+        let invalid = |what| miette!("Audit has an invalid {what}");
+        let name = |json: Option<&Json>| {
+            json.and_then(Json::as_str)
+                .and_then(|n| n.chars().exactly_one().ok())
+                .ok_or_else(|| invalid("robot name"))
+        };
+        let decisions = json
+            .get("decisions")
+            .and_then(Json::as_array)
+            .ok_or_else(|| invalid("list of decisions"))?
+            .iter()
+            .map(|decision| {
+                let cost = decision.get("cost").and_then(Json::as_f64);
+                let robots = decision
+                    .get("robots")
+                    .and_then(Json::as_array)
+                    .ok_or_else(|| invalid("pair of robots"))?;
+                let cells = decision
+                    .get("cells")
+                    .and_then(Json::as_array)
+                    .ok_or_else(|| invalid("list of cells"))?
+                    .iter()
+                    .map(|cell| {
+                        let coordinate = |c| cell.get(c).and_then(Json::as_f64).map(|c| c as i32);
+                        Some(Vertex::new(coordinate("x")?, coordinate("y")?))
+                    })
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| invalid("cell"))?;
+                let branches = decision
+                    .get("branches")
+                    .and_then(Json::as_array)
+                    .ok_or_else(|| invalid("list of branches"))?
+                    .iter()
+                    .map(|branch| {
+                        let cost = branch.get("cost").and_then(Json::as_f64);
+                        Ok((
+                            name(branch.get("boss"))?,
+                            name(branch.get("subordinate"))?,
+                            cost.map(|c| c as f32),
+                        ))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Decision {
+                    cost: cost.ok_or_else(|| invalid("cost"))? as f32,
+                    robots: (name(robots.first())?, name(robots.get(1))?),
+                    cells,
+                    branches,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let solution = json.get("solution").and_then(Json::as_f64);
+        let suboptimal = json.get("suboptimal").and_then(Json::as_bool);
+        Ok(Self {
+            decisions,
+            solution: solution.map(|c| c as f32),
+            suboptimal: suboptimal.unwrap_or_default(),
+        })
+    }
+}
+
+impl ToJson for Decision {
+    fn to_json(&self) -> Json {
+        let branches = self.branches.iter().map(|(boss, subordinate, cost)| {
+            Json::object([
+                ("boss", boss.to_json()),
+                ("subordinate", subordinate.to_json()),
+                ("cost", cost.to_json()),
+            ])
+        });
+        Json::object([
+            ("cost", self.cost.to_json()),
+            ("robots", Json::array([self.robots.0, self.robots.1])),
+            ("cells", Json::array(&self.cells)),
+            ("branches", Json::array(branches)),
+        ])
+    }
+}
+
+impl ToJson for Audit {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("decisions", Json::array(&self.decisions)),
+            ("solution", self.solution.to_json()),
+            ("suboptimal", self.suboptimal.to_json()),
+        ])
+    }
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (a, b) = self.robots;
+        let cells = self.cells.iter().unique().join(" ");
+        writeln!(f, "{a} × {b} at {cells} (cost {:.1})", self.cost)?;
+        for (boss, subordinate, cost) in &self.branches {
+            let outcome = cost.map_or("infeasible".to_string(), |c| format!("cost {c:.1}"));
+            writeln!(f, "    {subordinate} yields to {boss}: {outcome}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Audit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, decision) in self.decisions.iter().enumerate() {
+            write!(f, "#{:<3} {decision}", i + 1)?;
+        }
+        match self.solution {
+            Some(cost) if self.suboptimal => writeln!(
+                f,
+                "timed out, settled for cost {cost:.1} after {} decision(s)",
+                self.decisions.len()
+            ),
+            Some(cost) => writeln!(
+                f,
+                "solved with cost {cost:.1} after {} decision(s)",
+                self.decisions.len()
+            ),
+            None => writeln!(f, "not solved"),
+        }
+    }
+}
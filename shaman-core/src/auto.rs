@@ -0,0 +1,53 @@
+//! Picking a solver for each scenario from its features, see [Algorithm::Auto]
+use std::time::Duration;
+
+use miette::Result;
+
+use crate::{Algorithm, Rating, Shaman};
+
+/// CBS only pays off up to this many robots, its constraint tree grows too fast beyond
+const CBS_ROBOTS: usize = 6;
+/// How long CBS may take, unless the planner config limits the time already
+const CBS_TIME: Duration = Duration::from_secs(2);
+
+impl Shaman {
+    /// The algorithm best suited to solve this scenario. Robots whose shortest paths share no
+    /// cell get [Algorithm::Prioritized], which plans each just once. Few robots sharing
+    /// corridors get [Algorithm::Cbs], where the fixed priorities of PBS easily run out of
+    /// ideas. Everything else gets the faster [Algorithm::Pbs]
+    pub fn recommend_algorithm(&self) -> Algorithm {
+        let difficulty = self.difficulty();
+        if difficulty.overlap == 0. {
+            Algorithm::Prioritized
+        } else if self.robots.len() <= CBS_ROBOTS
+            && (difficulty.contention > 0. || difficulty.rating() > Rating::Easy)
+        {
+            Algorithm::Cbs
+        } else {
+            Algorithm::Pbs
+        }
+    }
+
+    /// Solve with the [Self::recommend_algorithm], falling back to PBS or CBS if that fails.
+    /// CBS gives up after [CBS_TIME], unless the planner config sets a time limit
+    pub(crate) fn solve_auto(self) -> Result<Self> {
+        let cbs = || -> Result<Self> {
+            let mut shaman = self.clone();
+            shaman.planner.max_time = shaman.planner.max_time.or(Some(CBS_TIME));
+            let mut solved = shaman.solve_with(Algorithm::Cbs)?;
+            solved.planner = self.planner;
+            Ok(solved)
+        };
+        match self.recommend_algorithm() {
+            Algorithm::Prioritized => self
+                .clone()
+                .solve_with(Algorithm::Prioritized)
+                .or_else(|_| self.clone().solve_with(Algorithm::Pbs)),
+            Algorithm::Cbs => cbs().or_else(|_| self.clone().solve_with(Algorithm::Pbs)),
+            _ if self.robots.len() <= CBS_ROBOTS => {
+                self.clone().solve_with(Algorithm::Pbs).or_else(|_| cbs())
+            }
+            _ => self.clone().solve_with(Algorithm::Pbs),
+        }
+    }
+}
@@ -0,0 +1,689 @@
+//! Programmatic construction of scenarios
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{RangeBounds, RangeInclusive},
+};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    Shaman, Time,
+    layout::{Vertex, inclusive},
+    parser,
+    rng::{RandomSource, Rng},
+    robot::NAMES,
+};
+
+/// Strategy where to put randomly placed robots and their goals
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Placement {
+    /// Anywhere on free space
+    #[default]
+    Random,
+    /// As close as possible to the four corners of the layout
+    Corners,
+    /// As close as possible to the border of the layout
+    Edges,
+}
+
+/// Build a [Shaman] scenario cell by cell instead of drawing a map file
+#[derive(Debug, Clone, Default)]
+pub struct ShamanBuilder {
+    width: usize,
+    height: usize,
+    obstacles: FxHashSet<Vertex>,
+    robots: Vec<(char, Vertex, Option<Vertex>)>,
+    weights: FxHashMap<char, f32>,
+    paces: FxHashMap<char, Time>,
+    classes: FxHashMap<char, u32>,
+    fleets: FxHashMap<char, String>,
+    exclusions: FxHashMap<char, FxHashSet<Vertex>>,
+    capacities: FxHashMap<Vertex, usize>,
+    windows: FxHashMap<Vertex, (Time, Time)>,
+    patrols: FxHashMap<char, (Vec<Vertex>, usize)>,
+    vias: FxHashMap<char, Vec<Vertex>>,
+    edge_costs: FxHashMap<(Vertex, Vertex), f32>,
+    closures: Vec<(Vertex, RangeInclusive<Time>)>,
+    /// How far robots keep away from obstacles, see [Self::inflate]
+    inflation: usize,
+    placement: Placement,
+    anonymous: bool,
+    settings: Vec<(String, String)>,
+}
+
+impl From<&Shaman> for ShamanBuilder {
+    fn from(shaman: &Shaman) -> Self {
+        let layout = &shaman.layout;
+        Self {
+            width: layout.width(),
+            height: layout.height(),
+            obstacles: (0..layout.width() as i32)
+                .cartesian_product(0..layout.height() as i32)
+                .map(|(x, y)| Vertex::new(x, y))
+                .filter(|v| layout.is_blocked(*v) && !layout.inflated().contains(v))
+                .collect(),
+            robots: shaman
+                .robots
+                .values()
+                .sorted_by_key(|r| r.name())
+                .map(|r| (r.name(), r.position().0, r.goal().map(|(g, _)| g)))
+                .collect(),
+            weights: shaman
+                .robots
+                .values()
+                .filter(|r| r.weight() != 1.)
+                .map(|r| (r.name(), r.weight()))
+                .collect(),
+            paces: shaman
+                .robots
+                .values()
+                .filter(|r| r.pace() != 1)
+                .map(|r| (r.name(), r.pace()))
+                .collect(),
+            classes: shaman
+                .robots
+                .values()
+                .filter_map(|r| Some((r.name(), r.class()?)))
+                .collect(),
+            fleets: shaman
+                .robots
+                .values()
+                .filter_map(|r| Some((r.name(), r.fleet()?.to_string())))
+                .collect(),
+            exclusions: shaman
+                .robots
+                .values()
+                .filter(|r| !r.excluded().is_empty())
+                .map(|r| (r.name(), r.excluded().clone()))
+                .collect(),
+            capacities: shaman.capacities.clone(),
+            windows: shaman
+                .robots
+                .values()
+                .filter_map(|r| Some((r.goal()?.0, r.window()?)))
+                .collect(),
+            patrols: shaman
+                .robots
+                .values()
+                .filter_map(|r| {
+                    let (waypoints, laps) = r.patrol()?;
+                    Some((r.name(), (waypoints.to_vec(), laps)))
+                })
+                .collect(),
+            vias: shaman
+                .robots
+                .values()
+                .filter(|r| !r.waypoints().is_empty())
+                .map(|r| (r.name(), r.waypoints().to_vec()))
+                .collect(),
+            edge_costs: layout.edge_costs().collect(),
+            closures: layout.closures().collect(),
+            inflation: layout.inflation(),
+            placement: Placement::default(),
+            anonymous: shaman.anonymous,
+            settings: shaman.settings.clone(),
+        }
+    }
+}
+
+impl ShamanBuilder {
+    /// Start from an empty layout without obstacles and robots
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// Mark the cell `v` as obstacle
+    pub fn block(mut self, v: Vertex) -> Self {
+        self.obstacles.insert(v);
+        self
+    }
+
+    /// Add a robot `name` starting at `start` and driving to `goal`
+    pub fn robot(mut self, name: char, start: Vertex, goal: Vertex) -> Self {
+        self.robots.retain(|(n, _, _)| *n != name);
+        self.robots.push((name, start, Some(goal)));
+        self
+    }
+
+    /// Make delays of robot `name` `weight` times as costly, which must be positive & finite for
+    /// [Self::build] to succeed
+    pub fn weight(mut self, name: char, weight: f32) -> Self {
+        self.weights.insert(name, weight);
+        self
+    }
+
+    /// Make robot `name` take `pace` timesteps to move to an adjacent cell
+    pub fn pace(mut self, name: char, pace: Time) -> Self {
+        self.paces.insert(name, pace);
+        self
+    }
+
+    /// Put robot `name` into priority `class`
+    pub fn class(mut self, name: char, class: u32) -> Self {
+        self.classes.insert(name, class);
+        self
+    }
+
+    /// Let robot `name` visit `waypoints` in this order on its way to its goal, see
+    /// [crate::Robot::waypoints]
+    pub fn via(mut self, name: char, waypoints: &[Vertex]) -> Self {
+        self.vias.insert(name, waypoints.to_vec());
+        self
+    }
+
+    /// Let robot `name`, which has no goal, patrol `waypoints` for `laps`, see
+    /// [crate::Robot::patrol]
+    pub fn patrol(mut self, name: char, waypoints: &[Vertex], laps: usize) -> Self {
+        self.patrols.insert(name, (waypoints.to_vec(), laps));
+        self
+    }
+
+    /// Tag robot `name` as part of `fleet`, see [crate::Robot::fleet]
+    pub fn fleet(mut self, name: char, fleet: &str) -> Self {
+        self.fleets.insert(name, fleet.to_string());
+        self
+    }
+
+    /// Forbid robot `name` to enter `v`
+    pub fn exclude(mut self, name: char, v: Vertex) -> Self {
+        self.exclusions.entry(name).or_default().insert(v);
+        self
+    }
+
+    /// Make moving from `from` to the adjacent `to` `cost` times as costly, but not vice versa.
+    /// [Self::build] fails for factors below `1` or infinite ones
+    pub fn edge_cost(mut self, from: Vertex, to: Vertex, cost: f32) -> Self {
+        self.edge_costs.insert((from, to), cost);
+        self
+    }
+
+    /// Block the free cell `v` during `window` only, e.g. `10..` for a door closing at
+    /// timestep 10, see [Shaman::close]
+    pub fn close(mut self, v: Vertex, window: impl RangeBounds<Time>) -> Self {
+        self.closures.extend(inclusive(window).map(|w| (v, w)));
+        self
+    }
+
+    /// Keep robots at least `radius` cells away from obstacles & the map's edge once built,
+    /// see [Shaman::inflate]
+    pub fn inflate(mut self, radius: usize) -> Self {
+        self.inflation += radius;
+        self
+    }
+
+    /// Make the goal at `v` a drop-off serving up to `capacity` robots, which may share it
+    pub fn capacity(mut self, v: Vertex, capacity: usize) -> Self {
+        self.capacities.insert(v, capacity);
+        self
+    }
+
+    /// Open the goal at `v` only from timestep `open` to `close` (inclusive)
+    pub fn window(mut self, v: Vertex, open: Time, close: Time) -> Self {
+        self.windows.insert(v, (open, close));
+        self
+    }
+
+    /// Make all goals interchangeable, see [Shaman::assign_goals]
+    pub fn anonymous(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+
+    /// Set `key` of the whole scenario to `value`, named like in a [crate::CONFIG_FILE], e.g.
+    /// `setting("movement", "eight-connected")`, see [Shaman::settings]
+    pub fn setting(mut self, key: &str, value: &str) -> Self {
+        self.settings.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Remove all robots defined so far
+    pub fn clear_robots(mut self) -> Self {
+        self.robots.clear();
+        self.weights.clear();
+        self.paces.clear();
+        self.classes.clear();
+        self.fleets.clear();
+        self.exclusions.clear();
+        self
+    }
+
+    /// Remove robot `name` with everything specific to it
+    pub fn remove_robot(mut self, name: char) -> Self {
+        self.robots.retain(|(n, _, _)| *n != name);
+        self.weights.remove(&name);
+        self.paces.remove(&name);
+        self.classes.remove(&name);
+        self.fleets.remove(&name);
+        self.exclusions.remove(&name);
+        self
+    }
+
+    /// Cut row `y` out of the layout, moving all rows below up by one. Robots starting or
+    /// driving to the row are removed
+    pub fn remove_row(mut self, y: i32) -> Self {
+        self.height = self.height.saturating_sub(1);
+        self.project(|v| match v.y.cmp(&y) {
+            Ordering::Less => Some(v),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(Vertex::new(v.x, v.y - 1)),
+        })
+    }
+
+    /// Cut column `x` out of the layout, moving all columns right of it left by one. Robots
+    /// starting or driving to the column are removed
+    pub fn remove_column(mut self, x: i32) -> Self {
+        self.width = self.width.saturating_sub(1);
+        self.project(|v| match v.x.cmp(&x) {
+            Ordering::Less => Some(v),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(Vertex::new(v.x - 1, v.y)),
+        })
+    }
+
+    /// Move every cell to where `f` maps it, dropping everything on cells mapped to `None`
+    fn project(mut self, f: impl Fn(Vertex) -> Option<Vertex>) -> Self {
+        let removed = self
+            .robots
+            .iter()
+            .filter(|(_, start, goal)| f(*start).is_none() || goal.is_some_and(|g| f(g).is_none()))
+            .map(|(name, _, _)| *name)
+            .collect_vec();
+        for name in removed {
+            self = self.remove_robot(name);
+        }
+        for (_, start, goal) in &mut self.robots {
+            *start = f(*start).unwrap();
+            *goal = goal.and_then(&f);
+        }
+        self.obstacles = self.obstacles.iter().filter_map(|v| f(*v)).collect();
+        for cells in self.exclusions.values_mut() {
+            *cells = cells.iter().filter_map(|v| f(*v)).collect();
+        }
+        self.capacities = std::mem::take(&mut self.capacities)
+            .into_iter()
+            .filter_map(|(v, k)| Some((f(v)?, k)))
+            .collect();
+        self.windows = std::mem::take(&mut self.windows)
+            .into_iter()
+            .filter_map(|(v, w)| Some((f(v)?, w)))
+            .collect();
+        self.edge_costs = std::mem::take(&mut self.edge_costs)
+            .into_iter()
+            .filter_map(|((a, b), c)| Some(((f(a)?, f(b)?), c)))
+            .collect();
+        self.closures = std::mem::take(&mut self.closures)
+            .into_iter()
+            .filter_map(|(v, w)| Some((f(v)?, w)))
+            .collect();
+        self
+    }
+
+    /// Which [Placement] strategy [Self::random_robots] should use
+    pub fn placement(mut self, placement: Placement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    /// Replace all robots by `n` new ones, whose starts & goals are distinct free cells
+    /// all reachable from each other. The same `seed` always yields the same scenario
+    pub fn random_robots(self, n: usize, seed: u64) -> Result<Self> {
+        self.random_robots_with(n, &mut Rng::new(seed))
+    }
+
+    /// Like [Self::random_robots], but drawing from `rng`
+    pub fn random_robots_with(mut self, n: usize, rng: &mut impl RandomSource) -> Result<Self> {
+        if n > NAMES.count() {
+            return Err(miette!(
+                "Cannot place {n} robots, only {} names are available",
+                NAMES.count()
+            ));
+        }
+        let layout = self.clone().clear_robots().build()?.layout;
+
+        // Pick the biggest connected area of free space, so any start can reach any goal
+        let mut unvisited = layout
+            .free_cells()
+            .sorted_by_key(|v| (v.y, v.x))
+            .collect_vec();
+        let mut area = FxHashSet::default();
+        while let Some(v) = unvisited.pop() {
+            let component = layout.reachable(v);
+            unvisited.retain(|c| !component.contains(c));
+            if component.len() > area.len() {
+                area = component;
+            }
+        }
+        if area.len() < 2 * n {
+            return Err(miette!(
+                "Cannot place {n} robots, the biggest free area has only {} cells",
+                area.len()
+            ));
+        }
+
+        let mut cells = area.into_iter().sorted_by_key(|v| (v.y, v.x)).collect_vec();
+        rng.shuffle(&mut cells);
+        let (w, h) = (self.width as i32 - 1, self.height as i32 - 1);
+        match self.placement {
+            Placement::Random => {}
+            Placement::Corners => {
+                // Round robin over the corners, so consecutive robots end up in different ones
+                let corners = [(0, 0), (w, h), (w, 0), (0, h)].map(|(x, y)| Vertex::new(x, y));
+                let mut taken = FxHashSet::default();
+                let mut ordered = vec![];
+                for corner in corners.iter().cycle().take(2 * n) {
+                    let next = cells
+                        .iter()
+                        .filter(|c| !taken.contains(*c))
+                        .min_by_key(|c| (c.x - corner.x).abs() + (c.y - corner.y).abs())
+                        .copied()
+                        .unwrap();
+                    taken.insert(next);
+                    ordered.push(next);
+                }
+                cells = ordered;
+            }
+            Placement::Edges => {
+                cells.sort_by_key(|v| v.x.min(v.y).min(w - v.x).min(h - v.y));
+            }
+        }
+
+        self.robots = NAMES
+            .zip(cells.iter().copied().tuples())
+            .take(n)
+            .map(|(name, (start, goal))| (name, start, Some(goal)))
+            .collect();
+        Ok(self)
+    }
+
+    /// Relocate `moves` randomly chosen obstacles (except those on the border) or goals to
+    /// random free cells, which aren't any robot's start or goal. The same `seed` always yields
+    /// the same scenario
+    pub fn perturb(self, moves: usize, seed: u64) -> Self {
+        self.perturb_with(moves, &mut Rng::new(seed))
+    }
+
+    /// Like [Self::perturb], but drawing from `rng`
+    pub fn perturb_with(mut self, moves: usize, rng: &mut impl RandomSource) -> Self {
+        for _ in 0..moves {
+            let taken = self
+                .robots
+                .iter()
+                .flat_map(|(_, start, goal)| std::iter::once(*start).chain(*goal))
+                .chain(self.obstacles.iter().copied())
+                .collect::<FxHashSet<_>>();
+            let free = (0..self.width as i32)
+                .cartesian_product(0..self.height as i32)
+                .map(|(x, y)| Vertex::new(x, y))
+                .filter(|v| !taken.contains(v))
+                .collect_vec();
+            // Keep the border intact, so maps stay enclosed
+            let (w, h) = (self.width as i32 - 1, self.height as i32 - 1);
+            let obstacles = self
+                .obstacles
+                .iter()
+                .copied()
+                .filter(|v| v.x > 0 && v.y > 0 && v.x < w && v.y < h)
+                .sorted_by_key(|v| (v.y, v.x))
+                .collect_vec();
+            let goals = self.robots.iter().filter(|(_, _, g)| g.is_some()).count();
+            if free.is_empty() || obstacles.len() + goals == 0 {
+                break;
+            }
+            let target = free[rng.below(free.len())];
+            match rng.below(obstacles.len() + goals) {
+                i if i < obstacles.len() => {
+                    self.obstacles.remove(&obstacles[i]);
+                    self.obstacles.insert(target);
+                }
+                i => {
+                    let (_, _, goal) = self
+                        .robots
+                        .iter_mut()
+                        .filter(|(_, _, g)| g.is_some())
+                        .nth(i - obstacles.len())
+                        .unwrap();
+                    *goal = Some(target);
+                }
+            }
+        }
+        self
+    }
+
+    /// The first robot (by name) driving to each goal, which draws it. All others refer to it
+    /// in the header
+    fn owners(&self) -> FxHashMap<Vertex, char> {
+        let mut owners = FxHashMap::default();
+        for (name, _, goal) in self.robots.iter().sorted_by_key(|(n, _, _)| *n) {
+            if let Some(goal) = goal {
+                owners.entry(*goal).or_insert(*name);
+            }
+        }
+        owners
+    }
+
+    /// Robots staying on their start, which can't draw their goal below themselves
+    fn stays(&self, owners: &FxHashMap<Vertex, char>) -> Vec<char> {
+        self.robots
+            .iter()
+            .filter(|(name, start, goal)| *goal == Some(*start) && owners.get(start) == Some(name))
+            .map(|(name, _, _)| *name)
+            .sorted()
+            .collect()
+    }
+
+    /// Goals drawn on the map by the robot owning them, see [Self::owners]
+    fn drawn_goals(&self, owners: &FxHashMap<Vertex, char>) -> Vec<(char, Vertex)> {
+        let stays = self.stays(owners);
+        self.robots
+            .iter()
+            .filter_map(|(name, _, goal)| Some((*name, (*goal)?)))
+            .filter(|(name, goal)| owners.get(goal) == Some(name) && !stays.contains(name))
+            .sorted_by_key(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Fail if a goal on another robot's start can't be marked from the cell above it, because
+    /// that is off the map, blocked, or taken by a robot or goal itself
+    fn check_goal_markers(&self) -> Result<()> {
+        let goals = self.drawn_goals(&self.owners());
+        let starts = self
+            .robots
+            .iter()
+            .map(|(n, s, _)| (*s, *n))
+            .collect::<FxHashMap<_, _>>();
+        for (name, goal) in &goals {
+            let Some(robot) = starts.get(goal) else {
+                continue;
+            };
+            let above = Vertex::new(goal.x, goal.y - 1);
+            let taken = match starts.get(&above) {
+                _ if above.y < 0 => "off the map".to_string(),
+                _ if self.obstacles.contains(&above) => "blocked".to_string(),
+                Some(other) => format!("taken by robot {other}"),
+                None => match goals.iter().find(|(_, g)| *g == above) {
+                    Some((other, _)) => format!("taken by the goal of robot {other}"),
+                    None => continue,
+                },
+            };
+            return Err(miette!(
+                "Goal of robot {name} at {goal} is where robot {robot} starts, so it's marked \
+                 from the cell above, which is {taken}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parse the drawn scenario, validating and planning it like any map file. Fails if a goal
+    /// on another robot's start can't be marked on the map
+    pub fn build(&self) -> Result<Shaman> {
+        self.check_goal_markers()?;
+        let map = self.to_string();
+        let mut shaman = parser::parse("generated", &map)?;
+        shaman.inflate_layout(self.inflation)?;
+        shaman.plan()?;
+        Ok(shaman)
+    }
+}
+
+/// Draw the scenario in the map file format
+impl Display for ShamanBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let owners = self.owners();
+        let shared = self
+            .robots
+            .iter()
+            .filter_map(|(name, _, goal)| Some((*name, *owners.get(&(*goal)?)?)))
+            .filter(|(name, owner)| name != owner)
+            .sorted()
+            .collect_vec();
+        let capacities = self
+            .capacities
+            .iter()
+            .filter_map(|(v, k)| Some((*owners.get(v)?, *k)))
+            .sorted()
+            .collect_vec();
+        let windows = self
+            .windows
+            .iter()
+            .filter_map(|(v, w)| Some((*owners.get(v)?, *w)))
+            .sorted()
+            .collect_vec();
+
+        let stays = self.stays(&owners);
+
+        let fleets = self
+            .fleets
+            .iter()
+            .map(|(name, fleet)| (fleet, *name))
+            .into_group_map()
+            .into_iter()
+            .map(|(fleet, names)| (fleet, names.into_iter().sorted().join(",")))
+            .sorted()
+            .collect_vec();
+
+        if !self.weights.is_empty()
+            || !self.paces.is_empty()
+            || !fleets.is_empty()
+            || !self.classes.is_empty()
+            || !self.exclusions.is_empty()
+            || !capacities.is_empty()
+            || !windows.is_empty()
+            || !shared.is_empty()
+            || !stays.is_empty()
+            || !self.patrols.is_empty()
+            || !self.vias.is_empty()
+            || !self.edge_costs.is_empty()
+            || !self.closures.is_empty()
+            || self.anonymous
+            || !self.settings.is_empty()
+        {
+            writeln!(f, "---")?;
+            for (key, value) in &self.settings {
+                writeln!(f, "{key} = {value}")?;
+            }
+            for (fleet, names) in &fleets {
+                writeln!(f, "fleet {names} = {fleet}")?;
+            }
+            for (name, weight) in self.weights.iter().sorted_by_key(|(n, _)| **n) {
+                writeln!(f, "weight {name} = {weight}")?;
+            }
+            for (name, pace) in self.paces.iter().sorted_by_key(|(n, _)| **n) {
+                writeln!(f, "pace {name} = {pace}")?;
+            }
+            for (name, class) in self.classes.iter().sorted_by_key(|(n, _)| **n) {
+                writeln!(f, "class {name} = {class}")?;
+            }
+            for (name, cells) in self.exclusions.iter().sorted_by_key(|(n, _)| **n) {
+                for v in cells.iter().sorted_by_key(|v| (v.y, v.x)) {
+                    writeln!(f, "exclude {name} = {v}")?;
+                }
+            }
+            for ((from, to), cost) in self
+                .edge_costs
+                .iter()
+                .sorted_by_key(|((a, b), _)| (a.y, a.x, b.y, b.x))
+            {
+                writeln!(f, "cost {from} > {to} = {cost}")?;
+            }
+            for (v, window) in self
+                .closures
+                .iter()
+                .sorted_by_key(|(v, w)| (v.y, v.x, *w.start(), *w.end()))
+            {
+                match *window.end() {
+                    Time::MAX => writeln!(f, "close {v} = {}..", window.start())?,
+                    end => writeln!(f, "close {v} = {}..{end}", window.start())?,
+                }
+            }
+            for (owner, capacity) in &capacities {
+                writeln!(f, "capacity {} = {capacity}", owner.to_ascii_lowercase())?;
+            }
+            for (owner, (open, close)) in &windows {
+                writeln!(f, "window {} = {open}..{close}", owner.to_ascii_lowercase())?;
+            }
+            for name in &stays {
+                writeln!(f, "stay {name}")?;
+            }
+            for (name, owner) in &shared {
+                writeln!(f, "goal {name} = {}", owner.to_ascii_lowercase())?;
+            }
+            for (name, waypoints) in self.vias.iter().sorted_by_key(|(n, _)| **n) {
+                writeln!(f, "via {name} = {}", waypoints.iter().join(" > "))?;
+            }
+            for (name, (waypoints, laps)) in self.patrols.iter().sorted_by_key(|(n, _)| **n) {
+                write!(f, "patrol {name} = {}", waypoints.iter().join(" > "))?;
+                match laps {
+                    1 => writeln!(f)?,
+                    laps => writeln!(f, " * {laps}")?,
+                }
+            }
+            if self.anonymous {
+                writeln!(f, "anonymous")?;
+            }
+            writeln!(f, "---")?;
+        }
+
+        let mut grid = vec![vec![' '; self.width]; self.height];
+        let mut put = |v: Vertex, c: char| {
+            if let Some(cell) = grid
+                .get_mut(v.y as usize)
+                .and_then(|row| row.get_mut(v.x as usize))
+            {
+                *cell = c;
+            }
+        };
+        for v in &self.obstacles {
+            put(*v, '#');
+        }
+        for (name, start, _) in &self.robots {
+            put(*start, *name);
+        }
+        let starts = self
+            .robots
+            .iter()
+            .map(|(_, s, _)| *s)
+            .collect::<FxHashSet<_>>();
+        for (name, goal) in self.drawn_goals(&owners) {
+            if starts.contains(&goal) {
+                // Goal is occupied by a robot, mark it from the cell above, which build() made
+                // sure is free
+                let south = char::from_u32(name as u32 - 0x41 + 0x24D0).unwrap();
+                put(Vertex::new(goal.x, goal.y - 1), south);
+            } else {
+                put(goal, name.to_ascii_lowercase());
+            }
+        }
+        for row in grid {
+            writeln!(f, "{}", row.into_iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
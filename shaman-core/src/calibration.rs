@@ -0,0 +1,180 @@
+//! Fitting a [CostModel] to how robots actually executed their routes
+//!
+//! Traces are plain text, one route per line with each action & how long it took:
+//!
+//! ```text
+//! E:0.75 E:0.5 N:1.5 wait:0.6 S:0.8
+//! ```
+use std::{fmt::Display, path::Path};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    astar::{Action, CostModel, Step},
+    rng::{RandomSource, Rng},
+};
+
+/// How long each action took, when a robot followed a route starting from standstill
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub steps: Vec<(Action, f32)>,
+}
+
+/// A [CostModel] estimated from recorded [Trace]s
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    /// Estimated costs, relative to moving straight
+    pub model: CostModel,
+    /// How long moving straight takes, i.e. the duration of one unit of cost
+    pub unit: f32,
+    /// Root mean square error between predicted & recorded durations of single actions
+    pub error: f32,
+    /// How many actions of each kind were recorded: wait, straight, start & turn
+    pub samples: [usize; 4],
+}
+
+impl Trace {
+    /// Pretend to execute `actions`, taking as long as `model` predicts (in units of `unit`)
+    /// but each varying randomly by up to `jitter` of that
+    pub fn jittered(
+        actions: &[Action],
+        model: &CostModel,
+        unit: f32,
+        jitter: f32,
+        seed: u64,
+    ) -> Self {
+        Self::jittered_with(actions, model, unit, jitter, &mut Rng::new(seed))
+    }
+
+    /// Like [Self::jittered], but drawing the variations from `rng`
+    pub fn jittered_with(
+        actions: &[Action],
+        model: &CostModel,
+        unit: f32,
+        jitter: f32,
+        rng: &mut impl RandomSource,
+    ) -> Self {
+        let steps = std::iter::once(Action::Wait)
+            .chain(actions.iter().copied())
+            .tuple_windows()
+            .map(|(previous, action)| {
+                let noise = 1. + jitter * (2. * rng.uniform() - 1.);
+                (action, model.cost(previous, action) * unit * noise)
+            })
+            .collect();
+        Self { steps }
+    }
+
+    /// Parse a single line of whitespace separated `action:duration` pairs. Durations must be
+    /// finite & not negative
+    pub fn parse(line: &str) -> Option<Self> {
+        let steps = line
+            .split_whitespace()
+            .map(|step| {
+                let (action, duration) = step.split_once(':')?;
+                let action = match action {
+                    "N" => Action::N,
+                    "W" => Action::W,
+                    "E" => Action::E,
+                    "S" => Action::S,
+                    "wait" => Action::Wait,
+                    _ => return None,
+                };
+                let duration = duration
+                    .parse()
+                    .ok()
+                    .filter(|d: &f32| d.is_finite() && *d >= 0.)?;
+                Some((action, duration))
+            })
+            .collect::<Option<_>>()?;
+        Some(Self { steps })
+    }
+
+    /// Read all traces from `file`, skipping empty lines
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<Vec<Self>> {
+        let file = file.as_ref().display().to_string();
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                Self::parse(line).ok_or_else(|| miette!("{file}:{}: invalid trace", i + 1))
+            })
+            .collect()
+    }
+}
+
+impl Calibration {
+    /// Least squares fit of a [CostModel] to `traces`, i.e. the mean duration of each kind of
+    /// action. Kinds which were never recorded keep the ratio of the default model
+    pub fn fit(traces: &[Trace]) -> Result<Self> {
+        let observations = traces
+            .iter()
+            .flat_map(|trace| {
+                std::iter::once(Action::Wait)
+                    .chain(trace.steps.iter().map(|(a, _)| *a))
+                    .tuple_windows()
+                    .zip(trace.steps.iter().map(|(_, d)| *d))
+                    .map(|((previous, action), d)| (Step::of(previous, action) as usize, d))
+            })
+            .collect_vec();
+
+        let mut samples = [0; 4];
+        let mut sums = [0.; 4];
+        for (k, d) in &observations {
+            samples[*k] += 1;
+            sums[*k] += d;
+        }
+        if samples[1] == 0 {
+            return Err(miette!(
+                "Traces need straight moves to calibrate the cost unit"
+            ));
+        }
+        let unit = sums[1] / samples[1] as f32;
+        let default = CostModel::default();
+        let defaults = [default.wait, 1., default.start, default.turn];
+        let [wait, straight, start, turn] = std::array::from_fn(|k| match samples[k] {
+            0 => defaults[k],
+            n => sums[k] / n as f32 / unit,
+        });
+        let model = CostModel {
+            wait,
+            straight,
+            start,
+            turn,
+        };
+
+        let costs = [wait, straight, start, turn];
+        let error = observations
+            .iter()
+            .map(|(k, d)| (costs[*k] * unit - d).powi(2))
+            .sum::<f32>()
+            / observations.len() as f32;
+        Ok(Self {
+            model,
+            unit,
+            error: error.sqrt(),
+            samples,
+        })
+    }
+}
+
+impl Display for Calibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let CostModel {
+            wait,
+            straight,
+            start,
+            turn,
+        } = self.model;
+        let [waits, straights, starts, turns] = self.samples;
+        writeln!(f, "wait:      {wait:.2} ({waits} samples)")?;
+        writeln!(f, "straight:  {straight:.2} ({straights} samples)")?;
+        writeln!(f, "start:     {start:.2} ({starts} samples)")?;
+        writeln!(f, "turn:      {turn:.2} ({turns} samples)")?;
+        writeln!(f, "unit:      {:.3}", self.unit)?;
+        writeln!(f, "error:     {:.3}", self.error)
+    }
+}
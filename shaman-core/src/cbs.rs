@@ -0,0 +1,194 @@
+//! Conflict based search, a complete but slower alternative to [Pbs](crate::Pbs)
+//!
+//! The high level searches a tree of constraints, where each node forbids single robots to be
+//! on a cell (vertex constraint) or to traverse an edge (edge constraint) at a specific time.
+//! Expanding a node resolves its first collision twice, constraining either of the two robots.
+//! Unlike PBS, which fixes the order of two robots for good, this never rules out a way to
+//! resolve a conflict. The cheapest node without any collisions is the solution.
+use std::{collections::BTreeMap, time::Instant};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+use ordered_float::OrderedFloat;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    Objective, Shaman, ShamanError, Time,
+    astar::RightOfWay,
+    focal,
+    instrumentation::record,
+    layout::Vertex,
+    rng::{RandomSource, Rng},
+    robot::Robot,
+};
+
+/// Give up after expanding this many nodes of the constraint tree, unless configured otherwise
+/// by [crate::PlannerConfig::max_nodes]
+const MAX_NODES: usize = 10_000;
+
+/// Where & when two robots collide first
+#[derive(Debug, Clone, Copy)]
+enum Collision {
+    /// Both robots are on the same cell at the same time
+    Vertex(Time, Vertex),
+    /// From the time to the next, the first robot takes the first move & the second robot the
+    /// second one, either swapping both cells or crossing the same diagonal
+    Edge(Time, (Vertex, Vertex), (Vertex, Vertex)),
+}
+
+/// A single node of the constraint tree
+#[derive(Debug, Clone)]
+struct Node {
+    robots: BTreeMap<char, Robot>,
+    constraints: FxHashMap<char, RightOfWay>,
+    objective: Objective,
+    /// Random tie breaker for equally costly nodes, lowest first, see [Shaman::with_seed]
+    tie: u64,
+    /// Tie breaker for equally costly nodes, preferring the ones created first
+    id: usize,
+}
+
+impl Node {
+    fn cost(&self) -> OrderedFloat<f32> {
+        self.objective.cost(self.robots.values()).into()
+    }
+
+    /// How many pairs of robots collide
+    fn conflicts(&self) -> usize {
+        self.robots
+            .values()
+            .tuple_combinations()
+            .filter(|(a, b)| collision(a, b).is_some())
+            .count()
+    }
+
+    /// The earliest collision of any two robots
+    fn first_collision(&self) -> Option<(char, char, Collision)> {
+        self.robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .tuple_combinations()
+            .filter_map(|(a, b)| Some((a.name(), b.name(), collision(a, b)?)))
+            .min_by_key(|(_, _, c)| match c {
+                Collision::Vertex(t, _) | Collision::Edge(t, _, _) => *t,
+            })
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.cost(), self.tie, self.id)
+            .cmp(&(other.cost(), other.tie, other.id))
+            .reverse() // for min heap
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Node {}
+
+/// Where `robot` is at `time`, `None` if it isn't on the map (anymore)
+fn position(robot: &Robot, time: Time) -> Option<Vertex> {
+    if robot.delivers() && time > robot.route().duration() {
+        return None;
+    }
+    robot.route().position_at(time)
+}
+
+/// First collision of `a` & `b`, including one of them running into the other parked on its goal
+fn collision(a: &Robot, b: &Robot) -> Option<Collision> {
+    let end = a.route().duration().max(b.route().duration());
+    (0..=end).find_map(|t| {
+        let (now, then) = (
+            (position(a, t), position(b, t)),
+            (position(a, t + 1), position(b, t + 1)),
+        );
+        match (now, then) {
+            ((Some(p), Some(q)), _) if p == q => Some(Collision::Vertex(t, p)),
+            ((Some(p), Some(q)), (Some(r), Some(s))) if p == s && q == r && p != q => {
+                Some(Collision::Edge(t, (p, r), (q, s)))
+            }
+            ((Some(p), Some(q)), (Some(r), Some(s))) if p.crosses(r, (q, s)) => {
+                Some(Collision::Edge(t, (p, r), (q, s)))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Find collision free routes, preferring the lowest cost by the scenario's [Objective]
+pub(crate) fn solve(mut shaman: Shaman) -> Result<Shaman> {
+    let mut queue = focal::Queue::new(shaman.planner.bound(), |n| n.cost().0, Node::conflicts);
+    queue.push(Node {
+        robots: std::mem::take(&mut shaman.robots),
+        constraints: Default::default(),
+        objective: shaman.objective,
+        tie: 0,
+        id: 0,
+    });
+    let mut rng = shaman.seed.map(Rng::new);
+    let mut created = 1;
+    let started = Instant::now();
+    record(|stats| stats.generated += 1);
+
+    let max_nodes = shaman.planner.max_nodes.unwrap_or(MAX_NODES);
+    for expanded in 0..max_nodes {
+        shaman.planner.check_time(&shaman.layout, started, || {
+            format!("expanding {expanded} nodes of the constraint tree")
+        })?;
+        let Some(node) = queue.pop() else {
+            return Err(miette!("Constraint tree exhausted, no solution exists"));
+        };
+        record(|stats| stats.expanded += 1);
+        let Some((a, b, collision)) = node.first_collision() else {
+            shaman.robots = node.robots;
+            return Ok(shaman);
+        };
+        record(|stats| stats.conflicts += 1);
+
+        for name in [a, b] {
+            if !node.robots[&name].evades() {
+                continue; // Frozen or goal-less robots don't evade anybody
+            }
+            let mut child = node.clone();
+            let constraint = child.constraints.entry(name).or_default();
+            match collision {
+                Collision::Vertex(t, v) => constraint.forbid(t, v),
+                Collision::Edge(t, (from, to), _) if name == a => {
+                    constraint.forbid_move(t, from, to)
+                }
+                Collision::Edge(t, _, (from, to)) => constraint.forbid_move(t, from, to),
+            }
+            let mut constraints = shaman.reservations.clone();
+            constraints += constraint.clone();
+            let robot = child.robots.get_mut(&name).unwrap();
+            match robot.plan(&shaman.layout, &constraints, shaman.planner) {
+                Ok(()) => {}
+                Err(e @ ShamanError::BudgetExceeded { .. }) => return Err(e.into()),
+                Err(_) => continue, // Constraints are unsatisfiable for this robot
+            }
+            child.tie = rng.as_mut().map_or(0, |rng| rng.next_u64());
+            child.id = created;
+            created += 1;
+            record(|stats| stats.generated += 1);
+            queue.push(child);
+        }
+    }
+    Err(ShamanError::BudgetExceeded {
+        src: shaman.layout.code(),
+        limit: format!("limit of {max_nodes} nodes of the constraint tree").into(),
+        progress: format!("expanding {max_nodes} nodes of the constraint tree").into(),
+        route: Box::new([]),
+    }
+    .into())
+}
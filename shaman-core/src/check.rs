@@ -0,0 +1,88 @@
+//! Finding every problem of a map at once, instead of stopping at the first one
+use std::path::Path;
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{Format, Shaman, ShamanError, parser};
+
+impl Shaman {
+    /// Every problem of the map in `file`, see [Self::check_str]
+    pub fn check<P: AsRef<Path>>(file: P) -> Result<Vec<ShamanError>> {
+        Self::check_as(file, Format::Auto)
+    }
+
+    /// Every problem of the map in `file` written in `format`, see [Self::check_str]. MovingAI
+    /// maps get imported first, failing if even that is impossible
+    pub fn check_as<P: AsRef<Path>>(file: P, format: Format) -> Result<Vec<ShamanError>> {
+        let path = file.as_ref();
+        let file = path.display().to_string();
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        match format.detect(path) {
+            Format::MovingAi => {
+                let shaman = parser::movingai::import(path, &content)?;
+                Ok(shaman.problems(vec![]))
+            }
+            _ => Ok(Self::check_str(&file, &content)),
+        }
+    }
+
+    /// Parse the map `content` without planning anything & list all problems found, instead
+    /// of failing on the first like [Self::parse_str] does: broken syntax, duplicate robots or
+    /// goals, ragged rows, goals shared beyond their capacity, robots without goals or starting
+    /// in their excluded areas & goals robots can't ever reach, neither around obstacles nor
+    /// around their excluded areas
+    pub fn check_str(name: &str, content: &str) -> Vec<ShamanError> {
+        let mut problems = vec![];
+        match parser::parse_leniently(name, content, &mut problems) {
+            Ok(shaman) => shaman.problems(problems),
+            Err(e) => {
+                problems.push(e);
+                problems
+            }
+        }
+    }
+
+    /// The `problems` found while parsing, followed by those of the parsed map
+    fn problems(&self, mut problems: Vec<ShamanError>) -> Vec<ShamanError> {
+        if self.robots.is_empty() {
+            let src = self.layout.code();
+            problems.push(ShamanError::NoRobots { src });
+        }
+        for robot in self.robots.values().sorted_by_key(|r| r.name()) {
+            let (start, start_span) = robot.position();
+            if robot.excluded().contains(&start) {
+                problems.push(ShamanError::StartExcluded {
+                    src: self.layout.code(),
+                    robot: robot.name(),
+                    highlight: start_span,
+                });
+            }
+            let Some((goal, span)) = robot.goal() else {
+                if robot.patrol().is_some() {
+                    continue;
+                }
+                problems.push(ShamanError::MissingGoal {
+                    src: self.layout.code(),
+                    robot: robot.name(),
+                    goal: robot.name().to_ascii_lowercase(),
+                    highlight: start_span,
+                });
+                continue;
+            };
+            let excluded = robot.excluded();
+            if self
+                .layout
+                .distance_avoiding(start, goal, excluded)
+                .is_none()
+            {
+                problems.push(ShamanError::RouteNotFound {
+                    src: self.layout.code(),
+                    start: start_span,
+                    goal: span,
+                });
+            }
+        }
+        problems
+    }
+}
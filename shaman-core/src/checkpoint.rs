@@ -0,0 +1,298 @@
+//! Saving & restoring the state of a running simulation
+//!
+//! A checkpoint is a plain text file, starting with the simulation state followed by the
+//! original map it was started from. Besides the robots' positions & remaining routes
+//! (including rotations in place), it holds everything which changed since the map was
+//! parsed: headings, goals, exclusions & waypoints of the robots, as well as blocked, closed &
+//! reserved cells and the cells blocked by inflating the obstacles:
+//!
+//! ```text
+//! shaman checkpoint
+//! time 3
+//! robot A 4/2 : 5/2@4 cw@5 6/2@6
+//! robot B 9/2 frozen : 9/2@4
+//! heading A E
+//! goal A 12/3
+//! exclude B 3/1 4/1
+//! waypoints A 8/2
+//! block 5/2 5/3
+//! close 7/1 3..=20
+//! reserve 4 6/1 6/2
+//! inflation 1 1/1 2/1
+//! ---
+//! <map file content>
+//! ```
+use std::{ops::RangeInclusive, path::Path};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    Shaman, Time,
+    astar::{Action, Rotation},
+    layout::Vertex,
+    parser,
+    robot::{Location, Robot},
+    route::Route,
+};
+
+const MAGIC: &str = "shaman checkpoint";
+
+impl Shaman {
+    /// Serialize the current state of the simulation, so it can be resumed with [Self::restore]
+    pub fn checkpoint(&self) -> String {
+        let code = self.layout.code();
+        // Only what changed since then needs saving besides the map itself
+        let original = parser::parse(code.name(), code.inner()).ok();
+        let mut out = format!("{MAGIC}\ntime {}\n", self.time);
+        for robot in self.robots.values().sorted_by_key(|r| r.name()) {
+            let frozen = if robot.is_frozen() { " frozen" } else { "" };
+            let rotations = robot.route().rotations().collect::<Vec<_>>();
+            let route = robot
+                .route()
+                .iter()
+                .map(|l| {
+                    let rotation = rotations.iter().find(|(t, _)| *t == l.time);
+                    let rotation = rotation.map_or(String::new(), |(t, r)| match r {
+                        Rotation::Clockwise => format!("cw@{t} "),
+                        Rotation::Counterclockwise => format!("ccw@{t} "),
+                    });
+                    format!("{rotation}{}@{}", l.position, l.time)
+                })
+                .join(" ");
+            let (name, position) = (robot.name(), robot.position().0);
+            out.push_str(&format!("robot {name} {position}{frozen} : {route}\n"));
+        }
+
+        for robot in self.robots.values().sorted_by_key(|r| r.name()) {
+            let name = robot.name();
+            let before = original.as_ref().and_then(|o| o.robots.get(&name));
+            if before.map(Robot::heading) != Some(robot.heading()) {
+                out.push_str(&format!("heading {name} {:?}\n", robot.heading()));
+            }
+            let goal = |r: &Robot| (r.goal().map(|g| g.0), r.delivers());
+            if let (Some(v), delivers) = goal(robot)
+                && before.map(goal) != Some(goal(robot))
+            {
+                let delivers = if delivers { " delivers" } else { "" };
+                out.push_str(&format!("goal {name} {v}{delivers}\n"));
+            }
+            let excluded = robot
+                .excluded()
+                .iter()
+                .filter(|v| before.is_none_or(|b| !b.excluded().contains(v)))
+                .sorted_by_key(|v| (v.y, v.x))
+                .collect_vec();
+            if !excluded.is_empty() {
+                out.push_str(&format!("exclude {name} {}\n", excluded.iter().join(" ")));
+            }
+            if before.map(Robot::waypoints) != Some(robot.waypoints()) {
+                let waypoints = robot.waypoints().iter().map(|v| format!(" {v}")).join("");
+                out.push_str(&format!("waypoints {name}{waypoints}\n"));
+            }
+        }
+
+        if let Some(original) = &original {
+            let blocked = original
+                .layout
+                .free_cells()
+                .filter(|v| self.layout.is_blocked(*v) && !self.layout.inflated().contains(v))
+                .sorted_by_key(|v| (v.y, v.x))
+                .collect_vec();
+            if !blocked.is_empty() {
+                out.push_str(&format!("block {}\n", blocked.iter().join(" ")));
+            }
+        }
+        let closed = |s: &Shaman| {
+            let closures = s.layout.closures();
+            closures
+                .sorted_by_key(|(v, w)| (v.y, v.x, *w.start()))
+                .collect_vec()
+        };
+        let before = original.as_ref().map(closed).unwrap_or_default();
+        for (v, window) in closed(self).into_iter().filter(|c| !before.contains(c)) {
+            out.push_str(&format!("close {v} {}\n", window_code(&window)));
+        }
+        let reserved = original
+            .as_ref()
+            .map(|o| o.reservations.reserved().collect_vec())
+            .unwrap_or_default();
+        let reserved = self
+            .reservations
+            .reserved()
+            .filter(|r| !reserved.contains(r))
+            .into_group_map_by(|(t, _)| *t);
+        for (t, cells) in reserved.into_iter().sorted_by_key(|(t, _)| *t) {
+            let cells = cells
+                .iter()
+                .map(|(_, v)| v)
+                .sorted_by_key(|v| (v.y, v.x))
+                .join(" ");
+            out.push_str(&format!("reserve {t} {cells}\n"));
+        }
+        if original.is_none_or(|o| o.layout.inflation() != self.layout.inflation()) {
+            let inflated = self.layout.inflated().iter().sorted_by_key(|v| (v.y, v.x));
+            let inflated = inflated.map(|v| format!(" {v}")).join("");
+            out.push_str(&format!(
+                "inflation {}{inflated}\n",
+                self.layout.inflation()
+            ));
+        }
+
+        out.push_str("---\n");
+        out.push_str(code.inner());
+        out
+    }
+
+    /// Write [Self::checkpoint] to `file`
+    pub fn save<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let file = file.as_ref();
+        std::fs::write(file, self.checkpoint()).map_err(|e| miette!("{}: {e}", file.display()))
+    }
+
+    /// Continue a simulation from a file written by [Self::save]
+    pub fn restore<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let file = file.as_ref().display().to_string();
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        Self::from_checkpoint(&file, &content)
+    }
+
+    /// Continue a simulation from the content of a [Self::checkpoint]
+    pub fn from_checkpoint(name: &str, content: &str) -> Result<Self> {
+        let invalid = |line: &str| miette!("{name}: invalid checkpoint line '{line}'");
+        let (state, map) = content
+            .split_once("\n---\n")
+            .ok_or_else(|| miette!("{name}: checkpoint contains no map"))?;
+        let mut lines = state.lines();
+        if lines.next() != Some(MAGIC) {
+            return Err(miette!("{name}: not a checkpoint file"));
+        }
+        let mut sim = parser::parse(name, map)?;
+
+        for line in lines {
+            let cells = |cells: &[&str]| {
+                cells
+                    .iter()
+                    .map(|v| vertex(v).ok_or_else(|| invalid(line)))
+                    .collect::<Result<Vec<_>>>()
+            };
+            match line.split_whitespace().collect_vec().as_slice() {
+                ["time", t] => sim.time = t.parse().map_err(|_| invalid(line))?,
+                ["robot", n, position, rest @ ..] => {
+                    let robot = robot(&mut sim, n).ok_or_else(|| invalid(line))?;
+                    let position = vertex(position).ok_or_else(|| invalid(line))?;
+                    let (frozen, route) = match rest {
+                        ["frozen", ":", route @ ..] => (true, route),
+                        [":", route @ ..] => (false, route),
+                        _ => return Err(invalid(line)),
+                    };
+                    let mut rotations = vec![];
+                    let mut locations = vec![];
+                    for l in route {
+                        match l.split_once('@') {
+                            Some(("cw", t)) => rotations.push((t, Rotation::Clockwise)),
+                            Some(("ccw", t)) => rotations.push((t, Rotation::Counterclockwise)),
+                            _ => locations.push(location(l).ok_or_else(|| invalid(line))?),
+                        }
+                    }
+                    let mut route = Route::from_iter(locations);
+                    for (t, rotation) in rotations {
+                        route.rotate(t.parse().map_err(|_| invalid(line))?, rotation);
+                    }
+                    robot.restore(position, route, frozen);
+                }
+                ["heading", n, heading] => {
+                    let heading = Action::COMPASS
+                        .into_iter()
+                        .chain([Action::Wait])
+                        .find(|a| format!("{a:?}") == *heading);
+                    let robot = robot(&mut sim, n);
+                    let (robot, heading) = robot.zip(heading).ok_or_else(|| invalid(line))?;
+                    robot.set_heading(heading);
+                }
+                ["goal", n, goal, rest @ ..] => {
+                    let goal = vertex(goal).ok_or_else(|| invalid(line))?;
+                    let delivers = match rest {
+                        [] => false,
+                        ["delivers"] => true,
+                        _ => return Err(invalid(line)),
+                    };
+                    let robot = robot(&mut sim, n).ok_or_else(|| invalid(line))?;
+                    robot.assign(goal);
+                    robot.set_delivers(delivers);
+                }
+                ["exclude", n, excluded @ ..] => {
+                    let excluded = cells(excluded)?;
+                    robot(&mut sim, n)
+                        .ok_or_else(|| invalid(line))?
+                        .exclude(excluded);
+                }
+                ["waypoints", n, waypoints @ ..] => {
+                    let waypoints = cells(waypoints)?;
+                    robot(&mut sim, n)
+                        .ok_or_else(|| invalid(line))?
+                        .set_waypoints(waypoints);
+                }
+                ["block", blocked @ ..] => {
+                    for v in cells(blocked)? {
+                        sim.layout.block(v);
+                    }
+                }
+                ["close", v, window] => {
+                    let v = vertex(v).ok_or_else(|| invalid(line))?;
+                    let window = window_of(window).ok_or_else(|| invalid(line))?;
+                    sim.layout.close(v, window);
+                }
+                ["reserve", t, reserved @ ..] => {
+                    let t: Time = t.parse().map_err(|_| invalid(line))?;
+                    sim.reservations.reserve(&cells(reserved)?, t..t + 1);
+                }
+                ["inflation", radius, inflated @ ..] => {
+                    let radius = radius.parse().map_err(|_| invalid(line))?;
+                    sim.layout.restore_inflation(radius, &cells(inflated)?);
+                }
+                _ => return Err(invalid(line)),
+            }
+        }
+        Ok(sim)
+    }
+}
+
+/// Robot named `n` of `sim`, if any
+fn robot<'a>(sim: &'a mut Shaman, n: &str) -> Option<&'a mut Robot> {
+    let n = n.chars().exactly_one().ok()?;
+    sim.robots.get_mut(&n)
+}
+
+/// `3..=20`, or `3..` for windows lasting forever
+fn window_code(window: &RangeInclusive<Time>) -> String {
+    match *window.end() {
+        Time::MAX => format!("{}..", window.start()),
+        end => format!("{}..={end}", window.start()),
+    }
+}
+
+/// Parse a [window_code]
+fn window_of(s: &str) -> Option<RangeInclusive<Time>> {
+    let (start, end) = s.split_once("..")?;
+    let end = match end {
+        "" => Time::MAX,
+        end => end.strip_prefix('=')?.parse().ok()?,
+    };
+    Some(start.parse().ok()?..=end)
+}
+
+/// Parse `x/y`
+pub(crate) fn vertex(s: &str) -> Option<Vertex> {
+    let (x, y) = s.split_once('/')?;
+    Some(Vertex::new(x.parse().ok()?, y.parse().ok()?))
+}
+
+/// Parse `x/y@t`
+fn location(s: &str) -> Option<Location> {
+    let (position, time) = s.split_once('@')?;
+    Some(Location {
+        position: vertex(position)?,
+        time: time.parse().ok()?,
+    })
+}
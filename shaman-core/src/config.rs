@@ -0,0 +1,370 @@
+//! Defaults for solving & rendering, read from a `shaman.toml` next to the maps instead of
+//! passing long lists of flags
+//!
+//! The file is a subset of TOML: top level settings, followed by `[planner]`, `[costs]` &
+//! `[palette]` sections, each at most once. Every key is optional but may only be given once.
+//! Keys are bare words of letters, digits, `-` & `_`, values are either double quoted strings
+//! without escapes or finite numbers. Comments start with `#` outside of strings. Everything
+//! else TOML has, like quoted or dotted keys, literal or multi-line strings, arrays, inline
+//! tables, dates or arrays of tables, is rejected instead of being misread:
+//!
+//! ```toml
+//! solver = "pbs"
+//! objective = "mix=0.5"
+//! order = "distance"
+//! seed = 42
+//! inflate = 0
+//! output = "board"
+//! format = "auto"
+//!
+//! [planner]
+//! planner = "sipp"
+//! heuristic = "true-distance"
+//! movement = "four-connected"
+//! rotate-cost = 1.0
+//! max-expansions = 10000
+//! max-time = 5.0            # seconds
+//! max-route-length = 200
+//! max-nodes = 10000         # of the CBS constraint tree
+//! suboptimality = 1.5
+//!
+//! [costs]
+//! wait = 1.2
+//! straight = 1.0
+//! start = 1.5
+//! turn = 3.0
+//!
+//! [palette]
+//! A = "#ff8800"             # a single robot
+//! pickers = "#00ffff"       # all robots of a fleet
+//! ```
+use std::{fmt::Debug, path::Path, str::FromStr, time::Duration};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    Algorithm, Shaman,
+    astar::{Heuristic, MovementModel, Planner, PlannerConfig},
+    objective::Objective,
+    parser::Format,
+    prioritized::Order,
+    render::Rgb,
+};
+
+/// Name of the file [Config::discover] looks for
+pub const CONFIG_FILE: &str = "shaman.toml";
+
+/// Variants of each setting, named in kebab case like on the command line
+const ALGORITHMS: &[Algorithm] = &[
+    Algorithm::Pbs,
+    Algorithm::Cbs,
+    Algorithm::Auto,
+    Algorithm::Prioritized,
+];
+const PLANNERS: &[Planner] = &[Planner::AStar, Planner::Sipp];
+const HEURISTICS: &[Heuristic] = &[
+    Heuristic::Manhattan,
+    Heuristic::Euclidean,
+    Heuristic::TrueDistance,
+];
+const MOVEMENTS: &[MovementModel] = &[MovementModel::FourConnected, MovementModel::EightConnected];
+const OUTPUTS: &[Output] = &[Output::Board, Output::Json, Output::Yaml, Output::Messages];
+const FORMATS: &[Format] = &[Format::Auto, Format::Shaman, Format::MovingAi];
+
+/// What to print about a played back scenario
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Output {
+    /// Animated & final board with a summary
+    #[default]
+    Board,
+    /// Timed waypoints of each robot, without animating anything
+    Json,
+    /// The same as [Output::Json] in YAML
+    Yaml,
+    /// Animated & final board with the summary as message IDs & parameters (JSON), to be
+    /// translated by whoever shows it, see [crate::Locale]
+    Messages,
+}
+
+/// Defaults for solving & rendering, usually read from a [CONFIG_FILE]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub algorithm: Algorithm,
+    pub objective: Objective,
+    /// Order [Algorithm::Prioritized] plans robots in
+    pub order: Order,
+    /// Seed breaking ties between equally costly ideas, see [Shaman::with_seed]
+    pub seed: Option<u64>,
+    /// Keep robots this many cells away from obstacles & the map's edge
+    pub inflate: usize,
+    /// How robots plan their routes, including the limits & cost model
+    pub planner: PlannerConfig,
+    /// Colors of single robots or whole fleets by name, instead of [crate::color_for]
+    pub palette: Vec<(String, Rgb)>,
+    pub output: Output,
+    pub format: Format,
+}
+
+impl Config {
+    /// Read the configuration from `file`
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let file = file.as_ref().display().to_string();
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        Self::parse(&content).map_err(|e| miette!("{file}:{e}"))
+    }
+
+    /// Read [CONFIG_FILE] from the current directory, if there is one
+    pub fn discover() -> Result<Option<Self>> {
+        match Path::new(CONFIG_FILE).is_file() {
+            true => Self::load(CONFIG_FILE).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// Parse the content of a configuration file, failing on unknown sections or keys, those
+    /// given twice & any syntax beyond the subset of TOML described in [the module](self)
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut config = Self::default();
+        let mut section = String::new();
+        let mut seen = vec![];
+        for (i, line) in s.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |e: String| miette!("{}: {e}", i + 1);
+            if line.starts_with("[[") {
+                return Err(error(format!(
+                    "Arrays of tables aren't supported, got '{line}'"
+                )));
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                if !["planner", "costs", "palette"].contains(&section.as_str()) {
+                    return Err(error(format!("Unknown section [{section}]")));
+                }
+                if seen.contains(&(section.clone(), None)) {
+                    return Err(error(format!("Section [{section}] is given twice")));
+                }
+                seen.push((section.clone(), None));
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("Expected 'key = value', got '{line}'")))?;
+            let (key, value) = (key.trim(), value.trim());
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+            {
+                return Err(error(format!("Expected a bare key, got '{key}'")));
+            }
+            let entry = (section.clone(), Some(key.to_string()));
+            if seen.contains(&entry) {
+                return Err(error(format!("Key '{key}' is given twice")));
+            }
+            seen.push(entry);
+            supported(value).map_err(error)?;
+            config.set(&section, key, value).map_err(error)?;
+        }
+        Ok(config)
+    }
+
+    /// Set `key` of `section` (empty at the top level) to the unparsed `value`
+    fn set(&mut self, section: &str, key: &str, value: &str) -> Result<(), String> {
+        let planner = &mut self.planner;
+        let costs = &mut planner.costs;
+        match (section, key) {
+            ("", "solver") => self.algorithm = variant(ALGORITHMS, string(value)?)?,
+            ("", "objective") => self.objective = string(value)?.parse()?,
+            ("", "order") => self.order = string(value)?.parse()?,
+            ("", "seed") => self.seed = Some(number(value)?),
+            ("", "inflate") => self.inflate = number(value)?,
+            ("", "output") => self.output = variant(OUTPUTS, string(value)?)?,
+            ("", "format") => self.format = variant(FORMATS, string(value)?)?,
+            ("planner", "planner") => planner.planner = variant(PLANNERS, string(value)?)?,
+            ("planner", "heuristic") => planner.heuristic = variant(HEURISTICS, string(value)?)?,
+            ("planner", "movement") => planner.movement = variant(MOVEMENTS, string(value)?)?,
+            ("planner", "rotate-cost") => planner.rotate_cost = Some(number(value)?),
+            ("planner", "max-expansions") => planner.max_expansions = Some(number(value)?),
+            ("planner", "max-time") => {
+                let seconds = number(value)?;
+                let time = Duration::try_from_secs_f32(seconds).map_err(|e| e.to_string())?;
+                planner.max_time = Some(time);
+            }
+            ("planner", "max-route-length") => planner.max_route_length = Some(number(value)?),
+            ("planner", "max-nodes") => planner.max_nodes = Some(number(value)?),
+            ("planner", "suboptimality") => match number(value)? {
+                factor if factor >= 1. => planner.suboptimality = Some(factor),
+                _ => return Err(format!("Expected a factor of at least 1, got '{value}'")),
+            },
+            ("costs", "wait") => costs.wait = number(value)?,
+            ("costs", "straight") => costs.straight = number(value)?,
+            ("costs", "start") => costs.start = number(value)?,
+            ("costs", "turn") => costs.turn = number(value)?,
+            ("palette", name) => self
+                .palette
+                .push((name.to_string(), string(value)?.parse()?)),
+            ("", key) => return Err(format!("Unknown key '{key}'")),
+            (section, key) => return Err(format!("Unknown key '{key}' in [{section}]")),
+        }
+        Ok(())
+    }
+}
+
+impl Shaman {
+    /// Parse the scenario in `file` written in the [Config::format] of `config`, with all of
+    /// its solver settings & palette applied, see [Self::with_config]
+    pub fn parse_with<P: AsRef<Path>>(file: P, config: &Config) -> Result<Self> {
+        Self::read_as(file, config.format)?.with_config(config)
+    }
+
+    /// `key = value` settings of the map's header, see [Self::with_map_settings]
+    pub fn settings(&self) -> &[(String, String)] {
+        &self.settings
+    }
+
+    /// Apply the settings of the map's header again & replan the robots accordingly, e.g.
+    /// after [Self::with_planner_config] overrode them, so the map has the last word
+    pub fn with_map_settings(mut self) -> Result<Self> {
+        if self.settings.is_empty() {
+            return Ok(self);
+        }
+        for (key, value) in self.settings.clone() {
+            self.apply_setting(&key, &value).map_err(|e| miette!(e))?;
+        }
+        self.plan()?;
+        Ok(self)
+    }
+
+    /// Apply a single `key = value` setting of a map's header, named like in a [CONFIG_FILE]
+    /// but without its section, e.g. `movement = eight-connected` or `max-time = 5`. Values
+    /// are bare words like in all other directives, without replanning anybody
+    pub(crate) fn apply_setting(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let section = match key {
+            "objective" | "order" | "seed" => "",
+            "planner" | "heuristic" | "movement" | "rotate-cost" | "max-expansions"
+            | "max-time" | "max-route-length" | "max-nodes" | "suboptimality" => "planner",
+            _ => return Err(format!("Unknown setting '{key}'")),
+        };
+        let value = match value.parse::<f64>() {
+            Ok(_) => value.to_string(),
+            Err(_) => format!("\"{value}\""),
+        };
+        let mut config = Config {
+            objective: self.objective,
+            order: self.order,
+            seed: self.seed,
+            planner: self.planner,
+            ..Default::default()
+        };
+        config.set(section, key, &value)?;
+        self.objective = config.objective;
+        self.order = config.order;
+        self.seed = config.seed;
+        self.planner = config.planner;
+        Ok(())
+    }
+
+    /// Apply all solver settings of `config` & its palette from now on, except for those the
+    /// map's header settles, and replan the robots accordingly, just once. Rendering settings
+    /// like [Config::output] are up to the caller
+    pub fn with_config(mut self, config: &Config) -> Result<Self> {
+        self.inflate_layout(config.inflate)?;
+        self.planner = config.planner;
+        self.objective = config.objective;
+        self.order = config.order;
+        self.seed = config.seed.or(self.seed);
+        for (key, value) in self.settings.clone() {
+            self.apply_setting(&key, &value).map_err(|e| miette!(e))?;
+        }
+        self.plan()?;
+        Ok(self.with_palette(&config.palette))
+    }
+
+    /// Draw robots in the colors of `palette` by their name, or else by their fleet's name
+    pub fn with_palette(mut self, palette: &[(String, Rgb)]) -> Self {
+        let color = |name: &str| palette.iter().find(|(n, _)| n == name).map(|(_, c)| *c);
+        for robot in self.robots.values_mut() {
+            let by_fleet = robot.fleet().and_then(color);
+            if let Some(c) = color(&robot.name().to_string()).or(by_fleet) {
+                robot.set_color(c);
+            }
+        }
+        self
+    }
+}
+
+/// `line` without a trailing `# comment`, unless the `#` is quoted like in colors
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Fail on TOML values outside of the supported subset, which would otherwise be misread
+fn supported(value: &str) -> Result<(), String> {
+    let unsupported = match value.chars().next() {
+        Some('[') => "Arrays",
+        Some('{') => "Inline tables",
+        Some('\'') => "Literal strings in single quotes",
+        _ if value.starts_with("\"\"\"") => "Multi-line strings",
+        _ => return Ok(()),
+    };
+    Err(format!("{unsupported} aren't supported, got '{value}'"))
+}
+
+/// Content of a double quoted `value`, which may neither contain escapes nor further quotes
+fn string(value: &str) -> Result<&str, String> {
+    let content = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| format!("Expected a quoted string, got '{value}'"))?;
+    match content.contains(['"', '\\']) {
+        true => Err(format!("Expected a string without escapes, got '{value}'")),
+        false => Ok(content),
+    }
+}
+
+/// A finite number, unlike `inf` or `nan` which Rust would parse as well
+fn number<T: FromStr>(value: &str) -> Result<T, String> {
+    let numeric = value
+        .chars()
+        .all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+    value
+        .parse()
+        .ok()
+        .filter(|_| numeric)
+        .ok_or_else(|| format!("Expected a number, got '{value}'"))
+}
+
+/// The one of `variants` called `name` in kebab case, like on the command line
+fn variant<T: Copy + Debug>(variants: &[T], name: &str) -> Result<T, String> {
+    let kebab = |v: &T| {
+        format!("{v:?}")
+            .chars()
+            .enumerate()
+            .flat_map(|(i, c)| match c.is_uppercase() && i > 0 {
+                true => vec!['-', c.to_ascii_lowercase()],
+                false => vec![c.to_ascii_lowercase()],
+            })
+            .collect::<String>()
+    };
+    variants
+        .iter()
+        .find(|v| kebab(v) == name)
+        .copied()
+        .ok_or_else(|| {
+            let names = variants.iter().map(kebab).join(", ");
+            format!("Expected one of {names}, got '{name}'")
+        })
+}
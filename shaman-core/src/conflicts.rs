@@ -0,0 +1,91 @@
+//! Which robots get into each other's way, if every robot followed its own shortest path
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::{
+    Shaman,
+    json::{Json, ToJson},
+};
+
+/// Robots as nodes, connected by an edge if their routes collide
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictGraph {
+    /// Names of all robots, sorted
+    pub robots: Vec<char>,
+    /// Pairs of colliding robots, sorted by their names
+    pub edges: Vec<ConflictEdge>,
+}
+
+/// Two robots whose routes collide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConflictEdge {
+    pub robots: (char, char),
+    /// How many times both collide along their routes
+    pub count: usize,
+}
+
+impl Shaman {
+    /// Conflicts between the robots' current routes. Before solving, these are the shortest
+    /// paths planned independently, so the graph tells which robots need to coordinate at all
+    pub fn conflict_graph(&self) -> ConflictGraph {
+        let robots = self
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .collect_vec();
+        ConflictGraph {
+            robots: robots.iter().map(|r| r.name()).collect(),
+            edges: robots
+                .iter()
+                .tuple_combinations()
+                .map(|(a, b)| ConflictEdge {
+                    robots: (a.name(), b.name()),
+                    count: a.collisions(b).len(),
+                })
+                .filter(|e| e.count > 0)
+                .collect(),
+        }
+    }
+}
+
+impl ConflictGraph {
+    /// The graph in Graphviz' DOT language, labeling edges with their collision `counts`
+    pub fn dot(&self, counts: bool) -> String {
+        let mut dot = "graph conflicts {\n".to_string();
+        for robot in &self.robots {
+            writeln!(dot, "  {robot};").unwrap();
+        }
+        for ConflictEdge {
+            robots: (a, b),
+            count,
+        } in &self.edges
+        {
+            match counts {
+                true => writeln!(dot, "  {a} -- {b} [label={count}];").unwrap(),
+                false => writeln!(dot, "  {a} -- {b};").unwrap(),
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl ToJson for ConflictEdge {
+    fn to_json(&self) -> Json {
+        let (a, b) = self.robots;
+        Json::object([
+            ("robots", Json::array([a, b])),
+            ("count", self.count.to_json()),
+        ])
+    }
+}
+
+impl ToJson for ConflictGraph {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("robots", Json::array(&self.robots)),
+            ("edges", Json::array(&self.edges)),
+        ])
+    }
+}
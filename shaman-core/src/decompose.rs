@@ -0,0 +1,98 @@
+//! Solving groups of robots which never get into each other's way separately
+//!
+//! Groups start as the connected components of the [ConflictGraph] and are solved separately,
+//! each ignoring all other groups & on separate threads with the `parallel` feature. If routes of different groups collide afterwards, these
+//! groups merge and get solved again, until no two groups interact anymore.
+use itertools::Itertools;
+use miette::Result;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+
+use crate::{Algorithm, ConflictGraph, Shaman};
+
+impl ConflictGraph {
+    /// Robots connected by conflicts, directly or via others. Each group is sorted by name, and
+    /// all groups by their first robot
+    pub fn groups(&self) -> Vec<Vec<char>> {
+        components(&self.robots, self.edges.iter().map(|e| e.robots))
+    }
+}
+
+/// Connected components of the graph of `robots` with `links` as edges
+fn components(robots: &[char], links: impl Iterator<Item = (char, char)>) -> Vec<Vec<char>> {
+    let mut parents = robots.iter().map(|r| (*r, *r)).collect::<FxHashMap<_, _>>();
+    fn root(parents: &FxHashMap<char, char>, mut robot: char) -> char {
+        while parents[&robot] != robot {
+            robot = parents[&robot];
+        }
+        robot
+    }
+    for (a, b) in links {
+        let (a, b) = (root(&parents, a), root(&parents, b));
+        parents.insert(a.max(b), a.min(b));
+    }
+    robots
+        .iter()
+        .copied()
+        .into_group_map_by(|r| root(&parents, *r))
+        .into_values()
+        .map(|group| group.into_iter().sorted().collect_vec())
+        .sorted()
+        .collect()
+}
+
+impl Shaman {
+    /// Like [Self::solve_with], but solve independent groups of robots separately, see
+    /// [ConflictGraph::groups], in parallel with the `parallel` feature. Much faster for sparse
+    /// scenarios, where few robots interact
+    pub fn solve_decomposed(self, algorithm: Algorithm) -> Result<Self> {
+        let mut groups = self.conflict_graph().groups();
+        let mut solved = FxHashMap::<Vec<char>, Shaman>::default();
+        loop {
+            let parts = groups
+                .iter()
+                .filter(|g| !solved.contains_key(*g))
+                .map(|group| {
+                    let mut part = self.clone();
+                    part.robots.retain(|n, _| group.contains(n));
+                    (group.clone(), part)
+                })
+                .collect_vec();
+            #[cfg(feature = "parallel")]
+            let parts = parts.into_par_iter();
+            #[cfg(not(feature = "parallel"))]
+            let parts = parts.into_iter();
+            let results = parts
+                .map(|(group, part)| (group, part.solve_with(algorithm)))
+                .collect::<Vec<_>>();
+            for (group, result) in results {
+                solved.insert(group, result?);
+            }
+
+            let mut merged = self.clone();
+            merged.audit = Default::default();
+            let mut cost = Some(0.);
+            for part in groups.iter().map(|g| &solved[g]) {
+                merged.robots.extend(part.robots.clone());
+                merged.audit.decisions.extend(part.audit.decisions.clone());
+                cost = cost.zip(part.audit.solution).map(|(a, b)| a + b);
+            }
+            merged.audit.solution = cost;
+
+            let conflicts = merged.conflict_graph();
+            if conflicts.edges.is_empty() {
+                return Ok(merged);
+            }
+            // Merge groups whose solutions collide, keeping the robots of each group together
+            let members = groups.iter().flat_map(|g| g.iter().tuple_windows());
+            let links = members.chain(conflicts.edges.iter().map(|e| (&e.robots.0, &e.robots.1)));
+            let joined = components(&conflicts.robots, links.map(|(a, b)| (*a, *b)));
+            if joined.len() == groups.len() {
+                // Collisions within a group, which its solver should have resolved
+                return self.solve_with(algorithm);
+            }
+            groups = joined;
+        }
+    }
+}
@@ -0,0 +1,229 @@
+#![allow(unused)] // false positive for ParseError struct
+
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceSpan};
+use thiserror::Error;
+
+use crate::layout::Vertex;
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum ShamanError {
+    #[error(
+        "Expected either an obstacle (# or █), a free cell (space), a robot (A..Z) or a goal (a..z)"
+    )]
+    InvalidCell {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Map contains no cells")]
+    EmptyMap {
+        #[source_code]
+        src: NamedSource<String>,
+    },
+
+    #[error("Row has {cells} cells, while the widest one has {widest}")]
+    #[diagnostic(
+        severity(Warning),
+        help("Missing cells at the end of a row are free, so better draw them explicitly")
+    )]
+    RaggedRow {
+        #[source_code]
+        src: NamedSource<String>,
+        cells: usize,
+        widest: usize,
+        #[label("ends here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Map contains no robots, there is nothing to solve")]
+    #[diagnostic(severity(Warning), help("Place robots (A..Z) and their goals (a..z)"))]
+    NoRobots {
+        #[source_code]
+        src: NamedSource<String>,
+    },
+
+    #[error("Robot names must be unique")]
+    DuplicateRobots {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("first")]
+        a: SourceSpan,
+        #[label("second")]
+        b: SourceSpan,
+    },
+
+    #[error("Only one goal per robot")]
+    DuplicateGoals {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("first")]
+        a: SourceSpan,
+        #[label("second")]
+        b: SourceSpan,
+    },
+
+    #[error("No robot named '{robot}' defined")]
+    NoRobotForGoal {
+        #[source_code]
+        src: NamedSource<String>,
+        robot: char,
+        #[label("for this goal")]
+        goal: SourceSpan,
+    },
+
+    #[error(
+        "Expected a header directive like 'weight A = 10', 'pace A = 3', 'class A = 1', 'exclude A = 2/3..5/6', 'capacity a = 3', 'goal B = a', 'window a = 5..10', 'movement = eight-connected', 'via A = 3/4 > 7/1', 'patrol A = 5/1 > 5/3', 'cost 2/3 > 3/3 = 2', 'close 2/3 = 10..20' or the closing '---'"
+    )]
+    InvalidHeader {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("No robot named '{robot}' defined")]
+    UnknownRobot {
+        #[source_code]
+        src: NamedSource<String>,
+        robot: char,
+        #[label("referenced here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("No robot belongs to fleet '{fleet}'")]
+    #[diagnostic(help("Tag robots with a fleet like `fleet A,B = {fleet}`"))]
+    UnknownFleet {
+        #[source_code]
+        src: NamedSource<String>,
+        fleet: String,
+        #[label("referenced here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Robot '{robot}' has no goal")]
+    NoSuchGoal {
+        #[source_code]
+        src: NamedSource<String>,
+        robot: char,
+        #[label("referenced here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Robot '{robot}' has no goal, so it never leaves its start")]
+    #[diagnostic(
+        severity(Warning),
+        help("Draw its goal '{goal}' on the map, unless it should block others like an obstacle")
+    )]
+    MissingGoal {
+        #[source_code]
+        src: NamedSource<String>,
+        robot: char,
+        goal: char,
+        #[label("this one")]
+        highlight: SourceSpan,
+    },
+
+    #[error("{message}")]
+    #[diagnostic(help(
+        "Settings are named like in shaman.toml, e.g. 'objective = makespan' or 'max-time = 5'"
+    ))]
+    InvalidSetting {
+        #[source_code]
+        src: NamedSource<String>,
+        message: String,
+        #[label("here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Robot '{robot}' patrols, so it can't drive to a goal as well")]
+    #[diagnostic(help("Remove its goal, or add it to the patrol's waypoints"))]
+    PatrolWithGoal {
+        #[source_code]
+        src: NamedSource<String>,
+        robot: char,
+        #[label("patrols here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Robot '{robot}' starts on a cell it must never enter")]
+    #[diagnostic(help("Shrink the area its 'exclude {robot}' directive covers"))]
+    StartExcluded {
+        #[source_code]
+        src: NamedSource<String>,
+        robot: char,
+        #[label("excluded here")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Goal can serve at most {capacity} robot(s), but {robots} drive there")]
+    #[diagnostic(help("Raise its capacity with a directive like 'capacity a = {robots}'"))]
+    GoalOverCapacity {
+        #[source_code]
+        src: NamedSource<String>,
+        capacity: usize,
+        robots: usize,
+        #[label("one too many")]
+        highlight: SourceSpan,
+    },
+
+    #[error("No route found")]
+    RouteNotFound {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("from here")]
+        start: SourceSpan,
+        #[label("to here")]
+        goal: SourceSpan,
+    },
+
+    #[error("Inflating obstacles by {radius} cell(s) blocks the {what} of robot '{robot}'")]
+    #[diagnostic(help("Inflate less, or keep it further away from obstacles & the map's edge"))]
+    Inflated {
+        #[source_code]
+        src: NamedSource<String>,
+        robot: char,
+        radius: usize,
+        /// Either "start" or "goal"
+        what: &'static str,
+        #[label("blocked")]
+        highlight: SourceSpan,
+    },
+
+    #[error("Goal closes at t={close}, but the robot arrives at t={arrival}")]
+    #[diagnostic(help("Extend its window with a directive like 'window a = {open}..{arrival}'"))]
+    GoalClosed {
+        #[source_code]
+        src: NamedSource<String>,
+        open: usize,
+        close: usize,
+        arrival: usize,
+        #[label("this goal")]
+        goal: SourceSpan,
+    },
+
+    #[error("Gave up after {progress}, exceeding the {limit}")]
+    #[diagnostic(help("Raise the limits of the PlannerConfig or try a faster planner"))]
+    BudgetExceeded {
+        #[source_code]
+        src: NamedSource<String>,
+        /// Which limit was exceeded, e.g. "limit of 100 expanded states"
+        limit: Box<str>,
+        /// How far the search got, e.g. "expanding 100 states"
+        progress: Box<str>,
+        /// Start & goal of the route, which was planned when giving up
+        #[label(collection)]
+        route: Box<[LabeledSpan]>,
+    },
+
+    #[error("Found {count} problem(s) in the map")]
+    Problems {
+        count: usize,
+        #[related]
+        problems: Vec<ShamanError>,
+    },
+
+    #[error("Cells {from} and {to} are not adjacent, no single action connects them")]
+    NotAdjacent { from: Vertex, to: Vertex },
+}
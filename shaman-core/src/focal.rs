@@ -0,0 +1,175 @@
+//! Focal search, letting the high level of [Pbs](crate::Pbs) & CBS trade solution quality for
+//! speed, see [crate::PlannerConfig::suboptimality]
+//!
+//! Instead of always expanding the cheapest node, focal search expands the node with the fewest
+//! conflicts among all nodes costing at most the bound times as much as the cheapest one. These
+//! are usually the closest to a solution, so large maps get solved with far fewer expansions.
+use std::{
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    ops::Bound::{Excluded, Included},
+    rc::Rc,
+};
+
+use ordered_float::OrderedFloat;
+
+/// Open list of a high level search, which also keeps the focal list of all nodes costing at
+/// most `bound` times as much as the cheapest one. Costs & conflicts are counted once per node
+#[derive(Debug)]
+pub(crate) struct Queue<T> {
+    bound: f32,
+    cost: fn(&T) -> f32,
+    conflicts: fn(&T) -> usize,
+    /// All nodes without a `bound` above `1`, which don't need the lists below
+    heap: BinaryHeap<T>,
+    /// All nodes by their cost, each bucket in the order the nodes would be expanded optimally
+    open: BTreeMap<OrderedFloat<f32>, BTreeSet<Rc<Entry<T>>>>,
+    /// Nodes costing at most `limit` by their conflicts
+    focal: BTreeSet<Focal<T>>,
+    limit: OrderedFloat<f32>,
+    pushed: usize,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    node: T,
+    cost: OrderedFloat<f32>,
+    conflicts: usize,
+    id: usize,
+}
+
+impl<T: Ord> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Cheapest first, then in the nodes' own order, which is the one of a max heap
+        (self.cost, &other.node, self.id).cmp(&(other.cost, &self.node, other.id))
+    }
+}
+impl<T: Ord> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T: Ord> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl<T: Ord> Eq for Entry<T> {}
+
+/// [Entry] ordered by its conflicts first. Ties go to the node which would come first anyway
+#[derive(Debug)]
+struct Focal<T>(Rc<Entry<T>>);
+
+impl<T: Ord> Ord for Focal<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.0.conflicts, &self.0).cmp(&(other.0.conflicts, &other.0))
+    }
+}
+impl<T: Ord> PartialOrd for Focal<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T: Ord> PartialEq for Focal<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<T: Ord> Eq for Focal<T> {}
+
+impl<T: Ord> Queue<T> {
+    /// An empty queue expanding nodes with the fewest `conflicts` among those costing at most
+    /// `bound` times as much as the cheapest one, the cheapest one without a `bound` above `1`
+    pub fn new(bound: f32, cost: fn(&T) -> f32, conflicts: fn(&T) -> usize) -> Self {
+        Self {
+            bound,
+            cost,
+            conflicts,
+            heap: BinaryHeap::new(),
+            open: BTreeMap::new(),
+            focal: BTreeSet::new(),
+            limit: OrderedFloat(f32::NEG_INFINITY),
+            pushed: 0,
+        }
+    }
+
+    pub fn push(&mut self, node: T) {
+        if self.bound <= 1. {
+            return self.heap.push(node);
+        }
+        let entry = Rc::new(Entry {
+            cost: (self.cost)(&node).into(),
+            conflicts: (self.conflicts)(&node),
+            id: self.pushed,
+            node,
+        });
+        self.pushed += 1;
+        if entry.cost <= self.limit {
+            self.focal.insert(Focal(entry.clone()));
+        }
+        self.open.entry(entry.cost).or_default().insert(entry);
+    }
+
+    /// Take the next node to expand, see [Queue::new]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.bound <= 1. {
+            return self.heap.pop();
+        }
+        self.refocus();
+        let Focal(entry) = self.focal.pop_first()?;
+        let bucket = self.open.get_mut(&entry.cost)?;
+        bucket.remove(&entry);
+        if bucket.is_empty() {
+            self.open.remove(&entry.cost);
+        }
+        Some(
+            Rc::into_inner(entry)
+                .expect("popped nodes are only queued once")
+                .node,
+        )
+    }
+
+    /// Move the nodes whose cost crossed the limit since the cheapest one changed in or out of
+    /// the focal list
+    fn refocus(&mut self) {
+        let Some(&cheapest) = self.open.keys().next() else {
+            return;
+        };
+        let limit = OrderedFloat(cheapest.0 * self.bound);
+        if limit == self.limit {
+            return;
+        } else if limit > self.limit {
+            let entering = self.open.range((Excluded(self.limit), Included(limit)));
+            let entering = entering.flat_map(|(_, bucket)| bucket).cloned();
+            self.focal.extend(entering.map(Focal));
+        } else {
+            for (_, bucket) in self.open.range((Excluded(limit), Included(self.limit))) {
+                for entry in bucket {
+                    self.focal.remove(&Focal(entry.clone()));
+                }
+            }
+        }
+        self.limit = limit;
+    }
+}
+
+impl<T> IntoIterator for Queue<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        drop(self.focal);
+        let entries = self.open.into_values().flatten();
+        let nodes = entries.filter_map(|entry| Some(Rc::into_inner(entry)?.node));
+        self.heap
+            .into_iter()
+            .chain(nodes)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl<T: Ord> Extend<T> for Queue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, nodes: I) {
+        nodes.into_iter().for_each(|node| self.push(node));
+    }
+}
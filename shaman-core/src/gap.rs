@@ -0,0 +1,194 @@
+//! Comparing plans against reference solutions, e.g. those of optimal solvers
+//!
+//! Plans are JSON documents like the ones exported for a solved [Shaman], of which only the
+//! robots' names & costs are compared:
+//!
+//! ```json
+//! {"robots": [{"name": "A", "cost": 12}, {"name": "B", "route": [{"x": 1, "y": 2, "t": 0}]}]}
+//! ```
+//!
+//! Robots without a `cost` use the time of the last location of their `route` instead.
+use std::{fmt::Display, path::Path};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    Shaman, Time,
+    json::{Json, ToJson},
+    messages::{English, Locale},
+};
+
+/// Costs of each robot in a solution of some scenario
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// Sorted by name
+    pub robots: Vec<(char, Time)>,
+}
+
+/// How much worse a plan does than the reference plan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub cost: Time,
+    pub reference: Time,
+}
+
+/// Result of [Plan::compare]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    /// Gap of each robot, sorted by name
+    pub robots: Vec<(char, Gap)>,
+}
+
+impl Plan {
+    pub fn from_json(json: &Json) -> Result<Self> {
+        let robots = json
+            .get("robots")
+            .and_then(Json::as_array)
+            .ok_or_else(|| miette!("Plan has no 'robots' list"))?;
+        let robots = robots
+            .iter()
+            .map(|robot| {
+                let name = robot
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .and_then(|n| n.chars().exactly_one().ok())
+                    .ok_or_else(|| miette!("Plan has a robot without single letter 'name'"))?;
+                let cost = robot.get("cost").and_then(Json::as_f64).or_else(|| {
+                    let route = robot.get("route")?.as_array()?;
+                    route.last()?.get("t")?.as_f64()
+                });
+                let cost = cost.ok_or_else(|| miette!("Plan has no cost of robot '{name}'"))?;
+                Ok((name, cost as Time))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(name) = robots.iter().map(|(n, _)| n).duplicates().next() {
+            return Err(miette!("Plan has robot '{name}' more than once"));
+        }
+        Ok(Self {
+            robots: robots.into_iter().sorted().collect(),
+        })
+    }
+
+    /// Read a plan from a JSON `file`
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let file = file.as_ref().display().to_string();
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        Json::parse(&content)
+            .and_then(|json| Self::from_json(&json))
+            .map_err(|e| miette!("{file}: {e}"))
+    }
+
+    /// Measure how much worse this plan does than `reference` for the same robots
+    pub fn compare(&self, reference: &Plan) -> Result<Comparison> {
+        let names = |plan: &Plan| plan.robots.iter().map(|(n, _)| *n).collect_vec();
+        if names(self) != names(reference) {
+            return Err(miette!(
+                "Plans are for different robots: {} vs. {}",
+                names(self).iter().join(""),
+                names(reference).iter().join("")
+            ));
+        }
+        let robots = self
+            .robots
+            .iter()
+            .zip(&reference.robots)
+            .map(|((name, cost), (_, reference))| {
+                let gap = Gap {
+                    cost: *cost,
+                    reference: *reference,
+                };
+                (*name, gap)
+            })
+            .collect();
+        Ok(Comparison { robots })
+    }
+}
+
+impl From<&Shaman> for Plan {
+    fn from(shaman: &Shaman) -> Self {
+        Self {
+            robots: shaman
+                .robots
+                .values()
+                .map(|r| (r.name(), r.route().duration()))
+                .sorted()
+                .collect(),
+        }
+    }
+}
+
+impl Gap {
+    /// Timesteps more than the reference, negative if this plan is better
+    pub fn absolute(&self) -> isize {
+        self.cost as isize - self.reference as isize
+    }
+
+    /// [Self::absolute] relative to the reference cost
+    pub fn relative(&self) -> f64 {
+        self.absolute() as f64 / self.reference.max(1) as f64
+    }
+}
+
+impl Comparison {
+    /// Gap of the sum of all costs
+    pub fn total(&self) -> Gap {
+        Gap {
+            cost: self.robots.iter().map(|(_, g)| g.cost).sum(),
+            reference: self.robots.iter().map(|(_, g)| g.reference).sum(),
+        }
+    }
+
+    /// Gap of the time the last robot arrives
+    pub fn makespan(&self) -> Gap {
+        let max = |f: fn(&Gap) -> Time| self.robots.iter().map(|(_, g)| f(g)).max();
+        Gap {
+            cost: max(|g| g.cost).unwrap_or_default(),
+            reference: max(|g| g.reference).unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for Gap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>4}  {:>9}  {:>+4} ({:+.1}%)",
+            self.cost,
+            self.reference,
+            self.absolute(),
+            self.relative() * 100.
+        )
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", English.render(self))
+    }
+}
+
+impl ToJson for Gap {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("cost", self.cost.to_json()),
+            ("reference", self.reference.to_json()),
+            ("gap", self.absolute().to_json()),
+            ("relative", self.relative().to_json()),
+        ])
+    }
+}
+
+impl ToJson for Comparison {
+    fn to_json(&self) -> Json {
+        let robots = self
+            .robots
+            .iter()
+            .map(|(name, gap)| Json::object([("name", name.to_json()), ("gap", gap.to_json())]));
+        Json::object([
+            ("total", self.total().to_json()),
+            ("makespan", self.makespan().to_json()),
+            ("robots", Json::array(robots)),
+        ])
+    }
+}
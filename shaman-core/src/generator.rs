@@ -0,0 +1,111 @@
+//! Random layouts from scratch, to benchmark & fuzz the solvers on maps nobody drew
+use std::collections::VecDeque;
+
+use itertools::Itertools;
+use miette::{Result, miette};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    ShamanBuilder,
+    layout::Vertex,
+    rng::{RandomSource, Rng},
+};
+
+impl ShamanBuilder {
+    /// Empty `width` x `height` layout enclosed by a border, with `density` (`0.0..1.0`) of
+    /// the cells inside it blocked by random obstacles. All free cells stay connected, so
+    /// robots placed by [Self::random_robots] afterwards can reach any goal. The same `seed`
+    /// always yields the same layout
+    pub fn random_layout(width: usize, height: usize, density: f32, seed: u64) -> Result<Self> {
+        Self::random_layout_with(width, height, density, &mut Rng::new(seed))
+    }
+
+    /// Like [Self::random_layout], but drawing from `rng`
+    pub fn random_layout_with(
+        width: usize,
+        height: usize,
+        density: f32,
+        rng: &mut impl RandomSource,
+    ) -> Result<Self> {
+        if width < 3 || height < 3 {
+            return Err(miette!(
+                "Layout of {width}x{height} cells has no space inside its border"
+            ));
+        }
+        if !(0. ..1.).contains(&density) {
+            return Err(miette!("Obstacle density must be in 0..1, not {density}"));
+        }
+        let (w, h) = (width as i32 - 1, height as i32 - 1);
+        let mut builder = (0..=w)
+            .cartesian_product(0..=h)
+            .filter(|(x, y)| *x == 0 || *y == 0 || *x == w || *y == h)
+            .fold(Self::new(width, height), |b, (x, y)| {
+                b.block(Vertex::new(x, y))
+            });
+
+        let mut free = (1..h)
+            .cartesian_product(1..w)
+            .map(|(y, x)| Vertex::new(x, y))
+            .collect::<FxHashSet<_>>();
+        let mut candidates = free
+            .iter()
+            .copied()
+            .sorted_by_key(|v| (v.y, v.x))
+            .collect_vec();
+        rng.shuffle(&mut candidates);
+
+        let target = (density * free.len() as f32).round() as usize;
+        let mut blocked = 0;
+        for v in candidates {
+            if blocked == target {
+                break;
+            }
+            // Skip obstacles which would cut off parts of the free space
+            free.remove(&v);
+            if still_connected(&free, v) {
+                builder = builder.block(v);
+                blocked += 1;
+            } else {
+                free.insert(v);
+            }
+        }
+        Ok(builder)
+    }
+}
+
+/// Horizontal & vertical moves, which connect free cells
+const STEPS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Whether every cell of `free` can still be reached from any other after blocking `v`, given
+/// they could before. That's the case if the free neighbours of `v` still reach each other,
+/// which searches from each of them in lockstep tell without exploring the whole layout: they
+/// usually meet around `v`, or a pocket being cut off runs out of cells quickly
+fn still_connected(free: &FxHashSet<Vertex>, v: Vertex) -> bool {
+    let near = |v: Vertex| STEPS.map(|(dx, dy)| Vertex::new(v.x + dx, v.y + dy));
+    let neighbours = near(v)
+        .into_iter()
+        .filter(|n| free.contains(n))
+        .collect_vec();
+    if neighbours.len() < 2 {
+        return true;
+    }
+    let mut searches = neighbours
+        .iter()
+        .map(|n| (FxHashSet::from_iter([*n]), VecDeque::from([*n])))
+        .collect_vec();
+    loop {
+        for (visited, queue) in &mut searches {
+            if neighbours.iter().all(|n| visited.contains(n)) {
+                return true;
+            }
+            let Some(v) = queue.pop_front() else {
+                return false;
+            };
+            for n in near(v) {
+                if free.contains(&n) && visited.insert(n) {
+                    queue.push_back(n);
+                }
+            }
+        }
+    }
+}
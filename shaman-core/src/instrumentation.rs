@@ -0,0 +1,150 @@
+//! Counting what solvers do, to compare algorithms & tune their configuration
+//!
+//! Counters are only recorded while [Shaman::solve_with_stats] runs on the current thread, so
+//! solving without asking for statistics costs nothing
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use miette::Result;
+
+use crate::{
+    Algorithm, Shaman, Time,
+    json::{Json, ToJson},
+};
+
+thread_local! {
+    static RECORDING: RefCell<Option<SolveStats>> = const { RefCell::new(None) };
+}
+
+/// What a solver did to find a solution, see [Shaman::solve_with_stats]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SolveStats {
+    pub algorithm: Algorithm,
+    /// States expanded by each low level search for a single route, in the order they ran
+    pub expansions: Vec<usize>,
+    /// High level nodes created, i.e. PBS ideas or nodes of the CBS constraint tree
+    pub generated: usize,
+    /// High level nodes checked for conflicts
+    pub expanded: usize,
+    /// Conflicts found between pairs of robots, each resolved by branching
+    pub conflicts: usize,
+    /// Wall time solving took
+    pub duration: Duration,
+    /// Sum of all robots' route durations of the solution
+    pub cost: Time,
+    /// Factor the solution costs at most more than an optimal one, i.e. its [Self::cost]
+    /// relative to the [Shaman::lower_bound] no solution undercuts
+    pub bound: f32,
+}
+
+/// Update the statistics with `f`, if any are being recorded on this thread
+pub(crate) fn record(f: impl FnOnce(&mut SolveStats)) {
+    RECORDING.with_borrow_mut(|stats| {
+        if let Some(stats) = stats {
+            f(stats)
+        }
+    });
+}
+
+/// Run `a` & `b` on the threads of a pool, counting their work as if they ran on this thread
+#[cfg(feature = "parallel")]
+pub(crate) fn join<A: Send, B: Send>(
+    a: impl FnOnce() -> A + Send,
+    b: impl FnOnce() -> B + Send,
+) -> (A, B) {
+    let recording = RECORDING.with_borrow(Option::is_some);
+    let ((a, counted_a), (b, counted_b)) =
+        rayon::join(|| counted(recording, a), || counted(recording, b));
+    record(|stats| {
+        for counted in [counted_a, counted_b].into_iter().flatten() {
+            stats.expansions.extend(counted.expansions);
+            stats.generated += counted.generated;
+            stats.expanded += counted.expanded;
+            stats.conflicts += counted.conflicts;
+        }
+    });
+    (a, b)
+}
+
+/// Result of `f` with what it did, if `recording`
+#[cfg(feature = "parallel")]
+fn counted<T>(recording: bool, f: impl FnOnce() -> T) -> (T, Option<SolveStats>) {
+    if !recording {
+        return (f(), None);
+    }
+    let previous = RECORDING.replace(Some(SolveStats::default()));
+    let result = f();
+    (result, RECORDING.replace(previous))
+}
+
+impl Shaman {
+    /// Like [Self::solve_with], but also count how much work that took
+    pub fn solve_with_stats(self, algorithm: Algorithm) -> Result<(Self, SolveStats)> {
+        let previous = RECORDING.replace(Some(SolveStats {
+            algorithm,
+            ..Default::default()
+        }));
+        let started = Instant::now();
+        let solved = self.solve_with(algorithm);
+        let stats = RECORDING.replace(previous);
+        let mut stats = stats.unwrap_or_default();
+        stats.duration = started.elapsed();
+        let solved = solved?;
+        stats.cost = solved.cost();
+        stats.bound = stats.cost as f32 / solved.lower_bound().max(1) as f32;
+        Ok((solved, stats))
+    }
+}
+
+impl SolveStats {
+    /// States expanded by all low level searches together
+    pub fn total_expansions(&self) -> usize {
+        self.expansions.iter().sum()
+    }
+
+    /// Average states expanded by a single low level search
+    pub fn mean_expansions(&self) -> f32 {
+        self.total_expansions() as f32 / self.expansions.len().max(1) as f32
+    }
+}
+
+impl Display for SolveStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "algorithm: {:?}", self.algorithm)?;
+        writeln!(
+            f,
+            "searches:  {} ({} states expanded, {:.1} on average, at most {})",
+            self.expansions.len(),
+            self.total_expansions(),
+            self.mean_expansions(),
+            self.expansions.iter().max().unwrap_or(&0)
+        )?;
+        writeln!(
+            f,
+            "nodes:     {} generated, {} expanded",
+            self.generated, self.expanded
+        )?;
+        writeln!(f, "conflicts: {}", self.conflicts)?;
+        writeln!(f, "time:      {:.3}s", self.duration.as_secs_f32())?;
+        writeln!(f, "cost:      {}", self.cost)?;
+        writeln!(f, "bound:     {:.2}", self.bound)
+    }
+}
+
+impl ToJson for SolveStats {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("algorithm", format!("{:?}", self.algorithm).to_json()),
+            ("expansions", Json::array(&self.expansions)),
+            ("generated", self.generated.to_json()),
+            ("expanded", self.expanded.to_json()),
+            ("conflicts", self.conflicts.to_json()),
+            ("duration", self.duration.as_secs_f64().to_json()),
+            ("cost", self.cost.to_json()),
+            ("bound", self.bound.to_json()),
+        ])
+    }
+}
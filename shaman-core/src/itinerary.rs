@@ -0,0 +1,173 @@
+//! Step-by-step instructions for each robot, for operators who don't read coordinate lists
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::{
+    Shaman, Time,
+    astar::{Action, Rotation},
+    json::{Json, ToJson},
+    layout::Vertex,
+    robot::Robot,
+};
+
+/// Route of a single robot as a sequence of [Leg]s, see [Robot::itinerary]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Itinerary {
+    pub robot: char,
+    /// Where the robot is when its route starts
+    pub start: Vertex,
+    /// Where the robot ends up, i.e. its goal if the route reaches it
+    pub end: Vertex,
+    pub legs: Vec<Leg>,
+}
+
+/// Repeating the same action from one timestep to a later one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leg {
+    pub action: Action,
+    /// Timestep the first action starts
+    pub from: Time,
+    /// Timestep the last action ends
+    pub to: Time,
+    /// Cells moved, `0` when waiting or rotating in place
+    pub cells: usize,
+}
+
+impl Shaman {
+    /// [Robot::itinerary] of every robot, sorted by name
+    pub fn itineraries(&self) -> Vec<Itinerary> {
+        self.robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .map(Robot::itinerary)
+            .collect()
+    }
+}
+
+impl Robot {
+    /// This robot's route as a run-length encoded sequence of actions. The waits of slow robots
+    /// before each move, due to their [Self::pace], count as part of moving
+    pub fn itinerary(&self) -> Itinerary {
+        let start = self.route().iter().next();
+        let from = start.map_or(0, |l| l.time);
+        let actions = self.route().to_actions().unwrap_or_default();
+        let dwell = self.pace() - 1;
+
+        // Each step is a single action & how many timesteps it takes
+        let mut steps = vec![];
+        let mut i = 0;
+        while i < actions.len() {
+            // Waiting right before moving is part of the move for slow robots
+            let paced = dwell > 0
+                && actions.get(i + dwell).is_some_and(Action::is_move)
+                && actions[i..i + dwell].iter().all(|a| *a == Action::Wait);
+            let step = match paced {
+                true => (actions[i + dwell], dwell + 1),
+                false => (actions[i], 1),
+            };
+            steps.push(step);
+            i += step.1;
+        }
+
+        let mut time = from;
+        let legs = steps
+            .into_iter()
+            .chunk_by(|(action, _)| *action)
+            .into_iter()
+            .map(|(action, steps)| {
+                let (count, duration) = steps.fold((0, 0), |(c, d), (_, t)| (c + 1, d + t));
+                let leg = Leg {
+                    action,
+                    from: time,
+                    to: time + duration,
+                    cells: if action.is_move() { count } else { 0 },
+                };
+                time += duration;
+                leg
+            })
+            .collect();
+        Itinerary {
+            robot: self.name(),
+            start: start.map_or(self.position().0, |l| l.position),
+            end: self
+                .route()
+                .iter()
+                .last()
+                .map_or(self.position().0, |l| l.position),
+            legs,
+        }
+    }
+}
+
+/// Compass direction an `action` moves into, the way it rotates or `wait`
+fn heading(action: Action) -> &'static str {
+    match action {
+        Action::Wait => "wait",
+        Action::Rotate(Rotation::Clockwise) => "clockwise",
+        Action::Rotate(Rotation::Counterclockwise) => "counterclockwise",
+        Action::N => "north",
+        Action::S => "south",
+        Action::W => "west",
+        Action::E => "east",
+        Action::NE => "north-east",
+        Action::NW => "north-west",
+        Action::SE => "south-east",
+        Action::SW => "south-west",
+    }
+}
+
+impl Display for Leg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps = self.to - self.from;
+        match steps {
+            1 => write!(f, "t={}", self.from)?,
+            _ => write!(f, "t={}–{}", self.from, self.to)?,
+        }
+        match (self.action, self.cells) {
+            (Action::Wait, _) if steps == 1 => write!(f, " wait"),
+            (Action::Wait, _) => write!(f, " wait {steps} steps"),
+            (Action::Rotate(_), _) if steps == 1 => write!(f, " turn {}", heading(self.action)),
+            (Action::Rotate(_), _) => {
+                write!(f, " turn {} {steps} times", heading(self.action))
+            }
+            (action, 1) => write!(f, " go {} 1 cell", heading(action)),
+            (action, cells) => write!(f, " go {} {cells} cells", heading(action)),
+        }
+    }
+}
+
+impl Display for Itinerary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {} → {}", self.robot, self.start, self.end)?;
+        if self.legs.is_empty() {
+            return writeln!(f, "  stay");
+        }
+        for leg in &self.legs {
+            writeln!(f, "  {leg}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ToJson for Leg {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("action", heading(self.action).to_json()),
+            ("from", self.from.to_json()),
+            ("to", self.to.to_json()),
+            ("cells", self.cells.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Itinerary {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("name", self.robot.to_json()),
+            ("start", self.start.to_json()),
+            ("end", self.end.to_json()),
+            ("legs", Json::array(&self.legs)),
+        ])
+    }
+}
@@ -0,0 +1,334 @@
+//! Minimal JSON (de)serialization for exporting results & importing plans
+use std::fmt::Display;
+
+use miette::{Result, miette};
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::{tag, take, take_while1},
+    character::complete::{char, multispace0},
+    combinator::{all_consuming, map_opt, value},
+    multi::{fold_many0, separated_list0},
+    number::complete::double,
+    sequence::{delimited, preceded, separated_pair},
+};
+
+use crate::{
+    layout::Vertex,
+    robot::Location,
+    route::{Route, RouteDiff},
+};
+
+/// A JSON document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// Types which can be exported as JSON
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+impl Json {
+    /// Build an object from `(key, value)` pairs, keeping their order
+    pub fn object<K: Into<String>>(fields: impl IntoIterator<Item = (K, Json)>) -> Self {
+        Self::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    pub fn array<T: ToJson>(items: impl IntoIterator<Item = T>) -> Self {
+        Self::Array(items.into_iter().map(|i| i.to_json()).collect())
+    }
+
+    /// Read a JSON document, e.g. a plan of another solver
+    pub fn parse(s: &str) -> Result<Self> {
+        all_consuming(delimited(multispace0, json, multispace0))
+            .parse(s)
+            .map(|(_, json)| json)
+            .map_err(|e| match e {
+                nom::Err::Error(e) | nom::Err::Failure(e) => {
+                    miette!("Invalid JSON at byte {}", s.len() - e.input.len())
+                }
+                nom::Err::Incomplete(_) => miette!("Incomplete JSON"),
+            })
+    }
+
+    /// The same document in block style YAML, e.g. for tools configured in YAML anyway
+    pub fn to_yaml(&self) -> String {
+        let mut yaml = String::new();
+        self.write_yaml(&mut yaml, 0);
+        if !self.is_block() {
+            yaml.push('\n');
+        }
+        yaml
+    }
+
+    /// Non-empty arrays & objects, which YAML writes on lines of their own
+    fn is_block(&self) -> bool {
+        match self {
+            Self::Array(items) => !items.is_empty(),
+            Self::Object(fields) => !fields.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Append blocks with a line per item, each indented by `indent` spaces. Everything else is
+    /// written inline as JSON, which YAML reads the same
+    fn write_yaml(&self, yaml: &mut String, indent: usize) {
+        let pad = " ".repeat(indent);
+        match self {
+            Self::Array(items) if self.is_block() => {
+                for item in items {
+                    let mut nested = String::new();
+                    item.write_yaml(&mut nested, indent + 2);
+                    match item.is_block() {
+                        true => yaml.push_str(&format!("{pad}- {}", &nested[indent + 2..])),
+                        false => yaml.push_str(&format!("{pad}- {nested}\n")),
+                    }
+                }
+            }
+            Self::Object(fields) if self.is_block() => {
+                for (key, value) in fields {
+                    let plain = key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    let key = match plain && !key.is_empty() {
+                        true => key.clone(),
+                        false => Self::String(key.clone()).to_string(),
+                    };
+                    match value.is_block() {
+                        true => {
+                            yaml.push_str(&format!("{pad}{key}:\n"));
+                            value.write_yaml(yaml, indent + 2);
+                        }
+                        false => yaml.push_str(&format!("{pad}{key}: {value}\n")),
+                    }
+                }
+            }
+            _ => yaml.push_str(&self.to_string()),
+        }
+    }
+
+    /// Value of the field `key`, if this is an object having it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+fn json(s: &str) -> IResult<&str, Json> {
+    let token = |c| delimited(multispace0, char(c), multispace0);
+    alt((
+        value(Json::Null, tag("null")),
+        value(Json::Bool(true), tag("true")),
+        value(Json::Bool(false), tag("false")),
+        double.map(Json::Number),
+        string.map(Json::String),
+        delimited(token('['), separated_list0(token(','), json), token(']')).map(Json::Array),
+        delimited(
+            token('{'),
+            separated_list0(token(','), separated_pair(string, token(':'), json)),
+            token('}'),
+        )
+        .map(Json::Object),
+    ))
+    .parse(s)
+}
+
+fn string(s: &str) -> IResult<&str, String> {
+    let escaped = preceded(
+        char('\\'),
+        alt((
+            value('"', char('"')),
+            value('\\', char('\\')),
+            value('/', char('/')),
+            value('\n', char('n')),
+            value('\r', char('r')),
+            value('\t', char('t')),
+            value('\u{8}', char('b')),
+            value('\u{c}', char('f')),
+            map_opt(preceded(char('u'), take(4usize)), |hex: &str| {
+                char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+            }),
+        )),
+    );
+    let chunk = alt((
+        take_while1(|c| c != '"' && c != '\\').map(str::to_string),
+        escaped.map(String::from),
+    ));
+    delimited(
+        char('"'),
+        fold_many0(chunk, String::new, |acc, chunk| acc + &chunk),
+        char('"'),
+    )
+    .parse(s)
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> Json {
+        (*self).to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Json {
+        self.as_ref().map_or(Json::Null, |t| t.to_json())
+    }
+}
+
+impl ToJson for Json {
+    fn to_json(&self) -> Json {
+        self.clone()
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> Json {
+        Json::Bool(*self)
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> Json {
+        Json::String(self.to_string())
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> Json {
+        Json::String(self.to_string())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Json {
+        Json::String(self.clone())
+    }
+}
+
+macro_rules! number {
+    ($($t:ty),*) => {
+        $(impl ToJson for $t {
+            fn to_json(&self) -> Json {
+                Json::Number(*self as f64)
+            }
+        })*
+    };
+}
+number!(usize, isize, u32, u64, i32, i64, f32, f64);
+
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) if n.is_finite() => write!(f, "{n}"),
+            Self::Number(_) => write!(f, "null"),
+            Self::String(s) => write_string(f, s),
+            Self::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Self::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl ToJson for Vertex {
+    fn to_json(&self) -> Json {
+        Json::object([("x", self.x.to_json()), ("y", self.y.to_json())])
+    }
+}
+
+impl ToJson for Location {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("x", self.position.x.to_json()),
+            ("y", self.position.y.to_json()),
+            ("t", self.time.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Route {
+    fn to_json(&self) -> Json {
+        Json::array(self.iter())
+    }
+}
+
+impl ToJson for RouteDiff {
+    fn to_json(&self) -> Json {
+        Json::object([
+            (
+                "keep_until",
+                self.keep_until.map_or(Json::Null, |t| t.to_json()),
+            ),
+            ("suffix", Json::array(&self.suffix)),
+        ])
+    }
+}
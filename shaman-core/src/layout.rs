@@ -0,0 +1,361 @@
+use itertools::Itertools;
+use miette::NamedSource;
+use std::{
+    fmt::Display,
+    ops::{Add, Bound, RangeBounds, RangeInclusive, Sub},
+    sync::{Arc, RwLock},
+};
+
+use crate::{Time, astar::Action};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::VecDeque;
+
+/// Shortest path lengths from all cells to a single one
+type Distances = Arc<FxHashMap<Vertex, usize>>;
+
+/// The definition of the 2D grid space, with free & blocked cells
+#[derive(Debug, Clone)]
+pub struct Layout {
+    code: NamedSource<String>,
+    space: FxHashSet<Vertex>,
+    width: usize,
+    height: usize,
+    /// Sum of all radii this layout was inflated by
+    inflation: usize,
+    /// Free cells blocked by inflating the obstacles, see [Self::inflate]
+    inflated: FxHashSet<Vertex>,
+    /// Factors of how costly moving from the first to the second cell is, e.g. up a ramp
+    edge_costs: FxHashMap<(Vertex, Vertex), f32>,
+    /// Timesteps free cells are blocked anyway, e.g. while a door is closed, see [Self::close]
+    closures: FxHashMap<Vertex, Vec<RangeInclusive<Time>>>,
+    /// Shortest path lengths to each goal asked for so far, see [Self::distances_to]
+    distance_cache: Arc<RwLock<FxHashMap<Vertex, Distances>>>,
+}
+
+/// Timesteps within `window`, both ends inclusive & open ends lasting forever. `None` if it's
+/// empty
+pub(crate) fn inclusive(window: impl RangeBounds<Time>) -> Option<RangeInclusive<Time>> {
+    let start = match window.start_bound() {
+        Bound::Included(t) => *t,
+        Bound::Excluded(t) => t + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match window.end_bound() {
+        Bound::Included(t) => *t,
+        Bound::Excluded(t) => t.checked_sub(1)?,
+        Bound::Unbounded => Time::MAX,
+    };
+    Some(start..=end).filter(|w| !w.is_empty())
+}
+
+/// Position of each cell in the layout
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Vertex {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Display for Vertex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.x, self.y)
+    }
+}
+impl Vertex {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn distance_squared(&self, other: Self) -> f32 {
+        ((self.x - other.x) as f32).powi(2) + ((self.y - other.y) as f32).powi(2)
+    }
+
+    /// Amount of horizontal & vertical steps between both vertices
+    pub fn manhattan_distance(&self, other: Self) -> usize {
+        (self.x.abs_diff(other.x) + self.y.abs_diff(other.y)) as usize
+    }
+
+    /// Whether moving diagonally from this vertex to `to` crosses the `other` move between the
+    /// remaining two corners of the same square, in either direction
+    pub(crate) fn crosses(&self, to: Self, other: (Self, Self)) -> bool {
+        let (a, b) = (Self::new(self.x, to.y), Self::new(to.x, self.y));
+        self.x != to.x && self.y != to.y && (other == (a, b) || other == (b, a))
+    }
+}
+
+impl Add for Vertex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+/// Lossy: non-adjacent vertices yield [Action::Wait], use [Action::try_from] to detect those
+impl Sub for Vertex {
+    type Output = Action;
+
+    fn sub(self, other: Self) -> Self::Output {
+        let d = Self::new(self.x - other.x, self.y - other.y);
+        Action::ALL
+            .iter()
+            .chain(&Action::DIAGONAL)
+            .copied()
+            .find(|a| a.direction() == d)
+            .unwrap_or_default()
+    }
+}
+
+impl Layout {
+    pub fn empty(code: NamedSource<String>, width: usize, height: usize) -> Self {
+        Self {
+            code,
+            space: (0..width)
+                .cartesian_product(0..height)
+                .map(|(x, y)| Vertex::new(x as i32, y as i32))
+                .collect(),
+            width,
+            height,
+            inflation: 0,
+            inflated: Default::default(),
+            edge_costs: Default::default(),
+            closures: Default::default(),
+            distance_cache: Default::default(),
+        }
+    }
+
+    pub(crate) fn free_cell_count(&self) -> usize {
+        self.space.len()
+    }
+
+    pub fn code(&self) -> NamedSource<String> {
+        self.code.clone()
+    }
+
+    /// Amount of rows of this layout
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Amount of columns of this layout
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    /// All cells of the rectangle spanned by both corners `a` & `b` (inclusive), which are
+    /// within this layout. Cells off the map are left out
+    pub(crate) fn rectangle(&self, a: Vertex, b: Vertex) -> impl Iterator<Item = Vertex> + use<> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let xs = a.x.min(b.x).max(0)..=a.x.max(b.x).min(width - 1);
+        let ys = a.y.min(b.y).max(0)..=a.y.max(b.y).min(height - 1);
+        xs.cartesian_product(ys).map(|(x, y)| Vertex::new(x, y))
+    }
+
+    /// How far robots keep away from obstacles, see [Self::inflate]
+    pub(crate) fn inflation(&self) -> usize {
+        self.inflation
+    }
+
+    /// Cells blocked by inflating the obstacles rather than drawn as such, see [Self::inflate]
+    pub(crate) fn inflated(&self) -> &FxHashSet<Vertex> {
+        &self.inflated
+    }
+
+    /// Restore how far robots keep away from obstacles along with the `cells` this blocked
+    pub(crate) fn restore_inflation(&mut self, inflation: usize, cells: &[Vertex]) {
+        self.inflation = inflation;
+        for v in cells {
+            if self.block(*v) {
+                self.inflated.insert(*v);
+            }
+        }
+    }
+
+    /// Factor of how costly moving from `from` to the adjacent `to` is, `1` unless overridden
+    /// & never below. Costs are directional, so moving back from `to` to `from` may cost
+    /// differently
+    pub fn edge_cost(&self, from: Vertex, to: Vertex) -> f32 {
+        self.edge_costs.get(&(from, to)).copied().unwrap_or(1.)
+    }
+
+    pub(crate) fn set_edge_cost(&mut self, from: Vertex, to: Vertex, cost: f32) {
+        self.edge_costs.insert((from, to), cost);
+    }
+
+    /// All overridden [Self::edge_cost]s
+    pub(crate) fn edge_costs(&self) -> impl Iterator<Item = ((Vertex, Vertex), f32)> + '_ {
+        self.edge_costs.iter().map(|(e, c)| (*e, *c))
+    }
+
+    /// Mark a single [Vertex] of this layout as obstacle
+    pub(crate) fn block(&mut self, v: Vertex) -> bool {
+        self.distance_cache = Default::default();
+        self.space.remove(&v)
+    }
+
+    pub fn is_blocked(&self, v: Vertex) -> bool {
+        !self.space.contains(&v)
+    }
+
+    /// Block the free cell `v` during `window` only, e.g. a door closing at timestep 10 with
+    /// `10..=Time::MAX`. Shortest distances ignore closures, so they stay admissible. Obstacles
+    /// & cells off the map are blocked all the time anyway
+    pub(crate) fn close(&mut self, v: Vertex, window: RangeInclusive<Time>) {
+        if !window.is_empty() && !self.is_blocked(v) {
+            self.closures.entry(v).or_default().push(window);
+        }
+    }
+
+    /// Whether `v` is an obstacle or closed at `time`, see [Self::close]
+    pub fn is_blocked_at(&self, v: Vertex, time: Time) -> bool {
+        self.is_blocked(v)
+            || self
+                .closures
+                .get(&v)
+                .is_some_and(|windows| windows.iter().any(|w| w.contains(&time)))
+    }
+
+    /// When `v` is closed, see [Self::close]
+    pub(crate) fn closed(&self, v: Vertex) -> &[RangeInclusive<Time>] {
+        self.closures.get(&v).map_or(&[], |windows| windows)
+    }
+
+    /// First timestep after `time` at which `v` is closed, so robots can't stay there forever
+    pub(crate) fn closes_after(&self, v: Vertex, time: Time) -> Option<Time> {
+        self.closures
+            .get(&v)?
+            .iter()
+            .filter(|w| *w.end() > time)
+            .map(|w| (*w.start()).max(time + 1))
+            .min()
+    }
+
+    /// All cells closed for a while, with when they are, see [Self::close]
+    pub(crate) fn closures(&self) -> impl Iterator<Item = (Vertex, RangeInclusive<Time>)> + '_ {
+        self.closures
+            .iter()
+            .flat_map(|(v, windows)| windows.iter().map(|w| (*v, w.clone())))
+    }
+
+    /// Timestep from which on no cell opens or closes anymore
+    pub(crate) fn settled(&self) -> Time {
+        self.closures()
+            .map(|(_, w)| match *w.end() {
+                Time::MAX => *w.start(),
+                end => end + 1,
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Block all cells within a Chebyshev distance of `radius` to any obstacle or the edge of
+    /// the map, so robots keep a safety margin to walls, drawn or not
+    pub fn inflate(&mut self, radius: usize) {
+        self.inflation += radius;
+        let r = radius as i32;
+        // Including the ring of cells just off the map
+        let obstacles = (-1..=self.width as i32)
+            .cartesian_product(-1..=self.height as i32)
+            .map(|(x, y)| Vertex::new(x, y))
+            .filter(|v| self.is_blocked(*v))
+            .collect::<Vec<_>>();
+        for v in obstacles {
+            for (dx, dy) in (-r..=r).cartesian_product(-r..=r) {
+                let u = v + Vertex::new(dx, dy);
+                if self.block(u) {
+                    self.inflated.insert(u);
+                }
+            }
+        }
+    }
+
+    /// All cells of this layout, where robots can move
+    pub(crate) fn free_cells(&self) -> impl Iterator<Item = Vertex> + '_ {
+        self.space.iter().copied()
+    }
+
+    /// Free cells which can be reached from `v` by a single action (excluding waiting)
+    pub(crate) fn neighbors(&self, v: Vertex) -> impl Iterator<Item = Vertex> + '_ {
+        Action::ALL
+            .iter()
+            .filter(|a| **a != Action::Wait)
+            .map(move |a| a.apply(v))
+            .filter(|n| !self.is_blocked(*n))
+    }
+
+    /// Length of the shortest path from `from` to `to` ignoring all robots, if there is any
+    pub(crate) fn distance(&self, from: Vertex, to: Vertex) -> Option<usize> {
+        self.distance_avoiding(from, to, &FxHashSet::default())
+    }
+
+    /// Like [Self::distance], but never entering any of the `avoided` cells
+    pub(crate) fn distance_avoiding(
+        &self,
+        from: Vertex,
+        to: Vertex,
+        avoided: &FxHashSet<Vertex>,
+    ) -> Option<usize> {
+        let mut visited = FxHashSet::from_iter([from]);
+        let mut queue = VecDeque::from([(from, 0)]);
+        while let Some((current, d)) = queue.pop_front() {
+            if current == to {
+                return Some(d);
+            }
+            for n in self.neighbors(current).filter(|n| !avoided.contains(n)) {
+                if visited.insert(n) {
+                    queue.push_back((n, d + 1));
+                }
+            }
+        }
+        None
+    }
+
+    /// Length of the shortest paths from `from` to every reachable cell ignoring all robots
+    pub(crate) fn distances(&self, from: Vertex) -> FxHashMap<Vertex, usize> {
+        let mut distances = FxHashMap::from_iter([(from, 0)]);
+        let mut queue = VecDeque::from([(from, 0)]);
+        while let Some((current, d)) = queue.pop_front() {
+            for n in self.neighbors(current) {
+                distances.entry(n).or_insert_with(|| {
+                    queue.push_back((n, d + 1));
+                    d + 1
+                });
+            }
+        }
+        distances
+    }
+
+    /// Like [Self::distances], but computed only once for each `goal`. Changing the layout
+    /// invalidates all cached distances
+    pub(crate) fn distances_to(&self, goal: Vertex) -> Distances {
+        if let Some(distances) = self.distance_cache.read().unwrap().get(&goal) {
+            return distances.clone();
+        }
+        let distances = Arc::new(self.distances(goal));
+        self.distance_cache
+            .write()
+            .unwrap()
+            .insert(goal, distances.clone());
+        distances
+    }
+
+    /// All free cells connected to `v`, i.e. its connected component of free space
+    pub(crate) fn reachable(&self, v: Vertex) -> FxHashSet<Vertex> {
+        let mut visited = FxHashSet::default();
+        if self.is_blocked(v) {
+            return visited;
+        }
+        let mut queue = VecDeque::from([v]);
+        visited.insert(v);
+        while let Some(current) = queue.pop_front() {
+            for n in self.neighbors(current) {
+                if visited.insert(n) {
+                    queue.push_back(n);
+                }
+            }
+        }
+        visited
+    }
+}
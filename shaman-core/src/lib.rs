@@ -0,0 +1,538 @@
+mod analysis;
+mod archive;
+mod assignment;
+mod astar;
+mod audit;
+mod auto;
+mod builder;
+mod calibration;
+mod cbs;
+mod check;
+mod checkpoint;
+mod config;
+mod conflicts;
+mod decompose;
+mod error;
+mod focal;
+mod gap;
+mod generator;
+mod instrumentation;
+mod itinerary;
+mod json;
+mod layout;
+mod lns;
+mod lookahead;
+mod messages;
+mod minimize;
+mod objective;
+mod parser;
+mod pbs;
+mod prioritized;
+mod quality;
+mod render;
+mod reservations;
+mod rng;
+mod robot;
+mod route;
+mod script;
+mod sensitivity;
+#[cfg(feature = "serde")]
+mod serialize;
+mod simulation;
+mod sipp;
+mod solution;
+mod summary;
+mod swaps;
+mod validate;
+
+pub use crate::{
+    analysis::{Bottleneck, Difficulty, Rating},
+    archive::ARCHIVE_EXTENSION,
+    astar::{
+        Action, CostModel, Heuristic, MovementModel, Planner, PlannerConfig, RightOfWay, Rotation,
+    },
+    audit::{Audit, Decision},
+    builder::{Placement, ShamanBuilder},
+    calibration::{Calibration, Trace},
+    config::{CONFIG_FILE, Config, Output},
+    conflicts::{ConflictEdge, ConflictGraph},
+    error::ShamanError,
+    gap::{Comparison, Gap, Plan},
+    instrumentation::SolveStats,
+    itinerary::{Itinerary, Leg},
+    json::{Json, ToJson},
+    layout::{Layout, Vertex},
+    lookahead::{Lookahead, Warning},
+    messages::{English, Locale, Message, Messages},
+    objective::Objective,
+    parser::Format,
+    pbs::Pbs,
+    prioritized::Order,
+    quality::{Grade, Quality},
+    render::{Frame, Rgb, color_for, width},
+    rng::{RandomSource, Rng},
+    robot::{Location, Robot},
+    route::{Collision, Route, RouteDiff},
+    script::Script,
+    sensitivity::Sensitivity,
+    simulation::{Control, Event, Notification, Outcome, Simulation, Snapshot},
+    solution::{
+        Attribution, Conflict, Delay, FleetStats, Flow, Impact, Rejection, Repair, RobotStats,
+        Solution, Stats,
+    },
+    summary::Summary,
+    swaps::SwapSuggestion,
+    validate::Violation,
+};
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::Read,
+    ops::{Range, RangeBounds},
+    path::Path,
+    time::Duration,
+};
+
+use crate::render::{MAGENTA, RESET, YELLOW};
+use itertools::Itertools;
+use miette::{NamedSource, Result, miette};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+pub type Time = usize;
+
+/// How to resolve conflicts between robots, see [Shaman::solve_with]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Algorithm {
+    /// Priority based search: fast, but neither optimal nor complete
+    #[default]
+    Pbs,
+    /// Conflict based search: complete, but slow for many robots
+    Cbs,
+    /// Pick one of the others from the robot count & how much their paths cross or contend
+    /// for corridors
+    Auto,
+    /// Plan robots one after another in a fixed order, each avoiding all planned before it:
+    /// fastest, but fails whenever that order doesn't work out, see [Shaman::with_order]
+    Prioritized,
+}
+
+/// Top level entry point for defining a layout & a list of robots
+#[derive(Debug, Clone)]
+pub struct Shaman {
+    robots: BTreeMap<char, Robot>,
+    layout: Layout,
+    reservations: RightOfWay,
+    time: Time,
+    /// Glyphs drawn instead of paths & free space, e.g. to show results of an analysis
+    overlay: FxHashMap<Vertex, char>,
+    /// Goals serving several robots, which leave the map there, with how many they can serve
+    capacities: FxHashMap<Vertex, usize>,
+    /// Decisions made while solving, see [Self::audit]
+    audit: Audit,
+    /// How robots plan their individual routes
+    planner: PlannerConfig,
+    /// Whether robots may take any goal instead of their own, see [Self::assign_goals]
+    anonymous: bool,
+    /// What solvers minimize
+    objective: Objective,
+    /// Order robots are planned in by [Algorithm::Prioritized]
+    order: Order,
+    /// `key = value` settings of the map's header, see [Self::settings]
+    settings: Vec<(String, String)>,
+    /// Seed breaking ties between equally costly ideas of the solvers, see [Self::with_seed]
+    seed: Option<u64>,
+}
+
+impl Shaman {
+    /// Parse the scenario in `file`, telling its [Format] by the file's extension
+    pub fn parse<P: AsRef<Path>>(file: P) -> Result<Self> {
+        Self::parse_as(file, Format::Auto)
+    }
+
+    /// Parse the scenario in `file` written in `format`
+    pub fn parse_as<P: AsRef<Path>>(file: P, format: Format) -> Result<Self> {
+        let mut sim = Self::read_as(file, format)?;
+        sim.plan()?;
+        Ok(sim)
+    }
+
+    /// Like [Self::parse_as], but without planning any routes yet
+    pub(crate) fn read_as<P: AsRef<Path>>(file: P, format: Format) -> Result<Self> {
+        let path = file.as_ref();
+        let file = path.display().to_string();
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        match format.detect(path) {
+            Format::MovingAi => parser::movingai::import(path, &content),
+            _ => Ok(parser::parse(&file, &content)?),
+        }
+    }
+
+    /// Parse the map `content`, calling it `name` in error reports
+    pub fn parse_str(name: &str, content: &str) -> Result<Self> {
+        let mut sim: Shaman = parser::parse(name, content)?;
+        sim.plan()?;
+        Ok(sim)
+    }
+
+    /// Parse a map read until the end of `reader`, calling it `name` in error reports
+    pub fn parse_reader<R: Read>(name: &str, mut reader: R) -> Result<Self> {
+        let mut content = String::new();
+        reader
+            .read_to_string(&mut content)
+            .map_err(|e| miette!("{name}: {e}"))?;
+        Self::parse_str(name, &content)
+    }
+
+    /// Plan every robot's shortest route, ignoring all other robots
+    fn plan(&mut self) -> Result<()> {
+        for robot in self.robots.values_mut() {
+            robot.plan(&self.layout, &self.reservations, self.planner)?;
+        }
+        Ok(())
+    }
+
+    /// Keep all robots off the `path` cells during `window`, e.g. for external traffic
+    /// Fails if anybody can't avoid them, leaving the reservations & routes as they were
+    pub fn reserve(&mut self, path: &[Vertex], window: Range<Time>) -> Result<()> {
+        let before = (self.reservations.clone(), self.robots.clone());
+        self.reservations.reserve(path, window);
+        if let Err(error) = self.plan() {
+            (self.reservations, self.robots) = before;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Block the free `cells` during `window` & replan the robots accordingly, e.g. `10..` for
+    /// a door closing at timestep 10 or `..10` for a pallet picked up then. Unlike
+    /// [Self::reserve], closed cells are part of the layout, drawn while they are closed
+    pub fn close(&mut self, cells: &[Vertex], window: impl RangeBounds<Time>) -> Result<()> {
+        if let Some(window) = layout::inclusive(window) {
+            for v in cells {
+                self.layout.close(*v, window.clone());
+            }
+        }
+        self.plan()
+    }
+
+    fn new(code: NamedSource<String>, width: i32, height: i32) -> Self {
+        Self {
+            robots: Default::default(),
+            reservations: Default::default(),
+            time: 0,
+            overlay: Default::default(),
+            capacities: Default::default(),
+            audit: Default::default(),
+            planner: Default::default(),
+            anonymous: false,
+            objective: Default::default(),
+            order: Default::default(),
+            settings: vec![],
+            seed: None,
+            layout: Layout::empty(code, width as usize, height as usize),
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Keep robots at least `radius` cells away from obstacles & the map's edge and replan them
+    /// accordingly. Fails for robots starting or driving that close to either
+    pub fn inflate(mut self, radius: usize) -> Result<Self> {
+        self.inflate_layout(radius)?;
+        self.plan()?;
+        Ok(self)
+    }
+
+    /// Inflate the layout by `radius` without replanning, see [Self::inflate]
+    pub(crate) fn inflate_layout(&mut self, radius: usize) -> Result<(), ShamanError> {
+        self.layout.inflate(radius);
+        for robot in self.robots.values().sorted_by_key(|r| r.name()) {
+            let start = ("start", robot.position());
+            let blocked = std::iter::once(start)
+                .chain(robot.goal().map(|g| ("goal", g)))
+                .find(|(_, (v, _))| self.layout.is_blocked(*v));
+            if let Some((what, (_, highlight))) = blocked {
+                return Err(ShamanError::Inflated {
+                    src: self.layout.code(),
+                    robot: robot.name(),
+                    radius,
+                    what,
+                    highlight,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Let all robots plan with `heuristic` from now on and replan them accordingly
+    pub fn with_heuristic(mut self, heuristic: Heuristic) -> Result<Self> {
+        self.planner.heuristic = heuristic;
+        self.plan()?;
+        Ok(self)
+    }
+
+    /// Let all robots move according to `movement` from now on and replan them accordingly
+    pub fn with_movement(mut self, movement: MovementModel) -> Result<Self> {
+        self.planner.movement = movement;
+        self.plan()?;
+        Ok(self)
+    }
+
+    /// How robots plan their routes, see [Self::with_planner_config]
+    pub fn planner_config(&self) -> PlannerConfig {
+        self.planner
+    }
+
+    /// Let all robots plan with `config` from now on, including its limits, and replan them
+    /// accordingly
+    pub fn with_planner_config(mut self, config: PlannerConfig) -> Result<Self> {
+        self.planner = config;
+        self.plan()?;
+        Ok(self)
+    }
+
+    /// Let all robots plan with the low level `planner` from now on and replan them accordingly
+    pub fn with_planner(mut self, planner: Planner) -> Result<Self> {
+        self.planner.planner = planner;
+        self.plan()?;
+        Ok(self)
+    }
+
+    /// Make robot `name` follow `route` verbatim, all other robots need to evade it
+    pub fn freeze(&mut self, name: char, route: Route) -> Result<()> {
+        self.robots
+            .get_mut(&name)
+            .ok_or_else(|| miette!("No robot named '{name}' defined"))?
+            .freeze(route);
+        Ok(())
+    }
+
+    /// Forbid robot `name` to ever enter `cells` and replan it accordingly
+    pub fn exclude(&mut self, name: char, cells: impl IntoIterator<Item = Vertex>) -> Result<()> {
+        let robot = self
+            .robots
+            .get_mut(&name)
+            .ok_or_else(|| miette!("No robot named '{name}' defined"))?;
+        robot.exclude(cells);
+        robot.plan(&self.layout, &self.reservations, self.planner)?;
+        Ok(())
+    }
+
+    /// Mark `cells` when rendering, e.g. to show results of an analysis
+    pub fn highlight(&mut self, cells: impl IntoIterator<Item = Vertex>) {
+        self.annotate(cells.into_iter().map(|v| (v, '▒')));
+    }
+
+    /// Draw a custom glyph on each cell when rendering
+    pub fn annotate(&mut self, glyphs: impl IntoIterator<Item = (Vertex, char)>) {
+        self.overlay.extend(glyphs);
+    }
+
+    /// All robots of this scenario, in no particular order
+    pub fn robots(&self) -> impl Iterator<Item = &Robot> {
+        self.robots.values()
+    }
+
+    pub fn simulate(&mut self) {
+        for robot in self.robots.values_mut() {
+            robot.simulate(self.planner.movement);
+        }
+        self.time += 1;
+    }
+
+    /// A copy of this scenario, simulated for `time` more timesteps along the robots' routes
+    pub fn at(&self, time: Time) -> Self {
+        let mut sim = self.clone();
+        for _ in 0..time {
+            sim.simulate();
+        }
+        sim
+    }
+
+    /// Amount of timesteps simulated so far
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    /// Amount of timesteps until the last robot stops moving
+    fn remaining_duration(&self) -> Time {
+        self.robots
+            .values()
+            .map(|r| r.route().iter().count())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Find collision-free routes for all robots, which is a no-op without any robots
+    pub fn solve(self) -> Result<Self> {
+        self.solve_with(Algorithm::default())
+    }
+
+    /// Like [Self::solve], but give up resolving conflicts after `timeout`. PBS then settles
+    /// for the cheapest conflict free idea found so far, flagged as [Audit::suboptimal], and
+    /// only fails if there is none
+    pub fn solve_timeout(mut self, timeout: Duration) -> Result<Self> {
+        let planner = self.planner;
+        self.planner.max_time = Some(timeout);
+        let mut solved = self.solve()?;
+        solved.planner = planner;
+        Ok(solved)
+    }
+
+    /// Seed breaking ties between equally costly ideas of the solvers, see [Self::with_seed]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Let PBS & CBS break ties between equally costly ideas randomly, drawn from `seed`,
+    /// instead of pursuing the one found first. Either way, the same map always yields the same
+    /// solution, but different seeds explore different ones
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Like [Self::solve], but with a specific `algorithm`
+    pub fn solve_with(mut self, algorithm: Algorithm) -> Result<Self> {
+        if self.robots.is_empty() {
+            return Ok(self);
+        }
+        if self.anonymous {
+            self.assign_goals()?;
+        }
+        match algorithm {
+            Algorithm::Pbs => Pbs::from(self).solve(),
+            Algorithm::Cbs => cbs::solve(self),
+            Algorithm::Auto => self.solve_auto(),
+            Algorithm::Prioritized => self.solve_prioritized(),
+        }
+    }
+
+    /// Sum of all robots' route durations
+    pub fn cost(&self) -> Time {
+        self.robots.values().map(|r| r.route().duration()).sum()
+    }
+
+    /// Sum of all robots' route durations, each multiplied with the robot's weight
+    pub fn weighted_cost(&self) -> f32 {
+        self.robots.values().map(|r| r.weighted_cost()).sum()
+    }
+
+    /// Amount of timesteps until the last robot arrives, i.e. how long playing back takes
+    pub fn simulation_duration(&self) -> Time {
+        self.robots
+            .values()
+            .map(|r| r.route().duration())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl Shaman {
+    /// Render the current state of the simulation, including a border. All cells are padded
+    /// to the width of the widest glyph, so columns stay aligned. When `animated`, conflicts
+    /// are only marked around the time they happen instead of all at once
+    pub fn frame(&self, animated: bool) -> Frame {
+        // Robots are at the locations simulated last
+        let now = self.time.saturating_sub(1);
+        let intersections = self
+            .robots
+            .values()
+            .tuple_combinations()
+            .flat_map(|(a, b)| a.conflicts_with(b))
+            .filter(|c| match *c {
+                _ if !animated => true,
+                // Robots sharing a goal keep colliding there after the later one arrived
+                Collision::Goal { t_from, .. } => now + 1 >= t_from,
+                Collision::Vertex { t, .. } | Collision::Edge { t, .. } => t.abs_diff(now) <= 1,
+            })
+            .flat_map(|c| c.cells())
+            .collect::<FxHashSet<_>>();
+
+        let mut grid = vec![];
+        for y in 0..self.layout.height() {
+            let mut row = vec![];
+            for x in 0..self.layout.width() {
+                let v = Vertex::new(x as i32, y as i32);
+                row.push(
+                    match self
+                        .robots
+                        .values()
+                        .find(|r| r.position().0 == v && !r.is_delivered())
+                    {
+                        Some(robot) if self.planner.rotate_cost.is_some() => robot.arrow(),
+                        Some(robot) => robot.to_string(),
+                        None => {
+                            if intersections.contains(&v) {
+                                format!("{MAGENTA}✕{RESET}")
+                            } else if let Some(glyph) = self.overlay.get(&v) {
+                                format!("{YELLOW}{glyph}{RESET}")
+                            } else if let Some(robot) = self
+                                .robots
+                                .values()
+                                .find(|r| r.route().iter().any(|n| n.position == v))
+                            {
+                                robot.pathicon()
+                            } else if self.layout.is_blocked(v) {
+                                // Obstacle
+                                "█".to_string()
+                            } else if self.layout.is_blocked_at(v, now) {
+                                // Closed for the time being
+                                "▓".to_string()
+                            } else {
+                                // Free space
+                                " ".to_string()
+                            }
+                        }
+                    },
+                );
+            }
+            grid.push(row);
+        }
+        self.boxed(grid)
+    }
+
+    /// Pad all cells of `grid` to the same width & draw a border around them
+    fn boxed(&self, grid: Vec<Vec<String>>) -> Frame {
+        let width = grid.iter().flatten().map(|c| render::width(c)).max();
+        let width = width.unwrap_or_default().max(1);
+        let border = |left, right| {
+            std::iter::once(left)
+                .chain(std::iter::repeat_n("─", self.layout.width() * width))
+                .chain(std::iter::once(right))
+                .map(str::to_string)
+                .collect_vec()
+        };
+        let mut frame = vec![border("╭", "╮")];
+        for row in grid {
+            let row = row.into_iter().map(|cell| {
+                let padding = width - render::width(&cell);
+                cell + &" ".repeat(padding)
+            });
+            let row = std::iter::once("│".to_string())
+                .chain(row)
+                .chain(std::iter::once("│".to_string()));
+            frame.push(row.collect());
+        }
+        frame.push(border("╰", "╯"));
+        frame
+    }
+
+    /// Render the current state like [Display], but without any colors, e.g. for snapshots
+    pub fn render_plain(&self) -> String {
+        render::strip(&self.to_string())
+    }
+}
+
+impl Display for Shaman {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.frame(false) {
+            writeln!(f, "{}", row.concat())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Improving solutions by large neighborhood search, for as long as there is time left
+//!
+//! Starting from the solution of any solver, each iteration destroys the routes of a few robots,
+//! replans them one by one around everybody else & keeps the result if it is cheaper by the
+//! scenario's [Objective](crate::Objective). Neighborhoods alternate between random robots and
+//! robots which got into each other's way on their shortest paths.
+use std::time::{Duration, Instant};
+
+use itertools::Itertools;
+use miette::Result;
+
+use crate::{
+    Algorithm, Shaman,
+    rng::{RandomSource, Rng},
+};
+
+/// How many robots each iteration replans at most
+const NEIGHBORHOOD: usize = 4;
+
+impl Shaman {
+    /// Like [Self::solve_with], but keep improving the solution until `budget` runs out, which
+    /// includes finding the first one with `algorithm`. Always tries the same neighborhoods, so
+    /// it is reproducible given enough time
+    pub fn solve_anytime(self, algorithm: Algorithm, budget: Duration) -> Result<Self> {
+        self.solve_anytime_with(algorithm, budget, &mut Rng::new(0), |_| {})
+    }
+
+    /// Like [Self::solve_anytime], but draw neighborhoods from `rng` & call `improved` with
+    /// every cheaper solution as soon as it is found
+    pub fn solve_anytime_with(
+        mut self,
+        algorithm: Algorithm,
+        budget: Duration,
+        rng: &mut impl RandomSource,
+        mut improved: impl FnMut(&Shaman),
+    ) -> Result<Self> {
+        let started = Instant::now();
+        // Robots getting into each other's way before solving are the ones worth replanning
+        // together
+        let graph = self.conflict_graph();
+        let planner = self.planner;
+        self.planner.max_time = Some(planner.max_time.map_or(budget, |t| t.min(budget)));
+        let mut best = self.solve_with(algorithm)?;
+        best.planner = planner;
+        improved(&best);
+
+        let cost = |shaman: &Shaman| shaman.objective.cost(shaman.robots.values());
+        let candidates = best
+            .robots
+            .values()
+            .filter(|r| r.evades())
+            .map(|r| r.name())
+            .sorted()
+            .collect_vec();
+        if candidates.len() < 2 {
+            return Ok(best);
+        }
+        let mut conflicts = false;
+        while started.elapsed() < budget {
+            // Alternate between both kinds of neighborhoods
+            conflicts = !conflicts && !graph.edges.is_empty();
+            let mut neighborhood = match conflicts {
+                true => {
+                    let pair = graph.edges[rng.below(graph.edges.len())].robots;
+                    let touches = |n: char| n == pair.0 || n == pair.1;
+                    graph
+                        .edges
+                        .iter()
+                        .filter(|e| touches(e.robots.0) || touches(e.robots.1))
+                        .flat_map(|e| [e.robots.0, e.robots.1])
+                        .filter(|n| candidates.contains(n))
+                        .unique()
+                        .collect_vec()
+                }
+                false => candidates.clone(),
+            };
+            rng.shuffle(&mut neighborhood);
+            neighborhood.truncate(NEIGHBORHOOD);
+            if let Some(repaired) = best.replan_neighborhood(&neighborhood)
+                && cost(&repaired) < cost(&best)
+            {
+                best = repaired;
+                improved(&best);
+            }
+        }
+        Ok(best)
+    }
+
+    /// This solution with the routes of the `neighborhood` replanned in this order, each
+    /// around all other robots. `None` if any of them can't make it or the result collides
+    fn replan_neighborhood(&self, neighborhood: &[char]) -> Option<Self> {
+        let mut repaired = self.clone();
+        let mut constraints = repaired.reservations.clone();
+        for robot in repaired.robots.values() {
+            if !neighborhood.contains(&robot.name()) {
+                constraints += robot.right_of_way(robot.route());
+            }
+        }
+        for name in neighborhood {
+            let robot = repaired.robots.get_mut(name)?;
+            robot
+                .plan(&repaired.layout, &constraints, repaired.planner)
+                .ok()?;
+            constraints += robot.right_of_way(robot.route());
+        }
+        let collides = repaired
+            .robots
+            .values()
+            .tuple_combinations()
+            .any(|(a, b)| a.conflicts(b));
+        (!collides).then_some(repaired)
+    }
+}
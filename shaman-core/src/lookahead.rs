@@ -0,0 +1,123 @@
+//! Warning about collisions robots are about to run into, e.g. after nudging one of them by
+//! hand or injecting events into a running [crate::Simulation]
+use std::fmt::Display;
+
+use itertools::Itertools;
+use miette::{Result, miette};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    Shaman, Time,
+    robot::{Location, Robot},
+    route::{Collision, Route},
+};
+
+/// Collision of two robots within the next few timesteps, see [Lookahead]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Warning {
+    pub robots: (char, char),
+    pub collision: Collision,
+    /// Timesteps from now until the collision happens, `0` if it already does
+    pub steps: Time,
+}
+
+/// Collisions along the robots' current routes within the next `steps` timesteps. Collisions
+/// are kept per pair of robots, so only pairs involving robots whose routes changed need to be
+/// checked again, see [Self::update]
+#[derive(Debug, Clone, Default)]
+pub struct Lookahead {
+    steps: Time,
+    collisions: FxHashMap<(char, char), Vec<Collision>>,
+}
+
+impl Shaman {
+    /// Collisions happening within the next `steps` timesteps, soonest first
+    pub fn lookahead(&self, steps: Time) -> Vec<Warning> {
+        Lookahead::new(self, steps).warnings(self)
+    }
+
+    /// Keep robot `name` waiting where it is for one more timestep, delaying the rest of its
+    /// route without replanning anybody, e.g. to see what a hold-up would run into
+    pub fn hold(&mut self, name: char) -> Result<()> {
+        let now = self.time.saturating_sub(1);
+        self.robots
+            .get_mut(&name)
+            .ok_or_else(|| miette!("No robot named '{name}' defined"))?
+            .hold(now);
+        Ok(())
+    }
+}
+
+impl Lookahead {
+    /// Check all pairs of robots of `sim`
+    pub fn new(sim: &Shaman, steps: Time) -> Self {
+        let mut lookahead = Self {
+            steps,
+            collisions: Default::default(),
+        };
+        lookahead.update(sim, sim.robots.keys().copied());
+        lookahead
+    }
+
+    /// Check all pairs of robots again, which involve one of the `changed` robots, e.g. after
+    /// nudging them or the ones replanned by [crate::Simulation::take_diffs]
+    pub fn update(&mut self, sim: &Shaman, changed: impl IntoIterator<Item = char>) {
+        let changed = changed.into_iter().collect::<FxHashSet<_>>();
+        let now = sim.time.saturating_sub(1);
+        // Including the current position, so robots which arrived already still park there
+        let remaining = |robot: &Robot| -> Route {
+            let current = Location {
+                position: robot.position().0,
+                time: now,
+            };
+            std::iter::once(current)
+                .chain(robot.route().iter().filter(|l| l.time > now))
+                .collect()
+        };
+        for (a, b) in sim
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .tuple_combinations()
+            .filter(|(a, b)| changed.contains(&a.name()) || changed.contains(&b.name()))
+        {
+            let collisions = match a.is_delivered() || b.is_delivered() {
+                true => vec![],
+                false => {
+                    remaining(a).conflicts_parking(&remaining(b), (!a.delivers(), !b.delivers()))
+                }
+            };
+            self.collisions.insert((a.name(), b.name()), collisions);
+        }
+    }
+
+    /// Collisions of `sim` happening from now on within the lookahead, soonest first. Robots
+    /// sharing a goal keep colliding there once both arrived
+    pub fn warnings(&self, sim: &Shaman) -> Vec<Warning> {
+        let now = sim.time.saturating_sub(1);
+        self.collisions
+            .iter()
+            .flat_map(|(robots, collisions)| {
+                collisions.iter().map(|collision| Warning {
+                    robots: *robots,
+                    collision: *collision,
+                    steps: collision.time().saturating_sub(now),
+                })
+            })
+            .filter(|w| w.collision.time() >= now || matches!(w.collision, Collision::Goal { .. }))
+            .filter(|w| w.steps < self.steps)
+            .sorted_by_key(|w| (w.steps, w.robots))
+            .collect()
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (a, b) = self.robots;
+        match self.steps {
+            0 => write!(f, "{a} × {b} now: {}", self.collision),
+            1 => write!(f, "{a} × {b} in 1 step: {}", self.collision),
+            n => write!(f, "{a} × {b} in {n} steps: {}", self.collision),
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Operator facing text as message IDs with parameters instead of prose, so embedders can
+//! translate it. [English] formats them like the CLI prints them
+use itertools::Itertools;
+use miette::Diagnostic;
+
+use crate::{
+    Comparison, Gap, ShamanError, Summary, Violation,
+    json::{Json, ToJson},
+};
+
+/// Locale independent message, e.g. `summary.makespan` with its parameter `makespan`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub id: &'static str,
+    /// Parameters by name, in the order they were added
+    pub args: Vec<(&'static str, Json)>,
+}
+
+/// Types describing themselves to operators by a sequence of [Message]s, one per line
+pub trait Messages {
+    fn messages(&self) -> Vec<Message>;
+}
+
+/// Formats [Message]s in some language
+pub trait Locale {
+    /// Text of a single `message`, without trailing newline
+    fn format(&self, message: &Message) -> String;
+
+    /// Text of all messages of `item`, one line each
+    fn render(&self, item: &impl Messages) -> String
+    where
+        Self: Sized,
+    {
+        item.messages()
+            .iter()
+            .map(|m| self.format(m) + "\n")
+            .collect()
+    }
+}
+
+/// The [Locale] of the CLI
+#[derive(Debug, Default, Clone, Copy)]
+pub struct English;
+
+impl Message {
+    pub fn new(id: &'static str) -> Self {
+        Self { id, args: vec![] }
+    }
+
+    /// Add the parameter `key`
+    pub fn arg(mut self, key: &'static str, value: impl ToJson) -> Self {
+        self.args.push((key, value.to_json()));
+        self
+    }
+
+    /// Value of the parameter `key`
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        self.args.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+}
+
+impl ToJson for Message {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("id", self.id.to_json()),
+            ("args", Json::object(self.args.iter().cloned())),
+        ])
+    }
+}
+
+impl Locale for English {
+    fn format(&self, message: &Message) -> String {
+        let number = |key| message.get(key).and_then(Json::as_f64).unwrap_or_default();
+        let text = |key| message.get(key).and_then(Json::as_str).unwrap_or_default();
+        let vertex = |key| {
+            let v = message.get(key);
+            let coordinate = |c| v.and_then(|v| v.get(c)).and_then(Json::as_f64);
+            format!(
+                "{}/{}",
+                coordinate("x").unwrap_or_default(),
+                coordinate("y").unwrap_or_default()
+            )
+        };
+        let gap = || {
+            format!(
+                "{:>4}  {:>9}  {:>+4} ({:+.1}%)",
+                number("cost"),
+                number("reference"),
+                number("gap"),
+                number("relative") * 100.
+            )
+        };
+        match message.id {
+            "summary.makespan" => format!("makespan:  {}", number("makespan")),
+            "summary.cost" => format!("cost:      {}", number("cost")),
+            "summary.cost.weighted" => format!(
+                "cost:      {} (weighted: {})",
+                number("cost"),
+                number("weighted") as f32
+            ),
+            "summary.bound" => format!(
+                "bound:     {} (gap {:.1}%)",
+                number("bound"),
+                number("gap") as f32 * 100.
+            ),
+            "summary.waits" => format!("waits:     {}", number("waits")),
+            "summary.arrivals" => {
+                let arrivals = message
+                    .get("arrivals")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|a| {
+                        let name = a.get("name").and_then(Json::as_str).unwrap_or_default();
+                        let time = a.get("time").and_then(Json::as_f64).unwrap_or_default();
+                        format!("{name}@{time}")
+                    })
+                    .join(" ");
+                format!("arrivals:  {arrivals}")
+            }
+            "summary.conflicts.none" => "conflicts: none".to_string(),
+            "summary.conflicts.found" => "conflicts: yes".to_string(),
+            "summary.quality" => format!(
+                "quality:   {:.0} ({})",
+                number("score") * 100.,
+                text("grade")
+            ),
+            "comparison.header" => "robot     cost  reference   gap".to_string(),
+            "comparison.robot" => format!("{:<8}  {}", text("name"), gap()),
+            "comparison.total" => format!("{:<8}  {}", "total", gap()),
+            "comparison.makespan" => format!("{:<8}  {}", "makespan", gap()),
+            "violation.timing" => format!(
+                "{} skips from t={} to t={}",
+                text("robot"),
+                number("from"),
+                number("to")
+            ),
+            "violation.jump" => format!(
+                "{} jumps {} → {}@{}",
+                text("robot"),
+                vertex("from"),
+                vertex("to"),
+                number("t")
+            ),
+            "violation.blocked" => format!(
+                "{} is on blocked cell {}@{}",
+                text("robot"),
+                vertex("v"),
+                number("t")
+            ),
+            "violation.vertex" => format!(
+                "{} & {} collide at {}@{}",
+                text("a"),
+                text("b"),
+                vertex("v"),
+                number("t")
+            ),
+            "violation.edge" => format!(
+                "{} & {} collide moving {} → {}@{}",
+                text("a"),
+                text("b"),
+                vertex("from"),
+                vertex("to"),
+                number("t")
+            ),
+            "violation.goal" => format!(
+                "{} runs into {} parked on {}@{}",
+                text("b"),
+                text("a"),
+                vertex("v"),
+                number("t")
+            ),
+            "violation.missed-goal" => format!(
+                "{} never reaches its goal {}",
+                text("robot"),
+                vertex("goal")
+            ),
+            "problem.invalid-cell" => "Expected either an obstacle (# or █), a free cell (space), \
+                a robot (A..Z) or a goal (a..z)"
+                .to_string(),
+            "problem.empty-map" => "Map contains no cells".to_string(),
+            "problem.ragged-row" => format!(
+                "Row has {} cells, while the widest one has {}",
+                number("cells"),
+                number("widest")
+            ),
+            "problem.no-robots" => "Map contains no robots, there is nothing to solve".to_string(),
+            "problem.duplicate-robots" => "Robot names must be unique".to_string(),
+            "problem.duplicate-goals" => "Only one goal per robot".to_string(),
+            "problem.no-robot-for-goal" | "problem.unknown-robot" => {
+                format!("No robot named '{}' defined", text("robot"))
+            }
+            "problem.invalid-header" => "Expected a header directive like 'weight A = 10', \
+                'pace A = 3', 'class A = 1', 'exclude A = 2/3..5/6', 'capacity a = 3', \
+                'goal B = a', 'window a = 5..10', 'movement = eight-connected', \
+                'via A = 3/4 > 7/1', 'patrol A = 5/1 > 5/3', 'cost 2/3 > 3/3 = 2', \
+                'close 2/3 = 10..20' or the closing '---'"
+                .to_string(),
+            "problem.unknown-fleet" => format!("No robot belongs to fleet '{}'", text("fleet")),
+            "problem.no-such-goal" => format!("Robot '{}' has no goal", text("robot")),
+            "problem.missing-goal" => format!(
+                "Robot '{}' has no goal, so it never leaves its start",
+                text("robot")
+            ),
+            "problem.invalid-setting" => text("message").to_string(),
+            "problem.patrol-with-goal" => format!(
+                "Robot '{}' patrols, so it can't drive to a goal as well",
+                text("robot")
+            ),
+            "problem.start-excluded" => format!(
+                "Robot '{}' starts on a cell it must never enter",
+                text("robot")
+            ),
+            "problem.goal-over-capacity" => format!(
+                "Goal can serve at most {} robot(s), but {} drive there",
+                number("capacity"),
+                number("robots")
+            ),
+            "problem.route-not-found" => "No route found".to_string(),
+            "problem.inflated" => format!(
+                "Inflating obstacles by {} cell(s) blocks the {} of robot '{}'",
+                number("radius"),
+                text("what"),
+                text("robot")
+            ),
+            "problem.goal-closed" => format!(
+                "Goal closes at t={}, but the robot arrives at t={}",
+                number("close"),
+                number("arrival")
+            ),
+            "problem.budget-exceeded" => format!(
+                "Gave up after {}, exceeding the {}",
+                text("progress"),
+                text("limit")
+            ),
+            "problem.problems" => format!("Found {} problem(s) in the map", number("count")),
+            "problem.not-adjacent" => format!(
+                "Cells {} and {} are not adjacent, no single action connects them",
+                vertex("from"),
+                vertex("to")
+            ),
+            id => {
+                let args = message.args.iter().map(|(k, v)| format!("{k}={v}"));
+                format!("{id}: {}", args.format(", "))
+            }
+        }
+    }
+}
+
+impl Messages for Summary {
+    fn messages(&self) -> Vec<Message> {
+        let cost = match self.weighted_cost == self.cost as f32 {
+            true => Message::new("summary.cost").arg("cost", self.cost),
+            false => Message::new("summary.cost.weighted")
+                .arg("cost", self.cost)
+                .arg("weighted", self.weighted_cost),
+        };
+        let arrivals = self
+            .arrivals
+            .iter()
+            .map(|(name, time)| Json::object([("name", name.to_json()), ("time", time.to_json())]));
+        vec![
+            Message::new("summary.makespan").arg("makespan", self.makespan),
+            cost,
+            Message::new("summary.bound")
+                .arg("bound", self.lower_bound)
+                .arg("gap", self.gap()),
+            Message::new("summary.waits").arg("waits", self.waits),
+            Message::new("summary.arrivals").arg("arrivals", Json::array(arrivals)),
+            Message::new(match self.conflicts {
+                true => "summary.conflicts.found",
+                false => "summary.conflicts.none",
+            }),
+            Message::new("summary.quality")
+                .arg("score", self.quality.score())
+                .arg("grade", self.quality.grade().to_string()),
+        ]
+    }
+}
+
+impl Messages for Comparison {
+    fn messages(&self) -> Vec<Message> {
+        let gap = |id, gap: Gap| {
+            Message::new(id)
+                .arg("cost", gap.cost)
+                .arg("reference", gap.reference)
+                .arg("gap", gap.absolute())
+                .arg("relative", gap.relative())
+        };
+        std::iter::once(Message::new("comparison.header"))
+            .chain(
+                self.robots
+                    .iter()
+                    .map(|(name, g)| gap("comparison.robot", *g).arg("name", *name)),
+            )
+            .chain([
+                gap("comparison.total", self.total()),
+                gap("comparison.makespan", self.makespan()),
+            ])
+            .collect()
+    }
+}
+
+impl Messages for Violation {
+    fn messages(&self) -> Vec<Message> {
+        let message = match *self {
+            Self::Timing { robot, from, to } => Message::new("violation.timing")
+                .arg("robot", robot)
+                .arg("from", from)
+                .arg("to", to),
+            Self::Jump { robot, from, to, t } => Message::new("violation.jump")
+                .arg("robot", robot)
+                .arg("from", from)
+                .arg("to", to)
+                .arg("t", t),
+            Self::Blocked { robot, v, t } => Message::new("violation.blocked")
+                .arg("robot", robot)
+                .arg("v", v)
+                .arg("t", t),
+            Self::Vertex { robots, v, t } => Message::new("violation.vertex")
+                .arg("a", robots.0)
+                .arg("b", robots.1)
+                .arg("v", v)
+                .arg("t", t),
+            Self::Edge {
+                robots,
+                from,
+                to,
+                t,
+            } => Message::new("violation.edge")
+                .arg("a", robots.0)
+                .arg("b", robots.1)
+                .arg("from", from)
+                .arg("to", to)
+                .arg("t", t),
+            Self::Goal { robots, v, t } => Message::new("violation.goal")
+                .arg("a", robots.0)
+                .arg("b", robots.1)
+                .arg("v", v)
+                .arg("t", t),
+            Self::MissedGoal { robot, goal } => Message::new("violation.missed-goal")
+                .arg("robot", robot)
+                .arg("goal", goal),
+        };
+        vec![message]
+    }
+}
+
+/// Problems of a map, e.g. those found by [crate::Shaman::check], each with the 1-based `line`
+/// & `column` it is reported at, if any. [ShamanError::Problems] lists all of its problems
+/// after its own message
+impl Messages for ShamanError {
+    fn messages(&self) -> Vec<Message> {
+        use ShamanError::*;
+        let message = match self {
+            InvalidCell { .. } => Message::new("problem.invalid-cell"),
+            EmptyMap { .. } => Message::new("problem.empty-map"),
+            RaggedRow { cells, widest, .. } => Message::new("problem.ragged-row")
+                .arg("cells", *cells)
+                .arg("widest", *widest),
+            NoRobots { .. } => Message::new("problem.no-robots"),
+            DuplicateRobots { .. } => Message::new("problem.duplicate-robots"),
+            DuplicateGoals { .. } => Message::new("problem.duplicate-goals"),
+            NoRobotForGoal { robot, .. } => {
+                Message::new("problem.no-robot-for-goal").arg("robot", *robot)
+            }
+            InvalidHeader { .. } => Message::new("problem.invalid-header"),
+            UnknownRobot { robot, .. } => {
+                Message::new("problem.unknown-robot").arg("robot", *robot)
+            }
+            UnknownFleet { fleet, .. } => {
+                Message::new("problem.unknown-fleet").arg("fleet", fleet.as_str())
+            }
+            NoSuchGoal { robot, .. } => Message::new("problem.no-such-goal").arg("robot", *robot),
+            MissingGoal { robot, goal, .. } => Message::new("problem.missing-goal")
+                .arg("robot", *robot)
+                .arg("goal", *goal),
+            InvalidSetting { message, .. } => {
+                Message::new("problem.invalid-setting").arg("message", message.as_str())
+            }
+            PatrolWithGoal { robot, .. } => {
+                Message::new("problem.patrol-with-goal").arg("robot", *robot)
+            }
+            StartExcluded { robot, .. } => {
+                Message::new("problem.start-excluded").arg("robot", *robot)
+            }
+            GoalOverCapacity {
+                capacity, robots, ..
+            } => Message::new("problem.goal-over-capacity")
+                .arg("capacity", *capacity)
+                .arg("robots", *robots),
+            RouteNotFound { .. } => Message::new("problem.route-not-found"),
+            Inflated {
+                robot,
+                radius,
+                what,
+                ..
+            } => Message::new("problem.inflated")
+                .arg("robot", *robot)
+                .arg("radius", *radius)
+                .arg("what", *what),
+            GoalClosed {
+                open,
+                close,
+                arrival,
+                ..
+            } => Message::new("problem.goal-closed")
+                .arg("open", *open)
+                .arg("close", *close)
+                .arg("arrival", *arrival),
+            BudgetExceeded {
+                limit, progress, ..
+            } => Message::new("problem.budget-exceeded")
+                .arg("limit", &**limit)
+                .arg("progress", &**progress),
+            Problems { count, problems } => {
+                let message = Message::new("problem.problems").arg("count", *count);
+                return std::iter::once(message)
+                    .chain(problems.iter().flat_map(Messages::messages))
+                    .collect();
+            }
+            NotAdjacent { from, to } => Message::new("problem.not-adjacent")
+                .arg("from", *from)
+                .arg("to", *to),
+        };
+        // Where the first label points to, like miette reports it
+        let span = self.labels().and_then(|mut labels| labels.next());
+        let contents = span
+            .zip(self.source_code())
+            .and_then(|(span, source)| source.read_span(span.inner(), 0, 0).ok());
+        vec![match contents {
+            Some(contents) => message
+                .arg("line", contents.line() + 1)
+                .arg("column", contents.column() + 1),
+            None => message,
+        }]
+    }
+}
@@ -0,0 +1,97 @@
+//! Shrinking failing scenarios into minimal reproductions, e.g. for bug reports
+//!
+//! Rows, columns & robots are removed delta debugging style: first in big chunks, then in
+//! ever smaller ones, keeping every reduction after which solving still fails the same way.
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{Algorithm, Shaman, builder::ShamanBuilder};
+
+/// A part of a scenario which can be removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Robot(char),
+    Row(i32),
+    Column(i32),
+}
+
+impl Shaman {
+    /// Remove as many rows, columns & robots as possible from this scenario, while solving it
+    /// with `algorithm` & its planner settings still fails with an error mentioning `preserve`
+    pub fn minimize(&self, algorithm: Algorithm, preserve: &str) -> Result<ShamanBuilder> {
+        let parts = self
+            .robots
+            .keys()
+            .sorted()
+            .map(|n| Part::Robot(*n))
+            .chain((0..self.layout.height() as i32).map(Part::Row))
+            .chain((0..self.layout.width() as i32).map(Part::Column))
+            .collect_vec();
+        let builder = ShamanBuilder::from(self);
+        let fails = |kept: &[Part]| {
+            reduce(&builder, &parts, kept)
+                .build()
+                .and_then(|s| s.configured_like(self))
+                .and_then(|s| s.solve_with(algorithm))
+                .is_err_and(|e| e.to_string().contains(preserve))
+        };
+        if !fails(&parts) {
+            return Err(miette!("Solving doesn't fail with \"{preserve}\""));
+        }
+        Ok(reduce(&builder, &parts, &ddmin(parts.clone(), fails)))
+    }
+}
+
+/// The smallest subset of `parts` found, for which the scenario still `fails`
+fn ddmin(mut parts: Vec<Part>, fails: impl Fn(&[Part]) -> bool) -> Vec<Part> {
+    let mut n = 2;
+    while parts.len() >= 2 {
+        let chunks = parts
+            .chunks(parts.len().div_ceil(n))
+            .map(<[Part]>::to_vec)
+            .collect_vec();
+        if let Some(chunk) = chunks.iter().find(|c| fails(c)) {
+            parts = chunk.clone();
+            n = 2;
+            continue;
+        }
+        let complement = (0..chunks.len())
+            .map(|i| {
+                let others = chunks.iter().enumerate().filter(|(j, _)| *j != i);
+                others.flat_map(|(_, c)| c).copied().collect_vec()
+            })
+            .find(|c| fails(c));
+        if let Some(complement) = complement {
+            parts = complement;
+            n = (n - 1).max(2);
+            continue;
+        }
+        if n >= parts.len() {
+            break;
+        }
+        n = (2 * n).min(parts.len());
+    }
+    parts
+}
+
+/// The scenario of `builder` with all `parts` but the `kept` ones removed
+fn reduce(builder: &ShamanBuilder, parts: &[Part], kept: &[Part]) -> ShamanBuilder {
+    let mut builder = builder.clone();
+    let (mut rows, mut columns) = (vec![], vec![]);
+    for part in parts.iter().copied() {
+        match part {
+            _ if kept.contains(&part) => {}
+            Part::Robot(name) => builder = builder.remove_robot(name),
+            Part::Row(y) => rows.push(y),
+            Part::Column(x) => columns.push(x),
+        }
+    }
+    // From the bottom right, so the remaining rows & columns keep their indices
+    for y in rows.into_iter().rev() {
+        builder = builder.remove_row(y);
+    }
+    for x in columns.into_iter().rev() {
+        builder = builder.remove_column(x);
+    }
+    builder
+}
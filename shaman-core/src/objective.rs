@@ -0,0 +1,63 @@
+//! What solvers minimize when comparing candidate solutions
+use std::str::FromStr;
+
+use crate::{Shaman, robot::Robot};
+
+/// Cost of a solution, which solvers minimize, see [Shaman::with_objective]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Objective {
+    /// Sum of all robots' route durations, each multiplied with the robot's weight
+    #[default]
+    SumOfCosts,
+    /// Time when the last robot arrives at its goal
+    Makespan,
+    /// Weighted sum of costs, plus the makespan multiplied with this factor
+    WeightedMix(f32),
+}
+
+impl Objective {
+    /// Cost of the `robots`' current routes under this objective
+    pub fn cost<'a>(&self, robots: impl IntoIterator<Item = &'a Robot>) -> f32 {
+        let (sum, makespan) = robots.into_iter().fold((0., 0), |(sum, makespan), r| {
+            (sum + r.weighted_cost(), makespan.max(r.route().duration()))
+        });
+        match self {
+            Self::SumOfCosts => sum,
+            Self::Makespan => makespan as f32,
+            Self::WeightedMix(factor) => sum + factor * makespan as f32,
+        }
+    }
+}
+
+/// `sum-of-costs`, `makespan` or `mix=<factor>` for a [Objective::WeightedMix]
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sum-of-costs" => Ok(Self::SumOfCosts),
+            "makespan" => Ok(Self::Makespan),
+            _ => s
+                .strip_prefix("mix=")
+                .and_then(|f| f.parse().ok())
+                .filter(|f: &f32| *f >= 0.)
+                .map(Self::WeightedMix)
+                .ok_or_else(|| {
+                    format!("Expected sum-of-costs, makespan or mix=<factor>, got '{s}'")
+                }),
+        }
+    }
+}
+
+impl Shaman {
+    /// What solvers minimize, see [Self::with_objective]
+    pub fn objective(&self) -> Objective {
+        self.objective
+    }
+
+    /// Let solvers minimize `objective` instead of the sum of costs from now on
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+}
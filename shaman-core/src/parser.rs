@@ -0,0 +1,678 @@
+use std::path::Path;
+
+use enum_as_inner::EnumAsInner;
+use itertools::Itertools;
+use miette::{Diagnostic, NamedSource, Result, Severity, SourceSpan};
+use nom::{
+    Parser,
+    branch::alt,
+    bytes::complete::{tag, take_till1, take_while, take_while1},
+    character::complete::{char, i32, newline, satisfy, space0, space1, u32, u64},
+    combinator::{consumed, cut, eof, opt, recognize, verify},
+    multi::{many_till, many0, separated_list1},
+    number::complete::float,
+    sequence::{delimited, preceded, separated_pair, terminated},
+};
+use nom_locate::{LocatedSpan, position};
+
+use crate::{Shaman, Time, astar::Action, error::ShamanError, layout::Vertex, robot::Robot};
+
+pub(crate) mod movingai;
+
+/// File formats scenarios are read in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Format {
+    /// MovingAI for `.map` & `.scen` files, otherwise shaman's own
+    #[default]
+    Auto,
+    /// Shaman's own map format, see the README
+    Shaman,
+    /// `.map` layouts or `.scen` scenarios of the movingai.com MAPF benchmarks, see
+    /// [movingai]
+    MovingAi,
+}
+
+impl Format {
+    /// Resolve [Self::Auto] by the extension of `file`
+    pub fn detect(self, file: &Path) -> Self {
+        match self {
+            Self::Auto if file.extension().is_some_and(|e| e == "map" || e == "scen") => {
+                Self::MovingAi
+            }
+            Self::Auto => Self::Shaman,
+            format => format,
+        }
+    }
+}
+
+type Span<'a> = LocatedSpan<&'a str>;
+type IResult<'a, T> = nom::IResult<Span<'a>, T>;
+
+/// Parse strictly, failing with all errors found at once (warnings aside), or with the only
+/// one if there is just one
+pub(crate) fn parse(filename: &str, s: &str) -> Result<Shaman, ShamanError> {
+    let mut problems = vec![];
+    let shaman = parse_leniently(filename, s, &mut problems)?;
+    let mut errors = problems
+        .into_iter()
+        .filter(|p| p.severity().is_none_or(|s| s == Severity::Error))
+        .collect_vec();
+    match errors.len() {
+        0 => Ok(shaman),
+        1 => Err(errors.remove(0)),
+        count => Err(ShamanError::Problems {
+            count,
+            problems: errors,
+        }),
+    }
+}
+
+/// Parse as far as possible, collecting all `problems` found on the way, including warnings.
+/// Only fails if the map's syntax is broken, so it can't be read any further
+pub(crate) fn parse_leniently(
+    filename: &str,
+    s: &str,
+    problems: &mut Vec<ShamanError>,
+) -> Result<Shaman, ShamanError> {
+    let src = NamedSource::new(filename, s.to_string());
+
+    let (s, directives) = opt(header).parse(Span::new(s)).map_err(|e| match e {
+        nom::Err::Incomplete(more) => {
+            panic!("Failed to parse header, expected more input: {more:?}")
+        }
+        nom::Err::Error(e) | nom::Err::Failure(e) => ShamanError::InvalidHeader {
+            src: src.clone(),
+            highlight: (e.input.location_offset(), 1).into(),
+        },
+    })?;
+
+    let (_, grid) = grid.parse(s).map_err(|e| match e {
+        nom::Err::Incomplete(more) => panic!("Failed to parse map, expected more input: {more:?}"),
+        nom::Err::Error(e) => ShamanError::InvalidCell {
+            src: src.clone(),
+            highlight: (e.input.location_offset(), 1).into(),
+        },
+        nom::Err::Failure(e) => panic!("Failed to parse map: {e}"),
+    })?;
+
+    for cell in grid.iter().flat_map(|(row, _)| row) {
+        if cell.inner.is_invalid() {
+            problems.push(ShamanError::InvalidCell {
+                src: src.clone(),
+                highlight: (cell.span.location_offset(), 1).into(),
+            });
+        }
+    }
+
+    let widest = grid
+        .iter()
+        .map(|(row, _)| row.len())
+        .max()
+        .unwrap_or_default();
+    for (row, end) in &grid {
+        if row.len() < widest {
+            problems.push(ShamanError::RaggedRow {
+                src: src.clone(),
+                cells: row.len(),
+                widest,
+                highlight: (end.location_offset(), 1).into(),
+            });
+        }
+    }
+
+    let grid = grid
+        .into_iter()
+        .map(|(row, _)| row)
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(move |(x, c)| ((x as i32, y as i32), c))
+        })
+        .collect::<Vec<_>>();
+    if grid.is_empty() {
+        return Err(ShamanError::EmptyMap { src });
+    }
+
+    let mut shaman = Shaman::new(
+        src.clone(),
+        grid.iter().map(|((x, _), _)| *x).max().unwrap_or_default() + 1,
+        grid.iter().map(|((_, y), _)| *y).max().unwrap_or_default() + 1,
+    );
+
+    let robots = grid
+        .iter()
+        .filter_map(|(c, cell)| Some((*c, cell.span, cell.inner.into_robot().ok()?)))
+        .sorted_by_key(|(_, _, n)| *n)
+        .collect::<Vec<_>>();
+
+    let dups = robots
+        .iter()
+        .cloned()
+        .map(|(_, span, n)| (n, span))
+        .into_group_map();
+
+    for (b, a) in dups
+        .into_iter()
+        .sorted_by_key(|(n, _)| *n)
+        .filter_map(|(_, mut ds)| Some((ds.pop()?, ds.pop()?)))
+    {
+        problems.push(ShamanError::DuplicateRobots {
+            src: src.clone(),
+            a: (a.location_offset(), 1).into(),
+            b: (b.location_offset(), 1).into(),
+        });
+    }
+
+    shaman.robots.extend(
+        robots
+            .into_iter()
+            .map(|((x, y), s, n)| (n, Robot::new(n, x, y, (s.location_offset(), 1).into()))),
+    );
+
+    for v in grid
+        .iter()
+        .filter(|(_, cell)| cell.inner.is_obstacle())
+        .map(|((x, y), _)| Vertex::new(*x, *y))
+    {
+        shaman.layout.block(v);
+    }
+
+    for ((x, y), Spanned { span, inner }) in grid {
+        let (n, goal) = match inner {
+            Cell::Goal(n) => (n, Vertex::new(x, y)),
+            Cell::GoalSouth(n) => (n, Vertex::new(x, y + 1)),
+            _ => continue,
+        };
+        let result = shaman
+            .robots
+            .get_mut(&n)
+            .ok_or(ShamanError::NoRobotForGoal {
+                src: src.clone(),
+                robot: n,
+                goal: (span.location_offset(), 1).into(),
+            })
+            .and_then(|r| r.set_goal(&shaman.layout, goal, (span.location_offset(), 1).into()));
+        problems.extend(result.err());
+    }
+
+    let directives = directives.into_iter().flatten().collect_vec();
+    // Before all other directives, so they may refer to fleets & goals defined further down
+    for Spanned { span, inner } in &directives {
+        let highlight: SourceSpan = (span.location_offset(), span.fragment().len()).into();
+        if let Directive::Stay(n) = inner {
+            let result = shaman
+                .robots
+                .get_mut(n)
+                .ok_or(ShamanError::UnknownRobot {
+                    src: src.clone(),
+                    robot: *n,
+                    highlight,
+                })
+                .and_then(|r| r.set_goal(&shaman.layout, r.position().0, highlight));
+            problems.extend(result.err());
+        }
+        let Directive::Fleet(names, fleet) = inner else {
+            continue;
+        };
+        for n in names {
+            match shaman.robots.get_mut(n) {
+                Some(robot) => robot.set_fleet(fleet),
+                None => problems.push(ShamanError::UnknownRobot {
+                    src: src.clone(),
+                    robot: *n,
+                    highlight,
+                }),
+            }
+        }
+    }
+
+    let mut windows = vec![];
+    let mut patrols = vec![];
+    let mut vias = vec![];
+    for Spanned { span, inner } in directives {
+        let highlight: SourceSpan = (span.location_offset(), span.fragment().len()).into();
+        let unknown = |robot| ShamanError::UnknownRobot {
+            src: src.clone(),
+            robot,
+            highlight,
+        };
+        let robots_of = |shaman: &Shaman, target: &Target| match target {
+            Target::Robot(n) => Ok(vec![*n]),
+            Target::Fleet(fleet) => {
+                let names = shaman
+                    .robots
+                    .values()
+                    .filter(|r| r.fleet() == Some(fleet.as_str()))
+                    .map(|r| r.name())
+                    .sorted()
+                    .collect_vec();
+                match names.is_empty() {
+                    true => Err(ShamanError::UnknownFleet {
+                        src: src.clone(),
+                        fleet: fleet.clone(),
+                        highlight,
+                    }),
+                    false => Ok(names),
+                }
+            }
+        };
+        let goal_of = |shaman: &Shaman, robot| {
+            shaman
+                .robots
+                .get(&robot)
+                .ok_or(unknown(robot))?
+                .goal()
+                .map(|(g, _)| g)
+                .ok_or(ShamanError::NoSuchGoal {
+                    src: src.clone(),
+                    robot,
+                    highlight,
+                })
+        };
+        let apply = || -> Result<(), ShamanError> {
+            match inner {
+                Directive::Fleet(..) | Directive::Stay(_) => {}
+                Directive::Weight(target, weight) => {
+                    for n in robots_of(&shaman, &target)? {
+                        shaman
+                            .robots
+                            .get_mut(&n)
+                            .ok_or(unknown(n))?
+                            .set_weight(weight);
+                    }
+                }
+                Directive::Pace(target, pace) => {
+                    for n in robots_of(&shaman, &target)? {
+                        shaman.robots.get_mut(&n).ok_or(unknown(n))?.set_pace(pace);
+                    }
+                }
+                Directive::Class(n, class) => shaman
+                    .robots
+                    .get_mut(&n)
+                    .ok_or(unknown(n))?
+                    .set_class(class),
+                Directive::Exclude(target, from, to) => {
+                    for n in robots_of(&shaman, &target)? {
+                        let cells = shaman.layout.rectangle(from, to);
+                        shaman.robots.get_mut(&n).ok_or(unknown(n))?.exclude(cells);
+                    }
+                }
+                Directive::Capacity(n, capacity) => {
+                    let goal = goal_of(&shaman, n)?;
+                    shaman.capacities.insert(goal, capacity);
+                }
+                Directive::Cost(from, to, cost) => {
+                    Action::try_from((from, to))?;
+                    shaman.layout.set_edge_cost(from, to, cost);
+                }
+                Directive::Close(from, to, open, close) => {
+                    for v in shaman.layout.rectangle(from, to) {
+                        shaman.layout.close(v, open..=close.unwrap_or(Time::MAX));
+                    }
+                }
+                Directive::Anonymous => shaman.anonymous = true,
+                Directive::Setting(key, value) => {
+                    shaman.apply_setting(&key, &value).map_err(|message| {
+                        ShamanError::InvalidSetting {
+                            src: src.clone(),
+                            message,
+                            highlight,
+                        }
+                    })?;
+                    shaman.settings.push((key, value));
+                }
+                Directive::Via(n, waypoints) => {
+                    shaman
+                        .robots
+                        .get_mut(&n)
+                        .ok_or(unknown(n))?
+                        .set_waypoints(waypoints);
+                    vias.push((n, highlight));
+                }
+                Directive::Patrol(n, waypoints, laps) => {
+                    shaman
+                        .robots
+                        .get_mut(&n)
+                        .ok_or(unknown(n))?
+                        .set_patrol(waypoints, laps);
+                    patrols.push((n, highlight));
+                }
+                Directive::Window(n, open, close) => {
+                    windows.push((goal_of(&shaman, n)?, open, close))
+                }
+                Directive::Goal(n, owner) => {
+                    let goal = goal_of(&shaman, owner)?;
+                    shaman.robots.get_mut(&n).ok_or(unknown(n))?.set_goal(
+                        &shaman.layout,
+                        goal,
+                        highlight,
+                    )?;
+                }
+            }
+            Ok(())
+        };
+        problems.extend(apply().err());
+    }
+
+    // After all directives, since `stay` & `goal` directives may give robots goals, too
+    for (n, highlight) in patrols {
+        if shaman.robots[&n].goal().is_some() {
+            problems.push(ShamanError::PatrolWithGoal {
+                src: src.clone(),
+                robot: n,
+                highlight,
+            });
+        }
+    }
+
+    for (n, highlight) in vias {
+        if shaman.robots[&n].goal().is_none() {
+            problems.push(ShamanError::NoSuchGoal {
+                src: src.clone(),
+                robot: n,
+                highlight,
+            });
+        }
+    }
+
+    // After all directives, so robots sharing a goal by a later `goal` directive get it too
+    for (goal, open, close) in windows {
+        for robot in shaman.robots.values_mut() {
+            if robot.goal().is_some_and(|(g, _)| g == goal) {
+                robot.set_window(open, close);
+            }
+        }
+    }
+
+    let goals = shaman
+        .robots
+        .values()
+        .filter_map(|r| Some((r.goal()?, r.name())))
+        .into_group_map_by(|((g, _), _)| *g);
+    for (goal, robots) in goals.into_iter().sorted_by_key(|(g, _)| (g.y, g.x)) {
+        let capacity = shaman.capacities.get(&goal).copied().unwrap_or(1);
+        if robots.len() > capacity {
+            let ((_, last), _) = robots
+                .iter()
+                .max_by_key(|((_, span), _)| span.offset())
+                .unwrap();
+            problems.push(ShamanError::GoalOverCapacity {
+                src: src.clone(),
+                capacity,
+                robots: robots.len(),
+                highlight: *last,
+            });
+        }
+        if shaman.capacities.contains_key(&goal) {
+            for (_, n) in robots {
+                shaman.robots.get_mut(&n).unwrap().set_delivers(true);
+            }
+        }
+    }
+
+    Ok(shaman)
+}
+
+struct Spanned<'a, T> {
+    span: Span<'a>,
+    inner: T,
+}
+
+#[derive(Debug, Clone, Copy, EnumAsInner)]
+enum Cell {
+    Free,
+    Robot(char),
+    Goal(char),
+    Obstacle,
+    GoalSouth(char),
+    /// Any other character, read as a free cell to find further problems after it
+    Invalid,
+}
+
+/// Robot or fleet of robots a [Directive] applies to
+#[derive(Debug, Clone)]
+enum Target {
+    /// `A`
+    Robot(char),
+    /// `pickers`: All robots tagged by a [Directive::Fleet]
+    Fleet(String),
+}
+
+/// Settings in the map's header section
+#[derive(Debug, Clone)]
+enum Directive {
+    /// `fleet A,B = pickers`: Robots A & B belong to the fleet `pickers`, e.g. of one vendor.
+    /// Other directives can refer to all its robots at once by its name
+    Fleet(Vec<char>, String),
+    /// `weight A = 10`: Delays of robot A are ten times as costly as others'. Also applies to
+    /// all robots of a fleet, e.g. `weight pickers = 10`
+    Weight(Target, f32),
+    /// `pace A = 3`: Robot A takes three timesteps to move to an adjacent cell, e.g. a slow
+    /// tugger among fast AGVs. Also applies to all robots of a fleet, e.g. `pace tuggers = 3`
+    Pace(Target, Time),
+    /// `class A = 1`: Robot A is planned before all robots of higher classes (or without one)
+    Class(char, u32),
+    /// `exclude A = 2/3..5/6`: Robot A must never enter the rectangle spanned by both corners
+    /// (inclusive), as far as it's on the map. A single `x/y` only excludes that cell. Also
+    /// applies to all robots of a fleet, e.g. `exclude pickers = 2/3`
+    Exclude(Target, Vertex, Vertex),
+    /// `capacity a = 3`: The goal of robot A is a drop-off like a chute, which serves up to
+    /// three robots. Robots driving there leave the map on arrival
+    Capacity(char, usize),
+    /// `goal B = a`: Robot B drives to the goal of robot A, instead of one drawn on the map
+    Goal(char, char),
+    /// `stay A`: The goal of robot A is where it starts. It occupies that cell for good, only
+    /// stepping aside to let others pass, instead of drawing its goal on the map
+    Stay(char),
+    /// `window a = 5..10`: The goal of robot A is only open from timestep 5 to 10 (inclusive),
+    /// e.g. a loading dock. Robots driving there wait nearby if they are early
+    Window(char, Time, Time),
+    /// `via A = 3/4 > 7/1`: Robot A visits 3/4, then 7/1, on its way to its goal
+    Via(char, Vec<Vertex>),
+    /// `patrol A = 5/1 > 5/3 * 2`: Robot A has no goal, but drives from its start to 5/1, 5/3
+    /// & back twice (once without `* 2`), like third party traffic everybody else evades
+    Patrol(char, Vec<Vertex>, usize),
+    /// `movement = eight-connected`: Setting of the whole scenario, named like in a
+    /// [crate::CONFIG_FILE], see [Shaman::settings]
+    Setting(String, String),
+    /// `anonymous`: Goals are interchangeable, robots drive to whichever one minimizes the
+    /// makespan instead of the one with their letter
+    Anonymous,
+    /// `cost 2/3 > 3/3 = 2`: Moving from the first to the adjacent second cell is twice as
+    /// costly, e.g. up a ramp. The opposite direction is unaffected. Diagonally adjacent cells
+    /// work the same with eight-connected movement. Factors below `1` would let the heuristics
+    /// overestimate, so they are rejected
+    Cost(Vertex, Vertex, f32),
+    /// `close 2/3..2/4 = 10..20`: The rectangle spanned by both corners (inclusive) is blocked
+    /// from timestep 10 to 20 (inclusive), e.g. a door. A single `x/y` only closes that cell,
+    /// `10..` closes it for good
+    Close(Vertex, Vertex, Time, Option<Time>),
+}
+
+/// Optional section before the grid, fenced by `---` lines, with one [Directive] per line
+fn header(s: Span) -> IResult<Vec<Spanned<Directive>>> {
+    let fence = || (tag("---"), newline);
+    let (s, _) = fence().parse(s)?;
+    cut(terminated(
+        many0(alt((
+            newline.map(|_| None),
+            terminated(directive, (space0, newline)).map(Some),
+        ))),
+        fence(),
+    ))
+    .map(|ds| ds.into_iter().flatten().collect())
+    .parse(s)
+}
+
+fn directive(s: Span) -> IResult<Spanned<Directive>> {
+    consumed(alt((
+        (
+            tag("fleet"),
+            space1,
+            separated_list1(delimited(space0, char(','), space0), robot),
+            equals,
+            fleet,
+        )
+            .map(|(_, _, ns, _, f)| Directive::Fleet(ns, f)),
+        (
+            tag("weight"),
+            space1,
+            target,
+            equals,
+            verify(float, |w: &f32| w.is_finite() && *w > 0.),
+        )
+            .map(|(_, _, t, _, w)| Directive::Weight(t, w)),
+        (tag("pace"), space1, target, equals, verify(u32, |p| *p > 0))
+            .map(|(_, _, t, _, p)| Directive::Pace(t, p as Time)),
+        (tag("class"), space1, robot, equals, u32).map(|(_, _, n, _, c)| Directive::Class(n, c)),
+        (
+            tag("exclude"),
+            space1,
+            target,
+            equals,
+            vertex,
+            opt(preceded(tag(".."), vertex)),
+        )
+            .map(|(_, _, t, _, a, b)| Directive::Exclude(t, a, b.unwrap_or(a))),
+        (tag("capacity"), space1, goal, equals, u32)
+            .map(|(_, _, n, _, k)| Directive::Capacity(n, k as usize)),
+        (tag("goal"), space1, robot, equals, goal).map(|(_, _, n, _, g)| Directive::Goal(n, g)),
+        (tag("stay"), space1, robot).map(|(_, _, n)| Directive::Stay(n)),
+        (
+            tag("window"),
+            space1,
+            goal,
+            equals,
+            verify(separated_pair(u32, tag(".."), u32), |(a, b)| a <= b),
+        )
+            .map(|(_, _, n, _, (a, b))| Directive::Window(n, a as Time, b as Time)),
+        (tag("via"), space1, robot, equals, waypoints).map(|(_, _, n, _, w)| Directive::Via(n, w)),
+        (
+            tag("patrol"),
+            space1,
+            robot,
+            equals,
+            waypoints,
+            opt(preceded(
+                delimited(space0, char('*'), space0),
+                verify(u32, |l| *l > 0),
+            )),
+        )
+            .map(|(_, _, n, _, w, laps)| Directive::Patrol(n, w, laps.unwrap_or(1) as usize)),
+        tag("anonymous").map(|_| Directive::Anonymous),
+        (
+            tag("cost"),
+            space1,
+            vertex,
+            delimited(space0, char('>'), space0),
+            vertex,
+            equals,
+            verify(float, |c: &f32| c.is_finite() && *c >= 1.),
+        )
+            .map(|(_, _, a, _, b, _, c)| Directive::Cost(a, b, c)),
+        (
+            tag("close"),
+            space1,
+            vertex,
+            opt(preceded(tag(".."), vertex)),
+            equals,
+            // Timesteps as wide as the builder prints them, so `..18446744073709551615` closes
+            // for good just like `..`
+            verify((u64, tag(".."), opt(u64)), |(a, _, b)| {
+                b.is_none_or(|b| *a <= b)
+            }),
+        )
+            .map(|(_, _, a, b, _, (open, _, close))| {
+                Directive::Close(a, b.unwrap_or(a), open as Time, close.map(|c| c as Time))
+            }),
+        (
+            recognize(take_while1(|c: char| c.is_ascii_lowercase() || c == '-')),
+            equals,
+            take_till1(|c| c == '\n'),
+        )
+            .map(|(k, _, v): (Span, _, Span)| {
+                Directive::Setting(k.fragment().to_string(), v.fragment().trim().to_string())
+            }),
+    )))
+    .map(|(span, inner)| Spanned { span, inner })
+    .parse(s)
+}
+
+fn robot(s: Span) -> IResult<char> {
+    satisfy(|c| c.is_ascii_uppercase()).parse(s)
+}
+
+/// Lowercase name of a fleet, e.g. `pickers` or `vendor-2`
+fn fleet(s: Span) -> IResult<String> {
+    recognize((
+        satisfy(|c| c.is_ascii_lowercase()),
+        take_while(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-'),
+    ))
+    .map(|f: Span| f.fragment().to_string())
+    .parse(s)
+}
+
+fn target(s: Span) -> IResult<Target> {
+    alt((robot.map(Target::Robot), fleet.map(Target::Fleet))).parse(s)
+}
+
+/// Lowercase letter of a goal, resolving to the name of its robot
+fn goal(s: Span) -> IResult<char> {
+    satisfy(|c| c.is_ascii_lowercase())
+        .map(|c| c.to_ascii_uppercase())
+        .parse(s)
+}
+
+/// `x/y` coordinates of a cell
+fn vertex(s: Span) -> IResult<Vertex> {
+    separated_pair(i32, char('/'), i32)
+        .map(|(x, y)| Vertex::new(x, y))
+        .parse(s)
+}
+
+/// Cells to visit in order, like `3/4 > 7/1`
+fn waypoints(s: Span) -> IResult<Vec<Vertex>> {
+    separated_list1(delimited(space0, char('>'), space0), vertex).parse(s)
+}
+
+fn equals(s: Span) -> IResult<char> {
+    delimited(space0, char('='), space0).parse(s)
+}
+
+/// Rows of cells, each with the newline ending it
+fn grid(s: Span) -> IResult<Vec<(Vec<Spanned<Cell>>, Span)>> {
+    many_till(
+        many_till(cell, consumed(newline)).map(|(cells, (end, _))| (cells, end)),
+        eof,
+    )
+    .map(ignore_delim())
+    .parse(s)
+}
+
+fn cell(s: Span) -> IResult<Spanned<Cell>> {
+    let (s, span) = position(s)?;
+    let (s, cell) = alt((
+        char(' ').map(always(Cell::Free)),
+        char('#').or(char('█')).map(always(Cell::Obstacle)),
+        satisfy(|c| c.is_ascii_uppercase()).map(Cell::Robot),
+        satisfy(|c| c.is_ascii_lowercase())
+            .map(|c| c.to_ascii_uppercase())
+            .map(Cell::Goal),
+        satisfy(|c| ('ⓐ'..='ⓩ').contains(&c))
+            .map(|c| ((c as u32 - 0x24D0 + 0x41) as u8) as char)
+            .map(Cell::GoalSouth),
+        satisfy(|c| c != '\n').map(always(Cell::Invalid)),
+    ))
+    .parse(s)?;
+    Ok((s, Spanned { span, inner: cell }))
+}
+
+pub fn always<A: Copy, B>(x: A) -> impl Fn(B) -> A {
+    move |_| x
+}
+
+pub fn ignore_delim<A, B>() -> impl Fn((A, B)) -> A {
+    |(a, _)| a
+}
@@ -0,0 +1,169 @@
+//! Importing the `.map` & `.scen` benchmark formats of <https://movingai.com/benchmarks/mapf>
+//!
+//! Maps start with a header of their size, followed by one character per cell, of which only
+//! `.`, `G` & `S` are passable:
+//!
+//! ```text
+//! type octile
+//! height 3
+//! width 4
+//! map
+//! @@@@
+//! @..@
+//! @@@@
+//! ```
+//!
+//! Scenarios list one agent per line with tab separated columns after a `version` line, of
+//! which only the map & the start and goal coordinates are used:
+//!
+//! ```text
+//! version 1
+//! 0 example.map 4 3 1 1 2 1 1
+//! ```
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use miette::{NamedSource, Result, SourceSpan, miette};
+use nom::{
+    Parser,
+    bytes::complete::{tag, take_till},
+    character::complete::{line_ending, space1, u32},
+    error::Error,
+    sequence::{delimited, terminated},
+};
+
+use crate::{
+    Shaman,
+    layout::Vertex,
+    robot::{NAMES, Robot},
+};
+
+/// Scenario of the `.map` or `.scen` file at `path` with the `content`, without planning any
+/// routes yet. Scenarios read the map they refer to relative to their own directory, or from it
+/// if there is none at that path. Reports point into the map, at the robots' cells
+pub(crate) fn import(path: &Path, content: &str) -> Result<Shaman> {
+    let name = path.display().to_string();
+    if !content.starts_with("version") {
+        return Ok(map(&name, content)?.0);
+    }
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let (map_path, agents) = scenario(&name, dir, content)?;
+    let map_path = match map_path.exists() {
+        true => map_path,
+        false => dir.join(map_path.file_name().unwrap_or_default()),
+    };
+    let map_name = map_path.display().to_string();
+    let map_content = std::fs::read_to_string(&map_path).map_err(|e| miette!("{map_name}: {e}"))?;
+    let (mut shaman, rows) = map(&map_name, &map_content)?;
+    // Where the cell `v` is written in the map, to point reports at it
+    let span = |v: Vertex| -> SourceSpan {
+        let row = rows[v.y as usize];
+        let x = map_content[row..].char_indices().nth(v.x as usize);
+        (row + x.map_or(0, |(x, _)| x), 1).into()
+    };
+    for (i, ((start, goal), name)) in agents.into_iter().zip(NAMES).enumerate() {
+        if shaman.layout.is_blocked(start) {
+            return Err(miette!(
+                "{map_name}: Agent {i} starts on an obstacle at {start}"
+            ));
+        }
+        if shaman.layout.is_blocked(goal) {
+            return Err(miette!(
+                "{map_name}: Agent {i} has its goal on an obstacle at {goal}"
+            ));
+        }
+        let mut robot = Robot::new(name, start.x, start.y, span(start));
+        robot.set_goal(&shaman.layout, goal, span(goal))?;
+        shaman.robots.insert(name, robot);
+    }
+    Ok(shaman)
+}
+
+/// Obstacles of a `.map` file called `name`, without any robots, and where each of its rows
+/// starts in `s`
+fn map(name: &str, s: &str) -> Result<(Shaman, Vec<usize>)> {
+    let (grid, (_, height, width, _)) = (
+        terminated((tag("type"), take_till(|c| c == '\n')), line_ending),
+        header("height"),
+        header("width"),
+        terminated(tag("map"), line_ending),
+    )
+        .parse(s)
+        .map_err(|_| miette!("{name}: MovingAI map should start with its type, height & width"))?;
+
+    let rows = grid.lines().collect_vec();
+    if rows.len() != height as usize {
+        return Err(miette!(
+            "{name}: Map should have {height} rows, but has {}",
+            rows.len()
+        ));
+    }
+    let code = NamedSource::new(name, s.to_string());
+    let mut shaman = Shaman::new(code, width as i32, height as i32);
+    let mut offsets = vec![];
+    for (y, row) in rows.into_iter().enumerate() {
+        if row.chars().count() != width as usize {
+            return Err(miette!(
+                "{name}: Row {y} should have {width} cells, but has {}",
+                row.chars().count()
+            ));
+        }
+        offsets.push(row.as_ptr() as usize - s.as_ptr() as usize);
+        for (x, c) in row.chars().enumerate() {
+            if !matches!(c, '.' | 'G' | 'S') {
+                shaman.layout.block(Vertex::new(x as i32, y as i32));
+            }
+        }
+    }
+    Ok((shaman, offsets))
+}
+
+/// `key value` line of a map's header
+fn header<'a>(key: &'static str) -> impl Parser<&'a str, Output = u32, Error = Error<&'a str>> {
+    delimited((tag(key), space1), u32, line_ending)
+}
+
+/// Path of the map a `.scen` file called `name` refers to, relative to `dir`, and the starts &
+/// goals of its agents. Fails for more agents than there are robot names
+fn scenario(name: &str, dir: &Path, s: &str) -> Result<(PathBuf, Vec<(Vertex, Vertex)>)> {
+    let mut lines = s.lines().filter(|l| !l.trim().is_empty());
+    if !lines.next().is_some_and(|l| l.starts_with("version")) {
+        return Err(miette!(
+            "{name}: MovingAI scenario should start with a version"
+        ));
+    }
+    let mut map = None;
+    let mut agents = vec![];
+    for (i, line) in lines.enumerate() {
+        let columns = line.split('\t').collect_vec();
+        let [_, file, _, _, sx, sy, gx, gy, ..] = columns[..] else {
+            return Err(miette!("{name}: Agent {i} should have at least 8 columns"));
+        };
+        let coordinate = |c: &str| {
+            c.trim()
+                .parse::<i32>()
+                .map_err(|e| miette!("{name}: Agent {i} has invalid coordinate '{c}': {e}"))
+        };
+        let start = Vertex::new(coordinate(sx)?, coordinate(sy)?);
+        let goal = Vertex::new(coordinate(gx)?, coordinate(gy)?);
+        match map {
+            None => map = Some(file),
+            Some(m) if m != file => {
+                return Err(miette!(
+                    "{name}: Agents are on different maps, '{m}' and '{file}'"
+                ));
+            }
+            Some(_) => {}
+        }
+        agents.push((start, goal));
+    }
+    let map = map.ok_or_else(|| miette!("{name}: MovingAI scenario has no agents"))?;
+    if agents.len() > NAMES.count() {
+        return Err(miette!(
+            "{name}: Scenario has {} agents, but at most {} robots can be named",
+            agents.len(),
+            NAMES.count()
+        ));
+    }
+    Ok((dir.join(map), agents))
+}
@@ -0,0 +1,419 @@
+//! Priority based solving of MAPF problem
+use std::{
+    collections::{BTreeMap, BinaryHeap},
+    time::Instant,
+};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+use ordered_float::OrderedFloat;
+use petgraph::{acyclic::Acyclic, algo::toposort, data::Build, prelude::*};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    Objective, Shaman, ShamanError,
+    astar::{PlannerConfig, RightOfWay},
+    audit::{Audit, Decision},
+    focal,
+    instrumentation::record,
+    layout::Layout,
+    rng::{RandomSource, Rng},
+    robot::Robot,
+    route::Collision,
+};
+
+/// Main entry point for finding the best [Idea] for a MAPF problem
+#[derive(Debug)]
+pub struct Pbs {
+    /// The scenario to solve, whose robots are moved into the [Idea]s
+    shaman: Shaman,
+    queue: BinaryHeap<Idea>,
+    /// Draws [Idea::tie]s, if the scenario has a [Shaman::seed]
+    rng: Option<Rng>,
+}
+
+impl From<Shaman> for Pbs {
+    fn from(mut value: Shaman) -> Self {
+        let mut queue = BinaryHeap::new();
+        queue.push(Idea {
+            robots: std::mem::take(&mut value.robots),
+            priorities: Acyclic::new(),
+            objective: value.objective,
+            revisions: Default::default(),
+            upstream: Default::default(),
+            tie: 0,
+        });
+
+        Self {
+            rng: value.seed.map(Rng::new),
+            shaman: value,
+            queue,
+        }
+    }
+}
+
+impl Pbs {
+    /// Pin some priorities up front, where each `(boss, subordinate)` pair makes the latter
+    /// always yield to the former. All other priorities are still decided by the search
+    pub fn with_priorities(mut self, edges: &[(char, char)]) -> Result<Self> {
+        let Some(mut idea) = self.queue.pop() else {
+            return Ok(self);
+        };
+        for (boss, subordinate) in edges {
+            if let Some(unknown) = [boss, subordinate]
+                .into_iter()
+                .find(|n| !idea.robots.contains_key(n))
+            {
+                return Err(miette!("Cannot pin priority of unknown robot '{unknown}'"));
+            }
+            let b = idea.find_or_create_node(*boss);
+            let s = idea.find_or_create_node(*subordinate);
+            if idea.priorities.try_add_edge(b, s, ()).is_err() {
+                return Err(miette!(
+                    "Pinning '{subordinate}' to yield to '{boss}' creates a cycle of priorities"
+                ));
+            }
+        }
+        if !edges.is_empty() {
+            idea.plan(
+                &self.shaman.layout,
+                &self.shaman.reservations,
+                self.shaman.planner,
+            )?;
+        }
+        self.queue.push(idea);
+        Ok(self)
+    }
+
+    /// Minimize `objective` instead of the one of the scenario
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.shaman.objective = objective;
+        for mut idea in std::mem::take(&mut self.queue) {
+            idea.objective = objective;
+            self.queue.push(idea);
+        }
+        self
+    }
+
+    /// Make robots of each priority class boss of all robots in the next lower class
+    fn with_classes(mut self) -> Result<Self> {
+        let Some(mut idea) = self.queue.pop() else {
+            return Ok(self);
+        };
+        let classes = idea
+            .robots
+            .values()
+            .map(|r| (r.class().is_none(), r.class(), r.name()))
+            .sorted()
+            .chunk_by(|(none, class, _)| (*none, *class))
+            .into_iter()
+            .map(|(_, group)| group.map(|(_, _, n)| n).collect_vec())
+            .collect_vec();
+
+        for (bosses, subordinates) in classes.iter().tuple_windows() {
+            for (boss, subordinate) in bosses.iter().cartesian_product(subordinates) {
+                let b = idea.find_or_create_node(*boss);
+                let s = idea.find_or_create_node(*subordinate);
+                idea.priorities.try_add_edge(b, s, ()).map_err(|_| {
+                    miette!(
+                        "Pinned priority of '{subordinate}' over '{boss}' contradicts their classes"
+                    )
+                })?;
+            }
+        }
+        if classes.len() > 1 {
+            idea.plan(
+                &self.shaman.layout,
+                &self.shaman.reservations,
+                self.shaman.planner,
+            )?;
+        }
+        self.queue.push(idea);
+        Ok(self)
+    }
+
+    /// Solve the MAPF problem by:
+    ///
+    /// 1. Finding a collision between any pair of robots
+    /// 2. Fixing one of the two and make the other use the first as [RightOfWay] constraint
+    /// 3. Repeating 2. with both robots flipped
+    pub fn solve(self) -> Result<Shaman> {
+        let mut pbs = self.with_classes()?;
+        let mut audit = Audit::default();
+        let started = Instant::now();
+        record(|stats| stats.generated += pbs.queue.len());
+        let mut queue =
+            focal::Queue::new(pbs.shaman.planner.bound(), |i| i.cost().0, Idea::conflicts);
+        queue.extend(std::mem::take(&mut pbs.queue));
+        while let Some(idea) = queue.pop() {
+            record(|stats| stats.expanded += 1);
+            let Some((a, b, collisions)) = idea
+                .robots
+                .values()
+                .tuple_combinations()
+                .map(|(a, b)| (a.name(), b.name(), a.conflicts_with(b)))
+                .find(|(_, _, collisions)| !collisions.is_empty())
+            else {
+                // No more conflicts (=
+                pbs.shaman.robots = idea.robots;
+                pbs.shaman.optimize_routes();
+                audit.solution = Some(pbs.shaman.objective_cost());
+                pbs.shaman.audit = audit;
+                return Ok(pbs.shaman);
+            };
+
+            let decisions = audit.decisions.len();
+            let timeout = pbs
+                .shaman
+                .planner
+                .check_time(&pbs.shaman.layout, started, || {
+                    format!("resolving {decisions} conflicts")
+                });
+            if let Err(e) = timeout {
+                // Settle for the cheapest conflict free idea waiting in the queue, if any
+                let Some(incumbent) = queue
+                    .into_iter()
+                    .filter(Idea::is_conflict_free)
+                    .min_by_key(|idea| (idea.cost(), idea.sum_of_costs()))
+                else {
+                    return Err(e.into());
+                };
+                audit.suboptimal = true;
+                pbs.shaman.robots = incumbent.robots;
+                pbs.shaman.optimize_routes();
+                audit.solution = Some(pbs.shaman.objective_cost());
+                pbs.shaman.audit = audit;
+                return Ok(pbs.shaman);
+            }
+
+            record(|stats| stats.conflicts += 1);
+            let mut decision = Decision {
+                cost: idea.cost().0,
+                robots: (a, b),
+                cells: collisions.iter().flat_map(Collision::cells).collect(),
+                branches: vec![],
+            };
+            let Shaman {
+                layout,
+                reservations,
+                planner,
+                ..
+            } = &pbs.shaman;
+            #[cfg(feature = "parallel")]
+            let children = {
+                // Ideas aren't Sync, so each thread gets its own
+                let twin = idea.clone();
+                let (ab, ba) = crate::instrumentation::join(
+                    move || idea.branch(layout, reservations, *planner, a, b),
+                    move || twin.branch(layout, reservations, *planner, b, a),
+                );
+                [ab, ba]
+            };
+            #[cfg(not(feature = "parallel"))]
+            let children = [
+                idea.branch(layout, reservations, *planner, a, b),
+                idea.branch(layout, reservations, *planner, b, a),
+            ];
+            for ((boss, subordinate), child) in [(a, b), (b, a)].into_iter().zip(children) {
+                let child = child?;
+                decision
+                    .branches
+                    .push((boss, subordinate, child.as_ref().map(|c| c.cost().0)));
+                if let Some(mut child) = child {
+                    child.tie = pbs.rng.as_mut().map_or(0, |rng| rng.next_u64());
+                    record(|stats| stats.generated += 1);
+                    queue.push(child);
+                }
+            }
+            audit.decisions.push(decision);
+        }
+
+        Err(miette!("Ran out of ideas =("))
+    }
+}
+
+impl Shaman {
+    /// Strip avoidable waits & detours from all routes PBS settled on, robot by robot around
+    /// everybody else, see [crate::Route::optimize]. Robots whose waits are part of how they
+    /// move, i.e. slower paced or rotating ones, or which have to pass waypoints or arrive in
+    /// a window keep their routes
+    fn optimize_routes(&mut self) {
+        if self.planner.rotate_cost.is_some() {
+            return;
+        }
+        let names = self.robots.keys().copied().collect_vec();
+        for name in names {
+            let robot = &self.robots[&name];
+            let keeps = robot.pace() > 1
+                || robot.window().is_some()
+                || robot.patrol().is_some()
+                || !robot.waypoints().is_empty();
+            if !robot.evades() || keeps {
+                continue;
+            }
+            let mut others = vec![self.reservations.clone()];
+            others.extend(
+                self.robots
+                    .values()
+                    .filter(|r| r.name() != name)
+                    .map(|r| r.right_of_way(r.route())),
+            );
+            let mut excluded = RightOfWay::default();
+            let cells = robot.excluded().iter().copied().collect_vec();
+            excluded.reserve(&cells, 0..robot.route().duration() + 1);
+            others.push(excluded);
+
+            let mut route = robot.route().clone();
+            route.optimize(&self.layout, &others, &self.planner.costs);
+            let robot = self.robots.get_mut(&name).unwrap();
+            let position = robot.position().0;
+            robot.restore(position, route, false);
+        }
+    }
+
+    /// Cost of the current routes by the scenario's [Objective]
+    fn objective_cost(&self) -> f32 {
+        self.objective.cost(self.robots.values())
+    }
+}
+
+/// A single possible solution to the overall MAPF problem
+#[derive(Debug, Default, Clone)]
+pub struct Idea {
+    priorities: Acyclic<StableDiGraph<char, ()>>,
+    robots: BTreeMap<char, Robot>,
+    objective: Objective,
+    /// How often each robot's route changed, identifying it within this idea's lineage
+    revisions: FxHashMap<char, u64>,
+    /// [Self::revisions] of the routes each robot was last planned around, by name
+    upstream: FxHashMap<char, Vec<(char, u64)>>,
+    /// Breaks ties between equally costly ideas, lowest first, see [Shaman::with_seed]
+    tie: u64,
+}
+
+impl Ord for Idea {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Among equally costly ideas, e.g. of the same makespan, prefer less overall travel
+        let key = |idea: &Self| (idea.cost(), idea.sum_of_costs(), idea.tie);
+        key(self).cmp(&key(other)).reverse() // for min heap
+    }
+}
+
+impl PartialEq for Idea {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+impl PartialOrd for Idea {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Eq for Idea {}
+
+impl Idea {
+    fn cost(&self) -> OrderedFloat<f32> {
+        self.objective.cost(self.robots.values()).into()
+    }
+
+    fn sum_of_costs(&self) -> OrderedFloat<f32> {
+        Objective::SumOfCosts.cost(self.robots.values()).into()
+    }
+
+    fn plan(
+        &mut self,
+        layout: &Layout,
+        reservations: &RightOfWay,
+        config: PlannerConfig,
+    ) -> Result<()> {
+        let order = toposort(&self.priorities, None)
+            .expect("Cycle detected")
+            .into_iter()
+            .map(|n| self.priorities[n])
+            .collect::<Vec<_>>();
+
+        // Robots only need replanning if the routes planned before them changed, e.g. not
+        // those before the subordinate of a new branch. Sorting keeps the key independent of
+        // the order they were planned in
+        let mut constraints = reservations.clone();
+        let mut upstream = vec![];
+        for n in &order {
+            let robot = self.robots.get_mut(n).unwrap();
+
+            if self.upstream.get(n) != Some(&upstream) {
+                let before = robot.route().clone();
+                robot.plan(layout, &constraints, config)?;
+                if *robot.route() != before {
+                    *self.revisions.entry(*n).or_default() += 1;
+                }
+                self.upstream.insert(*n, upstream.clone());
+            }
+            constraints += robot.right_of_way(robot.route());
+            let key = (*n, self.revisions.get(n).copied().unwrap_or_default());
+            upstream.insert(upstream.partition_point(|k| *k < key), key);
+        }
+
+        Ok(())
+    }
+
+    /// How many pairs of robots collide
+    fn conflicts(&self) -> usize {
+        self.robots
+            .values()
+            .tuple_combinations()
+            .filter(|(a, b)| a.conflicts(b))
+            .count()
+    }
+
+    fn is_conflict_free(&self) -> bool {
+        !self
+            .robots
+            .values()
+            .tuple_combinations()
+            .any(|(a, b)| a.conflicts(b))
+    }
+
+    fn find_or_create_node(&mut self, name: char) -> NodeIndex {
+        self.priorities
+            .node_indices()
+            .find(|n| self.priorities[*n] == name)
+            .unwrap_or_else(|| self.priorities.add_node(name))
+    }
+
+    fn branch(
+        &self,
+        layout: &Layout,
+        reservations: &RightOfWay,
+        config: PlannerConfig,
+        boss: char,
+        subordinate: char,
+    ) -> Result<Option<Self>> {
+        if !self.robots[&subordinate].evades() {
+            return Ok(None); // Frozen or goal-less robots don't evade anybody
+        }
+        let mut child = self.clone();
+
+        let b = child.find_or_create_node(boss);
+        let s = child.find_or_create_node(subordinate);
+        if child.priorities.contains_edge(b, s) {
+            return Ok(None);
+        }
+        if child.priorities.try_add_edge(b, s, ()).is_err() {
+            return Ok(None);
+        }
+
+        match child.plan(layout, reservations, config) {
+            Ok(()) => Ok(Some(child)),
+            Err(e) if is_budget_exceeded(&e) => Err(e),
+            Err(_) => Ok(None), // Plan would lead to deadlock
+        }
+    }
+}
+
+/// Whether `e` is about running out of budget, which no other branch could do any better
+pub(crate) fn is_budget_exceeded(e: &miette::Report) -> bool {
+    matches!(
+        e.downcast_ref::<ShamanError>(),
+        Some(ShamanError::BudgetExceeded { .. })
+    )
+}
@@ -0,0 +1,114 @@
+//! Greedy prioritized planning, a fast baseline to compare the other solvers against, see
+//! [crate::Algorithm::Prioritized]
+use std::str::FromStr;
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    Shaman,
+    instrumentation::record,
+    rng::{RandomSource, Rng},
+};
+
+/// Order [crate::Algorithm::Prioritized] plans robots in, see [Shaman::with_order]. Robots of
+/// lower priority classes always come first
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Robots with the longest shortest path to their goal first, which have the least room to
+    /// evade others
+    #[default]
+    Distance,
+    /// Robots in the order they appear on the map, row by row
+    Input,
+    /// Shuffled with this seed
+    Random(u64),
+}
+
+/// `distance`, `input`, `random` or `random=<seed>`
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "distance" => Ok(Self::Distance),
+            "input" => Ok(Self::Input),
+            "random" => Ok(Self::Random(0)),
+            _ => s
+                .strip_prefix("random=")
+                .and_then(|seed| seed.parse().ok())
+                .map(Self::Random)
+                .ok_or_else(|| format!("Expected distance, input or random=<seed>, got '{s}'")),
+        }
+    }
+}
+
+impl Shaman {
+    /// Order [crate::Algorithm::Prioritized] plans robots in, see [Self::with_order]
+    pub fn order(&self) -> Order {
+        self.order
+    }
+
+    /// Let [crate::Algorithm::Prioritized] plan robots in `order` from now on
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Names of all robots in the order to plan them in
+    fn planning_order(&self) -> Vec<char> {
+        let class = |name: &char| {
+            let robot = &self.robots[name];
+            // Frozen or goal-less robots don't evade anybody, so everybody else evades them
+            (robot.evades(), robot.class().is_none(), robot.class())
+        };
+        let mut names = self.robots.keys().copied().sorted().collect_vec();
+        match self.order {
+            Order::Distance => names.sort_by_cached_key(|n| {
+                let robot = &self.robots[n];
+                let distance = robot
+                    .goal()
+                    .and_then(|(goal, _)| self.layout.distance(robot.position().0, goal));
+                std::cmp::Reverse(distance.unwrap_or_default())
+            }),
+            Order::Input => names.sort_by_key(|n| self.robots[n].position().1.offset()),
+            Order::Random(seed) => Rng::new(seed).shuffle(&mut names),
+        }
+        // Stable, so the order within each class stays
+        names.sort_by_key(class);
+        names
+    }
+
+    /// Plan each robot once in [Self::order], avoiding the routes of all robots planned before
+    /// it. Fails if any robot can't get past those, or if robots planned early run into robots
+    /// planned later, e.g. still waiting on their start
+    pub(crate) fn solve_prioritized(mut self) -> Result<Self> {
+        let order = self.planning_order();
+        record(|stats| {
+            stats.generated += 1;
+            stats.expanded += 1;
+        });
+        let mut constraints = self.reservations.clone();
+        for name in &order {
+            let robot = self.robots.get_mut(name).unwrap();
+            robot.plan(&self.layout, &constraints, self.planner)?;
+            constraints += robot.right_of_way(robot.route());
+        }
+        let collision = self
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .tuple_combinations()
+            .find(|(a, b)| a.conflicts(b));
+        if let Some((a, b)) = collision {
+            record(|stats| stats.conflicts += 1);
+            return Err(miette!(
+                "Planning robots in the order {} left '{}' & '{}' colliding, try another order",
+                order.iter().join(""),
+                a.name(),
+                b.name(),
+            ));
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,159 @@
+//! Single figure of how good a solution is, to compare configurations without reading routes
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::{
+    Shaman,
+    json::{Json, ToJson},
+    rng::{RandomSource, Rng},
+    robot::Location,
+    route::Route,
+};
+
+/// Chance of a robot being held up for one timestep before each move, when checking robustness
+const JITTER: f32 = 0.1;
+/// How many jittered executions [Shaman::quality] checks
+const RUNS: usize = 20;
+
+/// How good the robots' routes are, see [Shaman::quality]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quality {
+    /// How much costlier the routes are than the lower bound, relative to it, see
+    /// [crate::Summary::gap]
+    pub gap: f32,
+    /// Fraction of all robots' timesteps spent waiting
+    pub congestion: f32,
+    /// Fraction of executions with random hold-ups, which still finish without collisions
+    pub robustness: f32,
+    /// Whether any two robots run into each other as planned
+    pub conflicts: bool,
+}
+
+/// Coarse classification of a [Quality], like school grades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl Shaman {
+    /// Rate the robots' current routes by their optimality gap, congestion & how well they
+    /// tolerate random hold-ups. Always checks the same hold-ups, so it is reproducible
+    pub fn quality(&self) -> Quality {
+        self.quality_with(RUNS, &mut Rng::new(0))
+    }
+
+    /// Like [Self::quality], but checking `runs` executions with hold-ups drawn from `rng`
+    pub fn quality_with(&self, runs: usize, rng: &mut impl RandomSource) -> Quality {
+        let robots = self
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .collect_vec();
+        let cost = self.cost();
+        let lower_bound = self.lower_bound();
+        let waits = robots.iter().map(|r| r.waits()).sum::<usize>();
+        let collides = |routes: &[Route]| {
+            (0..robots.len()).tuple_combinations().any(|(a, b)| {
+                let parks = (!robots[a].delivers(), !robots[b].delivers());
+                !routes[a].conflicts_parking(&routes[b], parks).is_empty()
+            })
+        };
+        let planned = robots.iter().map(|r| r.route().clone()).collect_vec();
+        let robust = (0..runs)
+            .filter(|_| {
+                let jittered = planned.iter().map(|r| jittered(r, rng)).collect_vec();
+                !collides(&jittered)
+            })
+            .count();
+
+        Quality {
+            gap: (cost as f32 - lower_bound as f32) / lower_bound.max(1) as f32,
+            congestion: waits as f32 / cost.max(1) as f32,
+            robustness: robust as f32 / runs.max(1) as f32,
+            conflicts: collides(&planned),
+        }
+    }
+}
+
+/// `route` with each move delayed by one more timestep with a chance of [JITTER]
+fn jittered(route: &Route, rng: &mut impl RandomSource) -> Route {
+    let mut delay = 0;
+    let mut previous = None;
+    let mut locations = vec![];
+    for l in route.iter() {
+        if let Some(position) = previous.filter(|_| rng.uniform() < JITTER) {
+            locations.push(Location {
+                position,
+                time: l.time + delay,
+            });
+            delay += 1;
+        }
+        locations.push(Location {
+            position: l.position,
+            time: l.time + delay,
+        });
+        previous = Some(l.position);
+    }
+    locations.into_iter().collect()
+}
+
+impl Quality {
+    /// Single number in `[0, 1]` combining all measures, higher is better. Routes with
+    /// conflicts score `0`
+    pub fn score(&self) -> f32 {
+        if self.conflicts {
+            return 0.;
+        }
+        let efficiency = (1. - self.gap).clamp(0., 1.);
+        (0.4 * efficiency + 0.2 * (1. - self.congestion) + 0.4 * self.robustness).clamp(0., 1.)
+    }
+
+    /// Grade of the [Self::score] in percent, as it is shown
+    pub fn grade(&self) -> Grade {
+        match (self.score() * 100.).round() as u32 {
+            90.. => Grade::A,
+            80.. => Grade::B,
+            70.. => Grade::C,
+            60.. => Grade::D,
+            _ => Grade::F,
+        }
+    }
+}
+
+impl Display for Grade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Display for Quality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "quality:    {:.0} ({})",
+            self.score() * 100.,
+            self.grade()
+        )?;
+        writeln!(f, "gap:        {:.1}%", self.gap * 100.)?;
+        writeln!(f, "congestion: {:.1}%", self.congestion * 100.)?;
+        writeln!(f, "robustness: {:.0}%", self.robustness * 100.)
+    }
+}
+
+impl ToJson for Quality {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("score", self.score().to_json()),
+            ("grade", self.grade().to_string().to_json()),
+            ("gap", self.gap.to_json()),
+            ("congestion", self.congestion.to_json()),
+            ("robustness", self.robustness.to_json()),
+            ("conflicts", self.conflicts.to_json()),
+        ])
+    }
+}
@@ -0,0 +1,115 @@
+//! Drawing frames as text, vector graphics & animations
+use std::fmt::Display;
+
+use unicode_width::UnicodeWidthStr;
+
+mod gif;
+mod svg;
+
+/// Rows of cells, each holding a single (possibly colored) glyph
+pub type Frame = Vec<Vec<String>>;
+
+/// Escape sequences to color the following text, from the 8-bit palette
+pub(crate) const MAGENTA: &str = "\x1b[38;5;5m";
+pub(crate) const YELLOW: &str = "\x1b[38;5;3m";
+/// Escape sequence to reset colors & styles to the terminal's defaults
+pub(crate) const RESET: &str = "\x1b[m";
+
+/// A true color, formatted as `#rrggbb`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Escape sequence to color the following text on the terminal
+    pub(crate) fn ansi(&self) -> String {
+        format!("\x1b[38;2;{};{};{}m", self.r, self.g, self.b)
+    }
+}
+
+/// `#rrggbb`, like it is formatted
+impl std::str::FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let channel = |i: usize| {
+            s.strip_prefix('#')
+                .filter(|hex| hex.len() == 6)
+                .and_then(|hex| hex.get(i..i + 2))
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        match (channel(0), channel(2), channel(4)) {
+            (Some(r), Some(g), Some(b)) => Ok(Self::new(r, g, b)),
+            _ => Err(format!("Expected a color like #ff8800, got '{s}'")),
+        }
+    }
+}
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Color of the robot called `name`, the same in every run & output format. Robots `A` to `D`
+/// are blue, red, green & yellow, the other letters get bright hues spread around the color
+/// wheel by the golden angle, so no two robots share a color. Any other name, e.g. of a fleet,
+/// gets a bright hue picked by hashing it
+pub fn color_for(name: &str) -> Rgb {
+    match name {
+        "A" => return Rgb::new(0, 0, 255),
+        "B" => return Rgb::new(255, 0, 0),
+        "C" => return Rgb::new(0, 255, 0),
+        "D" => return Rgb::new(255, 255, 0),
+        _ => {}
+    }
+    let hue = match name.as_bytes() {
+        // Golden angle in tenths of a degree, repeating only after 144 letters. Starting with
+        // orange, between the red & yellow of B & D
+        [letter @ b'E'..=b'Z'] => 300 + (*letter - b'E') as u64 * 2225,
+        // FNV-1a, which unlike std's hashers is guaranteed to be stable
+        _ => name.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        }),
+    };
+    let h = (hue % 3600) as f32 / 600.;
+    let x = 1. - (h % 2. - 1.).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1., x, 0.),
+        1 => (x, 1., 0.),
+        2 => (0., 1., x),
+        3 => (0., x, 1.),
+        4 => (x, 0., 1.),
+        _ => (1., 0., x),
+    };
+    let channel = |c: f32| (55. + 200. * c) as u8;
+    Rgb::new(channel(r), channel(g), channel(b))
+}
+
+/// How many terminal columns `cell` occupies, ignoring any escape sequences for colors etc
+pub fn width(cell: &str) -> usize {
+    strip(cell).width()
+}
+
+/// Remove all escape sequences for colors etc from `s`
+pub(crate) fn strip(s: &str) -> String {
+    let mut visible = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip `ESC [ parameters final`, where the final byte is in `@..~`
+            chars.next();
+            chars.find(|c| ('@'..='~').contains(c));
+        } else {
+            visible.push(c);
+        }
+    }
+    visible
+}
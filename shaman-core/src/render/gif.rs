@@ -0,0 +1,230 @@
+//! Rendering the simulation timestep by timestep into an animated GIF
+//!
+//! Each timestep becomes a frame of plain colored cells: robots are disks in their color on
+//! top of their goals, which are tinted in a lighter shade of it. Frames are encoded with a
+//! small LZW encoder, so no image library is needed.
+use itertools::Itertools;
+use miette::{Result, miette};
+use rustc_hash::FxHashMap;
+
+use crate::{Shaman, Time, layout::Vertex, render::Rgb};
+
+/// Edge length of a single cell in pixels
+const CELL: usize = 16;
+/// Radius of a robot's disk in pixels
+const RADIUS: f32 = 6.;
+const BACKGROUND: u8 = 0;
+const OBSTACLE: u8 = 1;
+/// Largest code the LZW encoder may use, as the GIF format allows 12 bits at most
+const MAX_CODE: u16 = 4095;
+
+impl Shaman {
+    /// Animated GIF showing each timestep of the robots' routes, playing `fps` frames per
+    /// second & looping forever. Fails for more than 127 robots, whose colors & those of their
+    /// goals don't fit into a GIF's palette, or maps wider or higher than 4095 cells
+    pub fn gif(&self, fps: f32) -> Result<Vec<u8>> {
+        let robots = self
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .collect_vec();
+        let mut palette = vec![Rgb::new(255, 255, 255), Rgb::new(68, 68, 68)];
+        let tint = |c: u8| (255. - 0.35 * (255. - c as f32)) as u8;
+        for robot in &robots {
+            let Rgb { r, g, b } = robot.color();
+            palette.extend([robot.color(), Rgb::new(tint(r), tint(g), tint(b))]);
+        }
+        // Each robot's color is followed by the one of its goal
+        let colors = (0..robots.len())
+            .map(|i| u8::try_from(2 + 2 * i + 1).map(|goal| goal - 1))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| miette!("GIFs fit at most 127 robots, got {}", robots.len()))?;
+        let goals = robots
+            .iter()
+            .zip(&colors)
+            .filter_map(|(r, color)| Some((r.goal()?.0, color + 1)))
+            .collect::<FxHashMap<_, _>>();
+
+        let (width, height) = (self.layout.width() * CELL, self.layout.height() * CELL);
+        let dimension = |length: usize| {
+            u16::try_from(length).map_err(|_| {
+                let cells = u16::MAX as usize / CELL;
+                miette!("GIFs fit maps of at most {cells} x {cells} cells, got {length} pixels")
+            })
+        };
+        let size = (dimension(width)?, dimension(height)?);
+        let mut background = vec![BACKGROUND; width * height];
+        for (y, x) in (0..self.layout.height()).cartesian_product(0..self.layout.width()) {
+            let v = Vertex::new(x as i32, y as i32);
+            let fill = match goals.get(&v) {
+                _ if self.layout.is_blocked(v) => OBSTACLE,
+                Some(goal) => *goal,
+                None => continue,
+            };
+            for (dy, dx) in (0..CELL).cartesian_product(0..CELL) {
+                background[(y * CELL + dy) * width + x * CELL + dx] = fill;
+            }
+        }
+
+        let delay = (100. / fps).round().max(2.) as u16;
+        let mut gif = Encoder::new(size.0, size.1, &palette);
+        for time in 0..=self.simulation_duration() {
+            let mut pixels = background.clone();
+            // Cells closed for a while look like obstacles meanwhile
+            for (v, _) in self.layout.closures().filter(|(_, w)| w.contains(&time)) {
+                let (x, y) = (v.x as usize * CELL, v.y as usize * CELL);
+                for (dy, dx) in (0..CELL).cartesian_product(0..CELL) {
+                    pixels[(y + dy) * width + x + dx] = OBSTACLE;
+                }
+            }
+            for (robot, color) in robots.iter().zip(&colors) {
+                if let Some(v) = position(robot, time) {
+                    disk(&mut pixels, width, v, *color);
+                }
+            }
+            gif.frame(&pixels, delay);
+        }
+        Ok(gif.finish())
+    }
+}
+
+/// Where `robot` is at `time`, `None` if it left the map already
+fn position(robot: &crate::robot::Robot, time: Time) -> Option<Vertex> {
+    let route = robot.route();
+    if robot.delivers() && time > route.duration() {
+        return None;
+    }
+    Some(route.position_at(time).unwrap_or(robot.position().0))
+}
+
+/// Draw a disk of `color` centered on the cell `v` into the `pixels` of an image `width` wide
+fn disk(pixels: &mut [u8], width: usize, v: Vertex, color: u8) {
+    let (cx, cy) = (v.x as usize * CELL, v.y as usize * CELL);
+    let center = CELL as f32 / 2. - 0.5;
+    for (dy, dx) in (0..CELL).cartesian_product(0..CELL) {
+        let (x, y) = (dx as f32 - center, dy as f32 - center);
+        if x * x + y * y <= RADIUS * RADIUS {
+            pixels[(cy + dy) * width + cx + dx] = color;
+        }
+    }
+}
+
+/// Writes GIF89a images, all sharing one global palette of up to 256 colors
+struct Encoder {
+    bytes: Vec<u8>,
+    width: u16,
+    height: u16,
+}
+
+impl Encoder {
+    fn new(width: u16, height: u16, palette: &[Rgb]) -> Self {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        // Global palette of 256 colors, background color 0, square pixels
+        bytes.extend([0xF7, 0, 0]);
+        for i in 0..256 {
+            let Rgb { r, g, b } = palette.get(i).copied().unwrap_or(Rgb::new(0, 0, 0));
+            bytes.extend([r, g, b]);
+        }
+        // Loop forever
+        bytes.extend(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+        Self {
+            bytes,
+            width,
+            height,
+        }
+    }
+
+    /// Append an image of palette indices, shown for `delay` hundredths of a second
+    fn frame(&mut self, pixels: &[u8], delay: u16) {
+        self.bytes.extend([0x21, 0xF9, 0x04, 0x00]);
+        self.bytes.extend(delay.to_le_bytes());
+        self.bytes.extend([0x00, 0x00]);
+
+        self.bytes.extend([0x2C, 0, 0, 0, 0]);
+        self.bytes.extend(self.width.to_le_bytes());
+        self.bytes.extend(self.height.to_le_bytes());
+        self.bytes.push(0x00);
+
+        self.bytes.push(8); // Minimum code size for 256 colors
+        for block in lzw(pixels).chunks(255) {
+            self.bytes.push(block.len() as u8);
+            self.bytes.extend(block);
+        }
+        self.bytes.push(0x00);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.bytes.push(0x3B);
+        self.bytes
+    }
+}
+
+/// Variable length LZW codes of `pixels`, starting at 9 bits & packed from the lowest bit
+fn lzw(pixels: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    let mut bits = Bits::default();
+    let mut codes = FxHashMap::<(u16, u8), u16>::default();
+    let (mut size, mut next) = (9, END + 1);
+    bits.write(CLEAR, size);
+
+    let mut prefix = None;
+    for &pixel in pixels {
+        let Some(current) = prefix else {
+            prefix = Some(pixel as u16);
+            continue;
+        };
+        if let Some(code) = codes.get(&(current, pixel)) {
+            prefix = Some(*code);
+            continue;
+        }
+        bits.write(current, size);
+        if next > MAX_CODE {
+            // Table is full, start over
+            bits.write(CLEAR, size);
+            codes.clear();
+            (size, next) = (9, END + 1);
+        } else {
+            codes.insert((current, pixel), next);
+            if next == 1 << size {
+                size += 1;
+            }
+            next += 1;
+        }
+        prefix = Some(pixel as u16);
+    }
+    if let Some(current) = prefix {
+        bits.write(current, size);
+    }
+    bits.write(END, size);
+    bits.finish()
+}
+
+/// Packs codes of varying bit lengths into bytes, lowest bits first
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    buffer: u32,
+    used: u32,
+}
+
+impl Bits {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= (code as u32) << self.used;
+        self.used += size;
+        while self.used >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.used -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
@@ -0,0 +1,118 @@
+//! Drawing scenarios & their routes as scalable vector graphics, e.g. for papers & wikis
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::{Shaman, Time, layout::Vertex};
+
+/// Edge length of a single cell in pixels
+const CELL: f32 = 32.;
+
+/// Pixel coordinates of the center of `v`
+fn center(v: Vertex) -> (f32, f32) {
+    ((v.x as f32 + 0.5) * CELL, (v.y as f32 + 0.5) * CELL)
+}
+
+impl Shaman {
+    /// SVG document of the layout with each robot's start, goal & route. Routes are polylines
+    /// in the robot's color, labeled with the timesteps the robot is on each of their cells
+    pub fn svg(&self) -> String {
+        let (width, height) = (self.layout.width(), self.layout.height());
+        let mut svg = String::new();
+        let (w, h) = (width as f32 * CELL, height as f32 * CELL);
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" text-anchor="middle">"#
+        )
+        .unwrap();
+        writeln!(svg, r#"  <rect width="{w}" height="{h}" fill="white"/>"#).unwrap();
+        for (y, x) in (0..height as i32).cartesian_product(0..width as i32) {
+            let v = Vertex::new(x, y);
+            if self.layout.is_blocked(v) {
+                let (x, y) = (x as f32 * CELL, y as f32 * CELL);
+                writeln!(
+                    svg,
+                    r##"  <rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="#444"/>"##
+                )
+                .unwrap();
+            }
+        }
+
+        // Cells closed for a while are shaded lighter, labeled with when they are closed
+        for (v, window) in self
+            .layout
+            .closures()
+            .sorted_by_key(|(v, w)| (v.y, v.x, *w.start()))
+        {
+            let (x, y) = (v.x as f32 * CELL, v.y as f32 * CELL);
+            let label = match *window.end() {
+                Time::MAX => format!("{}..", window.start()),
+                end => format!("{}..{end}", window.start()),
+            };
+            writeln!(
+                svg,
+                r##"  <rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="#aaa"><title>closed {label}</title></rect>"##
+            )
+            .unwrap();
+        }
+
+        for robot in self.robots.values().sorted_by_key(|r| r.name()) {
+            let color = robot.color();
+            let name = robot.name();
+            writeln!(svg, r#"  <g id="{name}" fill="{color}" stroke="{color}">"#).unwrap();
+            let route = robot.route().iter().collect_vec();
+            let points = route.iter().map(|l| center(l.position));
+            let points = points.map(|(x, y)| format!("{x},{y}")).join(" ");
+            writeln!(
+                svg,
+                r#"    <polyline points="{points}" fill="none" stroke-width="3" stroke-opacity="0.6"/>"#
+            )
+            .unwrap();
+            // Waiting robots occupy a cell for several timesteps, which share a label
+            for stay in route.chunk_by(|a, b| a.position == b.position) {
+                let (first, last) = (stay[0].time, stay[stay.len() - 1].time);
+                let (x, y) = center(stay[0].position);
+                let label = match first == last {
+                    true => first.to_string(),
+                    false => format!("{first}-{last}"),
+                };
+                writeln!(
+                    svg,
+                    r#"    <text x="{x}" y="{}" font-size="8" stroke="none">{label}</text>"#,
+                    y + CELL / 2. - 3.
+                )
+                .unwrap();
+            }
+
+            let (x, y) = center(robot.position().0);
+            writeln!(svg, r#"    <circle cx="{x}" cy="{y}" r="{}"/>"#, CELL / 3.).unwrap();
+            writeln!(
+                svg,
+                r#"    <text x="{x}" y="{}" font-size="14" fill="white" stroke="none">{name}</text>"#,
+                y + 5.
+            )
+            .unwrap();
+            if let Some((goal, _)) = robot.goal() {
+                let (x, y) = center(goal);
+                let size = CELL * 2. / 3.;
+                writeln!(
+                    svg,
+                    r#"    <rect x="{}" y="{}" width="{size}" height="{size}" fill="none" stroke-width="2"/>"#,
+                    x - size / 2.,
+                    y - size / 2.
+                )
+                .unwrap();
+                writeln!(
+                    svg,
+                    r#"    <text x="{x}" y="{}" font-size="14" stroke="none">{}</text>"#,
+                    y + 5.,
+                    name.to_ascii_lowercase()
+                )
+                .unwrap();
+            }
+            writeln!(svg, "  </g>").unwrap();
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
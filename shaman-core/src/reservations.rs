@@ -0,0 +1,48 @@
+//! The global reservation table, i.e. which cells the planned routes occupy at each timestep
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    Shaman, Time,
+    layout::Vertex,
+    render::{Frame, MAGENTA, RESET, YELLOW},
+};
+
+impl Shaman {
+    /// Robots reserving each cell at `time` by their routes, sorted by name. Cells reserved by
+    /// more than one robot are collisions
+    pub fn reservations_at(&self, time: Time) -> FxHashMap<Vertex, Vec<char>> {
+        let mut reservations = FxHashMap::<Vertex, Vec<char>>::default();
+        for robot in self.robots.values().sorted_by_key(|r| r.name()) {
+            let route = robot.route();
+            if robot.delivers() && time > route.duration() {
+                continue; // Left the map already
+            }
+            let position = route.position_at(time).unwrap_or(robot.position().0);
+            reservations.entry(position).or_default().push(robot.name());
+        }
+        reservations
+    }
+
+    /// The map at `time`, each reserved cell colored by its owner. Cells reserved by more than
+    /// one robot are crossed out, the ones kept free by [Self::reserve] shaded
+    pub fn reservations_frame(&self, time: Time) -> Frame {
+        let reservations = self.reservations_at(time);
+        let mut grid = vec![];
+        for y in 0..self.layout.height() {
+            let mut row = vec![];
+            for x in 0..self.layout.width() {
+                let v = Vertex::new(x as i32, y as i32);
+                row.push(match reservations.get(&v).map(Vec::as_slice) {
+                    Some([owner]) => self.robots[owner].to_string(),
+                    Some(_) => format!("{MAGENTA}✕{RESET}"),
+                    None if self.reservations.blocks(time, v) => format!("{YELLOW}░{RESET}"),
+                    None if self.layout.is_blocked_at(v, time) => "█".to_string(),
+                    None => " ".to_string(),
+                });
+            }
+            grid.push(row);
+        }
+        self.boxed(grid)
+    }
+}
@@ -0,0 +1,69 @@
+//! Randomness of generating & perturbing scenarios, pluggable via [RandomSource]
+
+/// Source of random numbers for everything random in this crate. Implement it for your own
+/// generator to share its stream with a simulation, those of the `rand` crate already do with
+/// the `rand` feature. Only [Self::next_u64] is required
+pub trait RandomSource {
+    /// Uniform random number over all of `u64`
+    fn next_u64(&mut self) -> u64;
+
+    /// Uniform random number in `0.0..1.0`
+    fn uniform(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform random number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        // Redraw the highest numbers, which would otherwise make the lowest results more likely
+        let n = n as u64;
+        let last = u64::MAX - (u64::MAX - n + 1) % n;
+        loop {
+            let x = self.next_u64();
+            if x <= last {
+                return (x % n) as usize;
+            }
+        }
+    }
+
+    /// Fisher-Yates shuffle of `items` in place
+    fn shuffle<T>(&mut self, items: &mut [T])
+    where
+        Self: Sized,
+    {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Any generator of the `rand` crate, e.g. `StdRng` or `ThreadRng`
+#[cfg(feature = "rand")]
+impl<R: rand_core::Rng + ?Sized> RandomSource for R {
+    fn next_u64(&mut self) -> u64 {
+        rand_core::Rng::next_u64(self)
+    }
+}
+
+/// Small, seedable pseudo random number generator (SplitMix64), reproducible from a single seed.
+/// Used by all functions taking a seed instead of a [RandomSource]
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl RandomSource for Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
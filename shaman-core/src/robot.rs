@@ -0,0 +1,526 @@
+use itertools::Itertools;
+use miette::SourceSpan;
+use rustc_hash::FxHashSet;
+use std::{fmt::Display, ops::RangeInclusive};
+
+use crate::{
+    Time,
+    astar::{Action, Kinematics, MovementModel, PlannerConfig, RightOfWay},
+    error::ShamanError,
+    layout::{Layout, Vertex},
+    render::{RESET, Rgb, color_for},
+    route::{Collision, Route},
+};
+
+/// Names a robot can have on a map
+pub(crate) const NAMES: RangeInclusive<char> = 'A'..='Z';
+
+/// Position of a robot at a specific point in time
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Location {
+    pub position: Vertex,
+    pub time: Time,
+}
+
+#[derive(Debug, Clone)]
+pub struct Robot {
+    name: char,
+    color: Rgb,
+    position: (Vertex, SourceSpan),
+    route: Route,
+    goal: Option<(Vertex, SourceSpan)>,
+    weight: f32,
+    /// Timesteps to move to an adjacent cell, see [Self::pace]
+    pace: Time,
+    /// Direction it faces, see [Self::heading]
+    heading: Action,
+    class: Option<u32>,
+    fleet: Option<String>,
+    frozen: bool,
+    excluded: FxHashSet<Vertex>,
+    delivers: bool,
+    /// First & last timestep the goal is open, see [Self::window]
+    window: Option<(Time, Time)>,
+    /// Waypoints & laps of the loop driven instead of a goal, see [Self::patrol]
+    patrol: Option<(Vec<Vertex>, usize)>,
+    /// Cells still to visit before the goal, see [Self::waypoints]
+    waypoints: Vec<Vertex>,
+}
+
+impl Robot {
+    pub fn new(name: char, x: i32, y: i32, span: SourceSpan) -> Self {
+        Self {
+            name,
+            color: color_for(&name.to_string()),
+            position: (Vertex::new(x, y), span),
+            route: Default::default(),
+            goal: None,
+            weight: 1.,
+            pace: 1,
+            heading: Action::Wait,
+            class: None,
+            fleet: None,
+            frozen: false,
+            excluded: Default::default(),
+            delivers: false,
+            window: None,
+            patrol: None,
+            waypoints: vec![],
+        }
+    }
+
+    pub fn name(&self) -> char {
+        self.name
+    }
+
+    /// How this robot is drawn in every output format, see [color_for]
+    pub fn color(&self) -> Rgb {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Rgb) {
+        self.color = color;
+    }
+
+    pub fn position(&self) -> (Vertex, SourceSpan) {
+        self.position
+    }
+
+    pub fn goal(&self) -> Option<(Vertex, SourceSpan)> {
+        self.goal
+    }
+
+    pub fn set_goal(
+        &mut self,
+        layout: &Layout,
+        v: Vertex,
+        span: SourceSpan,
+    ) -> Result<(), ShamanError> {
+        if let Some((_, s)) = self.goal {
+            return Err(ShamanError::DuplicateGoals {
+                src: layout.code(),
+                a: span,
+                b: s,
+            });
+        }
+        self.goal = Some((v, span));
+        Ok(())
+    }
+
+    /// Whether this robot leaves the map once it reached its goal, e.g. by dropping into a
+    /// chute shared with other robots, instead of parking there
+    pub fn delivers(&self) -> bool {
+        self.delivers
+    }
+
+    pub(crate) fn set_delivers(&mut self, delivers: bool) {
+        self.delivers = delivers;
+    }
+
+    /// Whether this robot already left the map at its goal, see [Self::delivers]
+    pub fn is_delivered(&self) -> bool {
+        self.delivers && self.route.iter().next().is_none() && self.goal_reached()
+    }
+
+    fn goal_reached(&self) -> bool {
+        self.goal.is_some_and(|(g, _)| g == self.position.0)
+    }
+
+    /// Cells where the routes of this & the `other` robot collide, see [Self::collisions]
+    pub fn intersection(&self, other: &Robot) -> Vec<Vertex> {
+        self.conflicts_with(other)
+            .iter()
+            .flat_map(Collision::cells)
+            .collect()
+    }
+
+    /// Where & when the routes of this & the `other` robot collide, see [Self::conflicts_with]
+    pub fn collisions(&self, other: &Robot) -> Vec<Location> {
+        self.conflicts_with(other)
+            .iter()
+            .flat_map(Collision::locations)
+            .collect()
+    }
+
+    /// How the routes of this & the `other` robot collide. Two delivering robots may end on
+    /// the same cell, since each leaves right after arriving
+    pub fn conflicts_with(&self, other: &Robot) -> Vec<Collision> {
+        self.route
+            .conflicts_parking(&other.route, (!self.delivers, !other.delivers))
+    }
+
+    pub fn conflicts(&self, other: &Robot) -> bool {
+        !self.conflicts_with(other).is_empty()
+    }
+
+    /// Constraint for other robots, when this robot follows `route`
+    pub(crate) fn right_of_way(&self, route: &Route) -> RightOfWay {
+        if self.delivers {
+            RightOfWay::passing(route)
+        } else {
+            route.into()
+        }
+    }
+
+    /// How costly each timestep of this robot's route is compared to other robots
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    pub fn set_weight(&mut self, weight: f32) {
+        self.weight = weight;
+    }
+
+    /// How many timesteps this robot takes to move to an adjacent cell, e.g. `3` for a slow
+    /// tugger. It stays on the cell it leaves until it arrives on the next
+    pub fn pace(&self) -> Time {
+        self.pace
+    }
+
+    pub fn set_pace(&mut self, pace: Time) {
+        self.pace = pace.max(1);
+    }
+
+    /// Direction this robot faces, i.e. the one it moved or rotated into last. [Action::Wait]
+    /// if it didn't move yet
+    pub fn heading(&self) -> Action {
+        self.heading
+    }
+
+    /// Arrow of the direction this robot faces or is about to move into, in its color
+    pub(crate) fn arrow(&self) -> String {
+        let upcoming = || {
+            let actions = self.route.to_actions().unwrap_or_default();
+            actions.into_iter().find_map(|a| a.arrow())
+        };
+        match self.heading.arrow().or_else(upcoming) {
+            Some(arrow) => format!("{}{arrow}{RESET}", self.color.ansi()),
+            None => self.to_string(),
+        }
+    }
+
+    /// Timesteps this robot waits along its route, not counting the ones it takes to move at
+    /// its [Self::pace] or to rotate in place
+    pub fn waits(&self) -> usize {
+        let actions = self.route.to_actions().unwrap_or_default();
+        let moves = actions.iter().filter(|a| a.is_move()).count();
+        let waits = actions.iter().filter(|a| **a == Action::Wait).count();
+        waits.saturating_sub(moves * (self.pace - 1))
+    }
+
+    /// Priority class of this robot. Robots of lower classes are planned first and never yield
+    /// to robots of higher classes or without a class
+    pub fn class(&self) -> Option<u32> {
+        self.class
+    }
+
+    pub fn set_class(&mut self, class: u32) {
+        self.class = Some(class);
+    }
+
+    /// Fleet this robot belongs to, e.g. all robots of one vendor. Robots of a fleet share the
+    /// same color, see [color_for]
+    pub fn fleet(&self) -> Option<&str> {
+        self.fleet.as_deref()
+    }
+
+    pub fn set_fleet(&mut self, fleet: &str) {
+        self.color = color_for(fleet);
+        self.fleet = Some(fleet.to_string());
+    }
+
+    /// Duration of this robot's route, multiplied by its [Self::weight]
+    pub fn weighted_cost(&self) -> f32 {
+        self.weight * self.route.duration() as f32
+    }
+
+    /// Cells this robot is never allowed to enter, in addition to the layout's obstacles
+    pub fn excluded(&self) -> &FxHashSet<Vertex> {
+        &self.excluded
+    }
+
+    pub fn exclude(&mut self, cells: impl IntoIterator<Item = Vertex>) {
+        self.excluded.extend(cells);
+    }
+
+    /// First & last timestep (inclusive) the goal is open. The robot may only enter it within,
+    /// waiting nearby if it's early. Robots starting on their goal may stay there anyway
+    pub fn window(&self) -> Option<(Time, Time)> {
+        self.window
+    }
+
+    pub fn set_window(&mut self, open: Time, close: Time) {
+        self.window = Some((open, close));
+    }
+
+    /// Waypoints this robot without a goal drives to in a loop & how many laps, returning to
+    /// its start after each. Others have to evade it, like third party traffic, until it parks
+    /// on its start after the last lap
+    pub fn patrol(&self) -> Option<(&[Vertex], usize)> {
+        self.patrol.as_ref().map(|(w, laps)| (w.as_slice(), *laps))
+    }
+
+    pub fn set_patrol(&mut self, waypoints: Vec<Vertex>, laps: usize) {
+        self.patrol = Some((waypoints, laps));
+    }
+
+    /// Cells this robot visits in this order before driving to its goal, e.g. to pick up
+    /// goods on the way. Visited ones are dropped while simulating, so replanning mid-run only
+    /// visits those left
+    pub fn waypoints(&self) -> &[Vertex] {
+        &self.waypoints
+    }
+
+    pub fn set_waypoints(&mut self, waypoints: Vec<Vertex>) {
+        self.waypoints = waypoints;
+    }
+
+    /// Whether this robot replans to make way for others, unlike frozen robots or robots
+    /// without a goal, which stay or patrol regardless
+    pub fn evades(&self) -> bool {
+        !self.frozen && self.goal.is_some()
+    }
+
+    /// Whether this robot follows an externally dictated route, which is never replanned
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Make this robot follow `route` verbatim, so that it only acts as constraint for others
+    pub fn freeze(&mut self, route: Route) {
+        if let Some(start) = route.iter().next() {
+            self.position.0 = start.position;
+        }
+        self.route = route;
+        self.frozen = true;
+    }
+
+    pub fn route(&self) -> &Route {
+        &self.route
+    }
+
+    pub fn pathicon(&self) -> String {
+        format!("{}·{RESET}", self.color.ansi())
+    }
+
+    pub(crate) fn set_heading(&mut self, heading: Action) {
+        self.heading = heading;
+    }
+
+    /// Continue from a previously saved `position` and remaining `route`
+    pub(crate) fn restore(&mut self, position: Vertex, route: Route, frozen: bool) {
+        self.position.0 = position;
+        self.route = route;
+        self.frozen = frozen;
+    }
+
+    /// Take the next step of the route, turning by a single rotation of `movement` when
+    /// rotating in place
+    pub(crate) fn simulate(&mut self, movement: MovementModel) {
+        let Some(next) = self.route.pop() else {
+            return;
+        };
+
+        if let Some(rotation) = self.route.rotation_at(next.time)
+            && let Some(heading) = self.heading.rotated(rotation, movement)
+        {
+            self.heading = heading;
+        } else if let Ok(action) = Action::try_from((self.position.0, next.position))
+            && action != Action::Wait
+        {
+            self.heading = action;
+        }
+        self.position.0 = next.position;
+        if self.waypoints.first() == Some(&next.position) {
+            self.waypoints.remove(0);
+        }
+    }
+
+    pub(crate) fn plan(
+        &mut self,
+        layout: &Layout,
+        constraint: &RightOfWay,
+        config: PlannerConfig,
+    ) -> Result<(), ShamanError> {
+        self.plan_from(layout, constraint, config, 0)
+    }
+
+    /// Plan the route from the current position, leaving at time `departure`
+    pub(crate) fn plan_from(
+        &mut self,
+        layout: &Layout,
+        constraint: &RightOfWay,
+        config: PlannerConfig,
+        departure: Time,
+    ) -> Result<(), ShamanError> {
+        if self.frozen {
+            return Ok(());
+        }
+        // Robots without a goal park where they are for good (or after patrolling), so others
+        // drive around them
+        let Some(goal) = self.goal else {
+            let parked = Location {
+                position: self.position.0,
+                time: departure,
+            };
+            self.route = std::iter::once(parked).collect();
+            if self.patrol.is_some() {
+                self.route = self.patrol_route(layout, config, departure)?;
+            }
+            return Ok(());
+        };
+        let mut closed = None;
+        if let Some((open, _)) = self.window.filter(|(open, _)| *open > departure) {
+            let mut constraint = constraint.clone();
+            constraint.reserve(&[goal.0], departure..open);
+            closed = Some(constraint);
+        }
+        let stops = self
+            .waypoints
+            .iter()
+            .map(|w| (*w, goal.1))
+            .chain([goal])
+            .collect_vec();
+        let constraint = closed.as_ref().unwrap_or(constraint);
+        let route = self.plan_via(
+            layout,
+            &stops,
+            departure,
+            constraint,
+            !self.delivers,
+            config,
+        )?;
+        let arrival = route.iter().last().map_or(departure, |l| l.time);
+        if let Some((open, close)) = self.window.filter(|(_, close)| arrival > *close) {
+            return Err(ShamanError::GoalClosed {
+                src: layout.code(),
+                open,
+                close,
+                arrival,
+                goal: goal.1,
+            });
+        }
+        self.route = route;
+        Ok(())
+    }
+
+    /// Shortest route along the [Self::patrol] loop, ignoring everybody else
+    fn patrol_route(
+        &self,
+        layout: &Layout,
+        config: PlannerConfig,
+        departure: Time,
+    ) -> Result<Route, ShamanError> {
+        let Some((waypoints, laps)) = &self.patrol else {
+            return Ok(Route::default());
+        };
+        let (start, span) = self.position;
+        let stops = std::iter::repeat_n(waypoints.iter().chain([&start]), *laps)
+            .flatten()
+            .map(|v| (*v, span))
+            .collect_vec();
+        self.plan_via(
+            layout,
+            &stops,
+            departure,
+            &RightOfWay::default(),
+            true,
+            config,
+        )
+    }
+
+    /// Plan a route from the current position to all `stops` in order, each leg leaving once
+    /// the previous one arrived. Only the last stop may be one to `park` on
+    fn plan_via(
+        &self,
+        layout: &Layout,
+        stops: &[(Vertex, SourceSpan)],
+        departure: Time,
+        constraint: &RightOfWay,
+        park: bool,
+        config: PlannerConfig,
+    ) -> Result<Route, ShamanError> {
+        let mut locations = vec![Location {
+            position: self.position.0,
+            time: departure,
+        }];
+        let mut heading = self.heading;
+        let mut rotations = vec![];
+        for (i, stop) in stops.iter().enumerate() {
+            let last = *locations.last().unwrap();
+            let leg = crate::astar::plan(
+                layout,
+                (last.position, self.position.1),
+                *stop,
+                last.time,
+                constraint,
+                &self.excluded,
+                park && i + 1 == stops.len(),
+                Kinematics {
+                    pace: self.pace,
+                    heading,
+                },
+                config,
+            )?;
+            for l in leg.iter().filter(|l| l.time > last.time) {
+                let previous = locations.last().unwrap().position;
+                if let Ok(action) = Action::try_from((previous, l.position))
+                    && action != Action::Wait
+                {
+                    heading = action;
+                }
+                locations.push(l);
+            }
+            rotations.extend(leg.rotations());
+        }
+        let mut route = locations.into_iter().collect::<Route>();
+        for (time, rotation) in rotations {
+            route.rotate(time, rotation);
+        }
+        Ok(route)
+    }
+
+    /// Wait on the current position at `now` for one more timestep, delaying the rest of the
+    /// route. Returns whether there was anything left to delay
+    pub(crate) fn hold(&mut self, now: Time) -> bool {
+        if self.route.iter().all(|l| l.time <= now) {
+            return false;
+        }
+        let wait = Location {
+            position: self.position.0,
+            time: now + 1,
+        };
+        self.route = self
+            .route
+            .iter()
+            .filter(|l| l.time <= now)
+            .chain(std::iter::once(wait))
+            .chain(
+                self.route
+                    .iter()
+                    .filter(|l| l.time > now)
+                    .map(|l| Location {
+                        time: l.time + 1,
+                        ..l
+                    }),
+            )
+            .collect();
+        true
+    }
+
+    /// Drop the first location of the route, e.g. when it was already simulated
+    pub(crate) fn skip_departure(&mut self) {
+        self.route.pop();
+    }
+
+    /// Drive to `goal` next, replacing the current one
+    pub(crate) fn assign(&mut self, goal: Vertex) {
+        self.goal = Some((goal, self.position.1));
+    }
+}
+
+impl Display for Robot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}●{RESET}", self.color.ansi())
+    }
+}
@@ -0,0 +1,436 @@
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use itertools::Itertools;
+
+use crate::{
+    Time,
+    astar::{Action, CostModel, RightOfWay, Rotation},
+    error::ShamanError,
+    layout::{Layout, Vertex},
+    robot::Location,
+};
+
+/// How many of the longest shortcuts [Route::optimize] tries from each location
+const SHORTCUTS: usize = 32;
+
+/// Timed sequence of [Location]s a robot follows, along with the timesteps it ends rotating in
+/// place instead of waiting. Routes only differing in how long they wait at the goal in the end
+/// or which waits are rotations are considered equal
+#[derive(Debug, Clone, Default)]
+pub struct Route(VecDeque<Location>, BTreeMap<Time, Rotation>);
+
+impl PartialEq for Route {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical().eq(other.canonical())
+    }
+}
+
+impl Eq for Route {}
+
+impl Hash for Route {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for location in self.canonical() {
+            location.hash(state);
+        }
+    }
+}
+
+/// How a replanned route differs from the one it replaces, see [Route::diff]. Executors only
+/// need to receive the [Self::suffix], replacing everything after [Self::keep_until]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDiff {
+    /// Last timestep both routes agree on, `None` if they differ right from the start
+    pub keep_until: Option<Time>,
+    /// Locations of the new route after the kept prefix
+    pub suffix: Vec<Location>,
+}
+
+/// How two routes collide, see [Route::conflicts_with]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Collision {
+    /// Both are on `v` at time `t`
+    Vertex { v: Vertex, t: Time },
+    /// Moving `from` one cell `to` the next between `t` & `t + 1`, the other swaps places or
+    /// crosses the same diagonal
+    Edge { from: Vertex, to: Vertex, t: Time },
+    /// Both end on `v`, so they collide once the later one arrives there at `t_from`
+    Goal { v: Vertex, t_from: Time },
+}
+
+impl Collision {
+    /// Where & when this collision happens, i.e. both ends of an edge
+    pub fn locations(&self) -> Vec<Location> {
+        match *self {
+            Self::Vertex { v, t } => vec![Location {
+                position: v,
+                time: t,
+            }],
+            Self::Edge { from, to, t } => vec![
+                Location {
+                    position: from,
+                    time: t,
+                },
+                Location {
+                    position: to,
+                    time: t + 1,
+                },
+            ],
+            Self::Goal { v, t_from } => vec![Location {
+                position: v,
+                time: t_from,
+            }],
+        }
+    }
+
+    /// Cells this collision happens on
+    pub fn cells(&self) -> Vec<Vertex> {
+        self.locations().iter().map(|l| l.position).collect()
+    }
+
+    /// When this collision happens first, i.e. when a shared goal gets occupied by both
+    pub fn time(&self) -> Time {
+        match *self {
+            Self::Vertex { t, .. } | Self::Edge { t, .. } => t,
+            Self::Goal { t_from, .. } => t_from,
+        }
+    }
+}
+
+impl Display for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Vertex { v, t } => write!(f, "vertex {v}@{t}"),
+            Self::Edge { from, to, t } => write!(f, "edge {from}→{to}@{t}"),
+            Self::Goal { v, t_from } => write!(f, "goal {v}@{t_from}.."),
+        }
+    }
+}
+
+impl Display for RouteDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.keep_until {
+            Some(t) => write!(f, "keep prefix to t={t}, then")?,
+            None => write!(f, "replace all by")?,
+        }
+        let suffix = self
+            .suffix
+            .iter()
+            .map(|l| format!("{}@{}", l.position, l.time));
+        write!(f, " {}", suffix.format(" "))
+    }
+}
+
+impl FromIterator<Location> for Route {
+    fn from_iter<T: IntoIterator<Item = Location>>(iter: T) -> Self {
+        Self(iter.into_iter().collect(), BTreeMap::new())
+    }
+}
+
+impl Route {
+    /// Locations without trailing waits at the goal
+    fn canonical(&self) -> impl Iterator<Item = &Location> {
+        let waiting = self
+            .0
+            .iter()
+            .rev()
+            .tuple_windows()
+            .take_while(|(then, now)| then.position == now.position)
+            .count();
+        self.0.iter().take(self.0.len() - waiting)
+    }
+
+    /// Drop all trailing waits at the goal
+    pub fn canonicalize(&mut self) {
+        let len = self.canonical().count();
+        self.0.truncate(len);
+    }
+
+    /// Stable hash of this route, equal for all routes which are [PartialEq]
+    pub fn hash_key(&self) -> u64 {
+        let mut hasher = FxHasher::default();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn duration(&self) -> Time {
+        self.0.back().map(|l| l.time).unwrap_or_default()
+    }
+
+    /// Where a robot following this route is at `time`, staying at its last location forever
+    pub fn position_at(&self, time: Time) -> Option<Vertex> {
+        if time < self.0.front()?.time {
+            return None;
+        }
+        self.0
+            .iter()
+            .find(|l| l.time == time)
+            .or(self.0.back())
+            .map(|l| l.position)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Location> {
+        self.0.iter().copied()
+    }
+
+    /// The sequence of [Action]s a robot has to take to follow this route
+    pub fn to_actions(&self) -> Result<Vec<Action>, ShamanError> {
+        self.0
+            .iter()
+            .tuple_windows()
+            .map(|(a, b)| match self.1.get(&b.time) {
+                Some(rotation) if a.position == b.position => Ok(Action::Rotate(*rotation)),
+                _ => Action::try_from((a.position, b.position)),
+            })
+            .collect()
+    }
+
+    /// Follow a sequence of [Action]s from `start`, one timestep each
+    pub fn from_actions(start: Location, actions: impl IntoIterator<Item = Action>) -> Self {
+        let mut route = Self::from_iter([start]);
+        let mut current = start;
+        for action in actions {
+            current = Location {
+                position: action.apply(current.position),
+                time: current.time + 1,
+            };
+            route.0.push_back(current);
+            if let Action::Rotate(rotation) = action {
+                route.rotate(current.time, rotation);
+            }
+        }
+        route
+    }
+
+    /// Turn the wait ending at `time` into rotating in place, see [Action::Rotate]
+    pub(crate) fn rotate(&mut self, time: Time, rotation: Rotation) {
+        self.1.insert(time, rotation);
+    }
+
+    /// The rotation in place ending at `time`, if any, see [Action::Rotate]
+    pub(crate) fn rotation_at(&self, time: Time) -> Option<Rotation> {
+        self.1.get(&time).copied()
+    }
+
+    /// All rotations in place by the timestep they end, see [Action::Rotate]
+    pub(crate) fn rotations(&self) -> impl Iterator<Item = (Time, Rotation)> {
+        self.1.iter().map(|(t, r)| (*t, *r))
+    }
+
+    pub fn conflicts(&self, other: &Self) -> bool {
+        !self.conflicts_with(other).is_empty()
+    }
+
+    /// Cells where both routes collide, including both ending on the same cell
+    pub fn intersection(&self, other: &Self) -> Vec<Vertex> {
+        self.collisions(other).iter().map(|l| l.position).collect()
+    }
+
+    /// Where & when both routes collide. Both ending on the same cell collides once the later
+    /// one arrives there
+    pub fn collisions(&self, other: &Self) -> Vec<Location> {
+        self.conflicts_with(other)
+            .iter()
+            .flat_map(Collision::locations)
+            .collect()
+    }
+
+    /// Where & when both routes are on the same cell, swap places or cross the same diagonal,
+    /// ignoring where they end
+    pub fn encounters(&self, other: &Self) -> Vec<Location> {
+        self.conflicts_parking(other, (false, false))
+            .iter()
+            .flat_map(Collision::locations)
+            .collect()
+    }
+
+    /// All [Collision]s of both routes, ordered by kind & then by time. Edges are given as
+    /// moved along by this route. Both stay on their last cell forever, like robots parking on
+    /// their goal, so passing it afterwards collides too
+    pub fn conflicts_with(&self, other: &Self) -> Vec<Collision> {
+        self.conflicts_parking(other, (true, true))
+    }
+
+    /// Like [Self::conflicts_with], but only routes which `park` (this & the other one) stay
+    /// on their last cell. Ending on the same cell collides unless neither parks
+    pub(crate) fn conflicts_parking(&self, other: &Self, parks: (bool, bool)) -> Vec<Collision> {
+        let occupied = other.0.iter().collect::<FxHashSet<_>>();
+        let mut conflicts = self
+            .0
+            .iter()
+            .filter(|l| occupied.contains(l))
+            .chain(self.passing(other, parks.1))
+            .chain(other.passing(self, parks.0))
+            .map(|l| Collision::Vertex {
+                v: l.position,
+                t: l.time,
+            })
+            .sorted_by_key(Collision::time)
+            .collect_vec();
+        conflicts.extend(
+            self.0
+                .iter()
+                .tuple_windows()
+                .filter(|(now, then)| {
+                    other
+                        .0
+                        .iter()
+                        .tuple_windows()
+                        .find(|(a, _)| a.time == now.time)
+                        .is_some_and(|(a, b)| {
+                            let (p, q) = (now.position, then.position);
+                            (b.position == p && a.position == q)
+                                || p.crosses(q, (a.position, b.position))
+                        })
+                })
+                .map(|(now, then)| Collision::Edge {
+                    from: now.position,
+                    to: then.position,
+                    t: now.time,
+                }),
+        );
+        conflicts.extend(
+            self.0
+                .back()
+                .zip(other.0.back())
+                .filter(|(a, b)| a.position == b.position && (parks.0 || parks.1))
+                .map(|(a, b)| Collision::Goal {
+                    v: a.position,
+                    t_from: a.time.max(b.time),
+                }),
+        );
+        conflicts
+    }
+
+    /// Locations of this route on the last cell of `other` after it arrived there, if it
+    /// `parks`. Ending there too is left to [Collision::Goal]
+    fn passing<'a>(&'a self, other: &Self, parks: bool) -> impl Iterator<Item = &'a Location> {
+        let end = other.0.back().filter(|_| parks).copied();
+        let last = self.0.back();
+        self.0.iter().filter(move |l| {
+            end.is_some_and(|e| l.position == e.position && l.time > e.time && Some(*l) != last)
+        })
+    }
+
+    /// What changes when following the `new` route instead of this one: the longest common
+    /// prefix is kept, all of `new` after it replaces the rest
+    pub fn diff(&self, new: &Self) -> RouteDiff {
+        let common = self
+            .0
+            .iter()
+            .zip(&new.0)
+            .take_while(|(a, b)| a == b)
+            .count();
+        RouteDiff {
+            keep_until: common.checked_sub(1).map(|i| new.0[i].time),
+            suffix: new.0.iter().skip(common).copied().collect(),
+        }
+    }
+
+    /// Strip avoidable waits & detours, as long as this route stays clear of `others`, e.g.
+    /// the [RightOfWay] of all other robots once their conflicts are resolved. Going from its
+    /// start to its end, cuts out the longest stretch from each location which returns to the
+    /// same cell, or which could be skipped by a single step to a neighboring cell, and arrives
+    /// that much earlier. Only a few of the longest stretches are tried per location, so
+    /// long waits take linear time. Shortcuts making the route costlier by `costs` are left
+    /// out, like stopping before a turn when turning on the move costs more. Only takes steps
+    /// along the axes, so any movement model can follow the result. Where & when the route
+    /// starts & ends never changes, except for arriving earlier
+    pub fn optimize(&mut self, layout: &Layout, others: &[RightOfWay], costs: &CostModel) {
+        let others = others.iter().cloned().sum::<RightOfWay>();
+        // Last timestep each cell is occupied, so staying on the last one is quick to check
+        let occupied = others
+            .occupancy()
+            .into_iter()
+            .map(|(v, (times, from))| match from {
+                Some(_) => (v, Time::MAX),
+                None => (v, times.into_iter().max().unwrap_or_default()),
+            })
+            .collect::<FxHashMap<_, _>>();
+        let mut cost = self.cost(layout, costs);
+        let mut i = 0;
+        while i < self.0.len() {
+            let from = self.0[i].position;
+            let shortcut = (i + 1..self.0.len())
+                .rev()
+                // Skip to the location after j if back on the same cell, else step to j
+                .filter_map(|j| match from.manhattan_distance(self.0[j].position) {
+                    0 => Some(j + 1),
+                    1 if j > i + 1 => Some(j),
+                    _ => None,
+                })
+                .take(SHORTCUTS)
+                .filter(|rest| self.is_clear(i, *rest, layout, &others, &occupied))
+                .map(|rest| self.shortcut(i, rest))
+                .map(|shortcut| (shortcut.cost(layout, costs), shortcut))
+                .find(|(shorter, _)| *shorter <= cost);
+            match shortcut {
+                // Cutting out one stretch may allow cutting out more from here
+                Some((shorter, shortcut)) => (cost, *self) = (shorter, shortcut),
+                None => i += 1,
+            }
+        }
+    }
+
+    /// This route up to location `i`, continuing right away with the locations from `rest` on
+    fn shortcut(&self, i: usize, rest: usize) -> Self {
+        let skipped = (rest - i - 1) as Time;
+        let mut route = self.0.iter().take(i + 1).copied().collect::<VecDeque<_>>();
+        route.extend(self.0.iter().skip(rest).map(|l| Location {
+            position: l.position,
+            time: l.time - skipped,
+        }));
+        Self(route, BTreeMap::new())
+    }
+
+    /// Cost of following this route from standstill by `costs`, like the planners add it up
+    pub(crate) fn cost(&self, layout: &Layout, costs: &CostModel) -> f32 {
+        let actions = self.to_actions().unwrap_or_default();
+        let previous = std::iter::once(Action::Wait).chain(actions.iter().copied());
+        self.0
+            .iter()
+            .zip(previous)
+            .zip(&actions)
+            .map(|((here, previous), action)| action.cost(previous, here.position, layout, costs))
+            .sum()
+    }
+
+    /// Whether the [Self::shortcut] from location `i` to `rest` stays clear of the layout's
+    /// obstacles, its closed cells & `others`, up to staying on its last cell forever, given
+    /// the last timestep each cell is `occupied` by them
+    fn is_clear(
+        &self,
+        i: usize,
+        rest: usize,
+        layout: &Layout,
+        others: &RightOfWay,
+        occupied: &FxHashMap<Vertex, Time>,
+    ) -> bool {
+        let skipped = (rest - i - 1) as Time;
+        let shortcut =
+            std::iter::once(self.0[i]).chain(self.0.iter().skip(rest).map(|l| Location {
+                position: l.position,
+                time: l.time - skipped,
+            }));
+        let mut last = self.0[i];
+        for (now, then) in shortcut.tuple_windows() {
+            let blocked = layout.is_blocked_at(then.position, then.time)
+                || others.blocks(then.time, then.position)
+                || others.swaps(now.time, now.position, then.position);
+            if blocked {
+                return false;
+            }
+            last = then;
+        }
+        layout.closes_after(last.position, last.time).is_none()
+            && occupied.get(&last.position).is_none_or(|t| *t <= last.time)
+    }
+
+    pub fn pop(&mut self) -> Option<Location> {
+        self.0.pop_front()
+    }
+}
@@ -0,0 +1,97 @@
+//! Reproducible dynamic scenarios, i.e. [Event]s happening at fixed timesteps of a [Simulation]
+//!
+//! Scripts are plain text, one event per line. Empty lines & everything after `#` are ignored:
+//!
+//! ```text
+//! at 10 block 4/5     # a new obstacle appears on cell 4/5
+//! at 20 task C 7/3    # robot C gets a new goal 7/3
+//! ```
+//!
+//! There is deliberately no YAML flavor: a line per event reads & diffs just as well, and keeps
+//! the library free of a YAML parser. `.yaml` & `.yml` files are rejected instead of misread
+use std::{fmt::Display, path::Path};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{Event, Simulation, Time, checkpoint::vertex, simulation::Control};
+
+/// Events to apply to a [Simulation], each at its timestep
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    /// Sorted by time, events of the same timestep in the order they were defined
+    pub events: Vec<(Time, Event)>,
+}
+
+impl Script {
+    /// Parse a whole script, see the [module docs](self) for its format
+    pub fn parse(content: &str) -> Result<Self> {
+        let events = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.split('#').next().unwrap_or_default()))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| event(line).ok_or_else(|| miette!("{}: invalid event", i + 1)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            events: events.into_iter().sorted_by_key(|(t, _)| *t).collect(),
+        })
+    }
+
+    /// Read the script from `file`
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let extension = file.as_ref().extension().and_then(|e| e.to_str());
+        let file = file.as_ref().display().to_string();
+        if matches!(extension, Some("yaml" | "yml")) {
+            return Err(miette!(
+                "{file}: events are plain text like `at 10 block 4/5`, not YAML"
+            ));
+        }
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        Self::parse(&content).map_err(|e| miette!("{file}:{e}"))
+    }
+}
+
+/// Parse a single `at <time> block <x>/<y>` or `at <time> task <robot> <x>/<y>`
+fn event(line: &str) -> Option<(Time, Event)> {
+    let words = line.split_whitespace().collect_vec();
+    let event = match words[..] {
+        ["at", _, "block", cell] => Event::Block(vertex(cell)?),
+        ["at", _, "task", robot, goal] => {
+            Event::Task(robot.chars().exactly_one().ok()?, vertex(goal)?)
+        }
+        _ => return None,
+    };
+    Some((words[1].parse().ok()?, event))
+}
+
+impl Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (time, event) in &self.events {
+            match event {
+                Event::Block(v) => writeln!(f, "at {time} block {v}")?,
+                Event::Task(robot, goal) => writeln!(f, "at {time} task {robot} {goal}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Simulation {
+    /// Apply each event of `script` once the simulation reaches its timestep. Events of
+    /// timesteps already simulated happen right after the next one
+    pub fn script(&mut self, script: Script) -> &mut Self {
+        let mut pending = script.events.into_iter().peekable();
+        self.on_tick(move |s| {
+            let events = pending
+                .peeking_take_while(|(t, _)| *t <= s.time)
+                .map(|(_, e)| e)
+                .collect_vec();
+            if events.is_empty() {
+                Control::Continue
+            } else {
+                Control::Inject(events)
+            }
+        })
+    }
+}
@@ -0,0 +1,111 @@
+//! How robust a scenario's solution is against small changes of its layout
+use std::fmt::Display;
+
+use itertools::Itertools;
+use miette::Result;
+
+use crate::{
+    Algorithm, Shaman, Time,
+    builder::ShamanBuilder,
+    rng::{RandomSource, Rng},
+};
+
+/// Solution costs of randomly perturbed variants of a scenario, see [Shaman::sensitivity]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sensitivity {
+    /// Cost of solving the unchanged scenario
+    pub baseline: Time,
+    /// Cost of each perturbed variant, `None` if it could not be solved
+    pub costs: Vec<Option<Time>>,
+}
+
+impl Shaman {
+    /// Solve `runs` variants of this scenario with `algorithm`, each with `moves` obstacles or
+    /// goals relocated randomly by [ShamanBuilder::perturb]. The same `seed` always yields the
+    /// same variants
+    pub fn sensitivity(
+        &self,
+        algorithm: Algorithm,
+        moves: usize,
+        runs: usize,
+        seed: u64,
+    ) -> Result<Sensitivity> {
+        self.sensitivity_with(algorithm, moves, runs, &mut Rng::new(seed))
+    }
+
+    /// Like [Self::sensitivity], but drawing all relocations from `rng`
+    pub fn sensitivity_with(
+        &self,
+        algorithm: Algorithm,
+        moves: usize,
+        runs: usize,
+        rng: &mut impl RandomSource,
+    ) -> Result<Sensitivity> {
+        let baseline = self.clone().solve_with(algorithm)?.cost();
+        let builder = ShamanBuilder::from(self);
+        let costs = (0..runs)
+            .map(|_| {
+                let variant = builder.clone().perturb_with(moves, rng);
+                variant
+                    .build()
+                    .and_then(|v| v.configured_like(self))
+                    .and_then(|v| v.solve_with(algorithm))
+                    .ok()
+                    .map(|s| s.cost())
+            })
+            .collect();
+        Ok(Sensitivity { baseline, costs })
+    }
+
+    /// This scenario planned & solved the same way as `other`, which the builder doesn't carry
+    pub(crate) fn configured_like(mut self, other: &Shaman) -> Result<Self> {
+        self.planner = other.planner;
+        self.objective = other.objective;
+        self.order = other.order;
+        self.seed = other.seed;
+        self.plan()?;
+        Ok(self)
+    }
+}
+
+impl Sensitivity {
+    /// Cost changes of all solvable variants compared to the baseline
+    pub fn deltas(&self) -> impl Iterator<Item = isize> + '_ {
+        self.costs
+            .iter()
+            .flatten()
+            .map(|c| *c as isize - self.baseline as isize)
+    }
+
+    /// Amount of variants which could not be solved
+    pub fn infeasible(&self) -> usize {
+        self.costs.iter().filter(|c| c.is_none()).count()
+    }
+
+    /// Average absolute cost change of all solvable variants, relative to the baseline
+    pub fn spread(&self) -> f32 {
+        let deltas = self.deltas().collect_vec();
+        if deltas.is_empty() {
+            return 0.;
+        }
+        let mean =
+            deltas.iter().map(|d| d.unsigned_abs()).sum::<usize>() as f32 / deltas.len() as f32;
+        mean / self.baseline.max(1) as f32
+    }
+}
+
+impl Display for Sensitivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "baseline:   {}", self.baseline)?;
+        let costs = self
+            .costs
+            .iter()
+            .map(|c| c.map_or("-".to_string(), |c| c.to_string()))
+            .join(" ");
+        writeln!(f, "variants:   {costs}")?;
+        let (min, max) = self.deltas().minmax().into_option().unwrap_or_default();
+        writeln!(f, "delta:      {min:+} .. {max:+}")?;
+        writeln!(f, "spread:     {:.1}%", self.spread() * 100.)?;
+        writeln!(f, "infeasible: {}/{}", self.infeasible(), self.costs.len())
+    }
+}
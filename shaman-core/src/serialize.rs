@@ -0,0 +1,54 @@
+//! [serde] support, serializing into the same documents as the [ToJson] export
+use serde::{
+    Serialize, Serializer,
+    ser::{SerializeMap, SerializeSeq},
+};
+
+use crate::{
+    Shaman,
+    json::{Json, ToJson},
+    layout::Vertex,
+    robot::{Location, Robot},
+    route::{Route, RouteDiff},
+};
+
+impl Serialize for Json {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            // Keep integers as such, e.g. for formats distinguishing them from floats
+            Self::Number(n) if n.fract() == 0. && n.abs() < i64::MAX as f64 => {
+                serializer.serialize_i64(*n as i64)
+            }
+            Self::Number(n) => serializer.serialize_f64(*n),
+            Self::String(s) => serializer.serialize_str(s),
+            Self::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Object(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (key, value) in fields {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+macro_rules! via_json {
+    ($($t:ty),*) => {
+        $(impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.to_json().serialize(serializer)
+            }
+        })*
+    };
+}
+
+via_json!(Vertex, Location, Route, RouteDiff, Robot, Shaman);
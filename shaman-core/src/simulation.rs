@@ -0,0 +1,699 @@
+//! Stepping through a solved scenario from library code
+use std::{collections::VecDeque, fmt::Display};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    Shaman, Time,
+    astar::Action,
+    layout::Vertex,
+    robot::Location,
+    route::{Route, RouteDiff},
+};
+
+/// Read-only view on the simulation after a timestep
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot<'a> {
+    /// Amount of timesteps simulated so far
+    pub time: Time,
+    pub shaman: &'a Shaman,
+    /// What happened to the robots during the last timestep
+    pub notifications: &'a [Notification],
+}
+
+/// What happened to a robot during a single timestep, see [Simulation::on_notification]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notification {
+    RobotMoved {
+        robot: char,
+        from: Vertex,
+        to: Vertex,
+    },
+    /// The robot stayed where it is, but its route isn't over yet
+    RobotWaited { robot: char, at: Vertex },
+    /// The robot reached its goal at the end of its route
+    RobotArrived { robot: char, goal: Vertex },
+    /// Both robots ended up on the same cell or swapped their cells, which solved scenarios
+    /// only run into after changing them while simulating
+    ConflictObserved { robots: (char, char), at: Vertex },
+    /// A hook injected this event, but it couldn't be applied, so nothing changed
+    EventFailed(Event),
+}
+
+/// What the simulation should do after a tick callback returned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    /// Return from [Simulation::run], which can be called again to resume
+    Pause,
+    /// Stop the simulation for good
+    Abort,
+    /// Change the scenario and continue
+    Inject(Vec<Event>),
+}
+
+/// Change to the scenario happening while it is simulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A new obstacle appears on this cell, robots passing it later replan
+    Block(Vertex),
+    /// A robot gets a new goal to drive to, as long as that goal has capacity left
+    Task(char, Vertex),
+}
+
+/// Why [Simulation::run] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// All robots reached their goals
+    Finished,
+    Paused,
+    Aborted,
+}
+
+/// Default of [Simulation::patience]
+const PATIENCE: Time = 10;
+
+type Hook = Box<dyn FnMut(&Snapshot) -> Control>;
+type Listener = Box<dyn FnMut(Time, &Notification)>;
+
+/// Explicitly stepped simulation, which can be scripted with per-tick callbacks
+pub struct Simulation {
+    shaman: Shaman,
+    hooks: Vec<Hook>,
+    listeners: Vec<Listener>,
+    /// What happened during the last timestep, see [Self::notifications]
+    notifications: Vec<Notification>,
+    aborted: bool,
+    /// Where idle robots drift to, see [Self::reposition]
+    staging: Vec<Vertex>,
+    /// Robots which already drifted to a staging cell since their last task
+    repositioned: FxHashSet<char>,
+    /// Goals of the shared task pool with the time they are released, see [Self::pass_token]
+    tasks: Vec<(Time, Vertex)>,
+    /// Consecutive timesteps in which nobody had a route left & no task got taken
+    idle: Time,
+    /// How many idle timesteps to wait for left tasks to become servable, see [Self::patience]
+    patience: Time,
+    /// Goals each robot drives to one after another, see [Self::queue]
+    queues: FxHashMap<char, VecDeque<Vertex>>,
+    /// Changes of replanned routes, which weren't taken yet, see [Self::take_diffs]
+    diffs: Vec<(char, RouteDiff)>,
+}
+
+impl From<Shaman> for Simulation {
+    fn from(shaman: Shaman) -> Self {
+        Self {
+            shaman,
+            hooks: vec![],
+            listeners: vec![],
+            notifications: vec![],
+            aborted: false,
+            staging: vec![],
+            repositioned: Default::default(),
+            tasks: vec![],
+            idle: 0,
+            patience: PATIENCE,
+            queues: Default::default(),
+            diffs: vec![],
+        }
+    }
+}
+
+impl Simulation {
+    /// Call `hook` after every timestep
+    pub fn on_tick(&mut self, hook: impl FnMut(&Snapshot) -> Control + 'static) -> &mut Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Call `listener` with the time & each [Notification] of every timestep, in the order of
+    /// the robots' names, before the tick hooks run
+    pub fn on_notification(
+        &mut self,
+        listener: impl FnMut(Time, &Notification) + 'static,
+    ) -> &mut Self {
+        self.listeners.push(Box::new(listener));
+        self
+    }
+
+    /// What happened to the robots during the last timestep
+    pub fn notifications(&self) -> &[Notification] {
+        &self.notifications
+    }
+
+    /// Let robots which finished their task drift to the nearest free of the staging `cells`
+    /// to clear the lanes, e.g. those of [Shaman::staging_cells]. They give way to all others
+    pub fn reposition(&mut self, cells: impl IntoIterator<Item = Vertex>) -> &mut Self {
+        self.staging = cells.into_iter().collect();
+        self
+    }
+
+    pub fn shaman(&self) -> &Shaman {
+        &self.shaman
+    }
+
+    pub fn into_inner(self) -> Shaman {
+        self.shaman
+    }
+
+    /// Whether no robot has any route left to follow and no task is left in the pool or any
+    /// queue, or none got taken for [Self::patience] timesteps, or the simulation was aborted
+    pub fn is_finished(&self) -> bool {
+        let done = self.tasks.is_empty() && self.queues.values().all(VecDeque::is_empty);
+        let stalled = self.idle >= self.patience;
+        self.aborted || self.shaman.remaining_duration() == 0 && (done || stalled)
+    }
+
+    /// Keep waiting for left tasks, which nobody can take right now, e.g. while their goal is
+    /// about to close, until nothing happened for `steps` timesteps in a row. Defaults to 10
+    pub fn patience(&mut self, steps: Time) -> &mut Self {
+        self.patience = steps;
+        self
+    }
+
+    /// Let robot `name` drive to all `goals` in this order (after its current one), i.e.
+    /// in lifelong fashion: whenever it completes a task, it replans to the next one around
+    /// the remaining routes of all others
+    pub fn queue(&mut self, name: char, goals: impl IntoIterator<Item = Vertex>) -> &mut Self {
+        self.queues.entry(name).or_default().extend(goals);
+        self.idle = 0;
+        self
+    }
+
+    /// Goals robot `name` still has to drive to after its current one, see [Self::queue]
+    pub fn queued(&self, name: char) -> impl Iterator<Item = Vertex> + '_ {
+        self.queues.get(&name).into_iter().flatten().copied()
+    }
+
+    /// Serve the shared pool of `tasks`, each a goal released at a time, with token passing:
+    /// Every timestep, idle robots take turns holding the token. The holder grabs the nearest
+    /// released task, which isn't the goal of anybody else, and plans its route to there
+    /// giving way to the remaining routes of all others
+    pub fn pass_token(&mut self, tasks: impl IntoIterator<Item = (Time, Vertex)>) -> &mut Self {
+        self.tasks = tasks.into_iter().sorted_by_key(|(t, _)| *t).collect();
+        self.idle = 0;
+        self
+    }
+
+    /// Tasks of the pool no robot took yet, with the time they are released
+    pub fn pending_tasks(&self) -> &[(Time, Vertex)] {
+        &self.tasks
+    }
+
+    /// How the routes of replanned robots changed since the last call, in the order they were
+    /// replanned, so executors only need to receive the changed portions
+    pub fn take_diffs(&mut self) -> Vec<(char, RouteDiff)> {
+        std::mem::take(&mut self.diffs)
+    }
+
+    /// Simulate until finished or a hook pauses or aborts
+    pub fn run(&mut self) -> Result<Outcome> {
+        while !self.is_finished() {
+            match self.step()? {
+                Control::Pause => return Ok(Outcome::Paused),
+                Control::Abort => return Ok(Outcome::Aborted),
+                _ => {}
+            }
+        }
+        Ok(if self.aborted {
+            Outcome::Aborted
+        } else {
+            Outcome::Finished
+        })
+    }
+
+    /// Advance a single timestep and let all hooks react to it. Aborting takes precedence over
+    /// pausing, events of all hooks get applied
+    pub fn step(&mut self) -> Result<Control> {
+        let before = self
+            .shaman
+            .robots
+            .values()
+            .map(|r| {
+                (
+                    r.name(),
+                    (r.position().0, r.route().iter().next().is_some()),
+                )
+            })
+            .collect();
+        let started = self.shaman.time() > 0;
+        self.shaman.simulate();
+        // The first timestep only takes the robots to their starts
+        self.notifications = match started {
+            true => notifications(&before, &self.shaman),
+            false => vec![],
+        };
+        for listener in &mut self.listeners {
+            for notification in &self.notifications {
+                listener(self.shaman.time(), notification);
+            }
+        }
+
+        let snapshot = Snapshot {
+            time: self.shaman.time(),
+            shaman: &self.shaman,
+            notifications: &self.notifications,
+        };
+        let controls = self.hooks.iter_mut().map(|h| h(&snapshot)).collect_vec();
+
+        let mut result = Control::Continue;
+        let mut failed = vec![];
+        for control in controls {
+            match control {
+                Control::Continue => {}
+                Control::Abort => {
+                    self.aborted = true;
+                    result = Control::Abort;
+                }
+                Control::Pause if result != Control::Abort => result = Control::Pause,
+                Control::Pause => {}
+                Control::Inject(events) => {
+                    for event in events {
+                        if self.apply(event).is_err() {
+                            failed.push(Notification::EventFailed(event));
+                        }
+                    }
+                }
+            }
+        }
+        // Reported after the others, since they happened after the hooks saw the timestep
+        for listener in &mut self.listeners {
+            for notification in &failed {
+                listener(self.shaman.time(), notification);
+            }
+        }
+        self.notifications.extend(failed);
+
+        let left = self.tasks.len() + self.queues.values().map(VecDeque::len).sum::<usize>();
+        self.next_tasks();
+        self.grab_tasks();
+        self.drift();
+        let taken =
+            self.tasks.len() + self.queues.values().map(VecDeque::len).sum::<usize>() < left;
+        let now = self.shaman.time();
+        let released = self.tasks.iter().all(|(t, _)| *t <= now);
+        self.idle = match taken || !released || self.shaman.remaining_duration() > 0 {
+            true => 0,
+            false => self.idle + 1,
+        };
+        Ok(result)
+    }
+
+    /// Send robots which completed their task to the next goal of their queue. Goals which
+    /// can't be served right now stay queued until the next timestep
+    fn next_tasks(&mut self) {
+        let idle = self
+            .shaman
+            .robots
+            .values()
+            .filter(|r| r.route().iter().next().is_none() && !r.is_frozen() && !r.is_delivered())
+            .map(|r| r.name())
+            .filter(|n| self.queues.get(n).is_some_and(|q| !q.is_empty()))
+            .sorted()
+            .collect_vec();
+        for name in idle {
+            let goal = self.queues[&name][0];
+            if self.apply(Event::Task(name, goal)).is_ok() {
+                self.queues.get_mut(&name).unwrap().pop_front();
+            }
+        }
+    }
+
+    /// Let idle robots take released tasks from the pool one after another, see
+    /// [Self::pass_token]
+    fn grab_tasks(&mut self) {
+        let now = self.shaman.time();
+        let idle = self
+            .shaman
+            .robots
+            .values()
+            .filter(|r| r.route().iter().next().is_none() && !r.is_frozen() && !r.delivers())
+            .map(|r| r.name())
+            .sorted()
+            .collect_vec();
+        for name in idle {
+            let goals = self
+                .shaman
+                .robots
+                .values()
+                .filter(|r| r.name() != name)
+                .filter_map(|r| r.goal().map(|g| g.0))
+                .collect::<FxHashSet<_>>();
+            let here = self.shaman.robots[&name].position().0;
+            let Some((i, goal)) = self
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, (t, goal))| *t <= now && !goals.contains(goal))
+                .filter_map(|(i, (_, goal))| Some((self.shaman.layout.distance(here, *goal)?, i)))
+                .min()
+                .map(|(_, i)| (i, self.tasks[i].1))
+            else {
+                continue;
+            };
+            // Failing keeps the task for someone else, who might find a way there
+            if self.apply(Event::Task(name, goal)).is_ok() {
+                self.tasks.remove(i);
+            }
+        }
+    }
+
+    /// Send idle robots to the nearest staging cell nobody else uses, if they can reach it
+    fn drift(&mut self) {
+        let idle = self
+            .shaman
+            .robots
+            .values()
+            .filter(|r| r.route().iter().next().is_none() && !r.is_frozen() && !r.delivers())
+            .map(|r| r.name())
+            .filter(|n| !self.repositioned.contains(n))
+            .sorted()
+            .collect_vec();
+        for name in idle {
+            self.repositioned.insert(name);
+            let taken = self
+                .shaman
+                .robots
+                .values()
+                .filter(|r| r.name() != name)
+                .flat_map(|r| [Some(r.position().0), r.goal().map(|g| g.0)])
+                .flatten()
+                .collect::<FxHashSet<_>>();
+            let robot = &self.shaman.robots[&name];
+            let here = robot.position().0;
+            let Some(cell) = self
+                .staging
+                .iter()
+                .filter(|v| !taken.contains(v))
+                .filter_map(|v| Some((self.shaman.layout.distance(here, *v)?, *v)))
+                .min_by_key(|(d, v)| (*d, v.y, v.x))
+                .map(|(_, v)| v)
+            else {
+                continue;
+            };
+            if cell == here {
+                continue;
+            }
+            let before = robot.clone();
+            self.shaman.robots.get_mut(&name).unwrap().assign(cell);
+            if self.replan(name).is_err() {
+                // Rather stay idle where it is than not arriving anywhere
+                self.shaman.robots.insert(name, before);
+            }
+        }
+    }
+
+    /// Change the scenario right now and replan affected robots around all others. Fails without
+    /// changing anything if that's impossible
+    pub fn apply(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Block(v) => self.block(v)?,
+            Event::Task(name, goal) => {
+                if !self.shaman.robots.contains_key(&name) {
+                    return Err(miette!("No robot named '{name}' defined"));
+                }
+                let served = self
+                    .shaman
+                    .robots
+                    .values()
+                    .filter(|r| r.name() != name && r.goal().is_some_and(|(g, _)| g == goal))
+                    .count();
+                let capacity = self.shaman.capacities.get(&goal).copied();
+                if served >= capacity.unwrap_or(1) {
+                    return Err(miette!(
+                        "Goal {goal} can serve at most {} robot(s), but already serves {served}",
+                        capacity.unwrap_or(1)
+                    ));
+                }
+                let before = (
+                    self.shaman.robots[&name].clone(),
+                    self.repositioned.contains(&name),
+                );
+                let robot = self.shaman.robots.get_mut(&name).unwrap();
+                robot.assign(goal);
+                robot.set_delivers(capacity.is_some());
+                self.repositioned.remove(&name);
+                if let Err(error) = self.replan(name) {
+                    // Leave the robot as it was, instead of with a goal its route doesn't reach
+                    let (robot, repositioned) = before;
+                    self.shaman.robots.insert(name, robot);
+                    if repositioned {
+                        self.repositioned.insert(name);
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move robot `name` by `action` during the next timestep as if driven by hand, instead of
+    /// along its route, then [Self::step]. From then on, it's frozen where it ends up. All other
+    /// robots whose routes would collide with it replan around it, or keep their routes if they
+    /// can't
+    pub fn drive(&mut self, name: char, action: Action) -> Result<Control> {
+        let robot = self
+            .shaman
+            .robots
+            .get(&name)
+            .ok_or_else(|| miette!("No robot named '{name}' defined"))?;
+        let here = robot.position().0;
+        let next = action.apply(here);
+        let closed = self.shaman.layout.is_blocked_at(next, self.now() + 1);
+        if closed || robot.excluded().contains(&next) {
+            return Err(miette!("Robot '{name}' can't drive {action:?} from {here}"));
+        }
+        if self.shaman.time() == 0 {
+            // The first timestep only takes the robots to their starts
+            self.step()?;
+        }
+        let now = self.now();
+        let ahead = Location {
+            position: next,
+            time: now + 1,
+        };
+        let robot = self.shaman.robots.get_mut(&name).unwrap();
+        robot.restore(here, Route::from_iter([ahead]), true);
+        self.make_way(name);
+        self.step()
+    }
+
+    /// Turn the free cell `v` into an obstacle right now, e.g. a pallet dropped in an aisle.
+    /// Robots whose remaining routes pass it replan from the current timestep around all
+    /// others. Fails without changing anything if a robot is on it or can't get past it anymore
+    pub fn block(&mut self, v: Vertex) -> Result<()> {
+        if let Some(robot) = self.robot_on(v) {
+            return Err(miette!("Can't block {v}, robot '{robot}' is on it"));
+        }
+        let before = (
+            self.shaman.layout.clone(),
+            self.shaman.robots.clone(),
+            self.diffs.len(),
+        );
+        self.shaman.layout.block(v);
+        let affected = self
+            .shaman
+            .robots
+            .values()
+            .filter(|r| self.remaining(r.name()).iter().any(|l| l.position == v))
+            .map(|r| r.name())
+            .collect_vec();
+        for name in affected {
+            if let Err(error) = self.replan(name) {
+                // Leave the scenario as it was, instead of half replanned around the obstacle
+                let (layout, robots, diffs) = before;
+                self.shaman.layout = layout;
+                self.shaman.robots = robots;
+                self.diffs.truncate(diffs);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Robot `name` turned out to be on `position` right now instead of where its route has
+    /// it, e.g. after slipping or being pushed aside. It replans from there around all other
+    /// robots' remaining routes. If it can't, it stays put for now, all others running into
+    /// it replan around it first, and it tries again. Frozen robots only stay put
+    pub fn divert(&mut self, name: char, position: Vertex) -> Result<()> {
+        let robot = self
+            .shaman
+            .robots
+            .get(&name)
+            .ok_or_else(|| miette!("No robot named '{name}' defined"))?;
+        if self.shaman.layout.is_blocked_at(position, self.now()) {
+            return Err(miette!(
+                "Robot '{name}' can't be on blocked cell {position}"
+            ));
+        }
+        if let Some(other) = self.robot_on(position).filter(|n| *n != name) {
+            return Err(miette!(
+                "Robot '{name}' can't be on {position}, robot '{other}' is there"
+            ));
+        }
+        let frozen = robot.is_frozen();
+        let before = robot.route().clone();
+        let robot = self.shaman.robots.get_mut(&name).unwrap();
+        robot.restore(position, Route::default(), frozen);
+        let mut planned = match frozen {
+            true => Ok(()),
+            false => self.plan_remaining(name),
+        };
+        if frozen || planned.is_err() {
+            self.make_way(name);
+            if !frozen {
+                planned = self.plan_remaining(name);
+            }
+        }
+        let after = self.shaman.robots[&name].route();
+        if before != *after {
+            self.diffs.push((name, before.diff(after)));
+        }
+        planned
+    }
+
+    /// Name of the robot on `v` right now, unless it left the map already
+    fn robot_on(&self, v: Vertex) -> Option<char> {
+        self.shaman
+            .robots
+            .values()
+            .find(|r| r.position().0 == v && !r.is_delivered())
+            .map(|r| r.name())
+    }
+
+    /// Replan all robots whose remaining routes collide with the one of robot `name` around
+    /// it, in alphabetical order. Robots failing to do so keep their routes
+    fn make_way(&mut self, name: char) {
+        let route = self.remaining(name);
+        let parks = !self.shaman.robots[&name].delivers();
+        let affected = self
+            .shaman
+            .robots
+            .values()
+            .filter(|r| r.name() != name && !r.is_frozen())
+            .filter(|r| {
+                !route
+                    .conflicts_parking(&self.remaining(r.name()), (parks, !r.delivers()))
+                    .is_empty()
+            })
+            .map(|r| r.name())
+            .sorted()
+            .collect_vec();
+        for other in affected {
+            let before = self.shaman.robots[&other].clone();
+            if self.replan(other).is_err() {
+                self.shaman.robots.insert(other, before);
+            }
+        }
+    }
+
+    /// Time of the robots' current positions
+    fn now(&self) -> Time {
+        self.shaman.time().saturating_sub(1)
+    }
+
+    /// The route `name` still has to drive, starting with its current position
+    fn remaining(&self, name: char) -> Route {
+        let now = self.now();
+        let robot = &self.shaman.robots[&name];
+        let current = Location {
+            position: robot.position().0,
+            time: now,
+        };
+        std::iter::once(current)
+            .chain(robot.route().iter().filter(|l| l.time > now))
+            .collect()
+    }
+
+    /// Plan a new route for `name` from where it is now, giving way to all other robots
+    fn replan(&mut self, name: char) -> Result<()> {
+        let before = self.shaman.robots[&name].route().clone();
+        self.plan_remaining(name)?;
+        let after = self.shaman.robots[&name].route();
+        if before != *after {
+            self.diffs.push((name, before.diff(after)));
+        }
+        Ok(())
+    }
+
+    /// Like [Self::replan], but without recording the change of the route
+    fn plan_remaining(&mut self, name: char) -> Result<()> {
+        let mut constraints = self.shaman.reservations.clone();
+        for other in self.shaman.robots.values().filter(|r| r.name() != name) {
+            constraints += other.right_of_way(&self.remaining(other.name()));
+        }
+        let now = self.now();
+        let started = self.shaman.time() > 0;
+        let robot = self.shaman.robots.get_mut(&name).unwrap();
+        robot.plan_from(&self.shaman.layout, &constraints, self.shaman.planner, now)?;
+        if started {
+            // The current position was already simulated
+            robot.skip_departure();
+        }
+        Ok(())
+    }
+}
+
+/// What happened to the robots of `after` a timestep, given where each robot was `before` it &
+/// whether it had any route left to follow
+fn notifications(before: &FxHashMap<char, (Vertex, bool)>, after: &Shaman) -> Vec<Notification> {
+    let robots = after
+        .robots
+        .values()
+        .filter(|r| !r.is_delivered())
+        .sorted_by_key(|r| r.name())
+        .map(|r| (r, before[&r.name()].0))
+        .collect_vec();
+    let mut notifications = vec![];
+    for (robot, from) in &robots {
+        let (name, to) = (robot.name(), robot.position().0);
+        if !before[&name].1 {
+            continue; // Idle all along
+        }
+        let done = robot.route().iter().next().is_none();
+        if *from != to {
+            notifications.push(Notification::RobotMoved {
+                robot: name,
+                from: *from,
+                to,
+            });
+        } else if !done {
+            notifications.push(Notification::RobotWaited {
+                robot: name,
+                at: to,
+            });
+        }
+        if done && let Some((goal, _)) = robot.goal().filter(|(g, _)| *g == to) {
+            notifications.push(Notification::RobotArrived { robot: name, goal });
+        }
+    }
+    for ((a, a_from), (b, b_from)) in robots.iter().tuple_combinations() {
+        let (a_to, b_to) = (a.position().0, b.position().0);
+        if a_to == b_to || (a_to == *b_from && b_to == *a_from) {
+            notifications.push(Notification::ConflictObserved {
+                robots: (a.name(), b.name()),
+                at: a_to,
+            });
+        }
+    }
+    notifications
+}
+
+impl Display for Notification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RobotMoved { robot, from, to } => write!(f, "{robot} moved {from} → {to}"),
+            Self::RobotWaited { robot, at } => write!(f, "{robot} waited at {at}"),
+            Self::RobotArrived { robot, goal } => write!(f, "{robot} arrived at {goal}"),
+            Self::ConflictObserved { robots: (a, b), at } => {
+                write!(f, "{a} & {b} collided at {at}")
+            }
+            Self::EventFailed(Event::Block(v)) => write!(f, "blocking {v} failed"),
+            Self::EventFailed(Event::Task(robot, goal)) => {
+                write!(f, "task {goal} for {robot} failed")
+            }
+        }
+    }
+}
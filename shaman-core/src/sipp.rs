@@ -0,0 +1,255 @@
+//! Safe interval path planning, an alternative to the time expanded [A*](crate::astar::solve())
+//!
+//! Instead of single timesteps, the time on each cell is split into safe intervals, during
+//! which nobody else occupies it. Waiting within an interval is a single step of the search, so
+//! long waits don't blow up the amount of expanded states. Each interval is entered as early as
+//! possible.
+use std::{collections::BinaryHeap, ops::RangeInclusive};
+
+use itertools::Itertools;
+use miette::SourceSpan;
+use ordered_float::OrderedFloat;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    Time,
+    astar::{Action, Budget, Kinematics, PlannerConfig, RightOfWay},
+    error::ShamanError,
+    layout::{Layout, Vertex},
+    robot::Location,
+    route::Route,
+};
+
+/// End of intervals nobody occupies the cell after
+const FOREVER: Time = Time::MAX;
+
+/// Maximal span of time during which nobody else occupies a cell, both ends inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Interval {
+    start: Time,
+    end: Time,
+}
+
+/// Being on a cell during one of its safe intervals, after arriving with `action` & facing
+/// `heading`, see [PlannerConfig::rotate_cost]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    position: Vertex,
+    interval: Interval,
+    action: Action,
+    heading: Action,
+}
+
+/// Split the time on a cell into the gaps between the times it is `occupied` & the windows it
+/// is `closed`
+fn intervals(
+    occupied: Option<&(Vec<Time>, Option<Time>)>,
+    closed: &[RangeInclusive<Time>],
+) -> Vec<Interval> {
+    let (times, from) = occupied.map_or((&[][..], None), |(times, from)| (&times[..], *from));
+    let blocked = times
+        .iter()
+        .map(|t| (*t, *t))
+        .chain(from.map(|f| (f, FOREVER)))
+        .chain(closed.iter().map(|w| (*w.start(), *w.end())))
+        .sorted();
+    let mut intervals = vec![];
+    let mut start = 0;
+    for (first, last) in blocked {
+        if first > start {
+            intervals.push(Interval {
+                start,
+                end: first - 1,
+            });
+        }
+        if last == FOREVER {
+            return intervals;
+        }
+        start = start.max(last + 1);
+    }
+    intervals.push(Interval {
+        start,
+        end: FOREVER,
+    });
+    intervals
+}
+
+/// Plan the cheapest route like [crate::astar::solve()] does, but over safe intervals
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn solve(
+    layout: &Layout,
+    start: (Vertex, SourceSpan),
+    goal: (Vertex, SourceSpan),
+    departure: Time,
+    constraint: &RightOfWay,
+    excluded: &FxHashSet<Vertex>,
+    park: bool,
+    kinematics: Kinematics,
+    config: &PlannerConfig,
+) -> Result<Route, ShamanError> {
+    let mut budget = Budget::new(config, layout, start, goal, kinematics.pace, constraint);
+    let occupancy = constraint.occupancy();
+    let mut safe = FxHashMap::<Vertex, Vec<Interval>>::default();
+    let mut safe_intervals = |v: Vertex| {
+        // Cells closed for a while are occupied like by anybody else
+        safe.entry(v)
+            .or_insert_with(|| intervals(occupancy.get(&v), layout.closed(v)))
+            .clone()
+    };
+
+    let first = safe_intervals(start.0)
+        .into_iter()
+        .find(|i| (i.start..=i.end).contains(&departure))
+        .unwrap_or(Interval {
+            start: departure,
+            end: departure, // Already occupied, so leave as soon as possible
+        });
+    let s = State {
+        position: start.0,
+        interval: first,
+        action: Action::Wait,
+        heading: match config.rotate_cost {
+            Some(_) => kinematics.heading,
+            None => Action::Wait,
+        },
+    };
+    // Cheapest cost & earliest arrival time of each state so far
+    let mut arrivals = FxHashMap::from_iter([(s, (0.0, departure))]);
+    let mut came_from = FxHashMap::<State, State>::default();
+    let mut expanded = FxHashSet::default();
+    let mut open = BinaryHeap::from([Item {
+        cost: 0.0.into(),
+        time: departure,
+        state: s,
+    }]);
+
+    while let Some(item) = open.pop() {
+        let state = item.state;
+        if !expanded.insert(state) {
+            continue; // Already expanded via a cheaper way
+        }
+        let (g, time) = arrivals[&state];
+        if budget.too_long(departure, time) {
+            continue;
+        }
+        budget.expand(state.position)?;
+
+        if state.position == goal.0 && !(park && state.interval.end != FOREVER) {
+            // Reached goal, unroll the waits in between the states
+            let mut route = vec![Location {
+                position: state.position,
+                time,
+            }];
+            let mut current = state;
+            let mut rotations = vec![];
+            while let Some(previous) = came_from.get(&current).copied() {
+                let (arrival, departure) = (arrivals[&previous].1, arrivals[&current].1 - 1);
+                route.extend((arrival..=departure).rev().map(|time| Location {
+                    position: previous.position,
+                    time,
+                }));
+                // Oriented robots rotated right before leaving
+                if let Some(rotation) = previous.heading.rotation_to(current.heading) {
+                    let count = previous
+                        .heading
+                        .rotations_to(current.heading, config.movement);
+                    let left = departure + 1 - kinematics.pace;
+                    rotations.extend((0..count as Time).map(|i| (left - i, rotation)));
+                }
+                current = previous;
+            }
+            let mut route = route.into_iter().rev().collect::<Route>();
+            for (time, rotation) in rotations {
+                route.rotate(time, rotation);
+            }
+            return Ok(route);
+        }
+
+        for action in config.movement.actions().filter(|a| *a != Action::Wait) {
+            let here = state.position;
+            let there = action.apply(here);
+            if layout.is_blocked(there)
+                || excluded.contains(&there)
+                || action.cuts_corner(here, layout)
+            {
+                continue;
+            }
+            // Oriented robots rotate here in place first, a timestep per rotation
+            let rotations = match config.rotate_cost {
+                Some(_) => state.heading.rotations_to(action, config.movement),
+                None => 0,
+            };
+            for interval in safe_intervals(there) {
+                // Leave no earlier than arriving here and no later than this interval ends,
+                // arriving there no earlier than rotating & moving takes
+                let earliest = (time + rotations + kinematics.pace).max(interval.start);
+                let latest = state.interval.end.saturating_add(1).min(interval.end);
+                let Some(arrival) =
+                    (earliest..=latest).find(|t| !constraint.swaps(t - 1, here, there))
+                else {
+                    continue;
+                };
+                let waited = arrival - 1 - time;
+                let cost = match waited {
+                    0 => action.cost(state.action, here, layout, &config.costs),
+                    _ => {
+                        let wait = Action::Wait.cost(Action::Wait, here, layout, &config.costs);
+                        let rotate = config.rotate_cost.unwrap_or_default() * rotations as f32;
+                        wait * (waited - rotations) as f32
+                            + rotate
+                            + action.cost(Action::Wait, here, layout, &config.costs)
+                    }
+                };
+                let candidate = State {
+                    position: there,
+                    interval,
+                    action,
+                    heading: match config.rotate_cost {
+                        Some(_) => action,
+                        None => Action::Wait,
+                    },
+                };
+                let tentative_g = g + cost;
+                if arrivals
+                    .get(&candidate)
+                    .is_none_or(|(g, t)| tentative_g < *g || (tentative_g == *g && arrival < *t))
+                {
+                    arrivals.insert(candidate, (tentative_g, arrival));
+                    came_from.insert(candidate, state);
+                    let h = config.estimate(layout, there, goal.0) * config.bound();
+                    open.push(Item {
+                        cost: OrderedFloat(tentative_g + h),
+                        time: arrival,
+                        state: candidate,
+                    });
+                }
+            }
+        }
+    }
+
+    Err(budget.exhausted())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Item {
+    state: State,
+    cost: OrderedFloat<f32>,
+    /// Arrival time, to prefer earlier arrivals among equally costly items
+    time: Time,
+}
+
+impl Ord for Item {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // reverse for min heap
+        other
+            .cost
+            .cmp(&self.cost)
+            .then_with(|| other.time.cmp(&self.time))
+    }
+}
+
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,653 @@
+//! Queries on solved scenarios
+use std::{fmt::Display, ops::Range};
+
+use itertools::Itertools;
+use miette::Diagnostic;
+use rustc_hash::FxHashMap;
+use thiserror::Error;
+
+use crate::{
+    Shaman, Time,
+    astar::Action,
+    json::{Json, ToJson},
+    layout::Vertex,
+    robot::{Location, Robot},
+    route::Route,
+    validate::Violation,
+};
+
+/// A solved scenario, where no robots conflict with each other
+#[derive(Debug)]
+pub struct Solution {
+    shaman: Shaman,
+}
+
+/// Only routes without any [Violation] make a solution, see [Shaman::violations]
+impl TryFrom<Shaman> for Solution {
+    type Error = Rejection;
+
+    fn try_from(shaman: Shaman) -> Result<Self, Rejection> {
+        match shaman.violations() {
+            violations if violations.is_empty() => Ok(Self { shaman }),
+            violations => Err(Rejection::Violations(violations)),
+        }
+    }
+}
+
+/// Two robots running into each other
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// Names of both robots, sorted
+    pub robots: (char, char),
+    /// Where they run into each other
+    pub cells: Vec<Vertex>,
+    /// Where & when they run into each other, sorted by time
+    pub locations: Vec<Location>,
+}
+
+/// What [Solution::replace_route] should do with robots conflicting with the new route
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Repair {
+    /// Refuse the new route
+    #[default]
+    Reject,
+    /// Replan conflicting robots so they give way to the new route
+    Replan,
+}
+
+/// Why [Solution::try_from] refused routes, or [Solution::replace_route] a single one
+#[derive(Debug, Clone, PartialEq, Eq, Error, Diagnostic)]
+pub enum Rejection {
+    #[error("No robot named '{0}' defined")]
+    UnknownRobot(char),
+    /// What the routes would violate, including collisions nobody could be replanned around
+    #[error("The routes aren't feasible:\n{}", .0.iter().join("\n"))]
+    Violations(Vec<Violation>),
+}
+
+impl Shaman {
+    /// All pairs of robots whose routes conflict with each other
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .tuple_combinations()
+            .filter_map(|(a, b)| {
+                let collisions = a.collisions(b);
+                (!collisions.is_empty()).then(|| Conflict {
+                    robots: (a.name(), b.name()),
+                    cells: collisions.iter().map(|l| l.position).collect(),
+                    locations: collisions
+                        .into_iter()
+                        .sorted_by_key(|l| (l.time, l.position.y, l.position.x))
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// How a single robot is affected by a change to the [Solution]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delay {
+    /// Robot arrives this many timesteps later (or earlier if negative)
+    Steps(isize),
+    /// Robot cannot reach its goal anymore
+    Blocked,
+}
+
+/// Estimated consequences of blocking a cell of a [Solution]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Impact {
+    /// All robots whose routes cross the blocked cell, sorted by name
+    pub affected: Vec<(char, Delay)>,
+}
+
+impl Impact {
+    /// Whether the cell can be blocked without any robot being unable to reach its goal
+    pub fn is_feasible(&self) -> bool {
+        self.affected.iter().all(|(_, d)| *d != Delay::Blocked)
+    }
+
+    /// Sum of the delays of all robots, which can still reach their goals
+    pub fn total_delay(&self) -> isize {
+        self.affected
+            .iter()
+            .filter_map(|(_, d)| match d {
+                Delay::Steps(n) => Some(*n),
+                Delay::Blocked => None,
+            })
+            .sum()
+    }
+}
+
+impl Solution {
+    pub fn shaman(&self) -> &Shaman {
+        &self.shaman
+    }
+
+    pub fn into_inner(self) -> Shaman {
+        self.shaman
+    }
+
+    /// Make `robot` follow the manually edited `route` from now on, which is never replanned.
+    /// Robots conflicting with it are either rejected or replanned, depending on `repair`. In
+    /// the latter case, only conflicting robots are replanned, giving way to all others. The
+    /// outcome is refused if it has any [Shaman::violations], e.g. the route jumps
+    pub fn replace_route(
+        &self,
+        robot: char,
+        route: Route,
+        repair: Repair,
+    ) -> Result<Solution, Rejection> {
+        let mut shaman = self.shaman.clone();
+        shaman
+            .freeze(robot, route)
+            .map_err(|_| Rejection::UnknownRobot(robot))?;
+        let affected = shaman
+            .conflicts()
+            .into_iter()
+            .filter_map(|c| match c.robots {
+                (a, b) if a == robot => Some(b),
+                (a, b) if b == robot => Some(a),
+                _ => None,
+            })
+            .collect_vec();
+
+        if repair == Repair::Replan {
+            for name in &affected {
+                let mut constraints = shaman.reservations.clone();
+                for other in shaman.robots.values().filter(|o| o.name() != *name) {
+                    constraints += other.right_of_way(other.route());
+                }
+                let robot = shaman.robots.get_mut(name).unwrap();
+                if robot
+                    .plan(&shaman.layout, &constraints, shaman.planner)
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+
+        Solution::try_from(shaman)
+    }
+
+    /// Estimate which robots would be affected and by how much, if `v` were blocked. Only
+    /// affected robots get replanned, each against the unchanged routes of all others
+    pub fn impact_of_block(&self, v: Vertex) -> Impact {
+        let mut layout = self.shaman.layout.clone();
+        layout.block(v);
+
+        let affected = self
+            .shaman
+            .robots
+            .values()
+            .filter(|r| r.route().iter().any(|l| l.position == v))
+            .sorted_by_key(|r| r.name())
+            .map(|robot| {
+                if robot.is_frozen() || robot.position().0 == v {
+                    return (robot.name(), Delay::Blocked);
+                }
+                let mut constraints = self.shaman.reservations.clone();
+                for other in self
+                    .shaman
+                    .robots
+                    .values()
+                    .filter(|o| o.name() != robot.name())
+                {
+                    constraints += other.right_of_way(other.route());
+                }
+                let before: Time = robot.route().duration();
+                let mut robot = robot.clone();
+                let delay = match robot.plan(&layout, &constraints, self.shaman.planner) {
+                    Ok(()) => Delay::Steps(robot.route().duration() as isize - before as isize),
+                    Err(_) => Delay::Blocked,
+                };
+                (robot.name(), delay)
+            })
+            .collect();
+        Impact { affected }
+    }
+}
+
+/// How a single robot follows its route
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RobotStats {
+    pub name: char,
+    /// Timesteps spent moving to a neighboring cell
+    pub moves: usize,
+    /// Timesteps spent standing still, see [Robot::waits]
+    pub waits: usize,
+    /// Amount of direction changes between moves
+    pub turns: usize,
+    /// Sum of all action costs according to the planner's [CostModel](crate::CostModel)
+    pub energy: f32,
+}
+
+/// [RobotStats] of all robots of a fleet, see [Robot::fleet]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FleetStats {
+    pub name: String,
+    /// Names of the fleet's robots, sorted
+    pub robots: Vec<char>,
+    pub moves: usize,
+    pub waits: usize,
+    pub turns: usize,
+    pub energy: f32,
+    /// Sum of the fleet's route durations
+    pub cost: Time,
+}
+
+/// Per robot breakdown of a [Solution]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    /// Sorted by robot name
+    pub robots: Vec<RobotStats>,
+    /// Sorted by fleet name, without robots not belonging to any fleet
+    pub fleets: Vec<FleetStats>,
+    /// No solution can cost less than this, see [Shaman::lower_bound]
+    pub lower_bound: Time,
+}
+
+impl Solution {
+    /// Break down every robot's route, measuring energy like the planner does
+    pub fn stats(&self) -> Stats {
+        let robots = self
+            .shaman
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .map(|robot| {
+                let actions = robot.route().to_actions().unwrap_or_default();
+                let moves = actions.iter().filter(|a| a.is_move()).collect_vec();
+                RobotStats {
+                    name: robot.name(),
+                    moves: moves.len(),
+                    waits: robot.waits(),
+                    turns: moves.iter().tuple_windows().filter(|(a, b)| a != b).count(),
+                    energy: robot
+                        .route()
+                        .cost(&self.shaman.layout, &self.shaman.planner.costs),
+                }
+            })
+            .collect_vec();
+        let fleets = self
+            .shaman
+            .robots
+            .values()
+            .filter_map(|r| Some((r.fleet()?, r)))
+            .into_group_map()
+            .into_iter()
+            .sorted_by_key(|(fleet, _)| *fleet)
+            .map(|(fleet, members)| {
+                let stats = robots
+                    .iter()
+                    .filter(|s| members.iter().any(|r| r.name() == s.name))
+                    .collect_vec();
+                FleetStats {
+                    name: fleet.to_string(),
+                    robots: members.iter().map(|r| r.name()).sorted().collect(),
+                    moves: stats.iter().map(|s| s.moves).sum(),
+                    waits: stats.iter().map(|s| s.waits).sum(),
+                    turns: stats.iter().map(|s| s.turns).sum(),
+                    energy: stats.iter().map(|s| s.energy).sum(),
+                    cost: members.iter().map(|r| r.route().duration()).sum(),
+                }
+            })
+            .collect();
+        Stats {
+            robots,
+            fleets,
+            lower_bound: self.shaman.lower_bound(),
+        }
+    }
+}
+
+impl Stats {
+    pub fn energy(&self) -> f32 {
+        self.robots.iter().map(|r| r.energy).sum()
+    }
+}
+
+impl ToJson for RobotStats {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("name", self.name.to_json()),
+            ("moves", self.moves.to_json()),
+            ("waits", self.waits.to_json()),
+            ("turns", self.turns.to_json()),
+            ("energy", self.energy.to_json()),
+        ])
+    }
+}
+
+impl ToJson for FleetStats {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("name", self.name.to_json()),
+            ("robots", Json::array(&self.robots)),
+            ("cost", self.cost.to_json()),
+            ("moves", self.moves.to_json()),
+            ("waits", self.waits.to_json()),
+            ("turns", self.turns.to_json()),
+            ("energy", self.energy.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Stats {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("energy", self.energy().to_json()),
+            ("lower_bound", self.lower_bound.to_json()),
+            ("robots", Json::array(&self.robots)),
+            ("fleets", Json::array(&self.fleets)),
+        ])
+    }
+}
+
+/// Name & timed route of a robot
+impl ToJson for Robot {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("name", self.name().to_json()),
+            ("cost", self.route().duration().to_json()),
+            ("route", self.route().to_json()),
+        ])
+    }
+}
+
+/// Timed routes of all robots, sorted by name
+impl ToJson for Shaman {
+    fn to_json(&self) -> Json {
+        let robots = self.robots.values().sorted_by_key(|r| r.name());
+        Json::object([
+            ("makespan", self.simulation_duration().to_json()),
+            ("cost", self.cost().to_json()),
+            ("robots", Json::array(robots)),
+            ("quality", self.quality().to_json()),
+        ])
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "robot  moves  waits  turns  energy")?;
+        for r in &self.robots {
+            writeln!(
+                f,
+                "{:<5}  {:>5}  {:>5}  {:>5}  {:>6.1}",
+                r.name, r.moves, r.waits, r.turns, r.energy
+            )?;
+        }
+        if self.fleets.is_empty() {
+            return Ok(());
+        }
+        writeln!(
+            f,
+            "\nfleet       robots      cost  moves  waits  turns  energy"
+        )?;
+        for fleet in &self.fleets {
+            writeln!(
+                f,
+                "{:<10}  {:<10}  {:>4}  {:>5}  {:>5}  {:>5}  {:>6.1}",
+                fleet.name,
+                fleet.robots.iter().join(","),
+                fleet.cost,
+                fleet.moves,
+                fleet.waits,
+                fleet.turns,
+                fleet.energy
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How often robots enter a cell in each direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flow {
+    pub cell: Vertex,
+    pub n: usize,
+    pub w: usize,
+    pub e: usize,
+    pub s: usize,
+    pub ne: usize,
+    pub nw: usize,
+    pub se: usize,
+    pub sw: usize,
+}
+
+impl Flow {
+    /// Direction most robots enter this cell from, if any
+    pub fn dominant(&self) -> Option<Action> {
+        [
+            (self.n, Action::N),
+            (self.w, Action::W),
+            (self.e, Action::E),
+            (self.s, Action::S),
+            (self.ne, Action::NE),
+            (self.nw, Action::NW),
+            (self.se, Action::SE),
+            (self.sw, Action::SW),
+        ]
+        .into_iter()
+        .filter(|(n, _)| *n > 0)
+        .max_by_key(|(n, _)| *n)
+        .map(|(_, a)| a)
+    }
+
+    /// How often robots enter this cell at all
+    pub fn total(&self) -> usize {
+        self.n + self.w + self.e + self.s + self.ne + self.nw + self.se + self.sw
+    }
+
+    /// Arrow pointing in the [Self::dominant] direction
+    pub fn arrow(&self) -> Option<char> {
+        Some(match self.dominant()? {
+            Action::N => '↑',
+            Action::W => '←',
+            Action::E => '→',
+            Action::S => '↓',
+            Action::NE => '↗',
+            Action::NW => '↖',
+            Action::SE => '↘',
+            Action::SW => '↙',
+            Action::Wait | Action::Rotate(_) => return None,
+        })
+    }
+}
+
+impl Solution {
+    /// Histogram of directions robots move into each cell, sorted by cell
+    pub fn flows(&self) -> Vec<Flow> {
+        let mut flows = FxHashMap::<Vertex, Flow>::default();
+        for route in self.shaman.robots.values().map(|r| r.route()) {
+            for (a, b) in route.iter().tuple_windows() {
+                let flow = flows.entry(b.position).or_insert(Flow {
+                    cell: b.position,
+                    n: 0,
+                    w: 0,
+                    e: 0,
+                    s: 0,
+                    ne: 0,
+                    nw: 0,
+                    se: 0,
+                    sw: 0,
+                });
+                match Action::try_from((a.position, b.position)) {
+                    Ok(Action::N) => flow.n += 1,
+                    Ok(Action::W) => flow.w += 1,
+                    Ok(Action::E) => flow.e += 1,
+                    Ok(Action::S) => flow.s += 1,
+                    Ok(Action::NE) => flow.ne += 1,
+                    Ok(Action::NW) => flow.nw += 1,
+                    Ok(Action::SE) => flow.se += 1,
+                    Ok(Action::SW) => flow.sw += 1,
+                    _ => {}
+                }
+            }
+        }
+        flows
+            .into_values()
+            .filter(|f| f.dominant().is_some())
+            .sorted_by_key(|f| (f.cell.y, f.cell.x))
+            .collect()
+    }
+}
+
+impl Solution {
+    /// When robots are on `v`, as ranges of consecutive timesteps sorted by their start. Robots
+    /// parking on `v` for good occupy it until [Time::MAX]
+    pub fn occupancy(&self, v: Vertex) -> Vec<(Range<Time>, char)> {
+        self.shaman
+            .robots
+            .values()
+            .flat_map(|robot| {
+                let mut ranges: Vec<Range<Time>> = vec![];
+                for l in robot.route().iter().filter(|l| l.position == v) {
+                    match ranges.last_mut() {
+                        Some(range) if range.end == l.time => range.end += 1,
+                        _ => ranges.push(l.time..l.time + 1),
+                    }
+                }
+                let last = robot.route().iter().last();
+                let parks =
+                    !robot.delivers() && last.map_or(robot.position().0, |l| l.position) == v;
+                match ranges.last_mut() {
+                    Some(range) if parks => range.end = Time::MAX,
+                    None if parks => ranges.push(self.shaman.time..Time::MAX),
+                    _ => {}
+                }
+                ranges.into_iter().map(|range| (range, robot.name()))
+            })
+            .sorted_by_key(|(range, name)| (range.start, *name))
+            .collect()
+    }
+
+    /// First timestep from which on no robot is on `v` anymore, e.g. to schedule maintenance
+    /// of a lane. `None` if a robot parks there for good
+    pub fn cell_free_after(&self, v: Vertex) -> Option<Time> {
+        match self
+            .occupancy(v)
+            .into_iter()
+            .map(|(range, _)| range.end)
+            .max()
+        {
+            Some(Time::MAX) => None,
+            Some(end) => Some(end),
+            None => Some(self.shaman.time),
+        }
+    }
+}
+
+impl ToJson for Flow {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("cell", self.cell.to_json()),
+            ("N", self.n.to_json()),
+            ("W", self.w.to_json()),
+            ("E", self.e.to_json()),
+            ("S", self.s.to_json()),
+            ("NE", self.ne.to_json()),
+            ("NW", self.nw.to_json()),
+            ("SE", self.se.to_json()),
+            ("SW", self.sw.to_json()),
+        ])
+    }
+}
+
+/// Why a robot arrives later than it would on its own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribution {
+    pub name: char,
+    /// Timesteps more than the shortest path to its goal, ignoring all other robots
+    pub delay: usize,
+    /// Timesteps of delay caused by each robot standing in the way, sorted by name
+    pub causes: Vec<(char, usize)>,
+    /// Timesteps of delay no other robot stood in the way, e.g. due to reservations
+    pub unexplained: usize,
+}
+
+impl Solution {
+    /// Attribute each robot's delay to the robots blocking its way. Every step not getting
+    /// closer to the goal is blamed on the robot occupying, or swapping with, a cell which
+    /// would have. If there is none, the robot passing by such a cell next is blamed instead
+    pub fn delays(&self) -> Vec<Attribution> {
+        let layout = &self.shaman.layout;
+        self.shaman
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .filter_map(|robot| {
+                let (goal, _) = robot.goal()?;
+                let distances = layout.distances(goal);
+                let distance = |v| distances.get(&v).copied().unwrap_or(usize::MAX);
+                let others = self
+                    .shaman
+                    .robots
+                    .values()
+                    .filter(|o| o.name() != robot.name())
+                    .sorted_by_key(|o| o.name())
+                    .collect_vec();
+
+                let mut causes = FxHashMap::<char, usize>::default();
+                let mut unexplained = 0;
+                for (now, then) in robot.route().iter().tuple_windows() {
+                    let (here, there) = (distance(now.position), distance(then.position));
+                    if there < here {
+                        continue;
+                    }
+                    let steps = if there == here { 1 } else { 2 };
+                    let closer = layout
+                        .neighbors(now.position)
+                        .filter(|n| distance(*n) < here)
+                        .collect_vec();
+                    let blocks = |o: &&&Robot, t: Time| {
+                        let route = o.route();
+                        let next = route.position_at(t + 1);
+                        next.is_some_and(|n| closer.contains(&n))
+                            || (route.position_at(t).is_some_and(|n| closer.contains(&n))
+                                && next == Some(now.position))
+                    };
+                    // Either somebody stands in the way right now, or will pass by soon
+                    let culprit = others.iter().find(|o| blocks(o, now.time)).or_else(|| {
+                        (then.time..robot.route().duration())
+                            .find_map(|t| others.iter().find(|o| blocks(o, t)))
+                    });
+                    match culprit {
+                        Some(o) => *causes.entry(o.name()).or_default() += steps,
+                        None => unexplained += steps,
+                    }
+                }
+                Some(Attribution {
+                    name: robot.name(),
+                    // Routes may end early or elsewhere, e.g. frozen ones, which isn't a delay
+                    delay: robot.route().iter().next().map_or(0, |start| {
+                        let duration = robot.route().duration().saturating_sub(start.time);
+                        duration.saturating_sub(distance(start.position))
+                    }),
+                    causes: causes.into_iter().sorted().collect(),
+                    unexplained,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Display for Attribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is {} steps late", self.name, self.delay)?;
+        let reasons = self
+            .causes
+            .iter()
+            .map(|(n, d)| format!("{d} because of {n}"))
+            .chain((self.unexplained > 0).then(|| format!("{} otherwise", self.unexplained)))
+            .join(", ");
+        if !reasons.is_empty() {
+            write!(f, ": {reasons}")?;
+        }
+        Ok(())
+    }
+}
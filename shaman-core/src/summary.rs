@@ -0,0 +1,103 @@
+//! Key figures of a (solved) scenario, shown after playback
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::{
+    Quality, Shaman, Time,
+    astar::MovementModel,
+    layout::Vertex,
+    messages::{English, Locale},
+};
+
+/// Overview how long a scenario takes and how well robots got along
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// Time when the last robot arrives at its goal
+    pub makespan: Time,
+    /// Sum of all robots' route durations
+    pub cost: Time,
+    /// Sum of all robots' route durations, multiplied by their weights
+    pub weighted_cost: f32,
+    /// No solution can cost less than this, see [Shaman::lower_bound]
+    pub lower_bound: Time,
+    /// Time each robot arrives at its goal, sorted by name
+    pub arrivals: Vec<(char, Time)>,
+    /// Amount of timesteps all robots spent waiting, see [crate::Robot::waits]
+    pub waits: usize,
+    /// Whether any two robots run into each other
+    pub conflicts: bool,
+    /// How good the routes are overall, see [Shaman::quality]
+    pub quality: Quality,
+}
+
+impl Shaman {
+    /// Sum of all robots' shortest path lengths, ignoring each other. No solution costs less,
+    /// so an optimal solver's cost is as close to it as possible
+    pub fn lower_bound(&self) -> Time {
+        self.robots
+            .values()
+            .map(|robot| {
+                let Some((goal, _)) = robot.goal() else {
+                    return robot.route().duration();
+                };
+                let distance = |from: Vertex, to: Vertex| match self.planner.movement {
+                    MovementModel::FourConnected => {
+                        self.layout.distances_to(to).get(&from).copied()
+                    }
+                    // Diagonal moves shorten paths to at most the larger of both distances
+                    MovementModel::EightConnected => {
+                        Some(from.x.abs_diff(to.x).max(from.y.abs_diff(to.y)) as usize)
+                    }
+                };
+                // Via all waypoints left in order
+                let distance = std::iter::once(robot.position().0)
+                    .chain(robot.waypoints().iter().copied())
+                    .chain([goal])
+                    .tuple_windows()
+                    .map(|(from, to)| distance(from, to).unwrap_or_default())
+                    .sum::<usize>();
+                match robot.is_frozen() {
+                    true => robot.route().duration(),
+                    false => self.time + distance * robot.pace(),
+                }
+            })
+            .sum()
+    }
+
+    /// Summarize the robots' current routes
+    pub fn summary(&self) -> Summary {
+        Summary {
+            makespan: self.simulation_duration(),
+            cost: self.cost(),
+            weighted_cost: self.weighted_cost(),
+            lower_bound: self.lower_bound(),
+            arrivals: self
+                .robots
+                .values()
+                .map(|r| (r.name(), r.route().duration()))
+                .sorted()
+                .collect(),
+            waits: self.robots.values().map(|r| r.waits()).sum(),
+            conflicts: self
+                .robots
+                .values()
+                .tuple_combinations()
+                .any(|(a, b)| a.conflicts(b)),
+            quality: self.quality(),
+        }
+    }
+}
+
+impl Summary {
+    /// How much costlier the routes are than the [Self::lower_bound], relative to it
+    pub fn gap(&self) -> f32 {
+        (self.cost as f32 - self.lower_bound as f32) / self.lower_bound.max(1) as f32
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", English.render(self))
+    }
+}
@@ -0,0 +1,116 @@
+//! Trading goals between robots, when a scenario can't be solved or is needlessly costly
+//!
+//! Candidates are swapping the goals of each pair of robots in conflict, as well as assigning
+//! all goals as [Shaman::assign_goals] does. Each candidate gets solved and is suggested, if it
+//! is feasible where the scenario isn't, or saves at least [MIN_SAVING] of its cost.
+use std::fmt::Display;
+
+use itertools::Itertools;
+use ordered_float::OrderedFloat;
+
+use crate::{Algorithm, Shaman, layout::Vertex};
+
+/// Fraction of the cost swapping goals needs to save at least to be worth it
+const MIN_SAVING: f32 = 0.1;
+
+/// Goals robots could trade, see [Shaman::suggest_swaps]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapSuggestion {
+    /// Pairs of robots trading their goals, in this order
+    pub swaps: Vec<(char, char)>,
+    /// Cost of the solution after trading, by the scenario's [Objective](crate::Objective)
+    pub cost: f32,
+    /// Cost of the solution without trading, `None` if there is none
+    pub before: Option<f32>,
+}
+
+impl Display for SwapSuggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let swaps = self.swaps.iter().map(|(a, b)| format!("{a} & {b}"));
+        write!(f, "swap {}: cost {}", swaps.format(", "), self.cost)?;
+        match self.before {
+            Some(before) => write!(f, " instead of {before}"),
+            None => write!(f, " instead of no solution"),
+        }
+    }
+}
+
+impl Shaman {
+    /// Goal swaps between robots which make this scenario solvable with `algorithm`, or much
+    /// cheaper to solve, cheapest first
+    pub fn suggest_swaps(&self, algorithm: Algorithm) -> Vec<SwapSuggestion> {
+        let cost = |shaman: &Shaman| shaman.objective.cost(shaman.robots.values());
+        let before = self.clone().solve_with(algorithm).ok().map(|s| cost(&s));
+        let goal = |n: char| {
+            let robot = &self.robots[&n];
+            robot.goal().filter(|_| !robot.is_frozen()).map(|(g, _)| g)
+        };
+        let mut candidates = self
+            .conflict_graph()
+            .edges
+            .iter()
+            .filter(|e| {
+                goal(e.robots.0)
+                    .zip(goal(e.robots.1))
+                    .is_some_and(|(a, b)| a != b)
+            })
+            .map(|e| vec![e.robots])
+            .collect_vec();
+        if let Some(goals) = self.optimal_goals() {
+            let swaps = transpositions(goals.iter().map(|(n, _)| goal(*n).unwrap()), &goals);
+            if !swaps.is_empty() && !candidates.contains(&swaps) {
+                candidates.push(swaps);
+            }
+        }
+
+        candidates
+            .into_iter()
+            .filter_map(|swaps| {
+                let mut swapped = self.clone();
+                for (a, b) in &swaps {
+                    swapped.swap_goals(*a, *b);
+                }
+                swapped.plan().ok()?;
+                let solved = swapped.solve_with(algorithm).ok()?;
+                let cost = cost(&solved);
+                let worth = before.is_none_or(|b| cost <= (1. - MIN_SAVING) * b);
+                worth.then_some(SwapSuggestion {
+                    swaps,
+                    cost,
+                    before,
+                })
+            })
+            .sorted_by_key(|s| (OrderedFloat(s.cost), s.swaps.len()))
+            .collect()
+    }
+
+    /// Let robots `a` & `b` drive to each other's goal. Doesn't replan them
+    fn swap_goals(&mut self, a: char, b: char) {
+        let goal = |n| self.robots[&n].goal().unwrap().0;
+        let (goal_a, goal_b) = (goal(a), goal(b));
+        self.reassign(a, goal_b);
+        self.reassign(b, goal_a);
+    }
+}
+
+/// Pairs of robots swapping their `current` goals one after another, until each robot has the
+/// goal given in `target`
+fn transpositions(
+    current: impl Iterator<Item = Vertex>,
+    target: &[(char, Vertex)],
+) -> Vec<(char, char)> {
+    let mut goals = current.collect_vec();
+    let mut swaps = vec![];
+    for (i, (name, goal)) in target.iter().enumerate() {
+        if goals[i] == *goal {
+            continue;
+        }
+        // One robot after this one has the wanted goal, but needs another one
+        let j = (i + 1..target.len())
+            .find(|j| goals[*j] == *goal && goals[*j] != target[*j].1)
+            .expect("Target has other goals than current ones");
+        goals.swap(i, j);
+        swaps.push((*name, target[j].0));
+    }
+    swaps
+}
@@ -0,0 +1,265 @@
+//! Verifying solutions independently of the solvers, e.g. plans produced by other tools
+//!
+//! Routes are checked location by location against the layout & each other, without relying
+//! on any of the bookkeeping used while solving. Robots which don't deliver stay on their last
+//! cell forever, so passing them afterwards is a violation as well.
+use std::{fmt::Display, path::Path};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+
+use crate::{
+    MovementModel, Shaman, Time,
+    astar::Action,
+    json::Json,
+    layout::Vertex,
+    messages::{English, Locale, Messages},
+    robot::{Location, Robot},
+    route::Route,
+};
+
+/// Something a solution must never do, see [Shaman::violations]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// Time doesn't advance by exactly one timestep from one location of a route to the next
+    Timing { robot: char, from: Time, to: Time },
+    /// The robot jumps between cells which aren't adjacent by the movement model
+    Jump {
+        robot: char,
+        from: Vertex,
+        to: Vertex,
+        t: Time,
+    },
+    /// The robot is on an obstacle, off the map, on a closed cell or on a cell it's excluded from
+    Blocked { robot: char, v: Vertex, t: Time },
+    /// Both robots are on the same cell at the same time
+    Vertex {
+        robots: (char, char),
+        v: Vertex,
+        t: Time,
+    },
+    /// Both robots swap their cells or cross the same diagonal from `t` to `t + 1`
+    Edge {
+        robots: (char, char),
+        from: Vertex,
+        to: Vertex,
+        t: Time,
+    },
+    /// The second robot runs into the first one, which already parked on its goal for good
+    Goal {
+        robots: (char, char),
+        v: Vertex,
+        t: Time,
+    },
+    /// The robot's route ends elsewhere than on its goal
+    MissedGoal { robot: char, goal: Vertex },
+}
+
+impl Shaman {
+    /// Everything the robots' current routes violate: gaps in time, jumps, obstacles and
+    /// collisions with each other, sorted by time within each kind. Empty for every solution
+    pub fn violations(&self) -> Vec<Violation> {
+        let robots = self
+            .robots
+            .values()
+            .sorted_by_key(|r| r.name())
+            .collect_vec();
+        let mut violations = vec![];
+        for robot in &robots {
+            violations.extend(self.route_violations(robot));
+        }
+
+        let end = robots
+            .iter()
+            .filter_map(|r| r.route().iter().last())
+            .map(|l| l.time)
+            .max()
+            .unwrap_or_default();
+        for (a, b) in robots.iter().tuple_combinations() {
+            let robots = (a.name(), b.name());
+            for t in 0..=end {
+                let (p, q) = (position(a, t), position(b, t));
+                if let (Some(p), Some(q)) = (p, q)
+                    && p == q
+                {
+                    violations.push(match (parked(a, t), parked(b, t)) {
+                        (true, false) => Violation::Goal { robots, v: p, t },
+                        (false, true) => Violation::Goal {
+                            robots: (robots.1, robots.0),
+                            v: p,
+                            t,
+                        },
+                        _ => Violation::Vertex { robots, v: p, t },
+                    });
+                }
+                let next = (position(a, t + 1), position(b, t + 1));
+                if let ((Some(p), Some(q)), (Some(r), Some(s))) = ((p, q), next)
+                    && p != r
+                    && ((p == s && q == r) || p.crosses(r, (q, s)))
+                {
+                    violations.push(Violation::Edge {
+                        robots,
+                        from: p,
+                        to: r,
+                        t,
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    /// Violations of a single `robot`'s route on its own, regardless of all others
+    fn route_violations(&self, robot: &Robot) -> Vec<Violation> {
+        let name = robot.name();
+        let mut violations = vec![];
+        for l in robot.route().iter() {
+            let closed = self.layout.is_blocked_at(l.position, l.time);
+            if closed || robot.excluded().contains(&l.position) {
+                violations.push(Violation::Blocked {
+                    robot: name,
+                    v: l.position,
+                    t: l.time,
+                });
+            }
+        }
+        for (now, then) in robot.route().iter().tuple_windows() {
+            if then.time != now.time + 1 {
+                violations.push(Violation::Timing {
+                    robot: name,
+                    from: now.time,
+                    to: then.time,
+                });
+            }
+            let adjacent = Action::try_from((now.position, then.position)).is_ok_and(|a| {
+                self.planner.movement == MovementModel::EightConnected
+                    || !matches!(a, Action::NE | Action::NW | Action::SE | Action::SW)
+            });
+            if !adjacent {
+                violations.push(Violation::Jump {
+                    robot: name,
+                    from: now.position,
+                    to: then.position,
+                    t: now.time,
+                });
+            }
+        }
+        // Robots staying on their last cell forever are in the way once it closes
+        if let Some(last) = robot.route().iter().last()
+            && !robot.delivers()
+            && let Some(t) = self.layout.closes_after(last.position, last.time)
+        {
+            violations.push(Violation::Blocked {
+                robot: name,
+                v: last.position,
+                t,
+            });
+        }
+        let last = robot.route().iter().last().map(|l| l.position);
+        if let Some((goal, _)) = robot.goal()
+            && last.unwrap_or(robot.position().0) != goal
+        {
+            violations.push(Violation::MissedGoal { robot: name, goal });
+        }
+        violations
+    }
+
+    /// Make each robot follow the route of the same name in `plan`, a JSON document like the
+    /// one exported for solved scenarios, to check it with [Self::violations]. Robots the plan
+    /// leaves out stay where they are:
+    ///
+    /// ```json
+    /// {"robots": [{"name": "A", "route": [{"x": 1, "y": 2, "t": 0}, {"x": 2, "y": 2, "t": 1}]}]}
+    /// ```
+    pub fn with_plan(mut self, plan: &Json) -> Result<Self> {
+        let robots = plan
+            .get("robots")
+            .and_then(Json::as_array)
+            .ok_or_else(|| miette!("Plan has no 'robots' list"))?;
+        for robot in self.robots.values_mut() {
+            robot.freeze(Route::default());
+        }
+        for robot in robots {
+            let name = robot
+                .get("name")
+                .and_then(Json::as_str)
+                .and_then(|n| n.chars().exactly_one().ok())
+                .ok_or_else(|| miette!("Plan has a robot without single letter 'name'"))?;
+            let route = robot
+                .get("route")
+                .and_then(Json::as_array)
+                .ok_or_else(|| miette!("Plan has no 'route' of robot '{name}'"))?
+                .iter()
+                .map(|l| {
+                    // Whole numbers which neither are negative nor overflow
+                    let field = |key, max: f64| {
+                        let value = l.get(key).and_then(Json::as_f64)?;
+                        (value.fract() == 0. && (0. ..=max).contains(&value)).then_some(value)
+                    };
+                    let coordinate = |key| field(key, i32::MAX as f64).map(|c| c as i32);
+                    Some(Location {
+                        position: Vertex::new(coordinate("x")?, coordinate("y")?),
+                        time: field("t", Time::MAX as f64)? as Time,
+                    })
+                })
+                .collect::<Option<Route>>()
+                .ok_or_else(|| {
+                    miette!(
+                        "Plan has an invalid location of robot '{name}', expected whole numbers \
+                         x, y & t of at least 0"
+                    )
+                })?;
+            let start = self.robots.get(&name).map(|r| r.position().0);
+            let first = route.iter().next().map(|l| l.position);
+            if let Some((start, first)) = start.zip(first)
+                && start != first
+            {
+                return Err(miette!(
+                    "Plan lets robot '{name}' start on {first} instead of {start}"
+                ));
+            }
+            self.freeze(name, route)?;
+        }
+        Ok(self)
+    }
+
+    /// Like [Self::with_plan], but read the plan from a JSON `file`
+    pub fn with_plan_file<P: AsRef<Path>>(self, file: P) -> Result<Self> {
+        let file = file.as_ref().display().to_string();
+        let content = std::fs::read_to_string(&file).map_err(|e| miette!("{file}: {e}"))?;
+        Json::parse(&content)
+            .and_then(|json| self.with_plan(&json))
+            .map_err(|e| miette!("{file}: {e}"))
+    }
+}
+
+/// Where `robot` is at `t`, staying on its last cell afterwards unless it delivers. `None`
+/// before its route starts or once it delivered. Robots without any route stay where they are
+fn position(robot: &Robot, t: Time) -> Option<Vertex> {
+    let route = robot.route();
+    let Some((first, last)) = route.iter().next().zip(route.iter().last()) else {
+        return (!robot.delivers()).then_some(robot.position().0);
+    };
+    match t {
+        t if t < first.time => None,
+        t if t > last.time && robot.delivers() => None,
+        t if t > last.time => Some(last.position),
+        t => route
+            .iter()
+            .take_while(|l| l.time <= t)
+            .last()
+            .map(|l| l.position),
+    }
+}
+
+/// Whether `robot` already parked at `t` for good, i.e. its route is over
+fn parked(robot: &Robot, t: Time) -> bool {
+    robot.route().iter().last().is_none_or(|l| t > l.time)
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = self.messages().iter().map(|m| English.format(m)).join("\n");
+        write!(f, "{line}")
+    }
+}
@@ -0,0 +1,45 @@
+//! Small maps whose optimal solutions are known
+//!
+//! Optimal solvers have to reach these values exactly, while suboptimal ones like PBS need to
+//! stay within [PBS_FACTOR] of them. Optima of sum of costs & makespan might come from
+//! different solutions.
+
+/// A map with the best achievable key figures
+pub struct Fixture {
+    pub map: &'static str,
+    /// Lowest possible sum of all robots' route durations
+    pub cost: usize,
+    /// Lowest possible time of the last robot arriving
+    pub makespan: usize,
+}
+
+/// How much worse than the optimum PBS may be
+pub const PBS_FACTOR: f32 = 1.25;
+
+pub const ALL: [Fixture; 4] = [
+    // Nothing in the way
+    Fixture {
+        map: "../maps/fixtures/single.txt",
+        cost: 4,
+        makespan: 4,
+    },
+    // Side by side without ever meeting
+    Fixture {
+        map: "../maps/fixtures/parallel.txt",
+        cost: 8,
+        makespan: 4,
+    },
+    // Both paths are unique, so one has to wait a step at the crossing
+    Fixture {
+        map: "../maps/fixtures/crossing.txt",
+        cost: 9,
+        makespan: 5,
+    },
+    // One robot dodges into the niche: either B waits in it (A=4, B=7) or A waits for B to
+    // leave it again (A=5, B=6)
+    Fixture {
+        map: "../maps/fixtures/niche.txt",
+        cost: 11,
+        makespan: 6,
+    },
+];
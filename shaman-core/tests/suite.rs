@@ -0,0 +1,3292 @@
+use itertools::Itertools;
+use miette::{Diagnostic, Severity};
+use rstest::rstest;
+use shaman_core::{
+    Action, Algorithm, Audit, Calibration, Collision, Config, Control, CostModel, Delay, English,
+    Event, Flow, Format, Grade, Heuristic, Itinerary, Json, Leg, Locale, Location, Lookahead,
+    Message, Messages, MovementModel, Notification, Objective, Order, Outcome, Pbs, Placement,
+    Plan, Planner, PlannerConfig, RandomSource, Rating, Rejection, Repair, RightOfWay, Rng, Route,
+    Script, Shaman, ShamanBuilder, ShamanError, Simulation, Solution, Time, ToJson, Trace, Vertex,
+    Violation, color_for,
+};
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+mod fixtures;
+
+#[rstest]
+fn regression(#[files("../maps/*.txt")] file: PathBuf) {
+    let solved = Shaman::parse(file).unwrap().solve().unwrap();
+    assert_eq!(solved.violations(), []);
+}
+
+#[test]
+fn validation_catches_every_kind_of_violation() {
+    let conflicted = Shaman::parse("../maps/swap.txt").unwrap();
+    assert!(
+        conflicted
+            .violations()
+            .iter()
+            .any(|v| matches!(v, Violation::Vertex { .. } | Violation::Edge { .. }))
+    );
+
+    let sim = ShamanBuilder::new(4, 2)
+        .block(Vertex::new(1, 1))
+        .robot('A', Vertex::new(0, 0), Vertex::new(3, 0))
+        .robot('B', Vertex::new(0, 1), Vertex::new(2, 1))
+        .build()
+        .unwrap();
+    let plan = Json::parse(
+        r#"{"robots": [
+            {"name": "A", "route": [{"x": 0, "y": 0, "t": 0}, {"x": 2, "y": 0, "t": 1},
+                                    {"x": 2, "y": 0, "t": 3}]},
+            {"name": "B", "route": [{"x": 0, "y": 1, "t": 0}, {"x": 1, "y": 1, "t": 1}]}
+        ]}"#,
+    )
+    .unwrap();
+    let violations = sim.clone().with_plan(&plan).unwrap().violations();
+    assert_eq!(
+        violations,
+        [
+            Violation::Jump {
+                robot: 'A',
+                from: Vertex::new(0, 0),
+                to: Vertex::new(2, 0),
+                t: 0
+            },
+            Violation::Timing {
+                robot: 'A',
+                from: 1,
+                to: 3
+            },
+            Violation::MissedGoal {
+                robot: 'A',
+                goal: Vertex::new(3, 0)
+            },
+            Violation::Blocked {
+                robot: 'B',
+                v: Vertex::new(1, 1),
+                t: 1
+            },
+            Violation::MissedGoal {
+                robot: 'B',
+                goal: Vertex::new(2, 1)
+            },
+        ]
+    );
+    assert_eq!(violations[0].to_string(), "A jumps 0/0 → 2/0@0");
+    let message = &violations[3].messages()[0];
+    assert_eq!(message.id, "violation.blocked");
+    assert_eq!(message.get("robot"), Some(&Json::String("B".into())));
+    assert_eq!(message.get("v"), Some(&Vertex::new(1, 1).to_json()));
+    assert_eq!(English.format(message), "B is on blocked cell 1/1@1");
+
+    let elsewhere =
+        Json::parse(r#"{"robots": [{"name": "A", "route": [{"x": 1, "y": 0, "t": 0}]}]}"#).unwrap();
+    let error = sim.clone().with_plan(&elsewhere).unwrap_err().to_string();
+    assert_eq!(error, "Plan lets robot 'A' start on 1/0 instead of 0/0");
+
+    // Robots left out of the plan stay put, in the way of everybody else
+    let passing = Json::parse(
+        r#"{"robots": [{"name": "B", "route": [{"x": 0, "y": 1, "t": 0}, {"x": 0, "y": 0, "t": 1},
+                                               {"x": 1, "y": 0, "t": 2}, {"x": 2, "y": 0, "t": 3},
+                                               {"x": 2, "y": 1, "t": 4}]}]}"#,
+    )
+    .unwrap();
+    let violations = sim.clone().with_plan(&passing).unwrap().violations();
+    assert_eq!(
+        violations,
+        [
+            Violation::MissedGoal {
+                robot: 'A',
+                goal: Vertex::new(3, 0)
+            },
+            Violation::Goal {
+                robots: ('A', 'B'),
+                v: Vertex::new(0, 0),
+                t: 1
+            },
+        ]
+    );
+
+    for location in [r#""x": 0.5, "y": 0"#, r#""x": -1, "y": 0"#] {
+        let plan = format!(r#"{{"robots": [{{"name": "A", "route": [{{{location}, "t": 0}}]}}]}}"#);
+        let error = sim
+            .clone()
+            .with_plan(&Json::parse(&plan).unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("invalid location of robot 'A'"));
+    }
+}
+
+#[rstest]
+#[case::no_path("../maps/impossible/no-path.txt", "No route found")]
+#[case::invalid_symbol("../maps/impossible/invalid-symbol.txt", "Expected either an obstacle")]
+#[case::no_space_to_evade("../maps/impossible/no-space-to-evade.txt", "Ran out of ideas")]
+#[case::empty("../maps/impossible/empty.txt", "Map contains no cells")]
+#[case::invalid_header("../maps/impossible/invalid-header.txt", "Expected a header directive")]
+#[case::over_capacity("../maps/impossible/over-capacity.txt", "can serve at most 2 robot(s)")]
+#[case::blocked_start(
+    "../maps/impossible/blocked-start.scen",
+    "Agent 1 starts on an obstacle at 4/2"
+)]
+#[case::too_many_agents(
+    "../maps/impossible/too-many-agents.scen",
+    "Scenario has 27 agents, but at most 26 robots can be named"
+)]
+fn impossible(#[case] file: &str, #[case] expectation: &str) {
+    let e = Shaman::parse(file).and_then(|s| s.solve()).unwrap_err();
+    let msg = format!("{e:#}");
+    assert!(
+        msg.contains(expectation),
+        "Expected that '{expectation}' would be part of the error but it wasn't: {msg}"
+    );
+}
+
+/// Compare frames before & after solving and after playback against `tests/goldens`. Run with
+/// `UPDATE_GOLDENS=1` to accept changed renderings
+#[rstest]
+fn renderings_match_goldens(#[files("../maps/*.txt")] file: PathBuf) {
+    let unsolved = Shaman::parse(&file).unwrap();
+    let mut sim = unsolved.clone().solve().unwrap();
+    let mut rendered = format!(
+        "unsolved\n{}solved\n{}",
+        unsolved.render_plain(),
+        sim.render_plain()
+    );
+    while sim.robots().any(|r| r.route().iter().next().is_some()) {
+        sim.simulate();
+    }
+    rendered += &format!("finished\n{}", sim.render_plain());
+
+    let golden = PathBuf::from("tests/goldens").join(file.file_name().unwrap());
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::write(&golden, &rendered).unwrap();
+    }
+    let expected = std::fs::read_to_string(&golden)
+        .unwrap_or_else(|e| panic!("{}: {e}, create it with UPDATE_GOLDENS=1", golden.display()));
+    assert_eq!(
+        rendered, expected,
+        "Rendering changed, accept it with UPDATE_GOLDENS=1"
+    );
+}
+
+#[test]
+fn maps_parse_from_strings_and_readers() {
+    let content = std::fs::read_to_string("../maps/swap.txt").unwrap();
+    let file = Shaman::parse("../maps/swap.txt").unwrap();
+    let string = Shaman::parse_str("swap", &content).unwrap();
+    let reader = Shaman::parse_reader("swap", content.as_bytes()).unwrap();
+    assert_eq!(string.to_string(), file.to_string());
+    assert_eq!(reader.to_string(), file.to_string());
+
+    let e = Shaman::parse_reader("network", &[0xff][..]).unwrap_err();
+    assert!(e.to_string().starts_with("network: "), "{e}");
+}
+
+#[rstest]
+fn actions_roundtrip(#[files("../maps/*.txt")] file: PathBuf) {
+    let sim = Shaman::parse(file).unwrap().solve().unwrap();
+    for robot in sim.robots() {
+        let route = robot.route();
+        let start = route.iter().next().unwrap();
+        let actions = route.to_actions().unwrap();
+        assert_eq!(&Route::from_actions(start, actions), route);
+    }
+}
+
+#[test]
+fn non_adjacent_vertices_have_no_action() {
+    let a = Vertex::new(0, 0);
+    assert_eq!(Action::try_from((a, Vertex::new(1, 0))).unwrap(), Action::E);
+    assert_eq!(Action::try_from((a, a)).unwrap(), Action::Wait);
+    assert_eq!(
+        Action::try_from((a, Vertex::new(1, 1))).unwrap(),
+        Action::SE
+    );
+    assert!(Action::try_from((a, Vertex::new(2, 1))).is_err());
+    assert!(Action::try_from((a, Vertex::new(0, 2))).is_err());
+}
+
+#[rstest]
+fn random_robots_are_reproducible(
+    #[values(Placement::Random, Placement::Corners, Placement::Edges)] placement: Placement,
+) {
+    let layout = Shaman::parse("../maps/maze.txt").unwrap();
+    let generate = |seed| {
+        ShamanBuilder::from(&layout)
+            .placement(placement)
+            .random_robots(4, seed)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(generate(7), generate(7));
+    ShamanBuilder::from(&layout)
+        .placement(placement)
+        .random_robots(4, 7)
+        .unwrap()
+        .build()
+        .unwrap();
+}
+
+/// Counts up, to tell apart which numbers were drawn from which source
+struct Counter(u64);
+
+impl RandomSource for Counter {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        self.0
+    }
+}
+
+#[test]
+fn random_sources_are_shared_by_the_caller() {
+    let layout = ShamanBuilder::from(&Shaman::parse("../maps/maze.txt").unwrap());
+    let seeded = layout.clone().random_robots(4, 7).unwrap();
+    let drawn = layout
+        .clone()
+        .random_robots_with(4, &mut Rng::new(7))
+        .unwrap();
+    assert_eq!(seeded.to_string(), drawn.to_string());
+
+    // Consecutive draws continue the same stream, instead of starting over
+    let mut rng = Counter(0);
+    let first = layout.clone().random_robots_with(4, &mut rng).unwrap();
+    let second = layout.clone().random_robots_with(4, &mut rng).unwrap();
+    assert_ne!(first.to_string(), second.to_string());
+    let mut again = Counter(0);
+    let replayed = layout.random_robots_with(4, &mut again).unwrap();
+    assert_eq!(first.to_string(), replayed.to_string());
+}
+
+/// Draws the given numbers, then zeros
+struct Sequence(Vec<u64>);
+
+impl RandomSource for Sequence {
+    fn next_u64(&mut self) -> u64 {
+        self.0.pop().unwrap_or_default()
+    }
+}
+
+#[test]
+fn random_numbers_below_a_bound_are_unbiased() {
+    // 2^64 leaves a remainder of 1 when divided by 3, so the highest number is redrawn
+    let mut rng = Sequence(vec![5, u64::MAX]);
+    assert_eq!(rng.below(3), 2);
+    let mut rng = Sequence(vec![5, u64::MAX - 1]);
+    assert_eq!(rng.below(3), (u64::MAX - 1) as usize % 3);
+    let mut rng = Sequence(vec![u64::MAX]);
+    assert_eq!(rng.below(4), 3);
+
+    let mut rng = Rng::new(7);
+    let mut counts = [0; 3];
+    (0..3000).for_each(|_| counts[rng.below(3)] += 1);
+    assert!(
+        counts.iter().all(|&c| (900..1100).contains(&c)),
+        "{counts:?}"
+    );
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn generators_of_rand_are_random_sources() {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    let layout = ShamanBuilder::from(&Shaman::parse("../maps/maze.txt").unwrap());
+    let draw = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        layout
+            .clone()
+            .random_robots_with(4, &mut rng)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(draw(7), draw(7));
+    assert_ne!(draw(7), draw(8));
+}
+
+#[test]
+fn crowded_maps_are_rated_harder() {
+    let difficulty = |file| Shaman::parse(file).unwrap().difficulty();
+    let escalator = difficulty("../maps/escalator.txt");
+    let fourway = difficulty("../maps/fourway.txt");
+    assert!(escalator.score() < fourway.score());
+    assert_eq!(fourway.rating(), Rating::Hard);
+}
+
+#[test]
+fn inflation_keeps_distance_to_obstacles() {
+    let builder = ShamanBuilder::new(9, 9).block(Vertex::new(4, 4));
+    let sim = builder
+        .clone()
+        .robot('A', Vertex::new(1, 4), Vertex::new(7, 4))
+        .build()
+        .unwrap()
+        .inflate(1)
+        .unwrap();
+    let layout = sim.layout();
+    assert!(layout.is_blocked(Vertex::new(3, 3)));
+    assert!(layout.is_blocked(Vertex::new(5, 5)));
+    assert!(!layout.is_blocked(Vertex::new(2, 2)));
+    // The edge of the map counts as a wall as well
+    assert!(layout.is_blocked(Vertex::new(0, 4)));
+    assert!(layout.is_blocked(Vertex::new(8, 8)));
+    let route = sim.robots().next().unwrap().route();
+    assert!(
+        route
+            .iter()
+            .all(|l| (l.position.y - 4).abs() >= 2 || (l.position.x - 4).abs() >= 2)
+    );
+
+    let at_the_edge = builder
+        .robot('A', Vertex::new(1, 4), Vertex::new(7, 4))
+        .robot('B', Vertex::new(2, 2), Vertex::new(8, 2))
+        .build()
+        .unwrap();
+    let e = at_the_edge.inflate(1).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Inflating obstacles by 1 cell(s) blocks the goal of robot 'B'"
+    );
+}
+
+#[test]
+fn weighted_robots_get_right_of_way() {
+    let sim = Shaman::parse("../maps/weighted.txt")
+        .unwrap()
+        .solve()
+        .unwrap();
+    let b = sim.robots().find(|r| r.name() == 'B').unwrap();
+    assert_eq!(b.weight(), 10.);
+    assert_eq!(b.route().duration(), 12);
+    assert!(sim.weighted_cost() > sim.cost() as f32);
+
+    // Only positive & finite weights keep costs comparable
+    for weight in ["0", "-2", "nan", "inf"] {
+        let map = format!("---\nweight A = {weight}\n---\n#####\n#A a#\n#####\n");
+        let e = Shaman::parse_str("weights", &map).unwrap_err();
+        assert!(
+            matches!(e.downcast_ref(), Some(ShamanError::InvalidHeader { .. })),
+            "weight {weight} was accepted: {e:?}"
+        );
+    }
+}
+
+#[test]
+fn higher_classes_never_yield() {
+    let sim = Shaman::parse("../maps/classes.txt")
+        .unwrap()
+        .solve()
+        .unwrap();
+    let duration = |name| {
+        let robot = sim.robots().find(|r| r.name() == name).unwrap();
+        robot.route().duration()
+    };
+    assert_eq!(duration('B'), 12);
+    assert!(duration('A') > 12);
+}
+
+#[test]
+fn frozen_routes_are_never_replanned() {
+    let mut sim = Shaman::parse("../maps/swap.txt").unwrap();
+    let start = Location {
+        position: Vertex::new(14, 2),
+        time: 0,
+    };
+    let actions = [Action::Wait; 3].into_iter().chain([Action::W; 12]);
+    let route = Route::from_actions(start, actions);
+    sim.freeze('B', route.clone()).unwrap();
+
+    let sim = sim.solve().unwrap();
+    let robot = |name| sim.robots().find(|r| r.name() == name).unwrap();
+    assert!(robot('B').is_frozen());
+    assert_eq!(robot('B').route(), &route);
+    assert!(robot('A').route().duration() > 12);
+}
+
+#[test]
+fn reservations_are_respected_by_all_robots() {
+    let mut sim = Shaman::parse("../maps/swap.txt").unwrap();
+    let corridor = (1..=3).map(|y| Vertex::new(8, y)).collect::<Vec<_>>();
+    sim.reserve(&corridor, 0..20).unwrap();
+
+    let sim = sim.solve().unwrap();
+    for robot in sim.robots() {
+        let crossing = robot.route().iter().find(|l| l.position.x == 8).unwrap();
+        assert!(crossing.time >= 20, "{} crossed too early", robot.name());
+    }
+}
+
+#[test]
+fn goals_reserved_late_are_waited_for() {
+    let mut sim = Shaman::parse_str("late", "#######\n#A   a#\n#######\n").unwrap();
+    sim.reserve(&[Vertex::new(5, 1)], 200..201).unwrap();
+    let a = sim.robots().next().unwrap();
+    assert_eq!(a.route().iter().last().unwrap().position, Vertex::new(5, 1));
+    assert!(a.route().duration() > 200);
+
+    // Reservations nobody can avoid are refused, leaving everything as it was
+    let config = PlannerConfig {
+        max_route_length: Some(10),
+        ..Default::default()
+    };
+    let mut sim = Shaman::parse_str("late", "#######\n#A   a#\n#######\n")
+        .unwrap()
+        .with_planner_config(config)
+        .unwrap();
+    let before = (
+        sim.checkpoint(),
+        sim.robots().next().unwrap().route().clone(),
+    );
+    assert!(sim.reserve(&[Vertex::new(5, 1)], 200..201).is_err());
+    let after = (
+        sim.checkpoint(),
+        sim.robots().next().unwrap().route().clone(),
+    );
+    assert_eq!(after, before);
+}
+
+#[rstest]
+fn solved_summary_has_no_conflicts(#[files("../maps/*.txt")] file: PathBuf) {
+    let summary = Shaman::parse(file).unwrap().solve().unwrap().summary();
+    assert!(!summary.conflicts);
+    let last = summary.arrivals.iter().map(|(_, t)| *t).max();
+    assert_eq!(last.unwrap_or_default(), summary.makespan);
+}
+
+#[rstest]
+fn no_solution_beats_the_lower_bound(
+    #[files("../maps/*.txt")] file: PathBuf,
+    #[values(MovementModel::FourConnected, MovementModel::EightConnected)] movement: MovementModel,
+) {
+    let sim = Shaman::parse(file)
+        .unwrap()
+        .with_movement(movement)
+        .unwrap();
+    let bound = sim.lower_bound();
+    let solved = sim.solve().unwrap();
+    assert_eq!(solved.lower_bound(), bound);
+    assert!(solved.cost() >= bound);
+    assert!(solved.summary().gap() >= 0.);
+    assert_eq!(
+        Solution::try_from(solved).unwrap().stats().lower_bound,
+        bound
+    );
+}
+
+#[test]
+fn checkpoints_restore_the_simulation_state() {
+    let mut sim = Shaman::parse("../maps/roundabout.txt")
+        .unwrap()
+        .solve()
+        .unwrap();
+    for _ in 0..3 {
+        sim.simulate();
+    }
+    let restored = Shaman::from_checkpoint("checkpoint", &sim.checkpoint()).unwrap();
+    assert_eq!(restored.time(), 3);
+    for robot in sim.robots() {
+        let other = restored
+            .robots()
+            .find(|r| r.name() == robot.name())
+            .unwrap();
+        assert_eq!(robot.position().0, other.position().0);
+        assert_eq!(robot.route(), other.route());
+    }
+    assert_eq!(sim.summary(), restored.summary());
+}
+
+#[test]
+fn checkpoints_restore_what_changed_while_simulating() {
+    let mut sim = Simulation::from(Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap());
+    sim.step().unwrap();
+    sim.step().unwrap();
+    sim.block(Vertex::new(8, 3)).unwrap();
+    sim.apply(Event::Task('B', Vertex::new(12, 3))).unwrap();
+    let mut sim = sim.into_inner();
+    sim.reserve(&[Vertex::new(5, 2)], 4..8).unwrap();
+    sim.close(&[Vertex::new(10, 2)], 6..).unwrap();
+    sim.exclude('A', [Vertex::new(6, 1)]).unwrap();
+    let route = sim
+        .robots()
+        .find(|r| r.name() == 'B')
+        .unwrap()
+        .route()
+        .clone();
+    sim.freeze('B', route).unwrap();
+
+    let checkpoint = sim.checkpoint();
+    let mut restored = Shaman::from_checkpoint("checkpoint", &checkpoint).unwrap();
+    assert_eq!(restored.checkpoint(), checkpoint);
+    for robot in sim.robots() {
+        let other = restored
+            .robots()
+            .find(|r| r.name() == robot.name())
+            .unwrap();
+        assert_eq!(robot.position().0, other.position().0);
+        assert_eq!(robot.route(), other.route());
+        assert_eq!(robot.goal().map(|g| g.0), other.goal().map(|g| g.0));
+        assert_eq!(robot.excluded(), other.excluded());
+        assert_eq!(robot.is_frozen(), other.is_frozen());
+        assert_eq!(robot.heading(), other.heading());
+    }
+
+    // Replanning respects the same obstacles, closures & reservations as before
+    sim.exclude('A', []).unwrap();
+    restored.exclude('A', []).unwrap();
+    let a = |s: &Shaman| {
+        s.robots()
+            .find(|r| r.name() == 'A')
+            .unwrap()
+            .route()
+            .clone()
+    };
+    assert_eq!(a(&sim), a(&restored));
+    for l in a(&restored).iter() {
+        assert_ne!(l.position, Vertex::new(8, 3));
+        assert!(!(l.position == Vertex::new(10, 2) && l.time >= 6));
+        assert!(!(l.position == Vertex::new(5, 2) && (4..8).contains(&l.time)));
+    }
+}
+
+#[test]
+fn chutes_serve_several_robots() {
+    let chute = Vertex::new(6, 2);
+    let mut sim = Shaman::parse("../maps/chute.txt").unwrap().solve().unwrap();
+    assert!(sim.conflicts().is_empty());
+    for robot in sim.robots() {
+        assert!(robot.delivers(), "{} should deliver", robot.name());
+        assert_eq!(robot.goal().unwrap().0, chute);
+    }
+    let arrivals = sim.summary().arrivals;
+    assert!(arrivals.iter().map(|(_, t)| t).all_unique(), "{arrivals:?}");
+
+    let rebuilt = ShamanBuilder::from(&sim).build().unwrap();
+    assert!(rebuilt.robots().all(|r| r.delivers()));
+
+    while sim.robots().any(|r| !r.is_delivered()) {
+        sim.simulate();
+    }
+    assert!(!sim.to_string().contains('●'));
+}
+
+#[test]
+fn all_letters_name_distinctly_colored_robots() {
+    let sim = ShamanBuilder::new(12, 8)
+        .random_robots(26, 3)
+        .unwrap()
+        .build()
+        .unwrap();
+    let names = sim.robots().map(|r| r.name()).sorted().collect::<String>();
+    assert_eq!(names, "ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+    assert!(sim.robots().map(|r| r.color()).all_unique());
+    for robot in sim.robots() {
+        assert_eq!(robot.color(), color_for(&robot.name().to_string()));
+    }
+    assert_eq!(color_for("A").to_string(), "#0000ff");
+    assert!(('A'..='Z').map(|n| color_for(&n.to_string())).all_unique());
+}
+
+#[rstest]
+fn heuristics_agree_on_optimal_routes(
+    #[files("../maps/*.txt")] file: PathBuf,
+    #[values(Heuristic::Euclidean, Heuristic::TrueDistance)] heuristic: Heuristic,
+) {
+    let energy = |sim: &Shaman| {
+        sim.robots()
+            .map(|r| CostModel::default().total(&r.route().to_actions().unwrap()))
+            .sum::<f32>()
+    };
+    let manhattan = Shaman::parse(&file).unwrap();
+    let other = manhattan.clone().with_heuristic(heuristic).unwrap();
+    assert!(
+        (energy(&manhattan) - energy(&other)).abs() < 1e-3,
+        "{} vs {}",
+        energy(&manhattan),
+        energy(&other)
+    );
+
+    for robot in manhattan.robots() {
+        let Some((goal, _)) = robot.goal() else {
+            continue; // Parks or patrols
+        };
+        let start = robot.position().0;
+        let layout = manhattan.layout();
+        let estimate = |h: Heuristic| h.estimate(layout, start, goal);
+        assert!(estimate(Heuristic::Euclidean) <= estimate(Heuristic::Manhattan));
+        assert!(estimate(Heuristic::Manhattan) <= estimate(Heuristic::TrueDistance));
+    }
+}
+
+#[test]
+fn ramps_are_only_costly_uphill() {
+    let ramp = Vertex::new(2, 1);
+    let sim = Shaman::parse("../maps/ramp.txt").unwrap().solve().unwrap();
+    let a = sim.robots().next().unwrap();
+    assert!(
+        a.route().iter().all(|l| l.position != ramp),
+        "{:?}",
+        a.route()
+    );
+
+    let downhill = ShamanBuilder::from(&sim)
+        .clear_robots()
+        .robot('A', Vertex::new(3, 1), Vertex::new(1, 1))
+        .build()
+        .unwrap();
+    let a = downhill.robots().next().unwrap();
+    assert!(
+        a.route().iter().any(|l| l.position == ramp),
+        "{:?}",
+        a.route()
+    );
+    assert_eq!(a.route().duration(), 2);
+
+    // Diagonal ramps are costly uphill just the same
+    let diagonal = |cost| {
+        let sim = ShamanBuilder::new(3, 3)
+            .robot('A', Vertex::new(0, 0), Vertex::new(2, 2))
+            .edge_cost(Vertex::new(0, 0), Vertex::new(1, 1), cost)
+            .setting("movement", "eight-connected")
+            .build()
+            .unwrap();
+        let a = sim.robots().next().unwrap();
+        a.route()
+            .iter()
+            .any(|l| l.position == Vertex::new(1, 1) && l.time == 1)
+    };
+    assert!(diagonal(1.));
+    assert!(!diagonal(10.));
+
+    // Cheaper edges would let the heuristics overestimate
+    for cost in ["0.5", "inf"] {
+        let map = format!("---\ncost 1/1 > 2/1 = {cost}\n---\n#####\n#A a#\n#####\n");
+        assert!(Shaman::parse_str("cheap", &map).is_err());
+    }
+}
+
+#[test]
+fn conflicts_know_when_they_happen() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap();
+    let conflicts = sim.conflicts();
+    let conflict = conflicts.iter().exactly_one().unwrap();
+    assert_eq!(conflict.robots, ('A', 'B'));
+    let middle = Location {
+        position: Vertex::new(8, 2),
+        time: 6,
+    };
+    assert!(conflict.locations.contains(&middle), "{conflict:?}");
+    assert!(conflict.locations.is_sorted_by_key(|l| l.time));
+    for location in &conflict.locations {
+        assert!(conflict.cells.contains(&location.position));
+    }
+}
+
+#[test]
+fn occupied_goals_refuse_tasks() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let mut sim = Simulation::from(sim);
+    let e = sim.apply(Event::Task('A', Vertex::new(2, 2))).unwrap_err();
+    assert!(
+        e.to_string().contains("can serve at most 1 robot(s)"),
+        "{e}"
+    );
+}
+
+#[test]
+fn failed_tasks_leave_the_robot_as_it_was() {
+    let map = "#######\n#A#   #\n#a#   #\n#######\n";
+    let sim = Shaman::parse_str("walled", map).unwrap().solve().unwrap();
+    let mut sim = Simulation::from(sim);
+    let unreachable = Vertex::new(4, 1);
+    assert!(sim.apply(Event::Task('A', unreachable)).is_err());
+    let a = sim.shaman().robots().next().unwrap();
+    assert_eq!(a.goal().unwrap().0, Vertex::new(1, 2));
+    assert_eq!(a.route().iter().last().unwrap().position, Vertex::new(1, 2));
+    assert!(sim.take_diffs().is_empty());
+
+    // Injected by a hook, the failure is reported & the timestep completes all the same
+    let log = Rc::new(RefCell::new(vec![]));
+    let logged = log.clone();
+    sim.on_notification(move |_, n| logged.borrow_mut().push(*n));
+    sim.on_tick(move |s| match s.time {
+        1 => Control::Inject(vec![Event::Task('A', unreachable)]),
+        _ => Control::Continue,
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    let failed = Notification::EventFailed(Event::Task('A', unreachable));
+    assert!(log.borrow().contains(&failed));
+    assert_eq!(failed.to_string(), "task 4/1 for A failed");
+    let a = sim.shaman().robots().next().unwrap();
+    assert_eq!(a.position().0, Vertex::new(1, 2));
+}
+
+#[test]
+fn tick_hooks_can_pause_and_inject_events() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let mut sim = Simulation::from(sim);
+    sim.on_tick(|s| match s.time {
+        2 => Control::Pause,
+        4 => Control::Inject(vec![Event::Task('A', Vertex::new(8, 3))]),
+        _ => Control::Continue,
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Paused);
+    assert_eq!(sim.shaman().time(), 2);
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+
+    let shaman = sim.into_inner();
+    let a = shaman.robots().find(|r| r.name() == 'A').unwrap();
+    assert_eq!(a.position().0, Vertex::new(8, 3));
+}
+
+#[test]
+fn simulations_notify_about_what_robots_did() {
+    // Unsolved, so both robots run into each other in the middle of the corridor
+    let sim = ShamanBuilder::new(5, 1)
+        .robot('A', Vertex::new(0, 0), Vertex::new(3, 0))
+        .robot('B', Vertex::new(4, 0), Vertex::new(1, 0))
+        .build()
+        .unwrap();
+    let mut sim = Simulation::from(sim);
+    let log = Rc::new(RefCell::new(vec![]));
+    let logged = log.clone();
+    sim.on_notification(move |t, n| logged.borrow_mut().push((t, *n)));
+    sim.on_tick(|s| {
+        assert_eq!(s.time == 1, s.notifications.is_empty());
+        Control::Continue
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+
+    let log = log.borrow();
+    let moved = |robot, from, to| Notification::RobotMoved {
+        robot,
+        from: Vertex::new(from, 0),
+        to: Vertex::new(to, 0),
+    };
+    assert_eq!(log[..2], [(2, moved('A', 0, 1)), (2, moved('B', 4, 3))]);
+    assert!(log.contains(&(
+        3,
+        Notification::ConflictObserved {
+            robots: ('A', 'B'),
+            at: Vertex::new(2, 0)
+        }
+    )));
+    let arrived = Notification::RobotArrived {
+        robot: 'A',
+        goal: Vertex::new(3, 0),
+    };
+    assert!(log.contains(&(4, arrived)));
+    assert_eq!(sim.notifications().len(), 4);
+    assert_eq!(arrived.to_string(), "A arrived at 3/0");
+}
+
+#[test]
+fn scripts_replay_events_at_their_time() {
+    let script = Script::load("../maps/swap.events").unwrap();
+    assert_eq!(
+        script.events,
+        [
+            (3, Event::Task('B', Vertex::new(8, 3))),
+            (12, Event::Block(Vertex::new(5, 2)))
+        ]
+    );
+    assert_eq!(Script::parse(&script.to_string()).unwrap(), script);
+
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let mut sim = Simulation::from(sim);
+    sim.script(script);
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    let shaman = sim.into_inner();
+    let b = shaman.robots().find(|r| r.name() == 'B').unwrap();
+    assert_eq!(b.position().0, Vertex::new(8, 3));
+    assert!(shaman.layout().is_blocked(Vertex::new(5, 2)));
+
+    let e = Script::parse("at 1 block 2/2\nat two block 3/3").unwrap_err();
+    assert_eq!(e.to_string(), "2: invalid event");
+
+    let e = Script::load("../maps/swap.events.yaml").unwrap_err();
+    assert!(e.to_string().contains("not YAML"), "{e}");
+}
+
+#[test]
+fn idle_robots_drift_to_staging_cells() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let staging = sim.staging_cells(2);
+    assert_eq!(staging.len(), 2);
+    for robot in sim.robots() {
+        assert!(!staging.contains(&robot.goal().unwrap().0));
+    }
+
+    let mut sim = Simulation::from(sim);
+    sim.reposition(staging.clone());
+    sim.on_tick(|s| {
+        let positions = s.shaman.robots().map(|r| r.position().0).collect_vec();
+        assert!(positions.iter().all_unique(), "collision at t={}", s.time);
+        Control::Continue
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    for robot in sim.shaman().robots() {
+        assert!(staging.contains(&robot.position().0), "{}", robot.name());
+    }
+}
+
+#[test]
+fn idle_robots_pass_the_token_for_pooled_tasks() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let tasks = [
+        (0, Vertex::new(8, 3)),
+        (5, Vertex::new(2, 3)),
+        (20, Vertex::new(14, 3)),
+        (20, Vertex::new(0, 0)), // on a wall, so nobody ever takes it
+    ];
+    let mut sim = Simulation::from(sim);
+    sim.pass_token(tasks);
+    sim.on_tick(|s| {
+        let positions = s.shaman.robots().map(|r| r.position().0).collect_vec();
+        assert!(positions.iter().all_unique(), "collision at t={}", s.time);
+        Control::Continue
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    assert_eq!(sim.pending_tasks(), [(20, Vertex::new(0, 0))]);
+    assert!(sim.shaman().time() > 20);
+    let positions = sim.shaman().robots().map(|r| r.position().0).collect_vec();
+    assert!(positions.contains(&Vertex::new(14, 3)));
+}
+
+#[test]
+fn lifelong_robots_work_off_their_queues() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let queues = [
+        ('A', [Vertex::new(8, 1), Vertex::new(12, 3)]),
+        ('B', [Vertex::new(8, 3), Vertex::new(4, 1)]),
+    ];
+    let mut sim = Simulation::from(sim);
+    for (name, goals) in queues {
+        sim.queue(name, goals);
+    }
+    sim.on_tick(|s| {
+        let positions = s.shaman.robots().map(|r| r.position().0).collect_vec();
+        assert!(positions.iter().all_unique(), "collision at t={}", s.time);
+        Control::Continue
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    for (name, goals) in queues {
+        assert_eq!(sim.queued(name).count(), 0);
+        let robot = sim.shaman().robots().find(|r| r.name() == name).unwrap();
+        assert_eq!(robot.position().0, goals[1]);
+    }
+}
+
+#[test]
+fn lifelong_robots_wait_for_their_queued_goals_to_become_servable() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let swapped = sim.robots().map(|r| r.route().duration()).max().unwrap();
+    let mut sim = Simulation::from(sim);
+    // A's next goal is where B parks, until B gets a task of its own a while later
+    sim.queue('A', [Vertex::new(2, 2)]);
+    sim.on_tick(move |s| match s.time == swapped + 8 {
+        true => Control::Inject(vec![Event::Task('B', Vertex::new(8, 3))]),
+        false => Control::Continue,
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    assert_eq!(sim.queued('A').count(), 0);
+    let robot = sim.shaman().robots().find(|r| r.name() == 'A').unwrap();
+    assert_eq!(robot.position().0, Vertex::new(2, 2));
+
+    // Unless it gives up before
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let mut sim = Simulation::from(sim);
+    sim.queue('A', [Vertex::new(2, 2)]).patience(5);
+    sim.on_tick(move |s| match s.time == swapped + 8 {
+        true => Control::Inject(vec![Event::Task('B', Vertex::new(8, 3))]),
+        false => Control::Continue,
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    assert_eq!(sim.queued('A').count(), 1);
+    assert!(sim.shaman().time() < swapped + 8);
+}
+
+#[test]
+fn tick_hooks_can_abort() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let mut sim = Simulation::from(sim);
+    sim.on_tick(|s| {
+        if s.time == 3 {
+            Control::Abort
+        } else {
+            Control::Continue
+        }
+    });
+    assert_eq!(sim.run().unwrap(), Outcome::Aborted);
+    assert!(sim.is_finished());
+    assert_eq!(sim.shaman().time(), 3);
+}
+
+#[test]
+fn blocking_cells_impacts_robots_crossing_them() {
+    let solution =
+        Solution::try_from(Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap()).unwrap();
+    let unused = solution.impact_of_block(Vertex::new(8, 3));
+    assert!(unused.is_feasible());
+
+    let corridor = solution.impact_of_block(Vertex::new(8, 2));
+    assert!(corridor.is_feasible());
+    assert!(!corridor.affected.is_empty());
+    assert!(corridor.total_delay() >= 0);
+
+    let start = solution.impact_of_block(Vertex::new(2, 2));
+    assert!(start.affected.contains(&('A', Delay::Blocked)));
+}
+
+#[test]
+fn corridor_cells_are_bottlenecks() {
+    let bottlenecks = Shaman::parse("../maps/roundabout.txt")
+        .unwrap()
+        .bottlenecks();
+    let busiest = bottlenecks.first().unwrap();
+    assert_eq!(busiest.traffic, 4);
+    assert!(busiest.cut);
+    assert_eq!(busiest.detour, None);
+}
+
+#[test]
+fn replaced_routes_are_validated_or_repaired() {
+    let solution =
+        Solution::try_from(Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap()).unwrap();
+    let start = Location {
+        position: Vertex::new(14, 2),
+        time: 0,
+    };
+    let straight = Route::from_actions(start, [Action::W; 12]);
+
+    let rejection = solution
+        .replace_route('B', straight.clone(), Repair::Reject)
+        .unwrap_err();
+    let Rejection::Violations(violations) = rejection else {
+        panic!("{rejection:?}")
+    };
+    assert!(violations.iter().any(|v| matches!(
+        v,
+        Violation::Vertex {
+            robots: ('A', 'B'),
+            ..
+        }
+    )));
+
+    let rejection = solution
+        .replace_route('Z', straight.clone(), Repair::Replan)
+        .unwrap_err();
+    assert_eq!(rejection, Rejection::UnknownRobot('Z'));
+
+    let jump = [
+        start,
+        Location {
+            position: Vertex::new(11, 2),
+            time: 1,
+        },
+    ];
+    let rejection = solution
+        .replace_route('B', Route::from_iter(jump), Repair::Replan)
+        .unwrap_err();
+    let Rejection::Violations(violations) = rejection else {
+        panic!("{rejection:?}")
+    };
+    assert!(
+        violations
+            .iter()
+            .any(|v| matches!(v, Violation::Jump { robot: 'B', .. }))
+    );
+
+    let repaired = solution
+        .replace_route('B', straight.clone(), Repair::Replan)
+        .unwrap();
+    assert!(repaired.shaman().conflicts().is_empty());
+    let b = repaired
+        .shaman()
+        .robots()
+        .find(|r| r.name() == 'B')
+        .unwrap();
+    assert_eq!(b.route(), &straight);
+}
+
+#[test]
+fn robots_never_enter_their_exclusion_zones() {
+    let sim = Shaman::parse("../maps/exclusion.txt")
+        .unwrap()
+        .solve()
+        .unwrap();
+    let a = sim.robots().find(|r| r.name() == 'A').unwrap();
+    assert_eq!(a.excluded().len(), 18);
+    assert!(
+        a.route()
+            .iter()
+            .all(|l| !a.excluded().contains(&l.position))
+    );
+
+    // Rectangles reaching off the map only exclude what's on it
+    let map = std::fs::read_to_string("../maps/exclusion.txt").unwrap();
+    let map = map.replacen("---\n", "---\nexclude B = 4/3..2000000000/2000000000\n", 1);
+    let sim = Shaman::parse_str("huge", &map).unwrap();
+    let b = sim.robots().find(|r| r.name() == 'B').unwrap();
+    assert_eq!(b.excluded().len(), 26);
+}
+
+#[rstest]
+fn stats_account_for_every_timestep(#[files("../maps/*.txt")] file: PathBuf) {
+    let solution = Solution::try_from(Shaman::parse(file).unwrap().solve().unwrap()).unwrap();
+    let stats = solution.stats();
+    for (robot, stats) in solution
+        .shaman()
+        .robots()
+        .sorted_by_key(|r| r.name())
+        .zip(&stats.robots)
+    {
+        assert_eq!(robot.name(), stats.name);
+        assert_eq!(stats.waits, robot.waits());
+        assert!(stats.moves + stats.waits <= robot.route().duration());
+        assert!(stats.turns <= stats.moves);
+    }
+    let json = stats.to_json().to_string();
+    assert!(json.starts_with("{\"energy\":"), "{json}");
+}
+
+#[test]
+fn stats_measure_energy_with_the_planners_costs() {
+    let sim = ShamanBuilder::new(3, 3)
+        .robot('A', Vertex::new(0, 0), Vertex::new(1, 1))
+        .pace('A', 2)
+        .build()
+        .unwrap()
+        .with_movement(MovementModel::EightConnected)
+        .unwrap();
+    let mut config = sim.planner_config();
+    config.costs.wait = 2.;
+    config.costs.start = 4.;
+    let solution = Solution::try_from(sim.with_planner_config(config).unwrap().solve().unwrap());
+    let stats = solution.unwrap().stats();
+
+    // Waiting for its pace doesn't count, the diagonal costs √2 times as much as a straight step
+    assert_eq!(stats.robots[0].moves, 1);
+    assert_eq!(stats.robots[0].waits, 0);
+    assert_eq!(stats.energy(), 2. + 4. * std::f32::consts::SQRT_2);
+}
+
+#[test]
+fn trailing_waits_dont_change_routes() {
+    let start = Location {
+        position: Vertex::new(0, 0),
+        time: 0,
+    };
+    let short = Route::from_actions(start, [Action::Wait, Action::E, Action::S]);
+    let mut long = Route::from_actions(start, [Action::Wait, Action::E, Action::S, Action::Wait]);
+    assert_eq!(short, long);
+    assert_eq!(short.hash_key(), long.hash_key());
+
+    long.canonicalize();
+    assert_eq!(long.duration(), 3);
+    assert_ne!(short, Route::from_actions(start, [Action::E, Action::S]));
+}
+
+#[rstest]
+fn flows_count_every_move_into_a_cell(
+    #[values(MovementModel::FourConnected, MovementModel::EightConnected)] movement: MovementModel,
+) {
+    let sim = Shaman::parse("../maps/swap.txt")
+        .unwrap()
+        .with_movement(movement)
+        .unwrap();
+    let solution = Solution::try_from(sim.solve().unwrap()).unwrap();
+    let moves = solution
+        .stats()
+        .robots
+        .iter()
+        .map(|r| r.moves)
+        .sum::<usize>();
+    let flows = solution.flows();
+    assert_eq!(flows.iter().map(Flow::total).sum::<usize>(), moves);
+    let diagonals = flows
+        .iter()
+        .map(|f| f.ne + f.nw + f.se + f.sw)
+        .sum::<usize>();
+    assert_eq!(diagonals > 0, movement == MovementModel::EightConnected);
+    assert!(flows.iter().all(|f| f.arrow().is_some()));
+    let json = Json::array(&flows).to_string();
+    assert!(json.starts_with("[{\"cell\":{\"x\":"), "{json}");
+}
+
+#[test]
+fn pinned_priorities_guide_the_solver() {
+    let shaman = Shaman::parse("../maps/swap.txt").unwrap();
+    let duration = |sim: &Shaman, name| {
+        let robot = sim.robots().find(|r| r.name() == name).unwrap();
+        robot.route().duration()
+    };
+    for (boss, subordinate) in [('A', 'B'), ('B', 'A')] {
+        let sim = Pbs::from(shaman.clone())
+            .with_priorities(&[(boss, subordinate)])
+            .unwrap()
+            .solve()
+            .unwrap();
+        assert_eq!(duration(&sim, boss), 12);
+        assert!(duration(&sim, subordinate) > 12);
+    }
+
+    let cyclic = Pbs::from(shaman.clone()).with_priorities(&[('A', 'B'), ('B', 'A')]);
+    assert!(format!("{:#}", cyclic.unwrap_err()).contains("cycle"));
+    assert!(Pbs::from(shaman).with_priorities(&[('A', 'Z')]).is_err());
+}
+
+#[rstest]
+fn delays_are_attributed_completely(#[files("../maps/*.txt")] file: PathBuf) {
+    let solution = Solution::try_from(Shaman::parse(file).unwrap().solve().unwrap()).unwrap();
+    for attribution in solution.delays() {
+        let explained = attribution.causes.iter().map(|(_, d)| d).sum::<usize>();
+        assert_eq!(attribution.delay, explained + attribution.unexplained);
+        assert!(
+            attribution
+                .causes
+                .iter()
+                .all(|(n, _)| *n != attribution.name)
+        );
+    }
+}
+
+#[test]
+fn yielding_robots_blame_their_boss() {
+    let solution =
+        Solution::try_from(Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap()).unwrap();
+    let delays = solution.delays();
+    let b = delays.iter().find(|a| a.name == 'B').unwrap();
+    assert_eq!(b.delay, 4);
+    assert!(b.causes.contains(&('A', 3)), "{b}");
+}
+
+#[test]
+fn routes_violating_anything_are_no_solution() {
+    let mut shaman = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let b = shaman.robots().find(|r| r.name() == 'B').unwrap();
+    let halfway = b.route().iter().take(4).collect::<Route>();
+    shaman.freeze('B', halfway).unwrap();
+    let e = Solution::try_from(shaman).unwrap_err();
+    let missed = Violation::MissedGoal {
+        robot: 'B',
+        goal: Vertex::new(2, 2),
+    };
+    assert_eq!(e, Rejection::Violations(vec![missed]));
+
+    let conflicted = Shaman::parse("../maps/swap.txt").unwrap();
+    let e = Solution::try_from(conflicted).unwrap_err();
+    assert!(
+        e.to_string().starts_with("The routes aren't feasible"),
+        "{e}"
+    );
+}
+
+#[rstest]
+fn pbs_stays_close_to_optimal(#[values(0, 1, 2, 3)] index: usize) {
+    let fixture = &fixtures::ALL[index];
+    let sim = Shaman::parse(fixture.map).unwrap().solve().unwrap();
+    assert!(sim.cost() >= fixture.cost, "{} beats optimum", fixture.map);
+    assert!(sim.summary().makespan >= fixture.makespan);
+    assert!(
+        sim.cost() as f32 <= fixture.cost as f32 * fixtures::PBS_FACTOR,
+        "{}: cost {} too far from optimum {}",
+        fixture.map,
+        sim.cost(),
+        fixture.cost
+    );
+}
+
+#[rstest]
+fn cbs_stays_close_to_optimal(#[values(0, 1, 2, 3)] index: usize) {
+    let fixture = &fixtures::ALL[index];
+    let sim = Shaman::parse(fixture.map)
+        .unwrap()
+        .solve_with(Algorithm::Cbs)
+        .unwrap();
+    assert!(sim.conflicts().is_empty());
+    assert!(sim.cost() >= fixture.cost, "{} beats optimum", fixture.map);
+    assert!(sim.cost() as f32 <= fixture.cost as f32 * fixtures::PBS_FACTOR);
+}
+
+#[test]
+fn auto_solver_picks_by_instance() {
+    let fourway = Shaman::parse("../maps/fourway.txt").unwrap();
+    assert_eq!(fourway.recommend_algorithm(), Algorithm::Cbs);
+    let crowd = Shaman::parse("../maps/crowd.txt").unwrap();
+    assert_eq!(crowd.recommend_algorithm(), Algorithm::Pbs);
+
+    let auto = fourway.clone().solve_with(Algorithm::Auto).unwrap();
+    assert!(auto.conflicts().is_empty());
+    assert!(auto.cost() <= fourway.solve_with(Algorithm::Pbs).unwrap().cost());
+
+    let lanes = ShamanBuilder::new(7, 5)
+        .robot('A', Vertex::new(1, 1), Vertex::new(5, 1))
+        .robot('B', Vertex::new(5, 3), Vertex::new(1, 3))
+        .build()
+        .unwrap();
+    assert_eq!(lanes.recommend_algorithm(), Algorithm::Prioritized);
+    let auto = lanes.solve_with(Algorithm::Auto).unwrap();
+    assert!(auto.conflicts().is_empty());
+}
+
+#[rstest]
+#[case::swap("../maps/swap.txt")]
+#[case::roundabout("../maps/roundabout.txt")]
+#[case::chute("../maps/chute.txt")]
+#[case::weighted("../maps/weighted.txt")]
+fn cbs_solves_without_collisions(
+    #[case] map: &str,
+    #[values(MovementModel::FourConnected, MovementModel::EightConnected)] movement: MovementModel,
+) {
+    let sim = Shaman::parse(map)
+        .unwrap()
+        .with_movement(movement)
+        .unwrap()
+        .solve_with(Algorithm::Cbs)
+        .unwrap();
+    let summary = sim.summary();
+    assert!(!summary.conflicts);
+    // Nobody may pass a cell, on which another robot already parked
+    for robot in sim.robots().filter(|r| !r.delivers()) {
+        let (goal, arrival) = (
+            robot.route().iter().last().unwrap(),
+            robot.route().duration(),
+        );
+        for other in sim.robots().filter(|o| o.name() != robot.name()) {
+            assert!(
+                other
+                    .route()
+                    .iter()
+                    .all(|l| l.position != goal.position || l.time < arrival),
+                "{} runs over {} parked at {}",
+                other.name(),
+                robot.name(),
+                goal.position
+            );
+        }
+    }
+}
+
+#[rstest]
+fn sipp_solves_without_collisions(#[files("../maps/*.txt")] file: PathBuf) {
+    let sim = Shaman::parse(&file)
+        .unwrap()
+        .with_planner(Planner::Sipp)
+        .unwrap()
+        .solve()
+        .unwrap();
+    assert!(sim.conflicts().is_empty());
+}
+
+#[rstest]
+fn diagonal_moves_never_cut_corners(
+    #[files("../maps/*.txt")] file: PathBuf,
+    #[values(Planner::AStar, Planner::Sipp)] planner: Planner,
+) {
+    let sim = Shaman::parse(&file)
+        .unwrap()
+        .with_movement(MovementModel::EightConnected)
+        .and_then(|s| s.with_planner(planner))
+        .and_then(|s| s.solve())
+        .unwrap();
+    assert!(sim.conflicts().is_empty());
+    let layout = sim.layout();
+    for route in sim.robots().map(|r| r.route()) {
+        for (a, b) in route.iter().tuple_windows() {
+            let (p, q) = (a.position, b.position);
+            assert!(
+                !layout.is_blocked(Vertex::new(p.x, q.y)),
+                "{p} > {q} cuts a corner"
+            );
+            assert!(
+                !layout.is_blocked(Vertex::new(q.x, p.y)),
+                "{p} > {q} cuts a corner"
+            );
+        }
+    }
+}
+
+#[test]
+fn diagonal_moves_shorten_open_routes() {
+    let sim = ShamanBuilder::new(6, 6)
+        .robot('A', Vertex::new(0, 0), Vertex::new(5, 5))
+        .build()
+        .unwrap();
+    let diagonal = sim
+        .clone()
+        .with_movement(MovementModel::EightConnected)
+        .unwrap();
+    let route = |s: &Shaman| s.robots().next().unwrap().route().clone();
+    assert!(route(&sim).duration() >= 10);
+    assert_eq!(route(&diagonal).to_actions().unwrap(), [Action::SE; 5]);
+}
+
+#[test]
+fn crossing_diagonals_collide() {
+    let start = |x, y| Location {
+        position: Vertex::new(x, y),
+        time: 0,
+    };
+    let a = Route::from_actions(start(0, 0), [Action::SE]);
+    let b = Route::from_actions(start(1, 0), [Action::SW]);
+    let c = Route::from_actions(start(1, 0), [Action::W]);
+    assert!(a.conflicts(&b));
+    assert!(!a.conflicts(&c));
+}
+
+#[test]
+fn sipp_waits_in_a_single_step() {
+    let goal = Vertex::new(3, 1);
+    let sim = Shaman::parse("../maps/ramp.txt").unwrap();
+    // Time expanded A* waits through reservations too, just in many more steps
+    let mut astar = sim.clone();
+    astar.reserve(&[goal], 0..40).unwrap();
+    assert_eq!(astar.robots().next().unwrap().route().duration(), 40);
+
+    let mut sim = sim.with_planner(Planner::Sipp).unwrap();
+    sim.reserve(&[goal], 0..40).unwrap();
+    let a = sim.robots().next().unwrap();
+    assert_eq!(a.route().iter().last().unwrap().position, goal);
+    assert_eq!(a.route().duration(), 40);
+    assert!(
+        a.route()
+            .iter()
+            .tuple_windows()
+            .all(|(l, m)| m.time == l.time + 1),
+        "{:?}",
+        a.route()
+    );
+}
+
+#[rstest]
+#[case::expansions(PlannerConfig { max_expansions: Some(200), ..Default::default() }, "expanding 200 states")]
+#[case::route_length(PlannerConfig { max_route_length: Some(20), ..Default::default() }, "route length limit of 20")]
+#[case::sipp(
+    PlannerConfig { planner: Planner::Sipp, max_expansions: Some(10), ..Default::default() },
+    "limit of 10 expanded states"
+)]
+fn planners_give_up_beyond_their_budget(#[case] config: PlannerConfig, #[case] expectation: &str) {
+    let e = Shaman::parse("../maps/maze.txt")
+        .unwrap()
+        .with_planner_config(config)
+        .unwrap_err();
+    let shaman_error = e.downcast_ref::<ShamanError>().unwrap();
+    assert!(matches!(shaman_error, ShamanError::BudgetExceeded { .. }));
+    assert!(e.to_string().contains(expectation), "{e}");
+}
+
+#[rstest]
+fn solvers_give_up_beyond_their_time(
+    #[values(Algorithm::Pbs, Algorithm::Cbs)] algorithm: Algorithm,
+) {
+    let config = PlannerConfig {
+        max_time: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    let sim = Shaman::parse("../maps/fourway.txt")
+        .unwrap()
+        .with_planner_config(config)
+        .unwrap();
+    let e = sim.solve_with(algorithm).unwrap_err();
+    assert!(e.to_string().contains("time limit of 0.000s"), "{e}");
+}
+
+#[test]
+fn cbs_gives_up_beyond_its_node_limit() {
+    let config = PlannerConfig {
+        max_nodes: Some(1),
+        ..Default::default()
+    };
+    let sim = Shaman::parse("../maps/fourway.txt")
+        .unwrap()
+        .with_planner_config(config)
+        .unwrap();
+    let e = sim.clone().solve_with(Algorithm::Cbs).unwrap_err();
+    let shaman_error = e.downcast_ref::<ShamanError>().unwrap();
+    assert!(matches!(shaman_error, ShamanError::BudgetExceeded { .. }));
+    assert!(e.to_string().contains("limit of 1 nodes"), "{e}");
+
+    let map = "---\nmax-nodes = 1\n---\nA a\n";
+    let sim = Shaman::parse_str("bounded", map).unwrap();
+    let sim = sim.with_map_settings().unwrap();
+    assert_eq!(sim.planner_config().max_nodes, Some(1));
+}
+
+#[test]
+fn solving_with_a_timeout_settles_or_fails() {
+    let solved = Shaman::parse("../maps/swap.txt")
+        .unwrap()
+        .solve_timeout(Duration::from_secs(10))
+        .unwrap();
+    assert!(solved.audit().solution.is_some());
+    assert!(!solved.audit().suboptimal);
+
+    let e = Shaman::parse("../maps/fourway.txt")
+        .unwrap()
+        .solve_timeout(Duration::ZERO)
+        .unwrap_err();
+    assert!(e.to_string().contains("time limit of 0.000s"), "{e}");
+
+    let settled = Audit {
+        decisions: vec![],
+        solution: Some(12.),
+        suboptimal: true,
+    };
+    assert_eq!(Audit::from_json(&settled.to_json()).unwrap(), settled);
+    assert!(
+        settled
+            .to_string()
+            .starts_with("timed out, settled for cost 12.0")
+    );
+}
+
+#[test]
+fn pbs_settles_for_a_conflict_free_idea_when_running_out_of_time() {
+    let open = |x: i32, y: i32| {
+        (1..=8).contains(&x) && (1..=9).contains(&y) // B's room
+            || y == 5 && (9..=30).contains(&x) // the corridor
+            || x == 21 && (1..=4).contains(&y) // A's way into it
+            || x == 17 && (6..=8).contains(&y) // A's way out of it
+            || x == 25 && (1..=8).contains(&y) // C's way across it
+    };
+    let mut builder = ShamanBuilder::new(32, 11);
+    for (x, y) in (0..32).cartesian_product(0..11) {
+        if !open(x, y) {
+            builder = builder.block(Vertex::new(x, y));
+        }
+    }
+    // All wait behind doors, so replanning B around A takes B's whole room for every timestep
+    // the door stays closed, long after the first decision was made. Making A yield is cheaper,
+    // but leaves B running into C, so only making B yield is a solution by then
+    let sim = builder
+        .robot('A', Vertex::new(21, 1), Vertex::new(17, 8))
+        .robot('B', Vertex::new(8, 5), Vertex::new(30, 5))
+        .robot('C', Vertex::new(25, 1), Vertex::new(25, 8))
+        .close(Vertex::new(9, 5), ..150)
+        .close(Vertex::new(21, 2), ..157)
+        .close(Vertex::new(25, 2), ..163)
+        .build()
+        .unwrap();
+    let optimal = sim.clone().solve_with(Algorithm::Pbs).unwrap();
+    assert_eq!(optimal.audit().decisions.len(), 2);
+
+    let settled = sim.solve_timeout(Duration::from_millis(25)).unwrap();
+    assert!(settled.audit().suboptimal);
+    assert_eq!(settled.audit().decisions.len(), 1);
+    assert_eq!(settled.violations(), []);
+    assert!(settled.cost() > optimal.cost());
+}
+
+#[test]
+fn maps_without_robots_solve_to_nothing() {
+    let sim = ShamanBuilder::new(3, 2).build().unwrap().solve().unwrap();
+    assert_eq!(sim.robots().count(), 0);
+    assert_eq!(sim.summary().makespan, 0);
+    assert!(sim.to_string().contains("╰───╯"));
+    assert!(ShamanBuilder::new(0, 0).build().is_err());
+}
+
+#[test]
+fn wide_glyphs_keep_frames_aligned() {
+    let mut sim = ShamanBuilder::new(3, 1).build().unwrap();
+    sim.annotate([(Vertex::new(1, 0), '🤖')]);
+    let frame = sim.to_string();
+    let lines = frame.lines().collect_vec();
+    assert_eq!(lines[0], "╭──────╮");
+    assert!(lines[1].starts_with("│  "), "{frame}");
+    assert!(lines[1].ends_with("  │"), "{frame}");
+    assert_eq!(lines[2], "╰──────╯");
+}
+
+#[test]
+fn archives_replay_the_packed_solution() {
+    let sim = Shaman::parse("../maps/roundabout.txt")
+        .unwrap()
+        .inflate(0)
+        .unwrap()
+        .solve()
+        .unwrap();
+    let archive = sim.pack();
+    let unpacked = Shaman::unpack("run.shmn", &archive).unwrap();
+    assert_eq!(unpacked.summary(), sim.summary());
+    assert_eq!(unpacked.to_string(), sim.to_string());
+    assert!(Shaman::unpack("run.shmn", &archive[..archive.len() / 2]).is_err());
+}
+
+#[rstest]
+fn audits_record_every_branch(#[files("../maps/*.txt")] file: PathBuf) {
+    let sim = Shaman::parse(file).unwrap().solve().unwrap();
+    let audit = sim.audit();
+    assert_eq!(audit.solution, Some(sim.weighted_cost()));
+    for decision in &audit.decisions {
+        let (a, b) = decision.robots;
+        assert!(!decision.cells.is_empty());
+        assert_eq!(
+            decision
+                .branches
+                .iter()
+                .map(|(boss, subordinate, _)| (*boss, *subordinate))
+                .collect_vec(),
+            vec![(a, b), (b, a)]
+        );
+    }
+    let unpacked = Shaman::unpack("run.shmn", &sim.pack()).unwrap();
+    assert_eq!(unpacked.audit(), audit);
+}
+
+#[test]
+fn robots_export_their_timed_waypoints() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    for robot in sim.robots() {
+        let json = robot.to_json();
+        assert_eq!(
+            json.get("name").and_then(Json::as_str),
+            Some(&*robot.name().to_string())
+        );
+        let times = json
+            .get("route")
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .map(|l| l.get("t").and_then(Json::as_f64).unwrap() as usize)
+            .collect_vec();
+        assert_eq!(times, (0..=robot.route().duration()).collect_vec());
+    }
+}
+
+#[rstest]
+fn reservations_follow_the_routes(#[values(false, true)] solved: bool) {
+    let mut sim = Shaman::parse("../maps/swap.txt").unwrap();
+    if solved {
+        sim = sim.solve().unwrap();
+    }
+    let end = sim.robots().map(|r| r.route().duration()).max().unwrap();
+    let mut collided = false;
+    for t in 0..=end + 1 {
+        let reservations = sim.reservations_at(t);
+        for robot in sim.robots() {
+            let position = robot.route().position_at(t).unwrap();
+            assert!(reservations[&position].contains(&robot.name()));
+        }
+        collided |= reservations.values().any(|owners| owners.len() > 1);
+    }
+    assert_eq!(collided, !solved);
+}
+
+#[test]
+fn yaml_exports_read_back_as_the_same_document() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let json = sim.to_json();
+    let yaml = json.to_yaml();
+    assert!(yaml.starts_with("makespan: "), "{yaml}");
+    let read: serde_json::Value = serde_yaml_ng::from_str(&yaml).unwrap();
+    assert_eq!(Json::parse(&read.to_string()).unwrap(), json);
+
+    let odd = Json::object([
+        ("empty", Json::Array(vec![])),
+        (
+            "nested",
+            Json::Array(vec![Json::array([1, 2]), Json::Object(vec![])]),
+        ),
+        ("needs quotes", "yes: no".to_json()),
+    ]);
+    let read: serde_json::Value = serde_yaml_ng::from_str(&odd.to_yaml()).unwrap();
+    assert_eq!(Json::parse(&read.to_string()).unwrap(), odd);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_serializes_like_the_json_export() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let json = serde_json::to_string(&sim).unwrap();
+    assert_eq!(Json::parse(&json).unwrap(), sim.to_json());
+
+    let yaml = serde_yaml_ng::to_string(&sim).unwrap();
+    let read: serde_json::Value = serde_yaml_ng::from_str(&yaml).unwrap();
+    assert_eq!(Json::parse(&read.to_string()).unwrap(), sim.to_json());
+
+    for robot in sim.robots() {
+        let json = serde_json::to_string(robot).unwrap();
+        assert_eq!(Json::parse(&json).unwrap(), robot.to_json());
+        let json = serde_json::to_string(robot.route()).unwrap();
+        assert_eq!(Json::parse(&json).unwrap(), robot.route().to_json());
+    }
+}
+
+#[test]
+fn plans_are_compared_against_references() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let exported = Json::parse(&sim.to_json().to_string()).unwrap();
+    assert_eq!(exported, sim.to_json());
+
+    let plan = Plan::from_json(&exported).unwrap();
+    assert_eq!(plan, Plan::from(&sim));
+    let reference = Json::parse(
+        r#"{"robots": [{"name": "A", "cost": 12}, {"name": "B", "route": [{"t": 0}, {"t": 14}]}]}"#,
+    )
+    .unwrap();
+    let comparison = plan.compare(&Plan::from_json(&reference).unwrap()).unwrap();
+    assert_eq!(comparison.robots[0].1.absolute(), 0);
+    assert_eq!(comparison.robots[1].1.absolute(), 2);
+    assert_eq!(comparison.total().reference, 26);
+    assert_eq!(comparison.makespan().cost, 16);
+
+    let other = Json::parse(r#"{"robots": [{"name": "A", "cost": 12}]}"#).unwrap();
+    assert!(plan.compare(&Plan::from_json(&other).unwrap()).is_err());
+    assert!(Json::parse(r#"{"robots": [}"#).is_err());
+}
+
+#[test]
+fn calibration_recovers_the_cost_model() {
+    let sim = Shaman::parse("../maps/roundabout.txt")
+        .unwrap()
+        .solve()
+        .unwrap();
+    let truth = CostModel {
+        wait: 0.5,
+        straight: 1.,
+        start: 2.,
+        turn: 4.,
+    };
+    let traces = sim
+        .robots()
+        .enumerate()
+        .map(|(i, r)| {
+            let actions = r.route().to_actions().unwrap();
+            Trace::jittered(&actions, &truth, 0.8, 0.1, i as u64)
+        })
+        // Optimal routes rather stop than turn, so add some turns by hand
+        .chain([Trace::jittered(
+            &[
+                Action::E,
+                Action::N,
+                Action::W,
+                Action::S,
+                Action::E,
+                Action::N,
+            ],
+            &truth,
+            0.8,
+            0.1,
+            42,
+        )])
+        .collect_vec();
+    let calibration = Calibration::fit(&traces).unwrap();
+    assert!((calibration.unit - 0.8).abs() < 0.05, "{calibration}");
+    for (fitted, expected) in [
+        (calibration.model.wait, truth.wait),
+        (calibration.model.start, truth.start),
+        (calibration.model.turn, truth.turn),
+    ] {
+        assert!((fitted - expected).abs() / expected < 0.1, "{calibration}");
+    }
+
+    let line = "E:0.75 E:0.5 N:1.5 wait:0.6";
+    assert_eq!(Trace::parse(line).unwrap().steps.len(), 4);
+    assert!(Trace::parse("X:1").is_none());
+    for duration in ["-1", "NaN", "inf", "-inf"] {
+        assert!(
+            Trace::parse(&format!("E:{duration}")).is_none(),
+            "{duration}"
+        );
+    }
+}
+
+#[test]
+fn perturbations_are_reproducible() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap();
+    let sensitivity = sim.sensitivity(Algorithm::Pbs, 2, 4, 7).unwrap();
+    assert_eq!(
+        sensitivity,
+        sim.sensitivity(Algorithm::Pbs, 2, 4, 7).unwrap()
+    );
+    assert_eq!(sensitivity.costs.len(), 4);
+    assert_eq!(sensitivity.baseline, sim.clone().solve().unwrap().cost());
+
+    let unchanged = sim.sensitivity(Algorithm::Pbs, 0, 2, 7).unwrap();
+    assert!(unchanged.deltas().all(|d| d == 0));
+    assert_eq!(unchanged.spread(), 0.);
+}
+
+#[test]
+fn perturbations_keep_the_planner_config_and_inflation() {
+    let drawn = ShamanBuilder::new(9, 9).block(Vertex::new(4, 4)).robot(
+        'A',
+        Vertex::new(1, 4),
+        Vertex::new(7, 4),
+    );
+    let sim = drawn.build().unwrap().inflate(1).unwrap();
+    let mut config = sim.planner_config();
+    config.costs.turn = 0.5;
+    let sim = sim.with_planner_config(config).unwrap();
+    let rebuilt = ShamanBuilder::from(&sim);
+    assert_eq!(rebuilt.to_string(), drawn.to_string());
+    assert!(
+        rebuilt
+            .build()
+            .unwrap()
+            .layout()
+            .is_blocked(Vertex::new(3, 3))
+    );
+
+    let unchanged = sim.sensitivity(Algorithm::Pbs, 0, 2, 7).unwrap();
+    assert!(unchanged.deltas().all(|d| d == 0), "{unchanged}");
+}
+
+#[test]
+fn minimized_maps_keep_failing() {
+    let sim = Shaman::parse("../maps/impossible/no-space-to-evade.txt").unwrap();
+    let minimal = sim.minimize(Algorithm::Pbs, "Ran out of ideas").unwrap();
+    let shrunk = minimal.build().unwrap();
+    assert!(minimal.to_string().len() < ShamanBuilder::from(&sim).to_string().len());
+    let e = shrunk.solve().unwrap_err();
+    assert!(e.to_string().contains("Ran out of ideas"));
+
+    let solvable = Shaman::parse("../maps/swap.txt").unwrap();
+    assert!(
+        solvable
+            .minimize(Algorithm::Pbs, "Ran out of ideas")
+            .is_err()
+    );
+}
+
+#[test]
+fn minimized_maps_keep_the_planner_config() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap();
+    let mut config = sim.planner_config();
+    config.max_nodes = Some(1);
+    let sim = sim.with_planner_config(config).unwrap();
+    let e = sim.clone().solve_with(Algorithm::Cbs).unwrap_err();
+    assert!(e.to_string().contains("Gave up"), "{e}");
+
+    let minimal = sim.minimize(Algorithm::Cbs, "Gave up").unwrap();
+    assert_eq!(minimal.build().unwrap().robots().count(), 2);
+}
+
+#[test]
+fn anonymous_goals_minimize_the_makespan() {
+    let corridor = ShamanBuilder::new(7, 1)
+        .robot('A', Vertex::new(0, 0), Vertex::new(5, 0))
+        .robot('B', Vertex::new(6, 0), Vertex::new(1, 0));
+    assert!(corridor.build().unwrap().solve().is_err());
+
+    let anonymous = corridor.anonymous();
+    assert!(anonymous.to_string().starts_with("---\nanonymous\n---\n"));
+    let sim = anonymous.build().unwrap();
+    assert!(sim.has_anonymous_goals());
+    let solved = sim.solve().unwrap();
+    let goal = |name| {
+        let robot = solved.robots().find(|r| r.name() == name).unwrap();
+        robot.goal().unwrap().0
+    };
+    assert_eq!(goal('A'), Vertex::new(1, 0));
+    assert_eq!(goal('B'), Vertex::new(5, 0));
+    assert_eq!(solved.summary().makespan, 1);
+}
+
+#[test]
+fn conflict_graphs_connect_colliding_robots() {
+    let sim = ShamanBuilder::new(5, 4)
+        .robot('A', Vertex::new(0, 1), Vertex::new(4, 1))
+        .robot('B', Vertex::new(4, 1), Vertex::new(0, 1))
+        .robot('C', Vertex::new(0, 3), Vertex::new(4, 3))
+        .build()
+        .unwrap();
+    let graph = sim.conflict_graph();
+    assert_eq!(graph.robots, vec!['A', 'B', 'C']);
+    assert_eq!(graph.edges.len(), 1);
+    let edge = graph.edges[0];
+    assert_eq!(edge.robots, ('A', 'B'));
+    assert!(edge.count > 0);
+
+    let dot = graph.dot(true);
+    assert!(dot.starts_with("graph conflicts {\n"));
+    assert!(dot.contains(&format!("  A -- B [label={}];\n", edge.count)));
+    assert!(graph.dot(false).contains("  A -- B;\n"));
+    let json = graph.to_json();
+    assert_eq!(json.get("edges").unwrap().as_array().unwrap().len(), 1);
+
+    assert!(sim.solve().unwrap().conflict_graph().edges.is_empty());
+}
+
+#[rstest]
+fn decomposed_solutions_are_conflict_free(#[files("../maps/*.txt")] file: PathBuf) {
+    let sim = Shaman::parse(file).unwrap();
+    let robots = sim.conflict_graph().groups().concat();
+    assert_eq!(
+        robots.into_iter().sorted().collect_vec(),
+        sim.robots().map(|r| r.name()).sorted().collect_vec()
+    );
+    let solved = sim.solve_decomposed(Algorithm::Pbs).unwrap();
+    assert!(!solved.summary().conflicts);
+}
+
+#[test]
+fn independent_groups_are_solved_separately() {
+    let sim = ShamanBuilder::new(5, 4)
+        .robot('A', Vertex::new(0, 1), Vertex::new(4, 1))
+        .robot('B', Vertex::new(4, 1), Vertex::new(0, 1))
+        .robot('C', Vertex::new(0, 3), Vertex::new(4, 3))
+        .build()
+        .unwrap();
+    assert_eq!(
+        sim.conflict_graph().groups(),
+        vec![vec!['A', 'B'], vec!['C']]
+    );
+    let decomposed = sim.clone().solve_decomposed(Algorithm::Pbs).unwrap();
+    assert!(!decomposed.summary().conflicts);
+    assert_eq!(decomposed.cost(), sim.solve().unwrap().cost());
+}
+
+#[rstest]
+fn objectives_are_minimized_without_conflicts(
+    #[files("../maps/*.txt")] file: PathBuf,
+    #[values(Objective::SumOfCosts, Objective::Makespan, Objective::WeightedMix(2.))]
+    objective: Objective,
+) {
+    let solved = Shaman::parse(file)
+        .unwrap()
+        .with_objective(objective)
+        .solve()
+        .unwrap();
+    assert_eq!(solved.objective(), objective);
+    let summary = solved.summary();
+    assert!(!summary.conflicts);
+    assert_eq!(
+        Objective::Makespan.cost(solved.robots()),
+        summary.makespan as f32
+    );
+    assert_eq!(
+        Objective::WeightedMix(2.).cost(solved.robots()),
+        summary.weighted_cost + 2. * summary.makespan as f32
+    );
+}
+
+#[test]
+fn objectives_parse_from_their_names() {
+    assert_eq!("sum-of-costs".parse(), Ok(Objective::SumOfCosts));
+    assert_eq!("makespan".parse(), Ok(Objective::Makespan));
+    assert_eq!("mix=0.5".parse(), Ok(Objective::WeightedMix(0.5)));
+    assert!("mix=-1".parse::<Objective>().is_err());
+    assert!("fastest".parse::<Objective>().is_err());
+}
+
+#[test]
+fn goal_swaps_make_deadlocks_solvable() {
+    let corridor = ShamanBuilder::new(7, 1)
+        .robot('A', Vertex::new(0, 0), Vertex::new(5, 0))
+        .robot('B', Vertex::new(6, 0), Vertex::new(1, 0))
+        .build()
+        .unwrap();
+    let suggestions = corridor.suggest_swaps(Algorithm::Pbs);
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0].swaps, vec![('A', 'B')]);
+    assert_eq!(suggestions[0].before, None);
+    assert!(suggestions[0].to_string().starts_with("swap A & B: cost 2"));
+
+    let swap = Shaman::parse("../maps/swap.txt").unwrap();
+    assert!(
+        swap.suggest_swaps(Algorithm::Pbs)
+            .iter()
+            .all(|s| s.before.is_some())
+    );
+}
+
+#[rstest]
+fn svgs_draw_every_route(#[files("../maps/*.txt")] file: PathBuf) {
+    let solved = Shaman::parse(file).unwrap().solve().unwrap();
+    let svg = solved.svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("<polyline").count(), solved.robots().count());
+    for robot in solved.robots() {
+        assert!(svg.contains(&format!(
+            "<g id=\"{}\" fill=\"{}\"",
+            robot.name(),
+            robot.color()
+        )));
+        let end = robot.route().duration();
+        assert!(svg.contains(&format!(">{end}</text>")) || svg.contains(&format!("-{end}</text>")));
+    }
+}
+
+#[test]
+fn goal_windows_delay_early_robots() {
+    let dock = ShamanBuilder::new(8, 1)
+        .robot('A', Vertex::new(0, 0), Vertex::new(3, 0))
+        .window(Vertex::new(3, 0), 6, 8);
+    assert!(dock.to_string().contains("window a = 6..8\n"));
+    let sim = dock.build().unwrap();
+    let robot = sim.robots().next().unwrap();
+    assert_eq!(robot.window(), Some((6, 8)));
+    let route = robot.route().iter().collect_vec();
+    assert_eq!(route.last().unwrap().position, Vertex::new(3, 0));
+    assert_eq!(route.last().unwrap().time, 6);
+    assert!(
+        route
+            .iter()
+            .all(|l| l.position != Vertex::new(3, 0) || l.time >= 6)
+    );
+
+    let closed = ShamanBuilder::new(8, 1)
+        .robot('A', Vertex::new(0, 0), Vertex::new(3, 0))
+        .window(Vertex::new(3, 0), 0, 1)
+        .build()
+        .unwrap_err();
+    assert!(closed.to_string().contains("Goal closes at t=1"));
+}
+
+#[test]
+fn gifs_animate_every_timestep() {
+    let solved = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let gif = solved.gif(4.).unwrap();
+    assert!(gif.starts_with(b"GIF89a"));
+    assert_eq!(gif.last(), Some(&0x3B));
+    // 17 x 5 cells of 16 pixels each
+    let size = [16 * 17u16, 16 * 5].map(u16::to_le_bytes);
+    assert_eq!(&gif[6..10], size.as_flattened());
+    assert!(gif.windows(11).any(|w| w == b"NETSCAPE2.0"));
+    // Each frame shows for a quarter second
+    let frames = gif.windows(6).filter(|w| w == b"\x21\xF9\x04\x00\x19\x00");
+    assert!(frames.count() > solved.summary().makespan);
+}
+
+#[test]
+fn gifs_decode_to_their_frames_even_past_a_full_code_table() {
+    // Irregular obstacles, so the 640 x 640 pixels fill the LZW code table several times
+    let (width, height) = (40, 40);
+    let mut builder = ShamanBuilder::new(width, height);
+    for (x, y) in (0..width as i32).cartesian_product(2..height as i32) {
+        if ((x * 31 + y * 17) ^ (x * y)) % 7 == 0 {
+            builder = builder.block(Vertex::new(x, y));
+        }
+    }
+    let sim = builder
+        .robot('A', Vertex::new(0, 0), Vertex::new(1, 0))
+        .build()
+        .unwrap();
+    let gif = sim.gif(4.).unwrap();
+
+    let (frame, clears) = first_gif_frame(&gif);
+    assert!(clears > 1, "the code table never filled up");
+    assert_eq!(frame.len(), width * 16 * height * 16);
+    for (x, y) in (0..width).cartesian_product(0..height) {
+        let expected = match sim.layout().is_blocked(Vertex::new(x as i32, y as i32)) {
+            true => 1,
+            false if y > 0 => 0,
+            false => continue,
+        };
+        for (dx, dy) in (0..16).cartesian_product(0..16) {
+            assert_eq!(frame[(y * 16 + dy) * width * 16 + x * 16 + dx], expected);
+        }
+    }
+}
+
+/// Palette indices of the first frame of `gif` & how many times its LZW codes cleared the
+/// code table, decoded independently of the encoder
+fn first_gif_frame(gif: &[u8]) -> (Vec<u8>, usize) {
+    // Data of the sub-blocks starting at `i` & where the next block starts
+    let blocks = |mut i: usize| {
+        let mut data: Vec<u8> = vec![];
+        while gif[i] > 0 {
+            let length = gif[i] as usize;
+            data.extend(&gif[i + 1..i + 1 + length]);
+            i += length + 1;
+        }
+        (data, i + 1)
+    };
+    // Header, screen descriptor & global palette
+    let mut i = 13 + 3 * (2 << (gif[10] & 7));
+    loop {
+        match gif[i] {
+            0x21 => i = blocks(i + 2).1,
+            0x2C => break,
+            block => panic!("Unexpected block {block:#x}"),
+        }
+    }
+    let min = gif[i + 10] as u32;
+    let data = blocks(i + 11).0;
+
+    let (clear, end) = (1 << min, (1 << min) + 1);
+    let mut table = (0..clear).map(|c| vec![c as u8]).collect_vec();
+    table.extend([vec![], vec![]]);
+    let (mut pixels, mut clears, mut previous) = (vec![], 0, None::<Vec<u8>>);
+    let (mut size, mut bit) = (min + 1, 0);
+    loop {
+        let code = (0..size).fold(0, |code, b| {
+            let at = bit + b as usize;
+            code | ((data[at / 8] as usize >> (at % 8)) & 1) << b
+        });
+        bit += size as usize;
+        if code == clear {
+            table.truncate(end + 1);
+            (size, previous, clears) = (min + 1, None, clears + 1);
+            continue;
+        } else if code == end {
+            return (pixels, clears);
+        }
+        let entry = match (table.get(code), &previous) {
+            (Some(entry), _) => entry.clone(),
+            (None, Some(p)) => [p.as_slice(), &p[..1]].concat(),
+            (None, None) => panic!("Unknown code {code}"),
+        };
+        pixels.extend(&entry);
+        if let Some(p) = previous
+            && table.len() < 4096
+        {
+            table.push([p.as_slice(), &entry[..1]].concat());
+        }
+        if table.len() == 1 << size && size < 12 {
+            size += 1;
+        }
+        previous = Some(entry);
+    }
+}
+
+/// Every solver & planner solves every map without conflicts. Solvers giving up within their
+/// time budget are inconclusive, not a disagreement. Costs aren't compared, since planners
+/// minimize the cost of actions, not durations, so not even CBS is optimal in route durations
+#[rstest]
+fn solvers_agree_on_every_map(
+    #[files("../maps/*.txt")] file: PathBuf,
+    #[values(Planner::AStar, Planner::Sipp)] planner: Planner,
+) {
+    let config = PlannerConfig {
+        planner,
+        max_time: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+    let sim = Shaman::parse(file)
+        .unwrap()
+        .with_planner_config(config)
+        .unwrap();
+    let solve = |algorithm| match sim.clone().solve_with(algorithm) {
+        Ok(solved) => assert!(!solved.summary().conflicts, "{algorithm:?} left conflicts"),
+        Err(e) if matches!(e.downcast_ref(), Some(ShamanError::BudgetExceeded { .. })) => {}
+        Err(e) => panic!("{algorithm:?} failed: {e}"),
+    };
+    for algorithm in [Algorithm::Pbs, Algorithm::Cbs, Algorithm::Auto] {
+        solve(algorithm);
+    }
+}
+
+#[test]
+fn any_timestep_is_reconstructed_from_the_routes() {
+    let mut sim = Shaman::parse("../maps/roundabout.txt")
+        .unwrap()
+        .solve()
+        .unwrap();
+    let start = sim.clone();
+    for time in 0..6 {
+        let rewound = start.at(time);
+        assert_eq!(rewound.time(), time);
+        assert_eq!(rewound.render_plain(), sim.render_plain(), "t={time}");
+        sim.simulate();
+    }
+    assert_eq!(start.time(), 0);
+}
+
+#[test]
+fn replans_send_only_the_changed_suffix() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let mut sim = Simulation::from(sim);
+    for _ in 0..3 {
+        sim.step().unwrap();
+    }
+    let route = |sim: &Simulation| {
+        let a = sim.shaman().robots().find(|r| r.name() == 'A').unwrap();
+        a.route().clone()
+    };
+    let before = route(&sim);
+    sim.apply(Event::Task('A', Vertex::new(8, 3))).unwrap();
+    let after = route(&sim);
+
+    let diffs = sim.take_diffs();
+    assert_eq!(diffs.len(), 1, "{diffs:?}");
+    let (name, diff) = &diffs[0];
+    assert_eq!(*name, 'A');
+    let keep = diff.keep_until.map_or(0, |t| t + 1);
+    assert!(diff.suffix.iter().all(|l| l.time >= keep), "{diff}");
+    let patched = before
+        .iter()
+        .filter(|l| diff.keep_until.is_some_and(|t| l.time <= t))
+        .chain(diff.suffix.iter().copied())
+        .collect::<Route>();
+    assert_eq!(patched, after);
+    assert_eq!(before.diff(&before).suffix, []);
+    assert!(sim.take_diffs().is_empty());
+}
+
+#[test]
+fn checks_list_every_problem() {
+    let map = "#####\n#A A#\n#b #\n#####\n";
+    let problems = Shaman::check_str("broken", map)
+        .into_iter()
+        .map(|p| p.to_string())
+        .collect_vec();
+    assert_eq!(problems.len(), 4, "{problems:#?}");
+    assert!(problems[0].contains("Row has 4 cells, while the widest one has 5"));
+    assert!(problems[1].contains("Robot names must be unique"));
+    assert!(problems[2].contains("No robot named 'B' defined"));
+    assert!(problems[3].contains("Robot 'A' has no goal"));
+
+    assert!(Shaman::check("../maps/swap.txt").unwrap().is_empty());
+}
+
+#[test]
+fn parsing_fails_with_every_error_at_once() {
+    let map = "#####\n#A?A#\n#a!b#\n#####\n";
+    let e = Shaman::parse_str("broken", map).unwrap_err();
+    let Some(ShamanError::Problems { count, problems }) = e.downcast_ref() else {
+        panic!("Expected all problems, got {e:?}");
+    };
+    assert_eq!(*count, 4);
+    let messages = problems.iter().map(|p| p.to_string()).collect_vec();
+    assert!(messages[0].starts_with("Expected either an obstacle"));
+    assert!(messages[1].starts_with("Expected either an obstacle"));
+    assert_eq!(
+        messages[2..],
+        ["Robot names must be unique", "No robot named 'B' defined"]
+    );
+
+    // A single error is reported as it is
+    let e = Shaman::parse_str("typo", "#####\n#A?a#\n#####\n").unwrap_err();
+    assert!(matches!(
+        e.downcast_ref(),
+        Some(ShamanError::InvalidCell { .. })
+    ));
+}
+
+#[test]
+fn checks_report_robots_which_cant_ever_finish() {
+    let map =
+        "---\nexclude B = 5/1\nexclude C = 2/1..2/2\n---\n#######\n#A b B#\n#C  c #\n#######\n";
+    let problems = Shaman::check_str("semantic", map);
+    let messages = problems.iter().map(|p| p.to_string()).collect_vec();
+    assert_eq!(
+        messages,
+        [
+            "Robot 'A' has no goal, so it never leaves its start",
+            "Robot 'B' starts on a cell it must never enter",
+            "No route found",
+        ]
+    );
+    assert_eq!(problems[0].severity(), Some(Severity::Warning));
+    assert_eq!(problems[1].severity(), None);
+
+    let problem = &problems[1].messages()[0];
+    assert_eq!(problem.id, "problem.start-excluded");
+    assert_eq!(problem.get("robot"), Some(&Json::String("B".into())));
+    assert_eq!(problem.get("line"), Some(&Json::Number(6.)));
+    assert_eq!(problem.get("column"), Some(&Json::Number(6.)));
+    let english = problems.iter().map(|p| English.render(p)).collect_vec();
+    assert_eq!(
+        english,
+        messages.iter().map(|m| format!("{m}\n")).collect_vec()
+    );
+}
+
+#[test]
+fn builders_mark_goals_on_other_starts_from_the_cell_above() {
+    // B drives to where A starts, which ⓑ marks from above
+    let builder = ShamanBuilder::new(3, 2)
+        .robot('A', Vertex::new(1, 1), Vertex::new(2, 1))
+        .robot('B', Vertex::new(0, 1), Vertex::new(1, 1));
+    assert_eq!(builder.to_string(), " ⓑ \nBAa\n");
+    let sim = builder.build().unwrap();
+    let goal = |name| sim.robots().find(|r| r.name() == name)?.goal().map(|g| g.0);
+    assert_eq!(goal('B'), Some(Vertex::new(1, 1)));
+
+    let error = |b: ShamanBuilder| b.build().unwrap_err().to_string();
+    let top = ShamanBuilder::new(3, 1)
+        .robot('A', Vertex::new(1, 0), Vertex::new(2, 0))
+        .robot('B', Vertex::new(0, 0), Vertex::new(1, 0));
+    assert_eq!(
+        error(top),
+        "Goal of robot B at 1/0 is where robot A starts, so it's marked from the cell above, \
+         which is off the map"
+    );
+    assert!(error(builder.clone().block(Vertex::new(1, 0))).ends_with("which is blocked"));
+    let below_robot = builder.robot('C', Vertex::new(1, 0), Vertex::new(2, 0));
+    assert!(error(below_robot).ends_with("which is taken by robot C"));
+}
+
+#[test]
+fn random_layouts_keep_their_free_space_connected() {
+    let generate = |seed| {
+        ShamanBuilder::random_layout(12, 7, 0.3, seed)
+            .unwrap()
+            .random_robots(4, seed)
+            .unwrap()
+            .to_string()
+    };
+    let map = generate(42);
+    assert_eq!(map, generate(42));
+    assert_ne!(map, generate(7));
+    assert_eq!(map.lines().count(), 7);
+    assert!(map.lines().all(|row| row.chars().count() == 12));
+    // 30% of the 10x5 cells inside the border
+    assert_eq!(map.matches('#').count(), 2 * 12 + 2 * 5 + 15);
+
+    let sim = Shaman::parse_str("generated", &map).unwrap();
+    assert_eq!(sim.robots().count(), 4);
+    sim.solve().unwrap();
+    assert!(ShamanBuilder::random_layout(2, 5, 0.2, 0).is_err());
+    assert!(ShamanBuilder::random_layout(5, 5, 1.2, 0).is_err());
+}
+
+#[test]
+fn summaries_are_translatable() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let summary = sim.summary();
+    assert_eq!(English.render(&summary), summary.to_string());
+    assert_eq!(
+        summary.to_string(),
+        "makespan:  16\ncost:      28\nbound:     24 (gap 16.7%)\nwaits:     2\n\
+         arrivals:  A@12 B@16\nconflicts: none\nquality:   92 (A)\n"
+    );
+    let messages = summary.messages();
+    assert_eq!(messages[0].id, "summary.makespan");
+    assert_eq!(messages[0].get("makespan"), Some(&Json::Number(16.)));
+
+    struct German;
+    impl Locale for German {
+        fn format(&self, message: &Message) -> String {
+            match message.id {
+                "summary.makespan" => format!("Dauer: {}", message.get("makespan").unwrap()),
+                _ => English.format(message),
+            }
+        }
+    }
+    assert!(
+        German
+            .render(&summary)
+            .starts_with("Dauer: 16\ncost:      28\n")
+    );
+
+    let plan = Plan::from(&sim);
+    let comparison = plan.compare(&plan).unwrap();
+    assert_eq!(English.render(&comparison), comparison.to_string());
+    assert!(
+        comparison
+            .to_string()
+            .contains("\ntotal       28         28    +0 (+0.0%)\n")
+    );
+}
+
+#[test]
+fn solutions_are_graded_by_quality() {
+    let unsolved = Shaman::parse("../maps/swap.txt").unwrap();
+    let conflicted = unsolved.quality();
+    assert!(conflicted.conflicts);
+    assert_eq!((conflicted.score(), conflicted.grade()), (0., Grade::F));
+
+    let solved = unsolved.solve().unwrap();
+    let quality = solved.quality();
+    assert_eq!(quality, solved.quality(), "same hold-ups every time");
+    assert!(!quality.conflicts);
+    assert!((0. ..=1.).contains(&quality.robustness));
+    assert_eq!(quality.congestion, 2. / 28.);
+    assert!(quality.grade() < Grade::F);
+    assert_eq!(solved.summary().quality, quality);
+
+    let json = solved.to_json();
+    let grade = json.get("quality").and_then(|q| q.get("grade"));
+    assert_eq!(grade.and_then(Json::as_str), Some("A"));
+}
+
+#[test]
+fn movingai_benchmarks_are_imported() {
+    let layout = Shaman::parse("../maps/movingai/corridors.map").unwrap();
+    assert_eq!(layout.robots().count(), 0);
+    assert_eq!(
+        ShamanBuilder::from(&layout).to_string(),
+        "#########\n#       #\n# ##### #\n#       #\n#   #   #\n#########\n"
+    );
+
+    let sim = Shaman::parse("../maps/movingai/corridors.scen").unwrap();
+    let robots = sim
+        .robots()
+        .map(|r| (r.name(), r.position().0, r.goal().unwrap().0))
+        .sorted_by_key(|(n, _, _)| *n)
+        .collect_vec();
+    assert_eq!(
+        robots,
+        [
+            ('A', Vertex::new(1, 1), Vertex::new(7, 3)),
+            ('B', Vertex::new(7, 1), Vertex::new(1, 3)),
+            ('C', Vertex::new(1, 3), Vertex::new(7, 1)),
+        ]
+    );
+    assert!(!sim.solve().unwrap().summary().conflicts);
+
+    // Goals on each other's starts, right below an obstacle, keep both
+    let sim = Shaman::parse("../maps/movingai/stacked.scen").unwrap();
+    assert!(sim.layout().is_blocked(Vertex::new(2, 2)));
+    assert!(!sim.layout().is_blocked(Vertex::new(1, 2)));
+    let obstacles = |sim: &Shaman| {
+        (0..9)
+            .cartesian_product(0..6)
+            .map(|(x, y)| Vertex::new(x, y))
+            .filter(|&v| sim.layout().is_blocked(v))
+            .collect_vec()
+    };
+    assert_eq!(obstacles(&sim), obstacles(&layout));
+    let goals = sim
+        .robots()
+        .map(|r| (r.name(), r.goal().unwrap().0))
+        .sorted_by_key(|(n, _)| *n)
+        .collect_vec();
+    assert_eq!(goals, [('A', Vertex::new(2, 3)), ('B', Vertex::new(1, 3))]);
+    let problems = Shaman::check("../maps/movingai/stacked.scen").unwrap();
+    assert!(problems.is_empty(), "{problems:?}");
+
+    let as_shaman = Shaman::parse_as("../maps/movingai/corridors.map", Format::Shaman).unwrap_err();
+    assert!(format!("{as_shaman:?}").contains("Expected either an obstacle"));
+}
+
+#[test]
+fn fleets_share_rules_colors_and_stats() {
+    let sim = Shaman::parse("../maps/fleets.txt").unwrap();
+    let robot = |name| sim.robots().find(|r| r.name() == name).unwrap();
+    assert_eq!(robot('A').fleet(), Some("pickers"));
+    assert_eq!(robot('C').fleet(), Some("tuggers"));
+    assert_eq!(robot('A').color(), robot('B').color());
+    assert_ne!(robot('A').color(), robot('C').color());
+    assert_eq!((robot('B').weight(), robot('C').weight()), (1., 3.));
+    assert!(robot('B').excluded().contains(&Vertex::new(5, 1)));
+    assert!(robot('C').excluded().is_empty());
+
+    let rebuilt = ShamanBuilder::from(&sim).to_string();
+    assert!(rebuilt.starts_with("---\nfleet A,B = pickers\nfleet C = tuggers\n"));
+    assert_eq!(ShamanBuilder::from(&sim.clone()).to_string(), rebuilt);
+
+    let stats = Solution::try_from(sim.solve().unwrap()).unwrap().stats();
+    let fleets = stats.fleets.iter().map(|f| &f.name).collect_vec();
+    assert_eq!(fleets, ["pickers", "tuggers"]);
+    assert_eq!(stats.fleets[0].robots, ['A', 'B']);
+    let moves = |names: &[char]| {
+        stats
+            .robots
+            .iter()
+            .filter(|r| names.contains(&r.name))
+            .map(|r| r.moves)
+            .sum::<usize>()
+    };
+    assert_eq!(stats.fleets[0].moves, moves(&['A', 'B']));
+
+    let unknown = Shaman::parse_str("unknown", "---\nweight pickers = 2\n---\nA a\n").unwrap_err();
+    assert!(
+        unknown
+            .to_string()
+            .contains("No robot belongs to fleet 'pickers'")
+    );
+}
+
+#[rstest]
+fn solve_stats_count_the_search(#[values(Algorithm::Pbs, Algorithm::Cbs)] algorithm: Algorithm) {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap();
+    let (solved, stats) = sim.clone().solve_with_stats(algorithm).unwrap();
+    assert_eq!(stats.algorithm, algorithm);
+    assert_eq!(stats.cost, solved.cost());
+    assert_eq!(stats.cost, sim.solve_with(algorithm).unwrap().cost());
+    assert!(stats.conflicts > 0);
+    assert_eq!(stats.expanded, stats.conflicts + 1);
+    assert!(stats.generated >= stats.expanded);
+    assert!(!stats.expansions.is_empty());
+    assert!(stats.expansions.iter().all(|n| *n > 0));
+    assert!(
+        stats
+            .to_string()
+            .contains(&format!("conflicts: {}\n", stats.conflicts))
+    );
+
+    // Nothing is recorded outside of solving with stats
+    let (_, again) = Shaman::parse("../maps/swap.txt")
+        .unwrap()
+        .solve_with_stats(algorithm)
+        .unwrap();
+    assert_eq!(again.expansions, stats.expansions);
+}
+
+#[rstest]
+fn bounded_suboptimal_search_trades_quality_for_speed(
+    #[values(Algorithm::Pbs, Algorithm::Cbs)] algorithm: Algorithm,
+) {
+    let sim = ShamanBuilder::random_layout(16, 10, 0.2, 3)
+        .and_then(|b| b.random_robots(12, 3))
+        .and_then(|b| b.build())
+        .unwrap();
+    let (cheapest, optimal) = sim.clone().solve_with_stats(algorithm).unwrap();
+    let config = PlannerConfig {
+        suboptimality: Some(1.5),
+        ..sim.planner_config()
+    };
+    let (solved, bounded) = sim
+        .with_planner_config(config)
+        .unwrap()
+        .solve_with_stats(algorithm)
+        .unwrap();
+    assert!(!solved.summary().conflicts);
+    assert!(bounded.bound >= 1.);
+    if algorithm == Algorithm::Cbs {
+        // Only CBS finds the cheapest solution, which both levels together stay within 1.5 of
+        assert!(solved.cost() as f32 <= 1.5 * cheapest.cost() as f32);
+    }
+    assert!(bounded.total_expansions() <= optimal.total_expansions());
+    assert!(bounded.to_string().contains("bound:     "));
+}
+
+#[test]
+fn cells_become_free_once_robots_left() {
+    let sim = ShamanBuilder::new(5, 2)
+        .robot('A', Vertex::new(0, 0), Vertex::new(4, 0))
+        .robot('B', Vertex::new(0, 1), Vertex::new(1, 1))
+        .build()
+        .unwrap()
+        .solve()
+        .unwrap();
+    let solution = Solution::try_from(sim.clone()).unwrap();
+    assert_eq!(solution.occupancy(Vertex::new(2, 0)), [(2..3, 'A')]);
+    assert_eq!(solution.cell_free_after(Vertex::new(2, 0)), Some(3));
+    assert_eq!(solution.occupancy(Vertex::new(0, 1)), [(0..1, 'B')]);
+    assert_eq!(solution.occupancy(Vertex::new(1, 1)), [(1..Time::MAX, 'B')]);
+    assert_eq!(solution.cell_free_after(Vertex::new(4, 0)), None);
+    assert_eq!(solution.cell_free_after(Vertex::new(3, 1)), Some(0));
+
+    let mut later = sim;
+    later.simulate();
+    later.simulate();
+    let solution = Solution::try_from(later).unwrap();
+    assert!(solution.occupancy(Vertex::new(0, 0)).is_empty());
+    assert_eq!(solution.cell_free_after(Vertex::new(0, 0)), Some(2));
+    assert_eq!(solution.occupancy(Vertex::new(1, 1)), [(2..Time::MAX, 'B')]);
+}
+
+#[test]
+fn route_conflicts_are_typed() {
+    let at = |x, time| Location {
+        position: Vertex::new(x, 0),
+        time,
+    };
+    let east = Route::from_actions(at(0, 0), [Action::E, Action::E, Action::E]);
+    let west = Route::from_actions(at(1, 0), [Action::W]);
+    assert_eq!(
+        east.conflicts_with(&west),
+        [Collision::Edge {
+            from: Vertex::new(0, 0),
+            to: Vertex::new(1, 0),
+            t: 0
+        }]
+    );
+    let late = Route::from_actions(at(5, 0), [Action::W, Action::W]);
+    assert_eq!(
+        east.conflicts_with(&late),
+        [Collision::Goal {
+            v: Vertex::new(3, 0),
+            t_from: 3
+        }]
+    );
+
+    let waiting = Route::from_actions(at(3, 0), [Action::Wait, Action::W]);
+    assert_eq!(
+        east.conflicts_with(&waiting),
+        [Collision::Vertex {
+            v: Vertex::new(2, 0),
+            t: 2
+        }]
+    );
+    assert_eq!(east.intersection(&waiting), [Vertex::new(2, 0)]);
+}
+
+#[test]
+fn robots_staying_on_their_start_occupy_it_for_good() {
+    let alone = ShamanBuilder::new(3, 1)
+        .robot('A', Vertex::new(1, 0), Vertex::new(1, 0))
+        .build()
+        .unwrap()
+        .solve()
+        .unwrap();
+    let a = alone.robots().next().unwrap();
+    assert_eq!(a.route().duration(), 0);
+    let solution = Solution::try_from(alone).unwrap();
+    assert_eq!(solution.occupancy(Vertex::new(1, 0)), [(0..Time::MAX, 'A')]);
+
+    let builder = ShamanBuilder::new(5, 2)
+        .robot('A', Vertex::new(2, 0), Vertex::new(2, 0))
+        .robot('B', Vertex::new(0, 0), Vertex::new(4, 0));
+    assert!(builder.to_string().starts_with("---\nstay A\n---\n"));
+    let solution = Solution::try_from(builder.build().unwrap().solve().unwrap()).unwrap();
+    assert!(solution.shaman().conflicts().is_empty());
+    assert_eq!(solution.cell_free_after(Vertex::new(2, 0)), None);
+    assert!(
+        Shaman::parse_str("stay", "---\nstay A\n---\n A a\n")
+            .is_err_and(|e| e.to_string().contains("goal"))
+    );
+}
+
+#[rstest]
+fn slow_robots_take_their_pace_per_cell(#[values(Planner::AStar, Planner::Sipp)] planner: Planner) {
+    let builder = ShamanBuilder::new(5, 3)
+        .robot('A', Vertex::new(0, 1), Vertex::new(4, 1))
+        .robot('B', Vertex::new(2, 0), Vertex::new(2, 2))
+        .pace('A', 3);
+    assert!(builder.to_string().starts_with("---\npace A = 3\n---\n"));
+    let sim = builder.build().unwrap();
+    assert_eq!(sim.lower_bound(), 12 + 2);
+    let solved = sim.with_planner(planner).unwrap().solve().unwrap();
+    assert!(solved.conflicts().is_empty());
+
+    let a = solved.robots().find(|r| r.name() == 'A').unwrap();
+    assert_eq!(a.pace(), 3);
+    assert!(a.route().duration() >= 12);
+    let actions = a.route().to_actions().unwrap();
+    for (i, _) in actions
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| **a != Action::Wait)
+    {
+        assert!(
+            i >= 2 && actions[i - 2..i] == [Action::Wait; 2],
+            "{actions:?}"
+        );
+    }
+    assert_eq!(a.waits(), actions.len() - 4 * 3);
+
+    let b = solved.robots().find(|r| r.name() == 'B').unwrap();
+    assert_eq!(b.route().duration(), 2);
+    assert!(
+        Shaman::parse_str("pace", "---\npace A = 0\n---\nA a\n")
+            .is_err_and(|e| e.to_string().contains("header directive"))
+    );
+}
+
+#[test]
+fn itineraries_run_length_encode_routes() {
+    let sim = Shaman::parse("../maps/swap.txt").unwrap().solve().unwrap();
+    let itineraries = sim.itineraries();
+    assert_eq!(itineraries.len(), 2);
+    let b = &itineraries[1];
+    assert_eq!(
+        (b.robot, b.start, b.end),
+        ('B', Vertex::new(14, 2), Vertex::new(2, 2))
+    );
+    assert_eq!(
+        b.to_string(),
+        "B: 14/2 → 2/2\n  t=0 go south 1 cell\n  t=1 wait\n  t=2–14 go west 12 cells\n  \
+         t=14 wait\n  t=15 go north 1 cell\n"
+    );
+    let moved = |i: &Itinerary| i.legs.iter().map(|l| l.cells).sum::<usize>();
+    assert_eq!(moved(b), 14);
+    assert_eq!(b.legs.last().unwrap().to, sim.summary().makespan);
+
+    let tuggers = Shaman::parse("../maps/tuggers.txt")
+        .unwrap()
+        .solve()
+        .unwrap();
+    let slow = tuggers
+        .robots()
+        .find(|r| r.name() == 'C')
+        .unwrap()
+        .itinerary();
+    assert_eq!(
+        slow.legs,
+        [Leg {
+            action: Action::W,
+            from: 0,
+            to: 21,
+            cells: 7
+        }]
+    );
+    let json = slow.to_json().to_string();
+    assert!(json.contains("\"legs\":[{\"action\":\"west\""), "{json}");
+}
+
+#[rstest]
+fn oriented_robots_rotate_in_place_before_turning(
+    #[values(Planner::AStar, Planner::Sipp)] planner: Planner,
+) {
+    let sim = ShamanBuilder::new(3, 3)
+        .robot('A', Vertex::new(0, 0), Vertex::new(2, 2))
+        .build()
+        .unwrap();
+    let config = PlannerConfig {
+        planner,
+        rotate_cost: Some(1.),
+        ..Default::default()
+    };
+    let solved = sim.with_planner_config(config).unwrap().solve().unwrap();
+    let a = solved.robots().next().unwrap();
+    let actions = a.route().to_actions().unwrap();
+    assert_eq!(actions.len(), 5, "{actions:?}");
+    let turn = actions
+        .iter()
+        .position(|a| matches!(a, Action::Rotate(_)))
+        .unwrap();
+    assert!(
+        turn > 0 && actions[turn - 1] != actions[turn + 1],
+        "{actions:?}"
+    );
+    assert!(!actions.contains(&Action::Wait), "{actions:?}");
+    assert!(solved.render_plain().contains(actions[0].arrow().unwrap()));
+    let start = a.route().iter().next().unwrap();
+    let replayed = Route::from_actions(start, actions.clone());
+    assert_eq!(replayed.to_actions().unwrap(), actions);
+
+    let heading = |t| solved.at(t).robots().next().unwrap().heading();
+    assert_eq!(heading(turn + 1), actions[turn - 1]);
+    assert_eq!(heading(turn + 2), actions[turn + 1]);
+    assert_eq!(a.waits(), 0);
+    let legs = a.itinerary().legs;
+    assert_eq!(legs[1].action, actions[turn]);
+    assert_eq!(legs[1].cells, 0);
+    assert!(a.itinerary().to_string().contains(" turn "));
+}
+
+#[test]
+fn prioritized_planning_plans_each_robot_once_in_order() {
+    let crowd = Shaman::parse("../maps/crowd.txt").unwrap();
+    for order in [Order::Distance, Order::Input, Order::Random(7)] {
+        let sim = crowd.clone().with_order(order);
+        assert_eq!(sim.order(), order);
+        let (solved, stats) = sim.solve_with_stats(Algorithm::Prioritized).unwrap();
+        assert!(!solved.summary().conflicts, "{order:?}");
+        assert_eq!(stats.expansions.len(), crowd.robots().count(), "{order:?}");
+    }
+    let random = |seed| {
+        let solved = crowd.clone().with_order(Order::Random(seed));
+        solved.solve_with(Algorithm::Prioritized).unwrap().to_json()
+    };
+    assert_eq!(random(7), random(7));
+
+    assert_eq!("random=3".parse(), Ok(Order::Random(3)));
+    assert_eq!("input".parse(), Ok(Order::Input));
+    assert!("fastest".parse::<Order>().is_err());
+    // No fixed order works out on every map
+    let fourway = Shaman::parse("../maps/fourway.txt").unwrap();
+    assert!(fourway.clone().solve_with(Algorithm::Prioritized).is_err());
+    assert!(fourway.solve_with(Algorithm::Pbs).is_ok());
+}
+
+#[test]
+fn config_files_set_defaults_for_solving_and_rendering() {
+    let config = Config::parse(
+        r##"
+        # Defaults for the fleets map
+        solver = "prioritized"
+        objective = "makespan"
+        order = "input"
+
+        [planner]
+        planner = "sipp"
+        movement = "eight-connected"
+        max-time = 2.5
+
+        [costs]
+        turn = 5
+
+        [palette]
+        A = "#ff8800"   # a single robot
+        pickers = "#00ffff"
+        tuggers = "#123456"
+        "##,
+    )
+    .unwrap();
+    assert_eq!(config.algorithm, Algorithm::Prioritized);
+    assert_eq!(config.objective, Objective::Makespan);
+    assert_eq!(config.order, Order::Input);
+    assert_eq!(config.planner.planner, Planner::Sipp);
+    assert_eq!(config.planner.movement, MovementModel::EightConnected);
+    assert_eq!(config.planner.max_time, Some(Duration::from_millis(2500)));
+    assert_eq!(config.planner.costs.turn, 5.);
+    assert_eq!(config.planner.costs.wait, CostModel::default().wait);
+
+    let sim = Shaman::parse("../maps/fleets.txt")
+        .unwrap()
+        .with_config(&config)
+        .unwrap();
+    assert_eq!(sim.objective(), Objective::Makespan);
+    assert_eq!(sim.planner_config(), config.planner);
+    let color = |name| sim.robots().find(|r| r.name() == name).unwrap().color();
+    // Robots by name before their fleet
+    assert_eq!(color('A').to_string(), "#ff8800");
+    assert_eq!(color('B').to_string(), "#00ffff");
+    assert_eq!(color('C').to_string(), "#123456");
+    sim.solve_with(config.algorithm).unwrap();
+
+    let error = |s: &str| Config::parse(s).unwrap_err().to_string();
+    assert_eq!(error("\nspeed = 3"), "2: Unknown key 'speed'");
+    assert_eq!(
+        error("[costs]\nturn = fast"),
+        "2: Expected a number, got 'fast'"
+    );
+    assert_eq!(error("[colors]"), "1: Unknown section [colors]");
+    assert!(error("solver = \"fastest\"").contains("pbs, cbs, auto, prioritized"));
+    assert!(error("[palette]\nA = \"orange\"").contains("#ff8800"));
+}
+
+#[test]
+fn config_files_reject_toml_beyond_their_subset() {
+    let error = |s: &str| Config::parse(s).unwrap_err().to_string();
+    assert_eq!(error("seed = 1\nseed = 2"), "2: Key 'seed' is given twice");
+    assert_eq!(
+        error("[costs]\nturn = 2\n[planner]\n[costs]"),
+        "4: Section [costs] is given twice"
+    );
+    assert_eq!(
+        error("[costs]\nturn = 2\n[costs]\nturn = 3"),
+        "3: Section [costs] is given twice"
+    );
+    assert_eq!(
+        error("planner.planner = \"sipp\""),
+        "1: Expected a bare key, got 'planner.planner'"
+    );
+    assert_eq!(
+        error("[palette]\n\"A\" = \"#ff8800\""),
+        "2: Expected a bare key, got '\"A\"'"
+    );
+    assert!(error("solver = 'cbs'").contains("Literal strings in single quotes aren't"));
+    assert!(error("solver = \"\"\"cbs\"\"\"").contains("Multi-line strings aren't"));
+    assert!(error("[palette]\nA = [1, 2]").contains("Arrays aren't"));
+    assert!(error("[costs]\nturn = { a = 1 }").contains("Inline tables aren't"));
+    assert!(error("[[palette]]").contains("Arrays of tables aren't"));
+    assert_eq!(
+        error("solver = \"cbs\" \"pbs\""),
+        "1: Expected a string without escapes, got '\"cbs\" \"pbs\"'"
+    );
+    assert!(error("solver = \"c\\bs\"").contains("without escapes"));
+    assert_eq!(
+        error("[costs]\nturn = inf"),
+        "2: Expected a number, got 'inf'"
+    );
+    assert_eq!(
+        error("[costs]\nwait = nan"),
+        "2: Expected a number, got 'nan'"
+    );
+    // Comments after values & between sections are fine
+    let config = Config::parse("[costs]\nturn = 2 # slow\n\n# more\n[planner]\n").unwrap();
+    assert_eq!(config.planner.costs.turn, 2.);
+}
+
+#[test]
+fn goal_less_robots_park_or_patrol_while_others_evade() {
+    let parked = "#######\n#B A b#\n#     #\n#######\n";
+    let solved = Shaman::parse_str("parked", parked)
+        .unwrap()
+        .solve()
+        .unwrap();
+    let route = |sim: &Shaman, name| {
+        sim.robots()
+            .find(|r| r.name() == name)
+            .unwrap()
+            .route()
+            .clone()
+    };
+    assert!(!solved.summary().conflicts);
+    assert!(
+        route(&solved, 'B')
+            .iter()
+            .all(|l| l.position != Vertex::new(3, 1))
+    );
+    assert_eq!(route(&solved, 'A').iter().count(), 1);
+
+    let sim = Shaman::parse("../maps/patrol.txt").unwrap();
+    let patrol = route(&sim, 'P');
+    let stops = patrol.iter().map(|l| l.position).dedup().collect_vec();
+    let (start, right, corner) = (Vertex::new(1, 1), Vertex::new(9, 1), Vertex::new(9, 4));
+    assert_eq!(stops.iter().filter(|v| **v == corner).count(), 2);
+    assert_eq!(stops.iter().filter(|v| **v == right).count(), 2);
+    assert_eq!(stops.last(), Some(&start));
+    let solved = sim.solve_with(Algorithm::Cbs).unwrap();
+    assert!(!solved.summary().conflicts);
+    assert_eq!(route(&solved, 'P'), patrol, "Patrols never evade anybody");
+    assert!(
+        ShamanBuilder::from(&solved)
+            .to_string()
+            .contains("patrol P = 9/1 > 9/4 * 2\n")
+    );
+
+    let both = Shaman::parse_str("both", "---\npatrol A = 3/0\n---\nA a \n").unwrap_err();
+    assert!(
+        both.to_string()
+            .contains("Robot 'A' patrols, so it can't drive to a goal")
+    );
+}
+
+#[test]
+fn waypoints_are_visited_in_order_on_the_way_to_the_goal() {
+    let (first, second) = (Vertex::new(3, 2), Vertex::new(5, 2));
+    let builder = ShamanBuilder::new(8, 4)
+        .robot('A', Vertex::new(1, 1), Vertex::new(6, 1))
+        .robot('B', Vertex::new(6, 2), Vertex::new(1, 2))
+        .via('A', &[first, second]);
+    let sim = builder.build().unwrap();
+    assert!(builder.to_string().contains("via A = 3/2 > 5/2\n"));
+    assert_eq!(ShamanBuilder::from(&sim).to_string(), builder.to_string());
+
+    let solved = sim.solve().unwrap();
+    assert!(!solved.summary().conflicts);
+    let robot = |sim: &Shaman| sim.robots().find(|r| r.name() == 'A').unwrap().clone();
+    let visits = |v| {
+        robot(&solved)
+            .route()
+            .iter()
+            .find(|l| l.position == v)
+            .map(|l| l.time)
+    };
+    assert!(visits(first).unwrap() < visits(second).unwrap());
+    // Detours to the waypoints count towards the lower bound
+    assert_eq!(solved.lower_bound(), (3 + 2 + 2) + 5);
+
+    let when = visits(first).unwrap();
+    assert_eq!(robot(&solved.at(when)).waypoints(), [first, second]);
+    assert_eq!(robot(&solved.at(when + 1)).waypoints(), [second]);
+
+    let goal_less = Shaman::parse_str("goal-less", "---\nvia A = 1/0\n---\nA  \n").unwrap_err();
+    assert!(goal_less.to_string().contains("Robot 'A' has no goal"));
+}
+
+#[test]
+fn maps_configure_their_scenario_in_the_header() {
+    let builder = ShamanBuilder::new(5, 3)
+        .robot('A', Vertex::new(0, 0), Vertex::new(4, 2))
+        .setting("objective", "makespan")
+        .setting("movement", "eight-connected")
+        .setting("max-time", "2.5");
+    let sim = builder.build().unwrap();
+    assert!(
+        builder
+            .to_string()
+            .starts_with("---\nobjective = makespan\n")
+    );
+    assert_eq!(ShamanBuilder::from(&sim).to_string(), builder.to_string());
+    assert_eq!(sim.objective(), Objective::Makespan);
+    assert_eq!(sim.planner_config().movement, MovementModel::EightConnected);
+    assert_eq!(
+        sim.planner_config().max_time,
+        Some(Duration::from_millis(2500))
+    );
+    assert_eq!(sim.settings().len(), 3);
+
+    // The map has the last word over flags & config files
+    let sim = sim
+        .with_planner_config(PlannerConfig::default())
+        .unwrap()
+        .with_objective(Objective::SumOfCosts)
+        .with_map_settings()
+        .unwrap();
+    assert_eq!(sim.objective(), Objective::Makespan);
+    assert_eq!(sim.planner_config().movement, MovementModel::EightConnected);
+
+    // Even before anybody plans with a config the map overrides
+    let config = Config {
+        planner: PlannerConfig {
+            max_route_length: Some(2),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let map = "---\nmax-route-length = 20\nseed = 4\n---\nA    a\n";
+    let sim = Shaman::parse_str("settled", map).unwrap();
+    let config = Config {
+        seed: Some(9),
+        ..config
+    };
+    let sim = sim.with_config(&config).unwrap();
+    assert_eq!(sim.planner_config().max_route_length, Some(20));
+    assert_eq!(sim.seed(), Some(4));
+
+    let error = |header: &str| {
+        let map = format!("---\n{header}\n---\nA a\n");
+        Shaman::parse_str("settings", &map).unwrap_err().to_string()
+    };
+    assert!(error("speed = 3").contains("Unknown setting 'speed'"));
+    assert!(error("movement = hexagonal").contains("got 'hexagonal'"));
+    assert!(error("max-time = soon").contains("Expected a number"));
+}
+
+#[rstest]
+fn anytime_solving_only_ever_improves_the_solution(
+    #[values(Algorithm::Pbs, Algorithm::Prioritized)] algorithm: Algorithm,
+) {
+    let sim = ShamanBuilder::random_layout(12, 8, 0.2, 2)
+        .and_then(|b| b.random_robots(6, 2))
+        .and_then(|b| b.build())
+        .unwrap();
+    let cost = |s: &Shaman| s.objective().cost(s.robots());
+    let mut costs = vec![];
+    let budget = Duration::from_millis(500);
+    let solved = sim
+        .clone()
+        .solve_anytime_with(algorithm, budget, &mut Rng::new(7), |s| costs.push(cost(s)))
+        .unwrap();
+    // The first solution the algorithm finds, then every improvement
+    assert_eq!(costs[0], cost(&sim.solve_with(algorithm).unwrap()));
+    assert!(costs.iter().tuple_windows().all(|(a, b)| b < a));
+    assert_eq!(cost(&solved), *costs.last().unwrap());
+    assert!(!solved.summary().conflicts);
+}
+
+#[test]
+fn holding_up_robots_warns_about_upcoming_collisions() {
+    let mut sim = ShamanBuilder::new(4, 3)
+        .robot('A', Vertex::new(0, 1), Vertex::new(3, 1))
+        .robot('B', Vertex::new(2, 0), Vertex::new(2, 2))
+        .build()
+        .unwrap();
+    let mut lookahead = Lookahead::new(&sim, 5);
+    assert!(lookahead.warnings(&sim).is_empty());
+
+    sim.hold('B').unwrap();
+    assert!(lookahead.warnings(&sim).is_empty(), "not updated yet");
+    lookahead.update(&sim, ['B']);
+    let warnings = lookahead.warnings(&sim);
+    assert_eq!(warnings, sim.lookahead(5));
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].robots, ('A', 'B'));
+    assert_eq!(
+        warnings[0].collision,
+        Collision::Vertex {
+            v: Vertex::new(2, 1),
+            t: 2
+        }
+    );
+    assert_eq!(warnings[0].to_string(), "A × B in 2 steps: vertex 2/1@2");
+    assert!(sim.lookahead(2).is_empty());
+    assert!(sim.hold('Z').is_err());
+}
+
+#[test]
+fn pbs_counts_every_branch_it_explored() {
+    let (solved, stats) = Shaman::parse("../maps/fourway.txt")
+        .unwrap()
+        .solve_with_stats(Algorithm::Pbs)
+        .unwrap();
+    let audit = solved.audit();
+    let feasible = audit
+        .decisions
+        .iter()
+        .flat_map(|d| &d.branches)
+        .filter(|(_, _, cost)| cost.is_some())
+        .count();
+    assert_eq!(stats.conflicts, audit.decisions.len());
+    assert_eq!(stats.generated, feasible + 1);
+    assert_eq!(
+        solved.cost(),
+        Shaman::parse("../maps/fourway.txt")
+            .unwrap()
+            .solve()
+            .unwrap()
+            .cost()
+    );
+}
+
+#[test]
+fn pbs_only_replans_robots_whose_upstream_changed() {
+    let (solved, stats) = Shaman::parse("../maps/crowd.txt")
+        .unwrap()
+        .solve_with_stats(Algorithm::Pbs)
+        .unwrap();
+    let robots = solved.robots().count();
+    let branches = solved
+        .audit()
+        .decisions
+        .iter()
+        .map(|d| d.branches.len())
+        .sum::<usize>();
+    // Replanning every robot of each branch would take one search each
+    assert!(stats.expansions.len() < branches * robots);
+    assert_eq!(solved.cost(), 112);
+}
+
+#[test]
+fn driven_robots_are_evaded_by_all_others() {
+    let sim = ShamanBuilder::new(5, 3)
+        .robot('A', Vertex::new(2, 0), Vertex::new(2, 0))
+        .robot('B', Vertex::new(0, 1), Vertex::new(4, 1))
+        .build()
+        .unwrap()
+        .solve()
+        .unwrap();
+    let mut sim = Simulation::from(sim);
+    assert!(sim.drive('A', Action::N).is_err());
+    assert!(sim.drive('Z', Action::S).is_err());
+
+    sim.drive('A', Action::S).unwrap();
+    let robot = |sim: &Simulation, name| {
+        sim.shaman()
+            .robots()
+            .find(|r| r.name() == name)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(robot(&sim, 'A').position().0, Vertex::new(2, 1));
+    assert!(robot(&sim, 'A').is_frozen());
+    let b = robot(&sim, 'B');
+    assert!(b.route().iter().all(|l| l.position != Vertex::new(2, 1)));
+    assert_eq!(sim.take_diffs().len(), 1);
+
+    while !sim.is_finished() {
+        sim.drive('A', Action::Wait).unwrap();
+    }
+    assert_eq!(robot(&sim, 'B').position().0, Vertex::new(4, 1));
+    assert_eq!(robot(&sim, 'A').position().0, Vertex::new(2, 1));
+}
+
+#[rstest]
+#[case::pbs(Algorithm::Pbs, (6, 6, 0.), 6, 2)]
+#[case::cbs(Algorithm::Cbs, (10, 8, 0.15), 8, 3)]
+fn solving_the_same_scenario_always_yields_the_same_solution(
+    #[case] algorithm: Algorithm,
+    #[case] (width, height, density): (usize, usize, f32),
+    #[case] robots: usize,
+    #[case] layout: u64,
+) {
+    let solve = |seed: Option<u64>| {
+        let sim = ShamanBuilder::random_layout(width, height, density, layout)
+            .and_then(|b| b.random_robots(robots, layout))
+            .and_then(|b| b.build())
+            .unwrap();
+        let sim = match seed {
+            Some(seed) => sim.with_seed(seed),
+            None => sim,
+        };
+        assert_eq!(sim.seed(), seed);
+        let solved = sim.solve_with(algorithm).unwrap();
+        assert_eq!(solved.violations(), []);
+        // Robots come in the order of their names, no matter how they are stored
+        let names = solved.robots().map(|r| r.name()).collect_vec();
+        assert!(names.is_sorted(), "{names:?}");
+        solved
+            .robots()
+            .map(|r| (r.name(), r.route().clone()))
+            .collect_vec()
+    };
+    for seed in [None, Some(7)] {
+        let first = solve(seed);
+        for _ in 0..3 {
+            assert_eq!(solve(seed), first);
+        }
+    }
+    // Different seeds explore different solutions
+    let solutions = (0..8).map(|seed| solve(Some(seed))).unique().count();
+    assert!(solutions > 1);
+}
+
+#[test]
+fn optimizing_routes_strips_avoidable_waits_and_detours() {
+    let sim = ShamanBuilder::new(6, 3).build().unwrap();
+    let start = Location {
+        position: Vertex::new(0, 1),
+        time: 0,
+    };
+    use Action::*;
+    let zigzag = Route::from_actions(start, [E, N, E, S, Wait, E, E]);
+    let uniform = CostModel {
+        wait: 1.,
+        straight: 1.,
+        start: 1.,
+        turn: 1.,
+    };
+    let mut route = zigzag.clone();
+    route.optimize(sim.layout(), &[], &uniform);
+    assert_eq!(route, Route::from_actions(start, [E; 4]));
+
+    // Stopping before a turn is cheaper than turning on the move by default
+    let mut route = Route::from_actions(start, [E, Wait, N]);
+    route.optimize(sim.layout(), &[], &CostModel::default());
+    assert_eq!(route, Route::from_actions(start, [E, Wait, N]));
+
+    // Somebody crossing the straight line keeps the robot waiting, but not detouring
+    let crossing = Route::from_actions(
+        Location {
+            position: Vertex::new(3, 0),
+            time: 0,
+        },
+        [Wait, Wait, S, S],
+    );
+    let mut route = zigzag.clone();
+    route.optimize(sim.layout(), &[RightOfWay::passing(&crossing)], &uniform);
+    assert_eq!(route, Route::from_actions(start, [E, E, Wait, E, E]));
+    assert!(route.encounters(&crossing).is_empty());
+}
+
+#[test]
+fn optimizing_long_routes_takes_linear_time() {
+    let sim = ShamanBuilder::new(200, 2).build().unwrap();
+    let at = |x, time| Location {
+        position: Vertex::new(x, 0),
+        time,
+    };
+    // Somebody sitting right ahead for ages keeps the robot waiting just as long
+    let sitting = Route::from_iter((0..2000).map(|t| at(1, t)));
+    let mut route = Route::from_iter(
+        (0..2000)
+            .map(|t| at(0, t))
+            .chain((1..200).map(|x| at(x, 1999 + x as Time))),
+    );
+    let waiting = route.clone();
+    let started = Instant::now();
+    route.optimize(
+        sim.layout(),
+        &[RightOfWay::passing(&sitting)],
+        &CostModel::default(),
+    );
+    assert!(
+        started.elapsed() < Duration::from_secs(2),
+        "{:?}",
+        started.elapsed()
+    );
+    assert_eq!(route, waiting);
+}
+
+#[rstest]
+fn robots_wait_for_doors_to_open_and_avoid_them_once_closed(
+    #[values(Planner::AStar, Planner::Sipp)] planner: Planner,
+) {
+    let corridor = ShamanBuilder::new(7, 1).robot('A', Vertex::new(0, 0), Vertex::new(6, 0));
+    let door = Vertex::new(3, 0);
+    let mut sim = corridor.build().unwrap().with_planner(planner).unwrap();
+    sim.close(&[door], ..5).unwrap();
+    let route = sim.robots().next().unwrap().route().clone();
+    assert_eq!(route.duration(), 8, "{route:?}");
+    assert!((0..5).all(|t| route.position_at(t) != Some(door)));
+    assert_eq!(sim.violations(), []);
+    let map = ShamanBuilder::from(&sim).to_string();
+    assert!(map.contains("close 3/0 = 0..4"), "{map}");
+
+    // Following the route planned before the door was closed runs into it
+    let open = corridor.build().unwrap();
+    let plan = open.to_json();
+    let mut closed = corridor.clone().close(door, ..5).build().unwrap();
+    assert!(closed.layout().is_blocked_at(door, 4));
+    assert!(!closed.layout().is_blocked_at(door, 5));
+    closed = closed.with_plan(&plan).unwrap();
+    assert_eq!(
+        closed.violations(),
+        [Violation::Blocked {
+            robot: 'A',
+            v: door,
+            t: 3
+        }]
+    );
+
+    // Closing for ages costs as little as closing for a moment, and survives printing maps
+    let behind = Vertex::new(1, 0);
+    let ages = 1 << 40;
+    let sim = corridor.clone().close(behind, 2..ages).build().unwrap();
+    let sim = sim.with_planner(planner).unwrap();
+    assert_eq!(sim.robots().next().unwrap().route().duration(), 6);
+    let map = ShamanBuilder::from(&sim).to_string();
+    assert!(
+        map.contains(&format!("close 1/0 = 2..{}", ages - 1)),
+        "{map}"
+    );
+    let forever = map.replace(&(ages - 1).to_string(), &Time::MAX.to_string());
+    let sim = Shaman::parse_str("forever", &forever).unwrap();
+    assert!(sim.layout().is_blocked_at(behind, Time::MAX));
+    assert!(
+        ShamanBuilder::from(&sim)
+            .to_string()
+            .contains("close 1/0 = 2..\n")
+    );
+
+    // Nobody can stay on a goal which closes for good
+    let goal = Vertex::new(6, 0);
+    assert!(corridor.close(goal, 10..).build().is_err());
+}
+
+#[test]
+fn deviating_robots_replan_from_where_they_are() {
+    let sim = ShamanBuilder::new(7, 3)
+        .robot('A', Vertex::new(0, 1), Vertex::new(6, 1))
+        .robot('B', Vertex::new(6, 0), Vertex::new(0, 0))
+        .build()
+        .unwrap()
+        .solve()
+        .unwrap();
+    let mut sim = Simulation::from(sim);
+    let conflicts = Rc::new(RefCell::new(0));
+    let counted = conflicts.clone();
+    sim.on_notification(move |_, n| {
+        if matches!(n, Notification::ConflictObserved { .. }) {
+            *counted.borrow_mut() += 1;
+        }
+    });
+    let robot = |sim: &Simulation, name| {
+        sim.shaman()
+            .robots()
+            .find(|r| r.name() == name)
+            .unwrap()
+            .clone()
+    };
+    sim.step().unwrap();
+    sim.step().unwrap();
+
+    // Pushed aside, robot A continues from where it ended up
+    let pushed = Vertex::new(1, 2);
+    assert!(sim.divert('A', robot(&sim, 'B').position().0).is_err());
+    assert!(sim.divert('Z', pushed).is_err());
+    sim.divert('A', pushed).unwrap();
+    let a = robot(&sim, 'A');
+    assert_eq!(a.position().0, pushed);
+    let next = a.route().iter().next().unwrap();
+    assert_eq!(next.time, sim.shaman().time());
+    assert!(next.position.manhattan_distance(pushed) <= 1);
+    assert_eq!(sim.take_diffs().len(), 1);
+    // Turning out to be where it is anyway changes nothing
+    sim.divert('A', pushed).unwrap();
+    assert!(sim.take_diffs().is_empty());
+
+    // A pallet dropped right ahead makes it take another way
+    let ahead = a.route().iter().nth(1).unwrap().position;
+    assert!(sim.block(robot(&sim, 'B').position().0).is_err());
+    sim.block(ahead).unwrap();
+    assert!(robot(&sim, 'A').route().iter().all(|l| l.position != ahead));
+    assert!(sim.divert('A', ahead).is_err());
+
+    assert_eq!(sim.run().unwrap(), Outcome::Finished);
+    assert_eq!(robot(&sim, 'A').position().0, Vertex::new(6, 1));
+    assert_eq!(robot(&sim, 'B').position().0, Vertex::new(0, 0));
+    assert_eq!(*conflicts.borrow(), 0);
+}
+
+#[test]
+fn blocking_cells_only_replans_robots_still_passing_them() {
+    let sim = ShamanBuilder::new(7, 2)
+        .robot('A', Vertex::new(0, 0), Vertex::new(6, 0))
+        .robot('B', Vertex::new(6, 1), Vertex::new(4, 0))
+        .build()
+        .unwrap()
+        .solve()
+        .unwrap();
+    let mut sim = Simulation::from(sim);
+    for _ in 0..3 {
+        sim.step().unwrap();
+    }
+    let routes = |sim: &Simulation| {
+        sim.shaman()
+            .robots()
+            .map(|r| r.route().clone())
+            .collect_vec()
+    };
+    let before = routes(&sim);
+
+    // Both robots already left their starts behind
+    sim.block(Vertex::new(0, 0)).unwrap();
+    sim.block(Vertex::new(6, 1)).unwrap();
+    assert!(sim.take_diffs().is_empty());
+
+    // Robot A could drive around the goal of robot B, but B can't get there anymore, so
+    // nothing changes at all
+    let now = sim.shaman().time();
+    assert!(sim.block(Vertex::new(4, 0)).is_err());
+    assert!(!sim.shaman().layout().is_blocked_at(Vertex::new(4, 0), now));
+    assert_eq!(routes(&sim), before);
+    assert!(sim.take_diffs().is_empty());
+}
@@ -0,0 +1,168 @@
+//! Playing back a solved scenario under keyboard control, pausing, stepping & rewinding it.
+//! Robots can be held up by hand to explore what-ifs, warning about collisions that causes,
+//! or driven around with the arrow keys while all others evade them
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use miette::Result;
+use shaman_core::{Action, Frame, Lookahead, Shaman, Simulation, Time};
+
+use crate::terminal::{self, Key, Keyboard, Screen};
+
+/// Slowest & fastest playback speeds in timesteps per second
+const SPEEDS: (f32, f32) = (0.25, 64.);
+/// How long to wait between looking for pressed keys
+const POLL: Duration = Duration::from_millis(20);
+
+/// Play back `sim` at `fps` timesteps per second, until the `q` key is pressed. Every timestep
+/// is reconstructed from the routes, so playback may go backwards as well. Pressing a robot's
+/// letter selects it, `w` then holds it up for a timestep, warning about collisions within the
+/// next `lookahead` timesteps
+pub(crate) fn control(sim: &Shaman, fps: f32, lookahead: Time) -> Result<Time> {
+    let mut end = sim.simulation_duration();
+    let mut fps = fps.clamp(SPEEDS.0, SPEEDS.1);
+    let (mut time, mut paused): (Time, _) = (0, false);
+    let mut next = Instant::now();
+    let mut screen = Screen::new()?;
+    let mut keyboard = Keyboard::new()?;
+    let (mut state, mut shown) = (sim.clone(), 0);
+    let mut warnings = Lookahead::new(&state, lookahead);
+    let (mut selected, mut holds) = (None, vec![]);
+    loop {
+        for key in keyboard.pressed()? {
+            match key {
+                Key::Char(' ') => paused = !paused,
+                Key::Left | Key::Char('h') => (paused, time) = (true, time.saturating_sub(1)),
+                Key::Right | Key::Char('l') => (paused, time) = (true, (time + 1).min(end)),
+                Key::Char('+') | Key::Char('=') => fps = (fps * 2.).min(SPEEDS.1),
+                Key::Char('-') => fps = (fps / 2.).max(SPEEDS.0),
+                Key::Char(c) if sim.robots().any(|r| r.name() == c) => selected = Some(c),
+                Key::Char('w') => {
+                    if let Some(name) = selected {
+                        holds.push((time, name));
+                        state.hold(name)?;
+                        warnings.update(&state, [name]);
+                        end += 1;
+                    }
+                }
+                Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Ok(time),
+                _ => {}
+            }
+        }
+        if !paused && Instant::now() >= next {
+            next = Instant::now() + Duration::from_secs_f32(1. / fps);
+            match time < end {
+                true => time += 1,
+                false => paused = true,
+            }
+        }
+        if shown != time {
+            let before = shown;
+            (state, shown) = (replay(sim, &holds, time), time);
+            match before < time {
+                // Routes only changed for robots held up in between
+                true => warnings.update(
+                    &state,
+                    holds
+                        .iter()
+                        .filter(|(t, _)| (before + 1..=time).contains(t))
+                        .map(|(_, n)| *n),
+                ),
+                // Robots drove further than before already
+                false => warnings = Lookahead::new(&state, lookahead),
+            }
+        }
+        screen.draw(&status(&state, &warnings, time, end, fps, paused, selected))?;
+        thread::sleep(POLL);
+    }
+}
+
+/// Drive robot `name` of `sim` with the arrow keys, one move per timestep at `fps` (or 2 if
+/// that's `0`), until the `q` key is pressed. All other robots follow their routes, replanning
+/// around the driven one where they would collide with it. Collisions within the next
+/// `lookahead` timesteps, which they couldn't evade, are listed below the board
+pub(crate) fn drive(sim: Shaman, name: char, fps: f32, lookahead: Time) -> Result<Shaman> {
+    let fps = if fps > 0. { fps } else { 2. }.clamp(SPEEDS.0, SPEEDS.1);
+    let mut sim = Simulation::from(sim);
+    let mut next = Instant::now();
+    let mut screen = Screen::new()?;
+    let mut keyboard = Keyboard::new()?;
+    let mut action = Action::Wait;
+    let mut blocked = None;
+    loop {
+        for key in keyboard.pressed()? {
+            action = match key {
+                Key::Up | Key::Char('k') => Action::N,
+                Key::Down | Key::Char('j') => Action::S,
+                Key::Left | Key::Char('h') => Action::W,
+                Key::Right | Key::Char('l') => Action::E,
+                Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Ok(sim.into_inner()),
+                _ => action,
+            };
+        }
+        if Instant::now() >= next {
+            next = Instant::now() + Duration::from_secs_f32(1. / fps);
+            // Bumping into walls just waits
+            blocked = sim.drive(name, action).err().map(|e| e.to_string());
+            if blocked.is_some() {
+                sim.drive(name, Action::Wait)?;
+            }
+            action = Action::Wait;
+        }
+        let mut frame = sim.shaman().frame(true);
+        let time = sim.shaman().time();
+        frame.push(terminal::text(&format!(
+            "driving {name} t={time} at {fps} fps"
+        )));
+        for warning in sim.shaman().lookahead(lookahead) {
+            frame.push(terminal::text(&format!("⚠ {warning}")));
+        }
+        if let Some(blocked) = &blocked {
+            frame.push(terminal::text(blocked));
+        }
+        frame.push(terminal::text("←/↑/→/↓ drive  q quit"));
+        screen.draw(&frame)?;
+        thread::sleep(POLL);
+    }
+}
+
+/// `sim` simulated for `time` timesteps, holding up robots at the times they were nudged
+fn replay(sim: &Shaman, holds: &[(Time, char)], time: Time) -> Shaman {
+    let mut state = sim.clone();
+    for t in 0..=time {
+        for (_, name) in holds.iter().filter(|(at, _)| *at == t) {
+            state.hold(*name).ok();
+        }
+        if t < time {
+            state.simulate();
+        }
+    }
+    state
+}
+
+/// The board at `time`, above what's playing, upcoming collisions & which keys control it
+fn status(
+    sim: &Shaman,
+    lookahead: &Lookahead,
+    time: Time,
+    end: Time,
+    fps: f32,
+    paused: bool,
+    selected: Option<char>,
+) -> Frame {
+    let mut frame = sim.frame(true);
+    let state = if paused { "⏸" } else { "▶" };
+    let selected = selected.map_or(String::new(), |n| format!("  robot {n} selected"));
+    frame.push(terminal::text(&format!(
+        "{state} t={time}/{end} at {fps} fps{selected}"
+    )));
+    for warning in lookahead.warnings(sim) {
+        frame.push(terminal::text(&format!("⚠ {warning}")));
+    }
+    frame.push(terminal::text(
+        "space pause  ←/→ step  +/- speed  A-Z select  w hold up  q quit",
+    ));
+    frame
+}
@@ -0,0 +1,56 @@
+//! Scrubbing interactively through the reservation table of a solved scenario
+use std::path::Path;
+
+use miette::Result;
+use shaman_core::{Frame, Shaman, Time};
+
+use crate::{
+    Options,
+    playback::load,
+    terminal::{self, Key, Keyboard, Screen},
+};
+
+/// Scrub through the reservation table of a scenario, one timestep per arrow key press
+pub fn inspect(map: &Path, options: &Options) -> Result<()> {
+    let sim = load(map, options)?;
+    let end = sim.simulation_duration();
+    let jump = (end / 10).max(1);
+    let mut screen = Screen::new()?;
+    let mut keyboard = Keyboard::new()?;
+    let mut time = 0;
+    loop {
+        screen.draw(&reservation_frame(&sim, time))?;
+        time = match keyboard.next()? {
+            Key::Left | Key::Char('h') => time.saturating_sub(1),
+            Key::Right | Key::Char('l') => (time + 1).min(end),
+            Key::PageUp => time.saturating_sub(jump),
+            Key::PageDown => (time + jump).min(end),
+            Key::Home => 0,
+            Key::End => end,
+            Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Ok(()),
+            // Redrawn like after any other key, at the new size
+            _ => time,
+        };
+    }
+}
+
+/// The map at `time`, each reserved cell colored by its owner, above a slider through all
+/// timesteps of the routes
+fn reservation_frame(sim: &Shaman, time: Time) -> Frame {
+    let mut frame = sim.reservations_frame(time);
+    let end = sim.simulation_duration();
+    let width = frame[0].len().saturating_sub(2).max(1);
+    let knob = match end {
+        0 => 0,
+        _ => time * (width - 1) / end,
+    };
+    let slider = (0..width).map(|x| if x == knob { "●" } else { "─" });
+    let slider = std::iter::once("├")
+        .chain(slider)
+        .chain(std::iter::once("┤"))
+        .map(str::to_string);
+    frame.push(slider.collect());
+    frame.push(terminal::text(&format!("t={time}/{end}")));
+    frame.push(terminal::text("←/→ step  PgUp/PgDn jump  Home/End  q quit"));
+    frame
+}
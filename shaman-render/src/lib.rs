@@ -0,0 +1,11 @@
+//! Playing back [shaman_core] scenarios on the terminal, including the keyboard controls
+mod controls;
+mod inspect;
+mod playback;
+mod terminal;
+
+pub use crate::{
+    inspect::inspect,
+    playback::{Options, level, montage, resume},
+    terminal::{Ansi, Crossterm, Terminal, enable_escape_sequences, terminal},
+};
@@ -0,0 +1,271 @@
+//! Playing back scenarios on the terminal
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use itertools::Itertools;
+use miette::{Result, miette};
+use shaman_core::{
+    ARCHIVE_EXTENSION, Algorithm, Config, Format, Frame, Json, Messages, Objective, Order, Output,
+    PlannerConfig, Rgb, Script, Shaman, ShamanError, Simulation, Time, ToJson, width,
+};
+
+use crate::{
+    controls,
+    terminal::{self, Screen},
+};
+
+/// How to load & play back scenarios
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// How many timesteps to simulate per second, `0` to only show the final frame
+    pub fps: f32,
+    /// Don't solve, but show the conflicted routes
+    pub stop: bool,
+    /// Keep robots this many cells away from obstacles & the map's edge
+    pub inflate: usize,
+    /// How to resolve conflicts between robots
+    pub algorithm: Algorithm,
+    /// What the solver minimizes
+    pub objective: Objective,
+    /// Order [Algorithm::Prioritized] plans robots in
+    pub order: Order,
+    /// Seed breaking ties between equally costly ideas, see [Shaman::with_seed]
+    pub seed: Option<u64>,
+    /// Solve independent groups of robots separately, see [Shaman::solve_decomposed]
+    pub decompose: bool,
+    /// Print the [shaman_core::SolveStats] of solving each map to stderr
+    pub stats: bool,
+    /// Keep improving the solution for this long, see [Shaman::solve_anytime]
+    pub anytime: Option<Duration>,
+    /// How robots plan their individual routes
+    pub planner: PlannerConfig,
+    /// Colors of single robots or whole fleets by name, see [Shaman::with_palette]
+    pub palette: Vec<(String, Rgb)>,
+    /// Save a checkpoint to this file when reaching the timestep & stop playback there
+    pub checkpoint: Option<(PathBuf, Time)>,
+    /// Events changing the scenario while it is played back
+    pub script: Option<Script>,
+    /// Let robots which finished early drift to this many cells with the least traffic
+    pub staging: usize,
+    pub output: Output,
+    /// Control playback with the keyboard, pausing, stepping & holding up robots
+    pub interactive: bool,
+    /// How many timesteps ahead to warn about collisions during [Self::interactive] playback
+    /// or while driving
+    pub lookahead: Time,
+    /// Drive this robot with the arrow keys, while all others evade it
+    pub drive: Option<char>,
+    /// Write the solved scenario as SVG to this file before playing it back
+    pub svg: Option<PathBuf>,
+    /// Write the solved scenario as animated GIF to this file before playing it back, at
+    /// [Self::fps] or 4 frames per second if that's `0`
+    pub gif: Option<PathBuf>,
+    /// Format of the map files
+    pub format: Format,
+}
+
+pub(crate) fn load(map: &Path, options: &Options) -> Result<Shaman> {
+    if map.extension().is_some_and(|e| e == ARCHIVE_EXTENSION) {
+        return Shaman::load_archive(map);
+    }
+    let config = Config {
+        algorithm: options.algorithm,
+        objective: options.objective,
+        order: options.order,
+        seed: options.seed,
+        inflate: options.inflate,
+        planner: options.planner,
+        palette: options.palette.clone(),
+        output: options.output,
+        format: options.format,
+    };
+    let sim = Shaman::parse_with(map, &config)?;
+    if options.stop {
+        Ok(sim)
+    } else if options.decompose {
+        sim.solve_decomposed(options.algorithm)
+    } else if let Some(budget) = options.anytime {
+        sim.solve_anytime(options.algorithm, budget)
+    } else if options.stats {
+        let (solved, stats) = sim.solve_with_stats(options.algorithm)?;
+        eprint!("{stats}");
+        Ok(solved)
+    } else {
+        sim.solve_with(options.algorithm)
+    }
+}
+
+pub fn level(map: &Path, options: &Options) -> Result<()> {
+    play(load(map, options)?, options)
+}
+
+/// Continue playing back a simulation saved with [Shaman::save]
+pub fn resume(checkpoint: &Path, options: &Options) -> Result<()> {
+    play(Shaman::restore(checkpoint)?, options)
+}
+
+fn play(sim: Shaman, options: &Options) -> Result<()> {
+    if let Some(file) = &options.svg {
+        std::fs::write(file, sim.svg()).map_err(|e| miette!("{}: {e}", file.display()))?;
+    }
+    if let Some(file) = &options.gif {
+        let fps = if options.fps > 0. { options.fps } else { 4. };
+        std::fs::write(file, sim.gif(fps)?).map_err(|e| miette!("{}: {e}", file.display()))?;
+    }
+    match options.output {
+        Output::Json => println!("{}", sim.to_json()),
+        Output::Yaml => print!("{}", sim.to_json().to_yaml()),
+        _ => {}
+    }
+    if matches!(options.output, Output::Json | Output::Yaml) {
+        return Ok(());
+    }
+    if sim.robots().next().is_none() {
+        let src = sim.layout().code();
+        eprintln!("{:?}", miette::Report::new(ShamanError::NoRobots { src }));
+    }
+    if let Some(name) = options.drive {
+        let sim = controls::drive(sim, name, options.fps, options.lookahead)?;
+        print!("{sim}");
+        return Ok(());
+    }
+    if options.interactive {
+        let time = controls::control(&sim, options.fps, options.lookahead)?;
+        print!("{}", sim.at(time));
+        print!("{}", summary(&sim, options.output));
+        return Ok(());
+    }
+    // Scripted events & repositioning change the routes, so the summary of the planned ones
+    // wouldn't apply
+    let dynamic = options.script.is_some() || options.staging > 0;
+    let summary = match dynamic {
+        true => String::new(),
+        false => summary(&sim, options.output),
+    };
+    let staging = sim.staging_cells(options.staging);
+    let mut sim = Simulation::from(sim);
+    sim.reposition(staging);
+    if let Some(script) = &options.script {
+        sim.script(script.clone());
+    }
+    if options.fps == 0. {
+        if dynamic {
+            // Routes change while simulating, so only the outcome is meaningful
+            sim.run()?;
+        }
+        println!("{}", sim.shaman());
+        print!("{summary}");
+        return Ok(());
+    }
+
+    let dt = Duration::from_secs_f32(1. / options.fps);
+    let mut screen = Screen::new()?;
+    while !sim.is_finished() {
+        let time = sim.shaman().time();
+        if let Some((file, _)) = options.checkpoint.as_ref().filter(|(_, t)| *t == time) {
+            sim.shaman().save(file)?;
+            drop(screen);
+            print!("{}", sim.shaman());
+            println!("Saved checkpoint at t={time} to {}", file.display());
+            return Ok(());
+        }
+        sim.step()?;
+        screen.draw(&sim.shaman().frame(true))?;
+        std::thread::sleep(dt);
+    }
+    drop(screen);
+    print!("{}", sim.shaman());
+    print!("{summary}");
+    Ok(())
+}
+
+fn summary(sim: &Shaman, output: Output) -> String {
+    match output {
+        Output::Messages => format!("{}\n", Json::array(sim.summary().messages())),
+        _ => sim.summary().to_string(),
+    }
+}
+
+/// Play several scenarios at once, tiled next to each other and synchronized by timestep
+pub fn montage<P: AsRef<Path>>(maps: &[P], options: &Options) -> Result<()> {
+    let mut sims = maps
+        .iter()
+        .map(|map| load(map.as_ref(), options))
+        .collect::<Result<Vec<_>>>()?;
+
+    if options.fps > 0. {
+        let dt = Duration::from_secs_f32(1. / options.fps);
+        let duration = sims.iter().map(|s| s.simulation_duration()).max();
+        let mut screen = Screen::new()?;
+        for _ in 0..=duration.unwrap_or_default() {
+            for sim in &mut sims {
+                sim.simulate();
+            }
+            screen.draw(&tile(&sims, true))?;
+            std::thread::sleep(dt);
+        }
+    }
+    for row in tile(&sims, false) {
+        println!("{}", row.concat());
+    }
+    Ok(())
+}
+
+/// Render all `sims` next to each other, wrapping into more rows where the terminal is too small
+fn tile(sims: &[Shaman], animated: bool) -> Frame {
+    const GAP: usize = 2;
+    let columns = terminal::terminal()
+        .size()
+        .map_or(usize::MAX, |(w, _)| w as usize);
+    let tiles = sims
+        .iter()
+        .map(|sim| {
+            let frame = sim.frame(animated);
+            let columns = frame[0].iter().map(|c| width(c)).sum::<usize>();
+            let mut title = terminal::text(sim.layout().code().name());
+            while title.iter().map(|c| width(c)).sum::<usize>() > columns {
+                title.pop();
+            }
+            let padding = columns - title.iter().map(|c| width(c)).sum::<usize>();
+            title.extend(std::iter::repeat_n(" ".to_string(), padding));
+            let lines = std::iter::once(title).chain(frame).collect::<Vec<_>>();
+            (columns, lines)
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows: Vec<Vec<&(usize, Frame)>> = vec![];
+    let mut used = usize::MAX;
+    for tile in &tiles {
+        if used.saturating_add(GAP + tile.0) > columns || rows.is_empty() {
+            rows.push(vec![]);
+            used = 0;
+        } else {
+            used += GAP;
+        }
+        used += tile.0;
+        rows.last_mut().unwrap().push(tile);
+    }
+
+    let mut out = Frame::new();
+    for row in rows {
+        let height = row
+            .iter()
+            .map(|(_, lines)| lines.len())
+            .max()
+            .unwrap_or_default();
+        for y in 0..height {
+            let line = row
+                .iter()
+                .map(|(width, lines)| match lines.get(y) {
+                    Some(line) => line.clone(),
+                    None => vec![" ".to_string(); *width],
+                })
+                .collect_vec()
+                .join(&[" ".repeat(GAP)][..]);
+            out.push(line);
+        }
+    }
+    out
+}
@@ -0,0 +1,338 @@
+//! Drawing frames on the terminal & reading keys from it
+use std::{
+    io::{Stdout, Write, stdout},
+    time::Duration,
+};
+
+use crossterm::{
+    event::{Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal::ClearType,
+};
+use miette::{Result, miette};
+use shaman_core::{Frame, width};
+
+/// Cells of a line of plain text
+pub(crate) fn text(s: &str) -> Vec<String> {
+    s.chars().map(String::from).collect()
+}
+
+/// Escape sequences & queries of a terminal backend, which playback draws frames with. See
+/// [terminal] for the one in use
+pub trait Terminal {
+    /// Columns & lines of the terminal, `None` if unknown
+    fn size(&self) -> Option<(u16, u16)>;
+    /// Switch to the alternate screen & hide the cursor
+    fn enter(&self) -> String;
+    /// Show the cursor again & switch back to the main screen
+    fn leave(&self) -> String;
+    /// Move the cursor to the 1-based `column` & `line`
+    fn goto(&self, column: u16, line: u16) -> String;
+    fn clear_all(&self) -> String;
+    /// Clear from the cursor to the end of its line
+    fn clear_until_newline(&self) -> String;
+    fn clear_line(&self) -> String;
+}
+
+/// Plain VT100 escape sequences, which also work on Windows 10 or later, e.g. for terminals
+/// misreporting their size. The size comes from the `COLUMNS` & `LINES` environment variables,
+/// if set
+pub struct Ansi;
+
+impl Terminal for Ansi {
+    fn size(&self) -> Option<(u16, u16)> {
+        let var = |name| std::env::var(name).ok()?.parse().ok();
+        var("COLUMNS").zip(var("LINES"))
+    }
+
+    fn enter(&self) -> String {
+        "\x1b[?1049h\x1b[?25l".to_string()
+    }
+
+    fn leave(&self) -> String {
+        "\x1b[?25h\x1b[?1049l".to_string()
+    }
+
+    fn goto(&self, column: u16, line: u16) -> String {
+        format!("\x1b[{line};{column}H")
+    }
+
+    fn clear_all(&self) -> String {
+        "\x1b[2J".to_string()
+    }
+
+    fn clear_until_newline(&self) -> String {
+        "\x1b[K".to_string()
+    }
+
+    fn clear_line(&self) -> String {
+        "\x1b[2K".to_string()
+    }
+}
+
+/// [crossterm], which also knows the size of the terminal, on Unix & Windows alike
+pub struct Crossterm;
+
+impl Crossterm {
+    /// Escape sequence of a crossterm `command`
+    fn ansi(command: impl crossterm::Command) -> String {
+        let mut s = String::new();
+        // Writing into a string never fails
+        let _ = command.write_ansi(&mut s);
+        s
+    }
+}
+
+impl Terminal for Crossterm {
+    fn size(&self) -> Option<(u16, u16)> {
+        crossterm::terminal::size().ok()
+    }
+
+    fn enter(&self) -> String {
+        Self::ansi(crossterm::terminal::EnterAlternateScreen) + &Self::ansi(crossterm::cursor::Hide)
+    }
+
+    fn leave(&self) -> String {
+        Self::ansi(crossterm::cursor::Show) + &Self::ansi(crossterm::terminal::LeaveAlternateScreen)
+    }
+
+    fn goto(&self, column: u16, line: u16) -> String {
+        // Crossterm counts from 0
+        Self::ansi(crossterm::cursor::MoveTo(column - 1, line - 1))
+    }
+
+    fn clear_all(&self) -> String {
+        Self::ansi(crossterm::terminal::Clear(ClearType::All))
+    }
+
+    fn clear_until_newline(&self) -> String {
+        Self::ansi(crossterm::terminal::Clear(ClearType::UntilNewLine))
+    }
+
+    fn clear_line(&self) -> String {
+        Self::ansi(crossterm::terminal::Clear(ClearType::CurrentLine))
+    }
+}
+
+/// The backend to draw with: [Crossterm], unless `SHAMAN_TERMINAL=ansi` asks for the plain
+/// [Ansi] one
+pub fn terminal() -> Box<dyn Terminal> {
+    match std::env::var("SHAMAN_TERMINAL") {
+        Ok(t) if t == "ansi" => Box::new(Ansi),
+        _ => Box::new(Crossterm),
+    }
+}
+
+/// Keys pressed on the terminal, see [Keyboard]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Key {
+    Char(char),
+    /// A letter pressed while holding control
+    Ctrl(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Esc,
+    /// Not a key, but the terminal changed its size & needs to be redrawn
+    Resize,
+}
+
+/// Reads keys as soon as they are pressed, switching the terminal into raw mode as long as it
+/// lives
+pub(crate) struct Keyboard;
+
+impl Keyboard {
+    pub(crate) fn new() -> Result<Self> {
+        crossterm::terminal::enable_raw_mode()
+            .map_err(|e| miette!("Failed to read keys from terminal: {e}"))?;
+        Ok(Self)
+    }
+
+    /// All keys pressed since the last call, without waiting for any
+    pub(crate) fn pressed(&mut self) -> Result<Vec<Key>> {
+        let mut keys = vec![];
+        while Self::io(crossterm::event::poll(Duration::ZERO))? {
+            keys.extend(Self::key(Self::io(crossterm::event::read())?));
+        }
+        Ok(keys)
+    }
+
+    /// The next key pressed, waiting for it
+    pub(crate) fn next(&mut self) -> Result<Key> {
+        loop {
+            if let Some(key) = Self::key(Self::io(crossterm::event::read())?) {
+                return Ok(key);
+            }
+        }
+    }
+
+    fn io<T>(result: std::io::Result<T>) -> Result<T> {
+        result.map_err(|e| miette!("Failed to read keys from terminal: {e}"))
+    }
+
+    /// Key of an `event`, ignoring releases, which Windows reports as well
+    fn key(event: Event) -> Option<Key> {
+        let key = match event {
+            Event::Resize(..) => return Some(Key::Resize),
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => return None,
+        };
+        Some(match key.code {
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::Esc => Key::Esc,
+            _ => return None,
+        })
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+/// Let the console interpret escape sequences like colors, which Windows consoles only do once
+/// asked to with `ENABLE_VIRTUAL_TERMINAL_PROCESSING`. Nothing to do anywhere else
+pub fn enable_escape_sequences() {
+    #[cfg(windows)]
+    console::enable_virtual_terminal_processing();
+}
+
+/// Just the bits of the Windows console API needed for [enable_escape_sequences]
+#[cfg(windows)]
+mod console {
+    use std::ffi::c_void;
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+    }
+
+    /// Enable VT processing on stdout & stderr, leaving redirected ones, which aren't consoles
+    /// & have no mode, alone
+    pub(super) fn enable_virtual_terminal_processing() {
+        for std_handle in [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+            // SAFETY: the handle is only used if it has a console mode, i.e. is a console
+            unsafe {
+                let console = GetStdHandle(std_handle);
+                let mut mode = 0;
+                if GetConsoleMode(console, &mut mode) != 0 {
+                    SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
+                }
+            }
+        }
+    }
+}
+
+/// Draws frames on the alternate screen, as long as it lives
+///
+/// Frames are positioned absolutely & cropped to the terminal. Only cells which changed since
+/// the previous frame are drawn, unless the terminal was resized in between, which clears it
+/// & redraws everything from scratch.
+pub(crate) struct Screen {
+    terminal: Box<dyn Terminal>,
+    out: Stdout,
+    size: Option<(u16, u16)>,
+    /// What is currently visible on the terminal
+    previous: Frame,
+}
+
+impl Screen {
+    pub(crate) fn new() -> Result<Self> {
+        enable_escape_sequences();
+        let mut screen = Self {
+            terminal: terminal(),
+            out: stdout(),
+            size: None,
+            previous: Frame::new(),
+        };
+        screen.write(&screen.terminal.enter())?;
+        Ok(screen)
+    }
+
+    pub(crate) fn draw(&mut self, frame: &Frame) -> Result<()> {
+        let size = self.terminal.size().unwrap_or((u16::MAX, u16::MAX));
+        let mut out = String::new();
+        if self.size != Some(size) {
+            out.push_str(&self.terminal.clear_all());
+            self.size = Some(size);
+            self.previous.clear();
+        }
+        let (columns, lines) = (size.0 as usize, size.1 as usize);
+        let frame = frame
+            .iter()
+            .take(lines)
+            .map(|row| {
+                let mut used = 0;
+                row.iter()
+                    .take_while(|cell| {
+                        used += width(cell);
+                        used <= columns
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Frame>();
+
+        for (y, row) in frame.iter().enumerate() {
+            let before = self.previous.get(y).map(Vec::as_slice).unwrap_or_default();
+            let (mut column, mut cursor) = (0, None);
+            let mut aligned = true;
+            for (x, cell) in row.iter().enumerate() {
+                let start = column;
+                column += width(cell);
+                // Cells after a cell of different width moved, so compare them by position
+                aligned &= before.get(x).is_some_and(|b| width(b) == width(cell));
+                if aligned && before.get(x) == Some(cell) {
+                    continue;
+                }
+                if cursor != Some(start) {
+                    out.push_str(&self.terminal.goto(start as u16 + 1, y as u16 + 1));
+                }
+                out.push_str(cell);
+                cursor = Some(column);
+            }
+            if before.iter().map(|c| width(c)).sum::<usize>() > column {
+                out.push_str(&self.terminal.goto(column as u16 + 1, y as u16 + 1));
+                out.push_str(&self.terminal.clear_until_newline());
+            }
+        }
+        for y in frame.len()..self.previous.len() {
+            out.push_str(&self.terminal.goto(1, y as u16 + 1));
+            out.push_str(&self.terminal.clear_line());
+        }
+        self.previous = frame;
+        self.write(&out)
+    }
+
+    fn write(&mut self, s: &str) -> Result<()> {
+        write!(self.out, "{s}")
+            .and_then(|_| self.out.flush())
+            .map_err(|e| miette!("Failed to draw on terminal: {e}"))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = self.write(&self.terminal.leave());
+    }
+}